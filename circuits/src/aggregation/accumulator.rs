@@ -0,0 +1,115 @@
+//! The accumulator of an aggregation, and its encoding as public inputs.
+//!
+//! Each coordinate of the two points is split into [`LIMBS`] little-endian
+//! limbs of [`LIMB_BITS`] bits, one scalar field element each, in the order
+//! `lhs.x`, `lhs.y`, `rhs.x`, `rhs.y`: [`ACCUMULATOR_INSTANCES`] elements.
+//! The limbs are the representation of base field elements of the
+//! non-native arithmetic of the aggregation circuit.
+
+use alloc::vec::Vec;
+use halo2_proofs::arithmetic::{CurveAffine, FieldExt};
+use halo2_proofs::halo2curves::bn256::{Bn256, Fq, Fr, G1Affine};
+use halo2_proofs::halo2curves::group::ff::PrimeField;
+use halo2_proofs::halo2curves::group::Group;
+use halo2_proofs::halo2curves::pairing::{MillerLoopResult, MultiMillerLoop};
+use halo2_proofs::poly::kzg::msm::PreparedG2KZG;
+
+pub const LIMBS: usize = 4;
+pub const LIMB_BITS: usize = 68;
+pub const ACCUMULATOR_INSTANCES: usize = 4 * LIMBS;
+
+/// The points of the pairing check `e(lhs, s_g2) = e(rhs, g2)` all the
+/// accumulated proofs verify up to. The default one, of two points at
+/// infinity, is the placeholder of a circuit without witnesses.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Accumulator {
+    pub lhs: G1Affine,
+    pub rhs: G1Affine,
+}
+
+impl Accumulator {
+    /// Checks the pairing of the accumulator with the G2 points of the params.
+    pub fn check(&self, prepared: &PreparedG2KZG<Bn256>) -> bool {
+        bool::from(
+            Bn256::multi_miller_loop(&[(&self.lhs, &prepared.s_g2), (&self.rhs, &prepared.neg_g2)])
+                .final_exponentiation()
+                .is_identity(),
+        )
+    }
+
+    /// The public inputs encoding the accumulator.
+    pub fn instances(&self) -> Vec<Fr> {
+        [self.lhs.x, self.lhs.y, self.rhs.x, self.rhs.y]
+            .iter()
+            .flat_map(limbs)
+            .collect()
+    }
+
+    /// Decodes the accumulator from its public inputs, `None` if a limb is out
+    /// of range or a point isn't on the curve.
+    pub fn from_instances(instances: &[Fr]) -> Option<Self> {
+        if instances.len() != ACCUMULATOR_INSTANCES {
+            return None;
+        }
+        let coordinates = instances
+            .chunks(LIMBS)
+            .map(from_limbs)
+            .collect::<Option<Vec<_>>>()?;
+        let point = |x: Fq, y: Fq| {
+            let point = G1Affine { x, y };
+            bool::from(point.is_on_curve()).then_some(point)
+        };
+        Some(Self {
+            lhs: point(coordinates[0], coordinates[1])?,
+            rhs: point(coordinates[2], coordinates[3])?,
+        })
+    }
+}
+
+fn limbs(value: &Fq) -> [Fr; LIMBS] {
+    let words = words(value.to_repr().as_ref());
+    let mask = (1u128 << LIMB_BITS) - 1;
+    let mut limbs = [Fr::zero(); LIMBS];
+    for (i, limb) in limbs.iter_mut().enumerate() {
+        let (word, shift) = (i * LIMB_BITS / 64, i * LIMB_BITS % 64);
+        let bits = (words[word] as u128 | (words[word + 1] as u128) << 64) >> shift;
+        *limb = Fr::from_u128(bits & mask);
+    }
+    limbs
+}
+
+fn from_limbs(limbs: &[Fr]) -> Option<Fq> {
+    let mut words = [0u64; LIMBS + 1];
+    for (i, limb) in limbs.iter().enumerate() {
+        let limb = limb_value(limb)?;
+        let (word, shift) = (i * LIMB_BITS / 64, i * LIMB_BITS % 64);
+        words[word] |= (limb << shift) as u64;
+        words[word + 1] |= (limb << shift >> 64) as u64;
+    }
+    if words[LIMBS] != 0 {
+        return None;
+    }
+    let mut repr = [0u8; 32];
+    for (bytes, word) in repr.chunks_mut(8).zip(words.iter()) {
+        bytes.copy_from_slice(&word.to_le_bytes());
+    }
+    Option::from(Fq::from_repr(repr))
+}
+
+/// The limb as an integer, `None` if it has more than [`LIMB_BITS`] bits.
+fn limb_value(limb: &Fr) -> Option<u128> {
+    let repr = limb.to_repr();
+    let words = words(repr.as_ref());
+    (words[2] == 0 && words[3] == 0 && words[1] >> (LIMB_BITS - 64) == 0)
+        .then_some(words[0] as u128 | (words[1] as u128) << 64)
+}
+
+/// Little-endian 64 bits words of a 32 bytes representation, and a zero word
+/// past the end.
+fn words(repr: &[u8]) -> [u64; LIMBS + 1] {
+    let mut words = [0u64; LIMBS + 1];
+    for (word, bytes) in words.iter_mut().zip(repr.chunks(8)) {
+        *word = u64::from_le_bytes(bytes.try_into().unwrap());
+    }
+    words
+}
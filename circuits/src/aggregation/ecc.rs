@@ -0,0 +1,473 @@
+//! BN256 G1 arithmetic over the [`integer`](super::integer) chip.
+//!
+//! Points are affine and never the identity. The additions are the
+//! incomplete ones, failing when the points have the same abscissa, and
+//! doublings fail for a point of ordinate zero, so the points computed are
+//! always right.
+//!
+//! A multi-scalar multiplication adds, for each window of four bits from the
+//! most significant, the multiple of each point by the digit of its scalar.
+//! The multiples are selected from a table of each point, with an auxiliary
+//! point `B` added so none is the identity: `T[k] = B + k * P`. The sum
+//! starts from another auxiliary point `A` and the auxiliary points are
+//! subtracted at the end.
+//!
+//! A table is sixteen rows with its tag and the index of each multiple, and
+//! the multiples of a scalar are selected on sixty four rows, with the
+//! running sum of its digits ending on the scalar:
+//!
+//! | digit | acc | x limbs | y limbs | tag | index | q_select | q_table |
+//! |-------|-----|---------|---------|-----|-------|----------|---------|
+//! | | `0` | | | | | 0 | 0 |
+//! | `d` | `16 * acc_prev + d` | of `T[d]` | of `T[d]` | `t` | | 1 | 0 |
+//! | | | of `T[k]` | of `T[k]` | `t` | `k` | 0 | 1 |
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::convert::TryInto;
+use halo2_proofs::arithmetic::{Field, FieldExt};
+use halo2_proofs::circuit::{Layouter, Value};
+use halo2_proofs::halo2curves::bn256::{Fq, Fr, G1Affine, G1};
+use halo2_proofs::halo2curves::group::ff::PrimeField;
+use halo2_proofs::halo2curves::group::Curve;
+use halo2_proofs::plonk::{
+    Advice, Column, ConstraintSystem, Constraints, Error, Expression, Fixed, Selector,
+};
+use halo2_proofs::poly::Rotation;
+
+use super::accumulator::LIMBS;
+use super::integer::{fq_limbs, AssignedInteger, AssignedNative, IntegerChip, IntegerConfig, Term};
+
+pub const WINDOW_BITS: usize = 4;
+pub const WINDOWS: usize = 64;
+const TABLE_SIZE: usize = 1 << WINDOW_BITS;
+
+#[derive(Clone, Debug)]
+pub struct AssignedPoint {
+    pub x: AssignedInteger,
+    pub y: AssignedInteger,
+}
+
+impl AssignedPoint {
+    pub fn value(&self) -> Value<G1Affine> {
+        self.x
+            .value
+            .zip(self.y.value)
+            .map(|(x, y)| G1Affine { x, y })
+    }
+}
+
+/// A point of a multi-scalar multiplication.
+#[derive(Clone, Debug)]
+pub enum Base {
+    Assigned(Box<AssignedPoint>),
+    Constant(G1Affine),
+}
+
+#[derive(Clone, Debug)]
+pub struct EccConfig {
+    integer: IntegerConfig,
+    digit: Column<Advice>,
+    acc: Column<Advice>,
+    limbs: [Column<Advice>; 2 * LIMBS],
+    tag: Column<Fixed>,
+    index: Column<Fixed>,
+    q_select: Selector,
+    q_table: Selector,
+}
+
+impl EccConfig {
+    pub fn configure(cs: &mut ConstraintSystem<Fr>, integer: IntegerConfig) -> Self {
+        let digit = cs.advice_column();
+        let acc = cs.advice_column();
+        let limbs = [(); 2 * LIMBS].map(|_| cs.advice_column());
+        cs.enable_equality(acc);
+        for column in limbs {
+            cs.enable_equality(column);
+        }
+        let tag = cs.fixed_column();
+        let index = cs.fixed_column();
+        let q_select = cs.complex_selector();
+        let q_table = cs.complex_selector();
+
+        cs.create_gate("msm digits", |vc| {
+            let q_select = vc.query_selector(q_select);
+            let digit = vc.query_advice(digit, Rotation::cur());
+            let acc_prev = vc.query_advice(acc, Rotation::prev());
+            let acc = vc.query_advice(acc, Rotation::cur());
+            Constraints::with_selector(
+                q_select,
+                [acc - acc_prev * Fr::from(TABLE_SIZE as u64) - digit],
+            )
+        });
+
+        // The tag of a table is not zero, so a selected multiple is one of its
+        // rows and the digit one of its indices.
+        cs.lookup_any("msm select", |vc| {
+            let q_select = vc.query_selector(q_select);
+            let q_table = vc.query_selector(q_table);
+            let tag = vc.query_fixed(tag, Rotation::cur());
+            let digit = vc.query_advice(digit, Rotation::cur());
+            let index = vc.query_fixed(index, Rotation::cur());
+            let limbs = limbs.map(|column| vc.query_advice(column, Rotation::cur()));
+            let tuple = |q: Expression<Fr>, key: Expression<Fr>| {
+                [tag.clone(), key]
+                    .into_iter()
+                    .chain(limbs.iter().cloned())
+                    .map(move |value| q.clone() * value)
+            };
+            tuple(q_select, digit).zip(tuple(q_table, index)).collect()
+        });
+
+        Self {
+            integer,
+            digit,
+            acc,
+            limbs,
+            tag,
+            index,
+            q_select,
+            q_table,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct EccChip {
+    config: EccConfig,
+    integer: IntegerChip,
+    one: AssignedInteger,
+    three: AssignedInteger,
+    /// The tag of the next table.
+    next_tag: u64,
+}
+
+impl EccChip {
+    pub fn new(config: EccConfig, layouter: &mut impl Layouter<Fr>) -> Result<Self, Error> {
+        let integer = IntegerChip::new(config.integer.clone());
+        let one = integer.constant(layouter, Fq::one())?;
+        let three = integer.constant(layouter, Fq::from(3))?;
+        Ok(Self {
+            config,
+            integer,
+            one,
+            three,
+            next_tag: 1,
+        })
+    }
+
+    pub fn integer(&self) -> &IntegerChip {
+        &self.integer
+    }
+
+    /// Assigns a point on the curve, returning it with its coordinates in the
+    /// scalar field.
+    pub fn load_point(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        point: Value<G1Affine>,
+    ) -> Result<(AssignedPoint, [AssignedNative; 2]), Error> {
+        let integer = &self.integer;
+        let (x, x_native) = integer.assign_canonical(layouter, point.map(|point| point.x))?;
+        let (y, y_native) = integer.assign_canonical(layouter, point.map(|point| point.y))?;
+        // `y^2 = x^3 + 3`
+        let square = integer.mul_add(layouter, &x, &x, None, &[])?;
+        let cube = integer.mul_add(layouter, &x, &square, None, &[Term::Add(&self.three)])?;
+        integer.assert_mul_add_zero(layouter, &y, &y, None, &[Term::Sub(&cube)])?;
+        Ok((AssignedPoint { x, y }, [x_native, y_native]))
+    }
+
+    pub fn constant_point(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        point: G1Affine,
+    ) -> Result<AssignedPoint, Error> {
+        Ok(AssignedPoint {
+            x: self.integer.constant(layouter, point.x)?,
+            y: self.integer.constant(layouter, point.y)?,
+        })
+    }
+
+    /// `p + q`, for points of different abscissas.
+    pub fn add(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        p: &AssignedPoint,
+        q: &AssignedPoint,
+    ) -> Result<AssignedPoint, Error> {
+        let integer = &self.integer;
+        let inverse = integer.assign(
+            layouter,
+            p.x.value
+                .zip(q.x.value)
+                .map(|(x1, x2)| (x2 - x1).invert().unwrap_or(Fq::zero())),
+        )?;
+        integer.assert_mul_add_zero(
+            layouter,
+            &inverse,
+            &q.x,
+            Some(&p.x),
+            &[Term::Sub(&self.one)],
+        )?;
+        let lambda = integer.mul_add(layouter, &inverse, &q.y, Some(&p.y), &[])?;
+        self.line(layouter, p, q, &lambda)
+    }
+
+    /// `2 * p`, for a point of ordinate not zero.
+    pub fn double(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        p: &AssignedPoint,
+    ) -> Result<AssignedPoint, Error> {
+        let integer = &self.integer;
+        let square = integer.mul_add(layouter, &p.x, &p.x, None, &[])?;
+        let y2 = integer.mul_add(layouter, &self.one, &p.y, None, &[Term::Add(&p.y)])?;
+        let lambda = integer.assign(
+            layouter,
+            p.x.value
+                .zip(p.y.value)
+                .map(|(x, y)| x.square() * Fq::from(3) * (y + y).invert().unwrap_or(Fq::zero())),
+        )?;
+        integer.assert_mul_add_zero(
+            layouter,
+            &lambda,
+            &y2,
+            None,
+            &[Term::Sub(&square), Term::Sub(&square), Term::Sub(&square)],
+        )?;
+        self.line(layouter, p, p, &lambda)
+    }
+
+    /// The third point on the line of slope `lambda` through `p` and `q`,
+    /// reflected.
+    fn line(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        p: &AssignedPoint,
+        q: &AssignedPoint,
+        lambda: &AssignedInteger,
+    ) -> Result<AssignedPoint, Error> {
+        let integer = &self.integer;
+        let x = integer.mul_add(
+            layouter,
+            lambda,
+            lambda,
+            None,
+            &[Term::Sub(&p.x), Term::Sub(&q.x)],
+        )?;
+        let y = integer.mul_add(layouter, lambda, &p.x, Some(&x), &[Term::Sub(&p.y)])?;
+        Ok(AssignedPoint { x, y })
+    }
+
+    /// `Σ scalar * base`.
+    pub fn msm(
+        &mut self,
+        layouter: &mut impl Layouter<Fr>,
+        terms: &[(AssignedNative, Base)],
+    ) -> Result<AssignedPoint, Error> {
+        let (a, b) = auxiliary_points();
+        let b_assigned = self.constant_point(layouter, b)?;
+
+        let mut selected = Vec::with_capacity(terms.len());
+        for (scalar, base) in terms.iter() {
+            let tag = self.next_tag;
+            self.next_tag += 1;
+            let table = match base {
+                Base::Assigned(point) => {
+                    let mut table = Vec::with_capacity(TABLE_SIZE);
+                    table.push(b_assigned.clone());
+                    for k in 1..TABLE_SIZE {
+                        table.push(self.add(layouter, &table[k - 1], point)?);
+                    }
+                    Table::Assigned(table)
+                }
+                Base::Constant(point) => {
+                    let mut table = Vec::with_capacity(TABLE_SIZE);
+                    table.push(b);
+                    for k in 1..TABLE_SIZE {
+                        table.push((table[k - 1] + point).to_affine());
+                    }
+                    Table::Constant(table)
+                }
+            };
+            self.assign_table(layouter, tag, &table)?;
+            selected.push(self.select(layouter, tag, scalar, &table)?);
+        }
+
+        let mut acc = self.constant_point(layouter, a)?;
+        for window in 0..WINDOWS {
+            if window > 0 {
+                for _ in 0..WINDOW_BITS {
+                    acc = self.double(layouter, &acc)?;
+                }
+            }
+            for multiples in selected.iter() {
+                acc = self.add(layouter, &acc, &multiples[window])?;
+            }
+        }
+        let correction = self.constant_point(layouter, -correction(a, b, terms.len()))?;
+        self.add(layouter, &acc, &correction)
+    }
+
+    fn assign_table(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        tag: u64,
+        table: &Table,
+    ) -> Result<(), Error> {
+        let config = &self.config;
+        layouter.assign_region(
+            || "msm table",
+            |mut region| {
+                for k in 0..TABLE_SIZE {
+                    config.q_table.enable(&mut region, k)?;
+                    region.assign_fixed(|| "tag", config.tag, k, || Value::known(Fr::from(tag)))?;
+                    region.assign_fixed(
+                        || "index",
+                        config.index,
+                        k,
+                        || Value::known(Fr::from(k as u64)),
+                    )?;
+                    match table {
+                        Table::Assigned(table) => {
+                            let limbs = table[k].x.limbs.iter().chain(table[k].y.limbs.iter());
+                            for (limb, column) in limbs.zip(config.limbs.iter()) {
+                                limb.copy_advice(|| "limb", &mut region, *column, k)?;
+                            }
+                        }
+                        Table::Constant(table) => {
+                            let limbs = fq_limbs(&table[k].x)
+                                .into_iter()
+                                .chain(fq_limbs(&table[k].y));
+                            for (limb, column) in limbs.zip(config.limbs.iter()) {
+                                region.assign_advice_from_constant(|| "limb", *column, k, limb)?;
+                            }
+                        }
+                    }
+                }
+                Ok(())
+            },
+        )
+    }
+
+    /// The multiples of the table by the digits of the scalar, from the most
+    /// significant.
+    fn select(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        tag: u64,
+        scalar: &AssignedNative,
+        table: &Table,
+    ) -> Result<Vec<AssignedPoint>, Error> {
+        let config = &self.config;
+        let digits = scalar.value().map(digits);
+        let values = match table {
+            Table::Assigned(table) => table
+                .iter()
+                .map(AssignedPoint::value)
+                .collect::<Value<Vec<_>>>(),
+            Table::Constant(table) => Value::known(table.clone()),
+        };
+        layouter.assign_region(
+            || "msm select",
+            |mut region| {
+                let mut acc =
+                    region.assign_advice_from_constant(|| "acc", config.acc, 0, Fr::zero())?;
+                let mut selected = Vec::with_capacity(WINDOWS);
+                for window in 0..WINDOWS {
+                    let row = window + 1;
+                    config.q_select.enable(&mut region, row)?;
+                    region.assign_fixed(
+                        || "tag",
+                        config.tag,
+                        row,
+                        || Value::known(Fr::from(tag)),
+                    )?;
+                    let digit = digits.as_ref().map(|digits| digits[window]);
+                    region.assign_advice(
+                        || "digit",
+                        config.digit,
+                        row,
+                        || digit.map(|digit| Fr::from(digit as u64)),
+                    )?;
+                    acc = region.assign_advice(
+                        || "acc",
+                        config.acc,
+                        row,
+                        || {
+                            acc.value().map(|acc| *acc * Fr::from(TABLE_SIZE as u64))
+                                + digit.map(|digit| Fr::from(digit as u64))
+                        },
+                    )?;
+                    let point = values
+                        .as_ref()
+                        .zip(digit)
+                        .map(|(values, digit)| values[digit as usize]);
+                    let coordinates = [point.map(|point| point.x), point.map(|point| point.y)];
+                    let mut columns = config.limbs.iter();
+                    let mut integers = coordinates.iter().map(|value| {
+                        let limbs = (0..LIMBS)
+                            .map(|i| {
+                                region.assign_advice(
+                                    || "limb",
+                                    *columns.next().unwrap(),
+                                    row,
+                                    || value.map(|value| fq_limbs(&value)[i]),
+                                )
+                            })
+                            .collect::<Result<Vec<_>, _>>()?;
+                        Ok::<_, Error>(AssignedInteger {
+                            limbs: limbs.try_into().unwrap(),
+                            value: *value,
+                        })
+                    });
+                    let x = integers.next().unwrap()?;
+                    let y = integers.next().unwrap()?;
+                    selected.push(AssignedPoint { x, y });
+                }
+                region.constrain_equal(acc.cell(), scalar.cell())?;
+                Ok(selected)
+            },
+        )
+    }
+}
+
+enum Table {
+    Assigned(Vec<AssignedPoint>),
+    Constant(Vec<G1Affine>),
+}
+
+/// The digits of a scalar, from the most significant.
+fn digits(scalar: &Fr) -> [u8; WINDOWS] {
+    let repr = scalar.to_repr();
+    let mut digits = [0; WINDOWS];
+    for (window, digit) in digits.iter_mut().enumerate() {
+        let i = WINDOWS - 1 - window;
+        *digit = (repr.as_ref()[i / 2] >> (4 * (i % 2))) & 0xf;
+    }
+    digits
+}
+
+/// The auxiliary points `A` and `B`, of unknown discrete logarithms to each
+/// other in practice.
+fn auxiliary_points() -> (G1Affine, G1Affine) {
+    let point = |label: &[u8]| {
+        let hash = blake2b_simd::Params::new()
+            .hash_length(64)
+            .personal(b"msm auxiliary")
+            .hash(label);
+        (G1Affine::generator() * Fr::from_bytes_wide(hash.as_array())).into()
+    };
+    (point(b"A"), point(b"B"))
+}
+
+/// What the auxiliary points add to the sum of `n` terms:
+/// `16^63 * A + (Σ 16^w) * n * B`.
+fn correction(a: G1Affine, b: G1Affine, n: usize) -> G1Affine {
+    let base = Fr::from(TABLE_SIZE as u64);
+    let a_factor = base.pow_vartime([(WINDOWS - 1) as u64]);
+    let b_factor = (0..WINDOWS).fold(Fr::zero(), |sum, window| {
+        sum + base.pow_vartime([window as u64])
+    }) * Fr::from(n as u64);
+    (G1::from(a) * a_factor + b * b_factor).to_affine()
+}
@@ -0,0 +1,863 @@
+//! Non-native arithmetic of BN256 base field elements over the scalar field,
+//! the coordinates of the G1 points the aggregation circuit computes with.
+//!
+//! An element is [`LIMBS`] little-endian limbs of [`LIMB_BITS`] bits. The
+//! limbs of an assigned element are range checked by chunks of
+//! [`CHUNK_BITS`] bits looked up in a table, the last limb with three of
+//! them, so elements are less than `2^255` but not necessarily reduced. The
+//! values the chip computes are.
+//!
+//! A single gate checks the products: for elements `a`, `b`, `b'`, up to
+//! three `c` added or subtracted and a result `r`,
+//!
+//! `a * (b - b') + Σ ±c + K = q * p + r`
+//!
+//! as integers, with `q` the quotient by the base field modulus `p` and `K`
+//! a multiple of `p` keeping the left side positive. The equation holds
+//! modulo `2^272`, limb by limb with two carries, and modulo the scalar field
+//! on the values of the elements. Both sides are less than `2^512` with
+//! elements less than `2^255`, so it holds over the integers. The result is
+//! left out to assert the expression is zero.
+//!
+//! | row | 0..4 | 4..8 | 8..12 | chunks |
+//! |-----|------|------|-------|--------|
+//! | 0 | `a` | `b` | `b'` | of `q` |
+//! | 1 | `c1` | `c2` | `c3` | of `r` |
+//! | 2 | `q` | `r` | carries | of the carries |
+//!
+//! The carries are stored plus `2^84`. An element assigned as a witness
+//! takes a row of its own with its chunks, and one checked to be reduced a
+//! second row with the chunks of `p - 1` minus it.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryInto;
+use halo2_proofs::arithmetic::Field;
+use halo2_proofs::circuit::{AssignedCell, Layouter, Region, Value};
+use halo2_proofs::halo2curves::bn256::{Fq, Fr};
+use halo2_proofs::halo2curves::group::ff::PrimeField;
+use halo2_proofs::plonk::{
+    Advice, Column, ConstraintSystem, Constraints, Error, Expression, Fixed, Selector, TableColumn,
+    VirtualCells,
+};
+use halo2_proofs::poly::Rotation;
+use num_bigint::{BigInt, BigUint, Sign};
+
+use super::accumulator::{LIMBS, LIMB_BITS};
+
+pub const CHUNK_BITS: usize = 17;
+/// Chunks of each limb of an element.
+const LIMB_CHUNKS: [usize; LIMBS] = [4, 4, 4, 3];
+const ELEMENT_CHUNKS: usize = 15;
+const CARRY_CHUNKS: usize = 5;
+const CARRY_OFFSET_BITS: usize = 84;
+pub const MAIN_COLUMNS: usize = 12;
+pub const RANGE_COLUMNS: usize = ELEMENT_CHUNKS;
+
+pub type AssignedNative = AssignedCell<Fr, Fr>;
+
+/// An assigned base field element.
+#[derive(Clone, Debug)]
+pub struct AssignedInteger {
+    pub limbs: [AssignedNative; LIMBS],
+    pub value: Value<Fq>,
+}
+
+/// A term added to or subtracted from a product.
+#[derive(Clone, Copy, Debug)]
+pub enum Term<'a> {
+    Add(&'a AssignedInteger),
+    Sub(&'a AssignedInteger),
+}
+
+impl<'a> Term<'a> {
+    fn integer(&self) -> &'a AssignedInteger {
+        match self {
+            Term::Add(integer) | Term::Sub(integer) => integer,
+        }
+    }
+
+    fn sign(&self) -> Fr {
+        match self {
+            Term::Add(_) => Fr::one(),
+            Term::Sub(_) => -Fr::one(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct IntegerConfig {
+    main: [Column<Advice>; MAIN_COLUMNS],
+    range: [Column<Advice>; RANGE_COLUMNS],
+    range_table: TableColumn,
+    q_mul: Selector,
+    q_range: Selector,
+    q_canonical: Selector,
+    /// Whether `b'` is subtracted from `b`.
+    b_sub: Column<Fixed>,
+    /// The sign of each `c`, zero for none.
+    terms: [Column<Fixed>; 3],
+    /// Whether there is a result, zero to assert the expression is zero.
+    result: Column<Fixed>,
+    constants: Constants,
+}
+
+impl IntegerConfig {
+    pub fn configure(cs: &mut ConstraintSystem<Fr>) -> Self {
+        let main = [(); MAIN_COLUMNS].map(|_| cs.advice_column());
+        let range = [(); RANGE_COLUMNS].map(|_| cs.advice_column());
+        for column in main {
+            cs.enable_equality(column);
+        }
+        let range_table = cs.lookup_table_column();
+        let q_mul = cs.selector();
+        let q_range = cs.selector();
+        let q_canonical = cs.selector();
+        let b_sub = cs.fixed_column();
+        let terms = [(); 3].map(|_| cs.fixed_column());
+        let result = cs.fixed_column();
+        let constants = Constants::new();
+
+        cs.create_gate("integer mul", |vc| {
+            let q_mul = vc.query_selector(q_mul);
+            let a = element(vc, &main, 0, 0);
+            let b = element(vc, &main, 4, 0);
+            let b_sub_element = element(vc, &main, 8, 0);
+            let c = [0, 4, 8].map(|column| element(vc, &main, column, 1));
+            let q = element(vc, &main, 0, 2);
+            let r = element(vc, &main, 4, 2);
+            let carry_offset = constant(power_of_two(CARRY_OFFSET_BITS));
+            let carry_lo = vc.query_advice(main[8], Rotation(2)) - carry_offset.clone();
+            let carry_hi = vc.query_advice(main[9], Rotation(2)) - carry_offset;
+            let b_sub = vc.query_fixed(b_sub, Rotation::cur());
+            let signs = terms.map(|column| vc.query_fixed(column, Rotation::cur()));
+            let result = vc.query_fixed(result, Rotation::cur());
+
+            let b_diff = (0..LIMBS)
+                .map(|j| b[j].clone() - b_sub.clone() * b_sub_element[j].clone())
+                .collect::<Vec<_>>();
+            let t = (0..LIMBS)
+                .map(|k| {
+                    let mut t = constant(constants.k_limbs[k]) - result.clone() * r[k].clone();
+                    for i in 0..=k {
+                        t = t + a[i].clone() * b_diff[k - i].clone()
+                            - q[i].clone() * constants.p_limbs[k - i];
+                    }
+                    for (sign, c) in signs.iter().zip(c.iter()) {
+                        t = t + sign.clone() * c[k].clone();
+                    }
+                    t
+                })
+                .collect::<Vec<_>>();
+            let limb = power_of_two(LIMB_BITS);
+            let two_limbs = power_of_two(2 * LIMB_BITS);
+            let lo = t[0].clone() + t[1].clone() * limb - carry_lo.clone() * two_limbs;
+            let hi = t[2].clone() + t[3].clone() * limb + carry_lo - carry_hi * two_limbs;
+
+            let mut native = compose(&a) * (compose(&b) - b_sub * compose(&b_sub_element))
+                + constant(constants.k_native)
+                - compose(&q) * constants.p_native
+                - result * compose(&r);
+            for (sign, c) in signs.iter().zip(c.iter()) {
+                native = native + sign.clone() * compose(c);
+            }
+
+            let mut constraints = vec![lo, hi, native];
+            constraints.extend(decompose(vc, &range, &q, 0));
+            constraints.extend(decompose(vc, &range, &r, 1));
+            for (i, offset) in [(8, 0), (9, CARRY_CHUNKS)] {
+                constraints.push(
+                    vc.query_advice(main[i], Rotation(2))
+                        - chunks(vc, &range, 2, offset, CARRY_CHUNKS),
+                );
+            }
+            Constraints::with_selector(q_mul, constraints)
+        });
+
+        cs.create_gate("integer range", |vc| {
+            let q_range = vc.query_selector(q_range);
+            let x = element(vc, &main, 0, 0);
+            Constraints::with_selector(q_range, decompose(vc, &range, &x, 0))
+        });
+
+        // `x + d = p - 1`, limb by limb with borrows, for `d` in range.
+        cs.create_gate("integer canonical", |vc| {
+            let q_canonical = vc.query_selector(q_canonical);
+            let x = element(vc, &main, 0, 0);
+            let d = element(vc, &main, 4, 0);
+            let borrows = [8, 9, 10].map(|i| vc.query_advice(main[i], Rotation::cur()));
+            let native = vc.query_advice(main[11], Rotation::cur());
+            let limb = power_of_two(LIMB_BITS);
+
+            let mut constraints = decompose(vc, &range, &x, 0);
+            constraints.extend(decompose(vc, &range, &d, 1));
+            for i in 0..LIMBS {
+                let mut sum = x[i].clone() + d[i].clone() - constant(constants.p_minus_one[i]);
+                if i > 0 {
+                    sum = sum + borrows[i - 1].clone();
+                }
+                if i < LIMBS - 1 {
+                    sum = sum - borrows[i].clone() * limb;
+                }
+                constraints.push(sum);
+            }
+            for borrow in borrows {
+                constraints.push(borrow.clone() * (constant(Fr::one()) - borrow));
+            }
+            constraints.push(native - compose(&x));
+            Constraints::with_selector(q_canonical, constraints)
+        });
+
+        for column in range {
+            cs.lookup("integer chunk", |vc| {
+                vec![(vc.query_advice(column, Rotation::cur()), range_table)]
+            });
+        }
+
+        Self {
+            main,
+            range,
+            range_table,
+            q_mul,
+            q_range,
+            q_canonical,
+            b_sub,
+            terms,
+            result,
+            constants,
+        }
+    }
+}
+
+fn constant(value: Fr) -> Expression<Fr> {
+    Expression::Constant(value)
+}
+
+fn element(
+    vc: &mut VirtualCells<'_, Fr>,
+    main: &[Column<Advice>; MAIN_COLUMNS],
+    first: usize,
+    row: i32,
+) -> [Expression<Fr>; LIMBS] {
+    [0, 1, 2, 3].map(|i| vc.query_advice(main[first + i], Rotation(row)))
+}
+
+/// The value of limbs in the scalar field.
+fn compose(limbs: &[Expression<Fr>; LIMBS]) -> Expression<Fr> {
+    limbs.iter().rev().fold(constant(Fr::zero()), |sum, limb| {
+        sum * power_of_two(LIMB_BITS) + limb.clone()
+    })
+}
+
+/// The `count` chunks on `row` from the range column `first`.
+fn chunks(
+    vc: &mut VirtualCells<'_, Fr>,
+    range: &[Column<Advice>; RANGE_COLUMNS],
+    row: i32,
+    first: usize,
+    count: usize,
+) -> Expression<Fr> {
+    (first..first + count)
+        .rev()
+        .fold(constant(Fr::zero()), |sum, column| {
+            sum * power_of_two(CHUNK_BITS) + vc.query_advice(range[column], Rotation(row))
+        })
+}
+
+/// The limbs of an element equal to its chunks on `row`.
+fn decompose(
+    vc: &mut VirtualCells<'_, Fr>,
+    range: &[Column<Advice>; RANGE_COLUMNS],
+    limbs: &[Expression<Fr>; LIMBS],
+    row: i32,
+) -> Vec<Expression<Fr>> {
+    let mut first = 0;
+    limbs
+        .iter()
+        .zip(LIMB_CHUNKS)
+        .map(|(limb, count)| {
+            let chunks = chunks(vc, range, row, first, count);
+            first += count;
+            limb.clone() - chunks
+        })
+        .collect()
+}
+
+fn power_of_two(bits: usize) -> Fr {
+    Fr::from(2).pow_vartime([bits as u64])
+}
+
+/// The constants of the gates, from the base field modulus `p`.
+#[derive(Clone, Debug)]
+struct Constants {
+    p: BigUint,
+    p_limbs: [Fr; LIMBS],
+    p_native: Fr,
+    p_minus_one: [Fr; LIMBS],
+    /// `K = (p + 3) * p`, more than what three subtracted terms and a
+    /// subtracted product of reduced elements take away.
+    k: BigUint,
+    /// The limbs of `K` modulo `2^272`.
+    k_limbs: [Fr; LIMBS],
+    k_native: Fr,
+}
+
+impl Constants {
+    fn new() -> Self {
+        let p = fq_to_big(&-Fq::one()) + 1u32;
+        let k = (&p + 3u32) * &p;
+        Self {
+            p_limbs: limbs(&p).map(|limb| big_to_fr(&limb.into())),
+            p_native: big_to_fr(&p.clone().into()),
+            p_minus_one: limbs(&(&p - 1u32)).map(|limb| big_to_fr(&limb.into())),
+            k_limbs: limbs(&k).map(|limb| big_to_fr(&limb.into())),
+            k_native: big_to_fr(&k.clone().into()),
+            p,
+            k,
+        }
+    }
+}
+
+fn fq_to_big(value: &Fq) -> BigUint {
+    BigUint::from_bytes_le(value.to_repr().as_ref())
+}
+
+/// The first [`LIMBS`] limbs of a value.
+fn limbs(value: &BigUint) -> [BigUint; LIMBS] {
+    let mask = (BigUint::from(1u32) << LIMB_BITS) - 1u32;
+    [0, 1, 2, 3].map(|i| (value >> (i * LIMB_BITS)) & &mask)
+}
+
+/// The value modulo the scalar field modulus.
+fn big_to_fr(value: &BigInt) -> Fr {
+    let modulus = BigInt::from_bytes_le(Sign::Plus, (-Fr::one()).to_repr().as_ref()) + 1;
+    let mut reduced: BigInt = value % &modulus;
+    if reduced.sign() == Sign::Minus {
+        reduced += modulus;
+    }
+    let mut repr = [0u8; 32];
+    let (_, bytes) = reduced.to_bytes_le();
+    repr[..bytes.len()].copy_from_slice(&bytes);
+    Fr::from_repr(repr).unwrap()
+}
+
+/// The chunks of a value, `count` of [`CHUNK_BITS`] bits.
+fn chunks_of(value: &BigUint, count: usize) -> Vec<Fr> {
+    let mask = (BigUint::from(1u32) << CHUNK_BITS) - 1u32;
+    (0..count)
+        .map(|i| big_to_fr(&((value >> (i * CHUNK_BITS)) & &mask).into()))
+        .collect()
+}
+
+/// The chunks of the limbs of an element.
+fn element_chunks(value: &BigUint) -> Vec<Fr> {
+    limbs(value)
+        .iter()
+        .zip(LIMB_CHUNKS)
+        .flat_map(|(limb, count)| chunks_of(limb, count))
+        .collect()
+}
+
+/// The limbs of a base field element in the scalar field.
+pub fn fq_limbs(value: &Fq) -> [Fr; LIMBS] {
+    limbs(&fq_to_big(value)).map(|limb| big_to_fr(&limb.into()))
+}
+
+/// The quotient, the carries plus their offset and the result of a product.
+struct MulWitness {
+    q: BigUint,
+    r: Fq,
+    carries: [BigUint; 2],
+}
+
+fn mul_witness(
+    constants: &Constants,
+    a: &Fq,
+    b: &Fq,
+    b_sub: &Fq,
+    terms: &[(Fr, Fq)],
+    with_result: bool,
+) -> MulWitness {
+    let mut r = *a * (*b - b_sub);
+    for (sign, c) in terms.iter() {
+        r = if *sign == Fr::one() { r + c } else { r - c };
+    }
+    let r = if with_result { r } else { Fq::zero() };
+    let signed = |value: &Fq| BigInt::from(fq_to_big(value));
+    let sign = |sign: &Fr| if *sign == Fr::one() { 1 } else { -1 };
+
+    let mut sum =
+        signed(a) * (signed(b) - signed(b_sub)) + BigInt::from(constants.k.clone()) - signed(&r);
+    for (s, c) in terms.iter() {
+        sum += sign(s) * signed(c);
+    }
+    // Positive when the elements are reduced, the remainder is zero when
+    // the expression is, the gate fails otherwise.
+    let q = sum.to_biguint().unwrap_or_default() / &constants.p;
+
+    let limbs_of = |value: &BigUint| limbs(value).map(BigInt::from);
+    let (a, b, b_sub, q_limbs, r_limbs) = (
+        limbs_of(&fq_to_big(a)),
+        limbs_of(&fq_to_big(b)),
+        limbs_of(&fq_to_big(b_sub)),
+        limbs_of(&q),
+        limbs_of(&fq_to_big(&r)),
+    );
+    let p = limbs_of(&constants.p);
+    let k = limbs_of(&constants.k);
+    let terms = terms
+        .iter()
+        .map(|(s, c)| (sign(s), limbs_of(&fq_to_big(c))))
+        .collect::<Vec<_>>();
+    let t = (0..LIMBS)
+        .map(|k_| {
+            let mut t = k[k_].clone() - &r_limbs[k_];
+            for i in 0..=k_ {
+                t += &a[i] * (&b[k_ - i] - &b_sub[k_ - i]) - &q_limbs[i] * &p[k_ - i];
+            }
+            for (s, c) in terms.iter() {
+                t += *s * &c[k_];
+            }
+            t
+        })
+        .collect::<Vec<_>>();
+    let carry_lo = (&t[0] + (&t[1] << LIMB_BITS)) >> (2 * LIMB_BITS);
+    let carry_hi = (&t[2] + (&t[3] << LIMB_BITS) + &carry_lo) >> (2 * LIMB_BITS);
+    let offset = BigInt::from(1) << CARRY_OFFSET_BITS;
+    let carries =
+        [carry_lo, carry_hi].map(|carry| (carry + &offset).to_biguint().unwrap_or_default());
+    MulWitness { q, r, carries }
+}
+
+#[derive(Clone, Debug)]
+pub struct IntegerChip {
+    config: IntegerConfig,
+}
+
+impl IntegerChip {
+    pub fn new(config: IntegerConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn load_range_table(&self, layouter: &mut impl Layouter<Fr>) -> Result<(), Error> {
+        layouter.assign_table(
+            || "integer chunks",
+            |mut table| {
+                for i in 0..1 << CHUNK_BITS {
+                    table.assign_cell(
+                        || "chunk",
+                        self.config.range_table,
+                        i,
+                        || Value::known(Fr::from(i as u64)),
+                    )?;
+                }
+                Ok(())
+            },
+        )
+    }
+
+    /// Assigns a witness in range, not necessarily reduced.
+    pub fn assign(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        value: Value<Fq>,
+    ) -> Result<AssignedInteger, Error> {
+        layouter.assign_region(
+            || "integer assign",
+            |mut region| {
+                self.config.q_range.enable(&mut region, 0)?;
+                self.assign_element(
+                    &mut region,
+                    0,
+                    0,
+                    value.map(|value| fq_to_big(&value)),
+                    true,
+                )
+                .map(|limbs| AssignedInteger { limbs, value })
+            },
+        )
+    }
+
+    /// Assigns a reduced witness, returning it with its value in the scalar
+    /// field.
+    pub fn assign_canonical(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        value: Value<Fq>,
+    ) -> Result<(AssignedInteger, AssignedNative), Error> {
+        layouter.assign_region(
+            || "integer canonical",
+            |mut region| {
+                let limbs = self.assign_canonical_rows(&mut region, value)?;
+                Ok((
+                    AssignedInteger {
+                        limbs: limbs[..LIMBS].to_vec().try_into().unwrap(),
+                        value,
+                    },
+                    limbs[LIMBS].clone(),
+                ))
+            },
+        )
+    }
+
+    /// Checks an element is reduced, returning its value in the scalar field.
+    pub fn canonical(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        x: &AssignedInteger,
+    ) -> Result<AssignedNative, Error> {
+        layouter.assign_region(
+            || "integer canonical",
+            |mut region| {
+                let limbs = self.assign_canonical_rows(&mut region, x.value)?;
+                for (limb, cell) in x.limbs.iter().zip(limbs.iter()) {
+                    region.constrain_equal(limb.cell(), cell.cell())?;
+                }
+                Ok(limbs[LIMBS].clone())
+            },
+        )
+    }
+
+    pub fn constant(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        value: Fq,
+    ) -> Result<AssignedInteger, Error> {
+        layouter.assign_region(
+            || "integer constant",
+            |mut region| {
+                let limbs = fq_limbs(&value)
+                    .iter()
+                    .enumerate()
+                    .map(|(i, limb)| {
+                        region.assign_advice_from_constant(|| "limb", self.config.main[i], 0, *limb)
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(AssignedInteger {
+                    limbs: limbs.try_into().unwrap(),
+                    value: Value::known(value),
+                })
+            },
+        )
+    }
+
+    /// `a * (b - b_sub) + Σ terms`.
+    pub fn mul_add(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        a: &AssignedInteger,
+        b: &AssignedInteger,
+        b_sub: Option<&AssignedInteger>,
+        terms: &[Term<'_>],
+    ) -> Result<AssignedInteger, Error> {
+        self.mul(layouter, a, b, b_sub, terms, true)
+            .map(|r| r.unwrap())
+    }
+
+    /// Asserts `a * (b - b_sub) + Σ terms` is zero.
+    pub fn assert_mul_add_zero(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        a: &AssignedInteger,
+        b: &AssignedInteger,
+        b_sub: Option<&AssignedInteger>,
+        terms: &[Term<'_>],
+    ) -> Result<(), Error> {
+        self.mul(layouter, a, b, b_sub, terms, false).map(|_| ())
+    }
+
+    fn mul(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        a: &AssignedInteger,
+        b: &AssignedInteger,
+        b_sub: Option<&AssignedInteger>,
+        terms: &[Term<'_>],
+        with_result: bool,
+    ) -> Result<Option<AssignedInteger>, Error> {
+        assert!(terms.len() <= 3);
+        let config = &self.config;
+        let witness = a
+            .value
+            .zip(b.value)
+            .zip(b_sub.map_or(Value::known(Fq::zero()), |b_sub| b_sub.value))
+            .zip(
+                terms
+                    .iter()
+                    .map(|term| term.integer().value.map(|value| (term.sign(), value)))
+                    .collect::<Value<Vec<_>>>(),
+            )
+            .map(|(((a, b), b_sub), terms)| {
+                mul_witness(&config.constants, &a, &b, &b_sub, &terms, with_result)
+            });
+
+        layouter.assign_region(
+            || "integer mul",
+            |mut region| {
+                config.q_mul.enable(&mut region, 0)?;
+                region.assign_fixed(
+                    || "b_sub",
+                    config.b_sub,
+                    0,
+                    || Value::known(Fr::from(b_sub.is_some() as u64)),
+                )?;
+                region.assign_fixed(
+                    || "result",
+                    config.result,
+                    0,
+                    || Value::known(Fr::from(with_result as u64)),
+                )?;
+                for (i, column) in config.terms.iter().enumerate() {
+                    let sign = terms.get(i).map_or(Fr::zero(), Term::sign);
+                    region.assign_fixed(|| "term sign", *column, 0, || Value::known(sign))?;
+                }
+
+                let zero = AssignedIntegerOrZero::Zero;
+                let operands = [
+                    (0, 0, AssignedIntegerOrZero::Integer(a)),
+                    (0, 4, AssignedIntegerOrZero::Integer(b)),
+                    (0, 8, b_sub.map_or(zero, AssignedIntegerOrZero::Integer)),
+                ]
+                .into_iter()
+                .chain((0..3).map(|i| {
+                    (
+                        1,
+                        4 * i,
+                        terms
+                            .get(i)
+                            .map_or(zero, |term| AssignedIntegerOrZero::Integer(term.integer())),
+                    )
+                }));
+                for (row, first, operand) in operands {
+                    match operand {
+                        AssignedIntegerOrZero::Integer(integer) => {
+                            for (i, limb) in integer.limbs.iter().enumerate() {
+                                limb.copy_advice(
+                                    || "limb",
+                                    &mut region,
+                                    config.main[first + i],
+                                    row,
+                                )?;
+                            }
+                        }
+                        AssignedIntegerOrZero::Zero => {
+                            self.assign_element(
+                                &mut region,
+                                row,
+                                first,
+                                Value::known(BigUint::default()),
+                                false,
+                            )?;
+                        }
+                    }
+                }
+
+                self.assign_element(
+                    &mut region,
+                    2,
+                    0,
+                    witness.as_ref().map(|w| w.q.clone()),
+                    false,
+                )?;
+                self.assign_chunks(&mut region, 0, witness.as_ref().map(|w| w.q.clone()))?;
+                let r = witness.as_ref().map(|w| w.r);
+                let limbs =
+                    self.assign_element(&mut region, 2, 4, r.map(|r| fq_to_big(&r)), false)?;
+                self.assign_chunks(&mut region, 1, r.map(|r| fq_to_big(&r)))?;
+                for (i, column) in [8, 9].into_iter().enumerate() {
+                    let carry = witness.as_ref().map(|w| w.carries[i].clone());
+                    region.assign_advice(
+                        || "carry",
+                        config.main[column],
+                        2,
+                        || carry.as_ref().map(|carry| big_to_fr(&carry.clone().into())),
+                    )?;
+                    for j in 0..CARRY_CHUNKS {
+                        region.assign_advice(
+                            || "carry chunk",
+                            config.range[i * CARRY_CHUNKS + j],
+                            2,
+                            || {
+                                carry
+                                    .as_ref()
+                                    .map(|carry| chunks_of(carry, CARRY_CHUNKS)[j])
+                            },
+                        )?;
+                    }
+                }
+                Ok(with_result.then_some(AssignedInteger { limbs, value: r }))
+            },
+        )
+    }
+
+    /// Assigns the limbs of a value from the main column `first`, with its
+    /// chunks on the same row if `chunks`.
+    fn assign_element(
+        &self,
+        region: &mut Region<'_, Fr>,
+        row: usize,
+        first: usize,
+        value: Value<BigUint>,
+        chunks: bool,
+    ) -> Result<[AssignedNative; LIMBS], Error> {
+        if chunks {
+            self.assign_chunks(region, row, value.clone())?;
+        }
+        let limbs = (0..LIMBS)
+            .map(|i| {
+                region.assign_advice(
+                    || "limb",
+                    self.config.main[first + i],
+                    row,
+                    || {
+                        value
+                            .as_ref()
+                            .map(|value| big_to_fr(&limbs(value)[i].clone().into()))
+                    },
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(limbs.try_into().unwrap())
+    }
+
+    fn assign_chunks(
+        &self,
+        region: &mut Region<'_, Fr>,
+        row: usize,
+        value: Value<BigUint>,
+    ) -> Result<(), Error> {
+        let chunks = value.map(|value| element_chunks(&value));
+        for (i, column) in self.config.range.iter().enumerate() {
+            region.assign_advice(|| "chunk", *column, row, || chunks.as_ref().map(|c| c[i]))?;
+        }
+        Ok(())
+    }
+
+    /// Assigns the two rows checking a value is reduced, returning its limbs
+    /// and its value in the scalar field.
+    fn assign_canonical_rows(
+        &self,
+        region: &mut Region<'_, Fr>,
+        value: Value<Fq>,
+    ) -> Result<Vec<AssignedNative>, Error> {
+        let config = &self.config;
+        config.q_canonical.enable(region, 0)?;
+        let x = value.map(|value| fq_to_big(&value));
+        let d = x.as_ref().map(|x| &config.constants.p - 1u32 - x);
+        let mut cells = self.assign_element(region, 0, 0, x.clone(), true)?.to_vec();
+        self.assign_element(region, 0, 4, d.clone(), false)?;
+        self.assign_chunks(region, 1, d.clone())?;
+        // The limb of `x` with the one of `d` exceeds the one of `p - 1`.
+        let borrows = x.zip(d).map(|(x, d)| {
+            let p_minus_one = limbs(&(&config.constants.p - 1u32));
+            let (x, d) = (limbs(&x), limbs(&d));
+            let mut borrow = BigUint::default();
+            (0..LIMBS - 1)
+                .map(|i| {
+                    borrow = (&x[i] + &d[i] + &borrow - &p_minus_one[i]) >> LIMB_BITS;
+                    big_to_fr(&borrow.clone().into())
+                })
+                .collect::<Vec<_>>()
+        });
+        for i in 0..LIMBS - 1 {
+            region.assign_advice(
+                || "borrow",
+                config.main[8 + i],
+                0,
+                || borrows.as_ref().map(|borrows| borrows[i]),
+            )?;
+        }
+        cells.push(region.assign_advice(
+            || "native",
+            config.main[11],
+            0,
+            || value.map(|value| big_to_fr(&fq_to_big(&value).into())),
+        )?);
+        Ok(cells)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum AssignedIntegerOrZero<'a> {
+    Integer(&'a AssignedInteger),
+    Zero,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_proofs::circuit::SimpleFloorPlanner;
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::plonk::{Circuit, Instance};
+
+    /// Computes `a * (b - b') + c1 - c2` and asserts `a * a^-1 - 1` is zero,
+    /// exposing the result in the scalar field.
+    #[derive(Default)]
+    struct TestCircuit {
+        values: [Fq; 5],
+    }
+
+    impl Circuit<Fr> for TestCircuit {
+        type Config = (IntegerConfig, Column<Instance>);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(cs: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let config = IntegerConfig::configure(cs);
+            let constants = cs.fixed_column();
+            cs.enable_constant(constants);
+            let instance = cs.instance_column();
+            cs.enable_equality(instance);
+            (config, instance)
+        }
+
+        fn synthesize(
+            &self,
+            (config, instance): Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let chip = IntegerChip::new(config);
+            chip.load_range_table(&mut layouter)?;
+            let [a, b, b_sub, c1, c2] = self
+                .values
+                .map(|value| chip.assign(&mut layouter, Value::known(value)));
+            let (a, b, b_sub, c1, c2) = (a?, b?, b_sub?, c1?, c2?);
+            let r = chip.mul_add(
+                &mut layouter,
+                &a,
+                &b,
+                Some(&b_sub),
+                &[Term::Add(&c1), Term::Sub(&c2)],
+            )?;
+            let native = chip.canonical(&mut layouter, &r)?;
+            layouter.constrain_instance(native.cell(), instance, 0)?;
+
+            let inverse = chip.assign(&mut layouter, a.value.map(|a| a.invert().unwrap()))?;
+            let one = chip.constant(&mut layouter, Fq::one())?;
+            chip.assert_mul_add_zero(&mut layouter, &a, &inverse, None, &[Term::Sub(&one)])
+        }
+    }
+
+    #[test]
+    fn multiplies_base_field_elements() {
+        let values = [
+            -Fq::from(3),
+            -Fq::one(),
+            Fq::from(5),
+            -Fq::from(7),
+            Fq::from(11),
+        ];
+        let r = values[0] * (values[1] - values[2]) + values[3] - values[4];
+        let native = big_to_fr(&fq_to_big(&r).into());
+
+        let circuit = TestCircuit { values };
+        let prover = MockProver::run(18, &circuit, vec![vec![native]]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let prover = MockProver::run(18, &circuit, vec![vec![native + Fr::one()]]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
@@ -0,0 +1,457 @@
+//! The aggregation circuit, verifying proofs of the main circuit in-circuit
+//! so that a single proof of it and one pairing check them all.
+//!
+//! The circuit verifies each proof up to the final pairing of SHPLONK, which
+//! leaves two points `left` and `right` with `e(left, s_g2) = e(right, g2)`.
+//! The points of the proofs are folded with the powers of a challenge of all
+//! of them into the [`Accumulator`], the first public inputs of the circuit,
+//! and the public inputs of the proofs follow. A verifier of the aggregation
+//! proof checks the pairing of the accumulator on top of it.
+//!
+//! The proofs must be made with the Poseidon transcript, the one the circuit
+//! computes: points are checked and absorbed with the non-native arithmetic
+//! of the base field, and the scalars, challenges and the evaluation of the
+//! constraints are computed over the scalar field.
+//!
+//! A proof of the main circuit takes about 150k rows, the range table of the
+//! non-native arithmetic `2^17`: one proof is aggregated with `k = 18`, more
+//! take larger `k`.
+
+mod accumulator;
+mod ecc;
+mod integer;
+mod native;
+mod transcript;
+mod verifier;
+
+pub use accumulator::{Accumulator, ACCUMULATOR_INSTANCES};
+
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::halo2curves::bn256::{Bn256, Fr, G1Affine, G1};
+use halo2_proofs::halo2curves::group::Curve;
+use halo2_proofs::plonk::{
+    verify_proof, Circuit, Column, ConstraintSystem, Error, Instance, VerifyingKey,
+};
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsVerifierKZG};
+use halo2_proofs::poly::kzg::multiopen::VerifierSHPLONK;
+use halo2_proofs::poly::kzg::strategy::DeferredStrategy;
+use halo2_proofs::transcript::{
+    Challenge255, EncodedChallenge, PoseidonRead, Transcript, TranscriptReadBuffer,
+};
+
+use crate::circuit::CircuitKind;
+use crate::fingerprint::{check_fingerprint, fingerprint};
+use crate::transcript::TranscriptKind;
+use crate::verify::{read_instances, read_params, read_vk, VerifyError};
+use crate::witness::{ProofWitness, VerifierKey};
+use accumulator::LIMBS;
+use ecc::{Base, EccChip, EccConfig};
+use integer::IntegerConfig;
+use native::{NativeChip, NativeConfig};
+use transcript::{TranscriptChip, TranscriptConfig, T};
+use verifier::{Protocol, Verifier};
+
+/// Circuit id recorded in the proof bundles of [`AggregationCircuit`].
+pub const CIRCUIT_ID: &str = "bs2-aggregation";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AggregationError {
+    /// The key isn't one of the main circuit, the only one aggregated: the
+    /// accumulator of an aggregation proof would be dropped.
+    UnsupportedCircuit(CircuitKind),
+    /// A proof isn't made with the Poseidon transcript, the only one the
+    /// circuit computes.
+    UnsupportedTranscript(TranscriptKind),
+    Verify(VerifyError),
+}
+
+impl fmt::Display for AggregationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AggregationError::UnsupportedCircuit(circuit) => {
+                write!(f, "{} proofs can't be aggregated, only main ones", circuit)
+            }
+            AggregationError::UnsupportedTranscript(transcript) => write!(
+                f,
+                "{} proofs can't be aggregated, only poseidon ones",
+                transcript
+            ),
+            AggregationError::Verify(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<VerifyError> for AggregationError {
+    fn from(err: VerifyError) -> Self {
+        Self::Verify(err)
+    }
+}
+
+/// Verifies `proofs` of the circuit of `vk` up to their final pairing,
+/// returning the accumulator the aggregation circuit computes for them.
+///
+/// The proofs aren't checked to verify: the accumulator only passes its
+/// pairing check if they all do.
+pub fn accumulate(
+    params: &ParamsVerifierKZG<Bn256>,
+    vk: &VerifyingKey<G1Affine>,
+    proofs: &[ProofWitness],
+) -> Result<Accumulator, AggregationError> {
+    if proofs.is_empty() {
+        return Err(VerifyError::NoProof.into());
+    }
+    let vk_fingerprint = fingerprint(vk);
+    let mut deferred = vec![];
+    for proof in proofs.iter() {
+        check_fingerprint(&vk_fingerprint, &proof.fingerprint)
+            .map_err(VerifyError::FingerprintMismatch)?;
+        if proof.transcript != TranscriptKind::Poseidon {
+            return Err(AggregationError::UnsupportedTranscript(proof.transcript));
+        }
+        let instances = read_instances(&proof.instances)?;
+        let instances: Vec<&[Fr]> = instances.iter().map(Vec::as_slice).collect();
+        let transcript =
+            &mut <PoseidonRead<_, G1Affine, Challenge255<_>> as TranscriptReadBuffer<_, _, _>>::init(
+                &proof.proof[..],
+            );
+        let msm = verify_proof::<KZGCommitmentScheme<Bn256>, VerifierSHPLONK<'_, Bn256>, _, _, _>(
+            params,
+            vk,
+            DeferredStrategy::new(params),
+            &[&instances],
+            transcript,
+        )
+        .map_err(VerifyError::from)?;
+        deferred.push((msm.eval(), transcript.squeeze_challenge().get_scalar()));
+    }
+
+    let (lhs, rhs) = match deferred.as_slice() {
+        [((lhs, rhs), _)] => (*lhs, *rhs),
+        _ => {
+            let transcript =
+                &mut <PoseidonRead<_, G1Affine, Challenge255<_>> as TranscriptReadBuffer<
+                    _,
+                    _,
+                    _,
+                >>::init(&[][..]);
+            for (_, challenge) in deferred.iter() {
+                transcript
+                    .common_scalar(*challenge)
+                    .map_err(|_| VerifyError::MalformedProof)?;
+            }
+            let r = transcript.squeeze_challenge().get_scalar();
+            let (mut lhs, mut rhs, mut power) = (G1::default(), G1::default(), Fr::one());
+            for ((left, right), _) in deferred.iter() {
+                lhs += *left * power;
+                rhs += *right * power;
+                power *= r;
+            }
+            (lhs.to_affine(), rhs.to_affine())
+        }
+    };
+    Ok(Accumulator { lhs, rhs })
+}
+
+/// A proof the circuit verifies.
+#[derive(Clone, Debug)]
+struct AggregatedProof {
+    /// The number of public inputs of each instance column.
+    rows: Vec<usize>,
+    proof: Value<Vec<u8>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct AggregationConfig {
+    ecc: EccConfig,
+    native: NativeConfig,
+    transcript: TranscriptConfig,
+    instance: Column<Instance>,
+}
+
+/// Verifies Poseidon proofs of the circuit of a verifying key, with the
+/// accumulator and the public inputs of the proofs as public inputs.
+#[derive(Clone, Debug)]
+pub struct AggregationCircuit {
+    protocol: Protocol,
+    proofs: Vec<AggregatedProof>,
+    instances: Vec<Fr>,
+    accumulator: Accumulator,
+}
+
+impl AggregationCircuit {
+    /// The circuit aggregating `proofs` of the circuit of the key.
+    pub fn new(key: &VerifierKey, proofs: &[ProofWitness]) -> Result<Self, AggregationError> {
+        if key.circuit != CircuitKind::Main {
+            return Err(AggregationError::UnsupportedCircuit(key.circuit));
+        }
+        let params = read_params(&key.params)?;
+        let vk = read_vk(key.circuit, &key.vk)?;
+        let accumulator = accumulate(&params, &vk, proofs)?;
+        let protocol = Protocol::new(&vk, params.g[0]);
+
+        let mut instances = accumulator.instances();
+        let mut aggregated = vec![];
+        for proof in proofs.iter() {
+            let columns = read_instances(&proof.instances)?;
+            if columns.len() != protocol.num_instance_columns() {
+                return Err(VerifyError::InstanceMismatch.into());
+            }
+            instances.extend(columns.iter().flatten());
+            aggregated.push(AggregatedProof {
+                rows: columns.iter().map(Vec::len).collect(),
+                proof: Value::known(proof.proof.clone()),
+            });
+        }
+        Ok(Self {
+            protocol,
+            proofs: aggregated,
+            instances,
+            accumulator,
+        })
+    }
+
+    pub fn accumulator(&self) -> Accumulator {
+        self.accumulator
+    }
+
+    /// The public inputs of the circuit: the accumulator, then the public
+    /// inputs of each proof column by column.
+    pub fn instances(&self) -> Vec<Vec<Fr>> {
+        vec![self.instances.clone()]
+    }
+}
+
+impl Circuit<Fr> for AggregationCircuit {
+    type Config = AggregationConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            protocol: self.protocol.clone(),
+            proofs: self
+                .proofs
+                .iter()
+                .map(|proof| AggregatedProof {
+                    rows: proof.rows.clone(),
+                    proof: Value::unknown(),
+                })
+                .collect(),
+            instances: Vec::new(),
+            accumulator: Accumulator::default(),
+        }
+    }
+
+    fn configure(cs: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let integer = IntegerConfig::configure(cs);
+        let ecc = EccConfig::configure(cs, integer);
+        let native = NativeConfig::configure(cs);
+        let state = [(); T].map(|_| cs.advice_column());
+        let partial_sbox = cs.advice_column();
+        let rc_a = [(); T].map(|_| cs.fixed_column());
+        let rc_b = [(); T].map(|_| cs.fixed_column());
+        let constants = cs.fixed_column();
+        let transcript =
+            TranscriptConfig::configure(cs, state, partial_sbox, rc_a, rc_b, constants);
+        let instance = cs.instance_column();
+        cs.enable_equality(instance);
+        AggregationConfig {
+            ecc,
+            native,
+            transcript,
+            instance,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        let layouter = &mut layouter;
+        let native = NativeChip::new(config.native.clone());
+        let mut ecc = EccChip::new(config.ecc.clone(), layouter)?;
+        ecc.integer().load_range_table(layouter)?;
+
+        let mut deferred = vec![];
+        let mut row = ACCUMULATOR_INSTANCES;
+        for proof in self.proofs.iter() {
+            let instances = (row..row + proof.rows.iter().sum::<usize>())
+                .map(|row| native.instance(layouter, config.instance, row))
+                .collect::<Result<Vec<_>, _>>()?;
+            row += instances.len();
+            let verifier = Verifier::new(&self.protocol, &ecc, &native, &config.transcript);
+            deferred.push(verifier.verify(
+                layouter,
+                &instances,
+                &proof.rows,
+                proof.proof.clone(),
+            )?);
+        }
+
+        let (lhs, rhs) = if deferred.len() == 1 {
+            let deferred = deferred.pop().unwrap();
+            let rhs = ecc.msm(layouter, &deferred.terms)?;
+            (deferred.h2, rhs)
+        } else {
+            let mut transcript = TranscriptChip::new(config.transcript.clone(), layouter)?;
+            for deferred in deferred.iter() {
+                transcript.common_scalar(layouter, &deferred.challenge)?;
+            }
+            let r = transcript.squeeze_challenge(layouter)?;
+            let mut power = native.constant(layouter, Fr::one())?;
+            let mut lhs_terms = vec![];
+            let mut terms: Vec<(_, Base)> = vec![];
+            for (i, deferred) in deferred.into_iter().enumerate() {
+                if i > 0 {
+                    power = native.mul(layouter, &power, &r)?;
+                }
+                lhs_terms.push((power.clone(), Base::Assigned(Box::new(deferred.h2))));
+                for (scalar, base) in deferred.terms.into_iter() {
+                    let scalar = if i > 0 {
+                        native.mul(layouter, &scalar, &power)?
+                    } else {
+                        scalar
+                    };
+                    // The points of the key are shared by the proofs.
+                    let merged = match &base {
+                        Base::Constant(point) => terms.iter_mut().find(
+                            |(_, other)| matches!(other, Base::Constant(other) if other == point),
+                        ),
+                        Base::Assigned(_) => None,
+                    };
+                    match merged {
+                        Some((sum, _)) => *sum = native.add(layouter, sum, &scalar)?,
+                        None => terms.push((scalar, base)),
+                    }
+                }
+            }
+            let lhs = ecc.msm(layouter, &lhs_terms)?;
+            let rhs = ecc.msm(layouter, &terms)?;
+            (lhs, rhs)
+        };
+
+        for (i, coordinate) in [&lhs.x, &lhs.y, &rhs.x, &rhs.y].into_iter().enumerate() {
+            ecc.integer().canonical(layouter, coordinate)?;
+            for (j, limb) in coordinate.limbs.iter().enumerate() {
+                layouter.constrain_instance(limb.cell(), config.instance, i * LIMBS + j)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bundle::ProofBundle;
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::poly::kzg::msm::PreparedG2KZG;
+
+    // See `verify::tests`.
+    const PARAMS: &[u8] = include_bytes!("../../testdata/verifier-params.bin");
+    const VK: &[u8] = include_bytes!("../../testdata/vk.bin");
+    const PROOF: &[u8] = include_bytes!("../../testdata/proof.bin");
+    const PROOF_POSEIDON: &[u8] = include_bytes!("../../testdata/proof-poseidon.bin");
+
+    fn proof_of(bundle: &[u8]) -> ProofWitness {
+        let bundle = ProofBundle::decode(bundle).unwrap();
+        ProofWitness {
+            fingerprint: bundle.fingerprint,
+            transcript: bundle.transcript,
+            instances: bundle.instances,
+            proof: bundle.proof,
+        }
+    }
+
+    fn key() -> VerifierKey {
+        VerifierKey {
+            params: PARAMS.to_vec(),
+            vk: VK.to_vec(),
+            circuit: CircuitKind::Main,
+            prepared: vec![],
+        }
+    }
+
+    #[test]
+    fn accumulates_proofs() {
+        let params = read_params(PARAMS).unwrap();
+        let vk = read_vk(CircuitKind::Main, VK).unwrap();
+        let prepared = PreparedG2KZG::new(&params);
+        let proof = proof_of(PROOF_POSEIDON);
+
+        let accumulator = accumulate(&params, &vk, &[proof.clone()]).unwrap();
+        assert!(accumulator.check(&prepared));
+        assert_eq!(
+            Accumulator::from_instances(&accumulator.instances()),
+            Some(accumulator)
+        );
+        let accumulator = accumulate(&params, &vk, &[proof.clone(), proof.clone()]).unwrap();
+        assert!(accumulator.check(&prepared));
+
+        // A valid point in place of the last opening proof.
+        let mut tampered = proof.clone();
+        let last = tampered.proof.len() - 32;
+        tampered.proof.copy_within(..32, last);
+        let accumulator = accumulate(&params, &vk, &[proof, tampered]).unwrap();
+        assert!(!accumulator.check(&prepared));
+
+        assert_eq!(
+            accumulate(&params, &vk, &[proof_of(PROOF)]),
+            Err(AggregationError::UnsupportedTranscript(
+                TranscriptKind::Blake2b
+            ))
+        );
+        assert_eq!(
+            accumulate(&params, &vk, &[]),
+            Err(AggregationError::Verify(VerifyError::NoProof))
+        );
+        // The key of an aggregation circuit.
+        let key = VerifierKey {
+            circuit: CircuitKind::Aggregation,
+            ..key()
+        };
+        assert!(matches!(
+            AggregationCircuit::new(&key, &[proof_of(PROOF_POSEIDON)]),
+            Err(AggregationError::UnsupportedCircuit(
+                CircuitKind::Aggregation
+            ))
+        ));
+    }
+
+    #[test]
+    fn verifies_proof_in_circuit() {
+        let proof = proof_of(PROOF_POSEIDON);
+        let circuit = AggregationCircuit::new(&key(), &[proof.clone()]).unwrap();
+        let instances = circuit.instances();
+        assert_eq!(
+            instances[0][ACCUMULATOR_INSTANCES..],
+            read_instances(&proof.instances).unwrap().concat()[..]
+        );
+        // One prover at a time, each takes about 2 GB.
+        let verify = |circuit: &AggregationCircuit, instances| {
+            MockProver::run(18, circuit, instances).unwrap().verify()
+        };
+        assert_eq!(verify(&circuit, instances.clone()), Ok(()));
+
+        // Another accumulator.
+        let mut other = instances.clone();
+        other[0][0] += Fr::one();
+        assert!(verify(&circuit, other).is_err());
+
+        // A valid point in place of the last opening proof, with the
+        // accumulator it leads to.
+        let mut tampered = proof;
+        let last = tampered.proof.len() - 32;
+        tampered.proof.copy_within(..32, last);
+        let circuit = AggregationCircuit::new(&key(), &[tampered]).unwrap();
+        assert_eq!(verify(&circuit, circuit.instances()), Ok(()));
+        let params = read_params(PARAMS).unwrap();
+        assert!(!circuit.accumulator().check(&PreparedG2KZG::new(&params)));
+        // With the accumulator of the valid proof.
+        assert!(verify(&circuit, instances).is_err());
+    }
+}
@@ -0,0 +1,320 @@
+//! Arithmetic of scalar field elements: the challenges, evaluations and
+//! scalars of the verifier.
+//!
+//! One gate, on every row:
+//!
+//! `q_m * a * b + q_a * a + q_b * b + q_c * c + q_d * d + q_const = 0`
+//!
+//! | a | b | c | d | q_a | q_b | q_c | q_d | q_m | q_const |
+//! |---|---|---|---|-----|-----|-----|-----|-----|---------|
+
+use alloc::vec::Vec;
+use core::convert::TryInto;
+use halo2_proofs::arithmetic::Field;
+use halo2_proofs::circuit::{Layouter, Value};
+use halo2_proofs::halo2curves::bn256::Fr;
+use halo2_proofs::plonk::{Advice, Column, ConstraintSystem, Error, Fixed, Instance};
+use halo2_proofs::poly::Rotation;
+
+use super::integer::AssignedNative;
+
+#[derive(Clone, Debug)]
+pub struct NativeConfig {
+    advice: [Column<Advice>; 4],
+    q: [Column<Fixed>; 4],
+    q_m: Column<Fixed>,
+    q_const: Column<Fixed>,
+}
+
+impl NativeConfig {
+    pub fn configure(cs: &mut ConstraintSystem<Fr>) -> Self {
+        let advice = [(); 4].map(|_| cs.advice_column());
+        for column in advice {
+            cs.enable_equality(column);
+        }
+        let q = [(); 4].map(|_| cs.fixed_column());
+        let q_m = cs.fixed_column();
+        let q_const = cs.fixed_column();
+
+        cs.create_gate("native", |vc| {
+            let advice = advice.map(|column| vc.query_advice(column, Rotation::cur()));
+            let q = q.map(|column| vc.query_fixed(column, Rotation::cur()));
+            let q_m = vc.query_fixed(q_m, Rotation::cur());
+            let q_const = vc.query_fixed(q_const, Rotation::cur());
+            let sum = advice.iter().zip(q.iter()).fold(
+                q_m * advice[0].clone() * advice[1].clone() + q_const,
+                |sum, (a, q)| sum + q.clone() * a.clone(),
+            );
+            [sum]
+        });
+
+        Self {
+            advice,
+            q,
+            q_m,
+            q_const,
+        }
+    }
+}
+
+/// A cell of a row of the gate.
+#[derive(Clone, Copy, Debug)]
+enum Operand<'a> {
+    Cell(&'a AssignedNative),
+    Witness(Value<Fr>),
+    Constant(Fr),
+    Zero,
+}
+
+#[derive(Clone, Debug)]
+pub struct NativeChip {
+    config: NativeConfig,
+}
+
+impl NativeChip {
+    pub fn new(config: NativeConfig) -> Self {
+        Self { config }
+    }
+
+    /// Assigns a row of the gate with its coefficients `[q_a, q_b, q_c, q_d]`.
+    fn row(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        operands: [Operand<'_>; 4],
+        q: [Fr; 4],
+        q_m: Fr,
+        q_const: Fr,
+    ) -> Result<[AssignedNative; 4], Error> {
+        let config = &self.config;
+        layouter.assign_region(
+            || "native",
+            |mut region| {
+                let cells = operands
+                    .iter()
+                    .zip(config.advice.iter())
+                    .map(|(operand, column)| match operand {
+                        Operand::Cell(cell) => cell.copy_advice(|| "cell", &mut region, *column, 0),
+                        Operand::Witness(value) => {
+                            region.assign_advice(|| "witness", *column, 0, || *value)
+                        }
+                        Operand::Constant(value) => {
+                            region.assign_advice_from_constant(|| "constant", *column, 0, *value)
+                        }
+                        Operand::Zero => {
+                            region.assign_advice(|| "zero", *column, 0, || Value::known(Fr::zero()))
+                        }
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                for (column, q) in config.q.iter().zip(q.iter()) {
+                    region.assign_fixed(|| "q", *column, 0, || Value::known(*q))?;
+                }
+                region.assign_fixed(|| "q_m", config.q_m, 0, || Value::known(q_m))?;
+                region.assign_fixed(|| "q_const", config.q_const, 0, || Value::known(q_const))?;
+                Ok(cells.try_into().unwrap())
+            },
+        )
+    }
+
+    pub fn assign(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        value: Value<Fr>,
+    ) -> Result<AssignedNative, Error> {
+        let [cell, ..] = self.row(
+            layouter,
+            [
+                Operand::Witness(value),
+                Operand::Zero,
+                Operand::Zero,
+                Operand::Zero,
+            ],
+            [Fr::zero(); 4],
+            Fr::zero(),
+            Fr::zero(),
+        )?;
+        Ok(cell)
+    }
+
+    /// The public input on the `row` of the instance `column`.
+    pub fn instance(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        column: Column<Instance>,
+        row: usize,
+    ) -> Result<AssignedNative, Error> {
+        let advice = self.config.advice[0];
+        layouter.assign_region(
+            || "native instance",
+            |mut region| region.assign_advice_from_instance(|| "instance", column, row, advice, 0),
+        )
+    }
+
+    pub fn constant(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        value: Fr,
+    ) -> Result<AssignedNative, Error> {
+        let [cell, ..] = self.row(
+            layouter,
+            [
+                Operand::Constant(value),
+                Operand::Zero,
+                Operand::Zero,
+                Operand::Zero,
+            ],
+            [Fr::zero(); 4],
+            Fr::zero(),
+            Fr::zero(),
+        )?;
+        Ok(cell)
+    }
+
+    /// `Σ coefficient * term + constant`.
+    pub fn linear(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        terms: &[(Fr, &AssignedNative)],
+        constant: Fr,
+    ) -> Result<AssignedNative, Error> {
+        // Three terms a row, the sum of a row is the first term of the next.
+        let mut sum = None::<AssignedNative>;
+        let mut terms = terms.iter();
+        let mut constant = Some(constant);
+        loop {
+            let mut operands = [Operand::Zero; 4];
+            let mut q = [Fr::zero(); 4];
+            let mut used = 0;
+            if let Some(sum) = sum.as_ref() {
+                operands[0] = Operand::Cell(sum);
+                q[0] = Fr::one();
+                used = 1;
+            }
+            let mut value = sum
+                .as_ref()
+                .map_or(Value::known(Fr::zero()), |sum| sum.value().copied());
+            while used < 3 {
+                match terms.next() {
+                    Some((coefficient, term)) => {
+                        operands[used] = Operand::Cell(term);
+                        q[used] = *coefficient;
+                        value = value + term.value().map(|term| *term * coefficient);
+                        used += 1;
+                    }
+                    None => break,
+                }
+            }
+            let constant = constant.take().unwrap_or_else(Fr::zero);
+            value = value.map(|value| value + constant);
+            operands[3] = Operand::Witness(value);
+            q[3] = -Fr::one();
+            let [.., out] = self.row(layouter, operands, q, Fr::zero(), constant)?;
+            sum = Some(out);
+            if terms.len() == 0 {
+                return Ok(sum.unwrap());
+            }
+        }
+    }
+
+    pub fn add(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        a: &AssignedNative,
+        b: &AssignedNative,
+    ) -> Result<AssignedNative, Error> {
+        self.linear(layouter, &[(Fr::one(), a), (Fr::one(), b)], Fr::zero())
+    }
+
+    /// `a * b + c`.
+    pub fn mul_add(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        a: &AssignedNative,
+        b: &AssignedNative,
+        c: &AssignedNative,
+    ) -> Result<AssignedNative, Error> {
+        let value = a.value().copied() * b.value() + c.value();
+        let [.., out] = self.row(
+            layouter,
+            [
+                Operand::Cell(a),
+                Operand::Cell(b),
+                Operand::Cell(c),
+                Operand::Witness(value),
+            ],
+            [Fr::zero(), Fr::zero(), Fr::one(), -Fr::one()],
+            Fr::one(),
+            Fr::zero(),
+        )?;
+        Ok(out)
+    }
+
+    pub fn mul(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        a: &AssignedNative,
+        b: &AssignedNative,
+    ) -> Result<AssignedNative, Error> {
+        let value = a.value().copied() * b.value();
+        let [.., out] = self.row(
+            layouter,
+            [
+                Operand::Cell(a),
+                Operand::Cell(b),
+                Operand::Zero,
+                Operand::Witness(value),
+            ],
+            [Fr::zero(), Fr::zero(), Fr::zero(), -Fr::one()],
+            Fr::one(),
+            Fr::zero(),
+        )?;
+        Ok(out)
+    }
+
+    /// `a / b`, for a non-zero `b`.
+    pub fn div(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        a: &AssignedNative,
+        b: &AssignedNative,
+    ) -> Result<AssignedNative, Error> {
+        let value = a
+            .value()
+            .zip(b.value())
+            .map(|(a, b)| *a * b.invert().unwrap_or_else(Fr::zero));
+        let [quotient, ..] = self.row(
+            layouter,
+            [
+                Operand::Witness(value),
+                Operand::Cell(b),
+                Operand::Cell(a),
+                Operand::Zero,
+            ],
+            [Fr::zero(), Fr::zero(), -Fr::one(), Fr::zero()],
+            Fr::one(),
+            Fr::zero(),
+        )?;
+        Ok(quotient)
+    }
+
+    /// The inverse of a non-zero `a`.
+    pub fn invert(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        a: &AssignedNative,
+    ) -> Result<AssignedNative, Error> {
+        let value = a.value().map(|a| a.invert().unwrap_or_else(Fr::zero));
+        let [inverse, ..] = self.row(
+            layouter,
+            [
+                Operand::Witness(value),
+                Operand::Cell(a),
+                Operand::Zero,
+                Operand::Zero,
+            ],
+            [Fr::zero(); 4],
+            Fr::one(),
+            -Fr::one(),
+        )?;
+        Ok(inverse)
+    }
+}
@@ -0,0 +1,358 @@
+//! Poseidon transcript chip, the in-circuit counterpart of `PoseidonRead`.
+//!
+//! The permutation is the one of `halo2_gadgets::poseidon` with the constants
+//! of the native transcript. The sponge is the one of the native transcript
+//! and differs from the `halo2_gadgets` one: the capacity word is the first of
+//! the state, initialized to `2^64`, inputs are added to the next [`RATE`]
+//! words, and a squeeze pads the pending inputs with a one before permuting
+//! and returns the second word.
+
+use alloc::vec::Vec;
+use core::convert::TryInto;
+use halo2_gadgets::poseidon::primitives::Spec;
+use halo2_gadgets::poseidon::{PoseidonInstructions, Pow5Chip, Pow5Config, StateWord};
+use halo2_proofs::arithmetic::{Field, FieldExt};
+use halo2_proofs::circuit::{AssignedCell, Chip, Layouter};
+use halo2_proofs::halo2curves::bn256::Fr;
+use halo2_proofs::plonk::{Advice, Column, ConstraintSystem, Constraints, Error, Fixed, Selector};
+use halo2_proofs::poly::Rotation;
+
+/// Width of the state of the native transcript.
+pub const T: usize = 9;
+pub const RATE: usize = 8;
+pub const R_F: usize = 8;
+pub const R_P: usize = 63;
+
+/// The permutation of the native Poseidon transcript.
+#[derive(Debug)]
+pub struct TranscriptSpec;
+
+impl Spec<Fr, T, RATE> for TranscriptSpec {
+    fn full_rounds() -> usize {
+        R_F
+    }
+
+    fn partial_rounds() -> usize {
+        R_P
+    }
+
+    fn sbox(val: Fr) -> Fr {
+        val.pow_vartime([5])
+    }
+
+    /// The native `Grain` takes the first matrix it samples.
+    fn secure_mds() -> usize {
+        0
+    }
+
+    fn constants() -> (Vec<[Fr; T]>, [[Fr; T]; T], [[Fr; T]; T]) {
+        let (round_constants, mds) = poseidon::Spec::<Fr, T, RATE>::reference(R_F, R_P);
+        (round_constants, mds.rows(), mds.invert().rows())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TranscriptConfig {
+    pow5: Pow5Config<Fr, T, RATE>,
+    state: [Column<Advice>; T],
+    s_absorb: Selector,
+}
+
+impl TranscriptConfig {
+    /// Configures the chip, `constants` holds the initial state and the
+    /// padding.
+    pub fn configure(
+        meta: &mut ConstraintSystem<Fr>,
+        state: [Column<Advice>; T],
+        partial_sbox: Column<Advice>,
+        rc_a: [Column<Fixed>; T],
+        rc_b: [Column<Fixed>; T],
+        constants: Column<Fixed>,
+    ) -> Self {
+        let pow5 = Pow5Chip::configure::<TranscriptSpec>(meta, state, partial_sbox, rc_a, rc_b);
+        meta.enable_constant(constants);
+
+        // The state on the previous row, the inputs on the current one and
+        // the state with the inputs added on the next one.
+        let s_absorb = meta.selector();
+        meta.create_gate("absorb", |meta| {
+            let s_absorb = meta.query_selector(s_absorb);
+            let capacity = meta.query_advice(state[0], Rotation::prev())
+                - meta.query_advice(state[0], Rotation::next());
+            let rate = (1..T).map(|i| {
+                meta.query_advice(state[i], Rotation::prev())
+                    + meta.query_advice(state[i], Rotation::cur())
+                    - meta.query_advice(state[i], Rotation::next())
+            });
+            Constraints::with_selector(
+                s_absorb,
+                core::iter::once(capacity).chain(rate).collect::<Vec<_>>(),
+            )
+        });
+
+        Self {
+            pow5,
+            state,
+            s_absorb,
+        }
+    }
+}
+
+/// Transcript absorbing assigned scalars and squeezing the challenges of the
+/// native transcript.
+#[derive(Debug)]
+pub struct TranscriptChip {
+    config: TranscriptConfig,
+    state: [StateWord<Fr>; T],
+    absorbing: Vec<AssignedCell<Fr, Fr>>,
+}
+
+impl TranscriptChip {
+    pub fn new(config: TranscriptConfig, layouter: &mut impl Layouter<Fr>) -> Result<Self, Error> {
+        let state = layouter.assign_region(
+            || "transcript initial state",
+            |mut region| {
+                (0..T)
+                    .map(|i| {
+                        let value = if i == 0 {
+                            Fr::from_u128(1 << 64)
+                        } else {
+                            Fr::zero()
+                        };
+                        region
+                            .assign_advice_from_constant(|| "state", config.state[i], 0, value)
+                            .map(StateWord::from)
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            },
+        )?;
+        Ok(Self {
+            config,
+            state: state.try_into().unwrap(),
+            absorbing: Vec::new(),
+        })
+    }
+
+    /// Absorbs a scalar, permuting the state when [`RATE`] of them are
+    /// pending.
+    pub fn common_scalar(
+        &mut self,
+        layouter: &mut impl Layouter<Fr>,
+        scalar: &AssignedCell<Fr, Fr>,
+    ) -> Result<(), Error> {
+        self.absorbing.push(scalar.clone());
+        if self.absorbing.len() == RATE {
+            self.absorb(layouter, false)?;
+        }
+        Ok(())
+    }
+
+    /// Squeezes a challenge after absorbing the pending scalars.
+    pub fn squeeze_challenge(
+        &mut self,
+        layouter: &mut impl Layouter<Fr>,
+    ) -> Result<AssignedCell<Fr, Fr>, Error> {
+        self.absorb(layouter, true)?;
+        Ok(self.state[1].clone().into())
+    }
+
+    fn absorb(&mut self, layouter: &mut impl Layouter<Fr>, pad: bool) -> Result<(), Error> {
+        let config = &self.config;
+        let absorbing = core::mem::take(&mut self.absorbing);
+        let state = layouter.assign_region(
+            || "transcript absorb",
+            |mut region| {
+                config.s_absorb.enable(&mut region, 1)?;
+                let mut values = Vec::with_capacity(T);
+                for (i, word) in self.state.iter().enumerate() {
+                    let word: AssignedCell<Fr, Fr> = word.clone().into();
+                    values.push(word.copy_advice(|| "state", &mut region, config.state[i], 0)?);
+                }
+                let mut inputs = Vec::with_capacity(RATE);
+                for i in 0..RATE {
+                    let column = config.state[i + 1];
+                    let input = match absorbing.get(i) {
+                        Some(input) => input.copy_advice(|| "input", &mut region, column, 1)?,
+                        None => {
+                            let padding = if pad && i == absorbing.len() {
+                                Fr::one()
+                            } else {
+                                Fr::zero()
+                            };
+                            region.assign_advice_from_constant(|| "padding", column, 1, padding)?
+                        }
+                    };
+                    inputs.push(input);
+                }
+                (0..T)
+                    .map(|i| {
+                        let value = match i {
+                            0 => values[0].value().copied(),
+                            i => values[i].value().copied() + inputs[i - 1].value(),
+                        };
+                        region
+                            .assign_advice(|| "state", config.state[i], 2, || value)
+                            .map(StateWord::from)
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            },
+        )?;
+        let chip = Pow5Chip::construct(config.pow5.clone());
+        self.state = <Pow5Chip<Fr, T, RATE> as PoseidonInstructions<
+            Fr,
+            TranscriptSpec,
+            T,
+            RATE,
+        >>::permute(&chip, layouter, &state.try_into().unwrap())?;
+        Ok(())
+    }
+}
+
+impl Chip<Fr> for TranscriptChip {
+    type Config = TranscriptConfig;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use halo2_proofs::circuit::{SimpleFloorPlanner, Value};
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::halo2curves::bn256::G1Affine;
+    use halo2_proofs::plonk::{Circuit, Instance};
+    use halo2_proofs::transcript::{
+        Challenge255, EncodedChallenge, PoseidonRead, Transcript, TranscriptReadBuffer,
+    };
+
+    /// Absorbs `scalars`, squeezing a challenge after the number of scalars of
+    /// each of `squeezes`.
+    #[derive(Default)]
+    struct TestCircuit {
+        scalars: Vec<Fr>,
+        squeezes: Vec<usize>,
+    }
+
+    impl Circuit<Fr> for TestCircuit {
+        type Config = (TranscriptConfig, Column<Advice>, Column<Instance>);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self {
+                scalars: self.scalars.iter().map(|_| Fr::zero()).collect(),
+                squeezes: self.squeezes.clone(),
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let state = [(); T].map(|_| meta.advice_column());
+            let partial_sbox = meta.advice_column();
+            let rc_a = [(); T].map(|_| meta.fixed_column());
+            let rc_b = [(); T].map(|_| meta.fixed_column());
+            let constants = meta.fixed_column();
+            let instance = meta.instance_column();
+            meta.enable_equality(instance);
+            let config =
+                TranscriptConfig::configure(meta, state, partial_sbox, rc_a, rc_b, constants);
+            (config, state[0], instance)
+        }
+
+        fn synthesize(
+            &self,
+            (config, scalars, instance): Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let mut chip = TranscriptChip::new(config, &mut layouter)?;
+            let scalars = layouter.assign_region(
+                || "scalars",
+                |mut region| {
+                    self.scalars
+                        .iter()
+                        .enumerate()
+                        .map(|(i, scalar)| {
+                            region.assign_advice(|| "scalar", scalars, i, || Value::known(*scalar))
+                        })
+                        .collect::<Result<Vec<_>, _>>()
+                },
+            )?;
+            let mut absorbed = 0;
+            for (i, squeeze) in self.squeezes.iter().enumerate() {
+                for scalar in scalars[absorbed..*squeeze].iter() {
+                    chip.common_scalar(&mut layouter, scalar)?;
+                }
+                absorbed = *squeeze;
+                let challenge = chip.squeeze_challenge(&mut layouter)?;
+                layouter.constrain_instance(challenge.cell(), instance, i)?;
+            }
+            Ok(())
+        }
+    }
+
+    /// The permutation of [`TranscriptSpec`] with the constants generated by
+    /// `halo2_gadgets`.
+    #[derive(Debug)]
+    struct GeneratedSpec;
+
+    impl Spec<Fr, T, RATE> for GeneratedSpec {
+        fn full_rounds() -> usize {
+            TranscriptSpec::full_rounds()
+        }
+
+        fn partial_rounds() -> usize {
+            TranscriptSpec::partial_rounds()
+        }
+
+        fn sbox(val: Fr) -> Fr {
+            TranscriptSpec::sbox(val)
+        }
+
+        fn secure_mds() -> usize {
+            TranscriptSpec::secure_mds()
+        }
+    }
+
+    #[test]
+    fn secure_mds_generates_the_native_constants() {
+        assert_eq!(GeneratedSpec::constants(), TranscriptSpec::constants());
+    }
+
+    #[test]
+    fn squeezes_native_challenges() {
+        let scalars = (0..20).map(|i| Fr::from(i * i + 7)).collect::<Vec<_>>();
+        // Squeezes with pending scalars, after a full rate, twice in a row.
+        let squeezes = vec![3, 11, 11, 20];
+
+        let mut transcript =
+            <PoseidonRead<_, G1Affine, Challenge255<G1Affine>> as TranscriptReadBuffer<_, _, _>>::init(
+                &[][..],
+            );
+        let mut absorbed = 0;
+        let challenges = squeezes
+            .iter()
+            .map(|squeeze| {
+                for scalar in scalars[absorbed..*squeeze].iter() {
+                    transcript.common_scalar(*scalar).unwrap();
+                }
+                absorbed = *squeeze;
+                transcript.squeeze_challenge().get_scalar()
+            })
+            .collect::<Vec<_>>();
+
+        let circuit = TestCircuit { scalars, squeezes };
+        let prover = MockProver::run(9, &circuit, vec![challenges.clone()]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let mut challenges = challenges;
+        challenges[2] += Fr::one();
+        let prover = MockProver::run(9, &circuit, vec![challenges]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
@@ -0,0 +1,1060 @@
+//! The verifier of a proof in-circuit, following `verify_proof` with SHPLONK
+//! and the Poseidon transcript step by step, as the reference verifier of
+//! `cli export` does, up to the final pairing.
+//!
+//! The commitments of the proof are loaded as points checked to be on the
+//! curve and absorbed by their coordinates, its evaluations as scalars, the
+//! evaluation of the quotient the constraints imply is computed with the
+//! native chip, and the SHPLONK check is left as the terms of the MSM of its
+//! `right` point with `h2` as its `left` one. One more challenge is squeezed
+//! after `h2`, binding the whole proof, to fold several proofs with.
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeSet;
+use alloc::vec;
+use alloc::vec::Vec;
+use halo2_proofs::arithmetic::{Field, FieldExt};
+use halo2_proofs::circuit::{Layouter, Value};
+use halo2_proofs::halo2curves::bn256::{Fr, G1Affine};
+use halo2_proofs::halo2curves::group::ff::PrimeField;
+use halo2_proofs::halo2curves::group::GroupEncoding;
+use halo2_proofs::plonk::{Any, Error, Expression, VerifyingKey};
+
+use super::ecc::{AssignedPoint, Base, EccChip};
+use super::integer::AssignedNative;
+use super::native::NativeChip;
+use super::transcript::{TranscriptChip, TranscriptConfig};
+
+/// A commitment of the proof or of the verifying key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Commitment {
+    Advice(usize),
+    Fixed(usize),
+    PermutationCommon(usize),
+    PermutationProduct(usize),
+    LookupPermutedInput(usize),
+    LookupPermutedTable(usize),
+    LookupProduct(usize),
+    VanishingH,
+    VanishingRandom,
+}
+
+/// A column of the permutation, by its query at the current row.
+#[derive(Clone, Copy, Debug)]
+enum PermutationColumn {
+    Advice(usize),
+    Fixed(usize),
+    Instance(usize),
+}
+
+/// The input and table expressions of a lookup.
+type Lookup = (Vec<Expression<Fr>>, Vec<Expression<Fr>>);
+
+/// What the verifier needs of a verifying key and of the params.
+#[derive(Clone, Debug)]
+pub struct Protocol {
+    k: u32,
+    omega: Fr,
+    omega_inv: Fr,
+    transcript_repr: Fr,
+    blinding_factors: usize,
+    quotient_poly_degree: usize,
+    g: G1Affine,
+    advice_phases: Vec<u8>,
+    challenge_phases: Vec<u8>,
+    advice_queries: Vec<(usize, i32)>,
+    fixed_queries: Vec<(usize, i32)>,
+    instance_queries: Vec<(usize, i32)>,
+    num_instance_columns: usize,
+    permutation_columns: Vec<PermutationColumn>,
+    chunk_len: usize,
+    permutation_commitments: Vec<G1Affine>,
+    fixed_commitments: Vec<G1Affine>,
+    lookups: Vec<Lookup>,
+    gates: Vec<Expression<Fr>>,
+    /// The sets of rotations of the multiopen argument with the commitments
+    /// opened at each, in the order of the verifier.
+    sets: Vec<(Vec<i32>, Vec<Commitment>)>,
+    rotations: Vec<i32>,
+}
+
+impl Protocol {
+    /// The protocol of `vk`, with `g` the first point of the params.
+    pub fn new(vk: &VerifyingKey<G1Affine>, g: G1Affine) -> Self {
+        let cs = vk.cs();
+        let domain = vk.get_domain();
+        let advice_queries = cs
+            .advice_queries()
+            .iter()
+            .map(|(column, rotation)| (column.index(), rotation.0))
+            .collect::<Vec<_>>();
+        let fixed_queries = cs
+            .fixed_queries()
+            .iter()
+            .map(|(column, rotation)| (column.index(), rotation.0))
+            .collect::<Vec<_>>();
+        let instance_queries = cs
+            .instance_queries()
+            .iter()
+            .map(|(column, rotation)| (column.index(), rotation.0))
+            .collect::<Vec<_>>();
+        let current = |queries: &[(usize, i32)], column: usize| {
+            queries
+                .iter()
+                .position(|query| *query == (column, 0))
+                .expect("permutation columns are queried at the current row")
+        };
+        let permutation_columns = cs
+            .permutation()
+            .get_columns()
+            .iter()
+            .map(|column| match column.column_type() {
+                Any::Advice(_) => {
+                    PermutationColumn::Advice(current(&advice_queries, column.index()))
+                }
+                Any::Fixed => PermutationColumn::Fixed(current(&fixed_queries, column.index())),
+                Any::Instance => {
+                    PermutationColumn::Instance(current(&instance_queries, column.index()))
+                }
+            })
+            .collect();
+
+        let mut protocol = Self {
+            k: domain.k(),
+            omega: domain.get_omega(),
+            omega_inv: domain.get_omega_inv(),
+            transcript_repr: vk.transcript_repr(),
+            blinding_factors: cs.blinding_factors(),
+            quotient_poly_degree: domain.get_quotient_poly_degree(),
+            g,
+            advice_phases: cs.advice_column_phase(),
+            challenge_phases: cs.challenge_phase(),
+            advice_queries,
+            fixed_queries,
+            instance_queries,
+            num_instance_columns: cs.num_instance_columns(),
+            permutation_columns,
+            chunk_len: cs.degree() - 2,
+            permutation_commitments: vk.permutation().commitments().clone(),
+            fixed_commitments: vk.fixed_commitments().clone(),
+            lookups: cs
+                .lookups()
+                .iter()
+                .map(|lookup| {
+                    (
+                        lookup.input_expressions().clone(),
+                        lookup.table_expressions().clone(),
+                    )
+                })
+                .collect(),
+            gates: cs
+                .gates()
+                .iter()
+                .flat_map(|gate| gate.polynomials().iter().cloned())
+                .collect(),
+            sets: vec![],
+            rotations: vec![],
+        };
+        protocol.layout_sets();
+        protocol
+    }
+
+    pub fn num_instance_columns(&self) -> usize {
+        self.num_instance_columns
+    }
+
+    fn permutation_products(&self) -> usize {
+        self.permutation_columns.chunks(self.chunk_len).count()
+    }
+
+    /// Rotation of the last usable row.
+    fn last_rotation(&self) -> i32 {
+        -(self.blinding_factors as i32 + 1)
+    }
+
+    /// Groups the openings of the verifier into the sets of rotations of the
+    /// multiopen argument.
+    fn layout_sets(&mut self) {
+        let last = self.last_rotation();
+        let products = self.permutation_products();
+        let mut openings = vec![];
+        openings.extend(
+            self.advice_queries
+                .iter()
+                .map(|(column, rotation)| (Commitment::Advice(*column), *rotation)),
+        );
+        for i in 0..products {
+            openings.push((Commitment::PermutationProduct(i), 0));
+            openings.push((Commitment::PermutationProduct(i), 1));
+        }
+        for i in (0..products).rev().skip(1) {
+            openings.push((Commitment::PermutationProduct(i), last));
+        }
+        for i in 0..self.lookups.len() {
+            openings.push((Commitment::LookupProduct(i), 0));
+            openings.push((Commitment::LookupPermutedInput(i), 0));
+            openings.push((Commitment::LookupPermutedTable(i), 0));
+            openings.push((Commitment::LookupPermutedInput(i), -1));
+            openings.push((Commitment::LookupProduct(i), 1));
+        }
+        openings.extend(
+            self.fixed_queries
+                .iter()
+                .map(|(column, rotation)| (Commitment::Fixed(*column), *rotation)),
+        );
+        openings.extend(
+            (0..self.permutation_commitments.len()).map(|i| (Commitment::PermutationCommon(i), 0)),
+        );
+        openings.push((Commitment::VanishingH, 0));
+        openings.push((Commitment::VanishingRandom, 0));
+
+        let mut commitments: Vec<(Commitment, BTreeSet<i32>)> = vec![];
+        for (commitment, rotation) in openings.iter() {
+            match commitments
+                .iter_mut()
+                .find(|(other, _)| other == commitment)
+            {
+                Some((_, rotations)) => {
+                    rotations.insert(*rotation);
+                }
+                None => commitments.push((*commitment, BTreeSet::from([*rotation]))),
+            }
+        }
+        let mut sets: Vec<(BTreeSet<i32>, Vec<Commitment>)> = vec![];
+        for (commitment, rotations) in commitments {
+            match sets.iter_mut().find(|(set, _)| *set == rotations) {
+                Some((_, commitments)) => commitments.push(commitment),
+                None => sets.push((rotations, vec![commitment])),
+            }
+        }
+        self.rotations = openings
+            .iter()
+            .map(|(_, rotation)| *rotation)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        self.sets = sets
+            .into_iter()
+            .map(|(rotations, commitments)| (rotations.into_iter().collect(), commitments))
+            .collect();
+    }
+
+    /// `omega^rotation`.
+    fn omega_pow(&self, rotation: i32) -> Fr {
+        if rotation >= 0 {
+            self.omega.pow_vartime([rotation as u64])
+        } else {
+            self.omega_inv.pow_vartime([rotation.unsigned_abs() as u64])
+        }
+    }
+}
+
+/// A value of the verifier, a constant or an assigned cell.
+#[derive(Clone, Debug)]
+enum Eval {
+    Constant(Fr),
+    Cell(AssignedNative),
+}
+
+impl From<&AssignedNative> for Eval {
+    fn from(cell: &AssignedNative) -> Self {
+        Eval::Cell(cell.clone())
+    }
+}
+
+/// The commitments and evaluations of a proof.
+#[derive(Default)]
+struct Proof {
+    advice: Vec<AssignedPoint>,
+    lookup_permuted: Vec<(AssignedPoint, AssignedPoint)>,
+    permutation_products: Vec<AssignedPoint>,
+    lookup_products: Vec<AssignedPoint>,
+    vanishing_random: Option<AssignedPoint>,
+    vanishing_h: Vec<AssignedPoint>,
+    advice_evals: Vec<AssignedNative>,
+    fixed_evals: Vec<AssignedNative>,
+    instance_evals: Vec<Eval>,
+    vanishing_random_eval: Option<AssignedNative>,
+    permutation_evals: Vec<AssignedNative>,
+    /// At `x`, `x * omega` and the last usable row.
+    permutation_product_evals: Vec<(AssignedNative, AssignedNative, Option<AssignedNative>)>,
+    /// The product at `x` and `x * omega`, the permuted input at `x` and
+    /// `x / omega` and the permuted table at `x`.
+    lookup_evals: Vec<[AssignedNative; 5]>,
+    challenges: Vec<AssignedNative>,
+}
+
+/// What verifying a proof leaves to the final pairing.
+#[derive(Debug)]
+pub struct Deferred {
+    /// The terms of the `right` point.
+    pub terms: Vec<(AssignedNative, Base)>,
+    /// The `left` point.
+    pub h2: AssignedPoint,
+    /// The challenge squeezed after `h2`.
+    pub challenge: AssignedNative,
+}
+
+/// Reads the commitments and evaluations of a proof in order.
+struct ProofReader {
+    proof: Value<Vec<u8>>,
+    offset: usize,
+}
+
+impl ProofReader {
+    fn read(&mut self) -> Value<[u8; 32]> {
+        let offset = self.offset;
+        self.offset += 32;
+        self.proof.as_ref().map(|proof| {
+            let mut bytes = [0; 32];
+            if let Some(read) = proof.get(offset..offset + 32) {
+                bytes.copy_from_slice(read);
+            }
+            bytes
+        })
+    }
+
+    /// A point of the proof, the generator if it isn't one.
+    fn read_point(&mut self) -> Value<G1Affine> {
+        self.read().map(|bytes| {
+            let mut repr = <G1Affine as GroupEncoding>::Repr::default();
+            repr.as_mut().copy_from_slice(&bytes);
+            Option::from(G1Affine::from_bytes(&repr)).unwrap_or_else(G1Affine::generator)
+        })
+    }
+
+    /// A scalar of the proof, zero if it isn't one.
+    fn read_scalar(&mut self) -> Value<Fr> {
+        self.read()
+            .map(|bytes| Option::from(Fr::from_repr(bytes)).unwrap_or_else(Fr::zero))
+    }
+}
+
+pub struct Verifier<'a> {
+    protocol: &'a Protocol,
+    ecc: &'a EccChip,
+    native: &'a NativeChip,
+    transcript: &'a TranscriptConfig,
+}
+
+impl<'a> Verifier<'a> {
+    pub fn new(
+        protocol: &'a Protocol,
+        ecc: &'a EccChip,
+        native: &'a NativeChip,
+        transcript: &'a TranscriptConfig,
+    ) -> Self {
+        Self {
+            protocol,
+            ecc,
+            native,
+            transcript,
+        }
+    }
+
+    /// Verifies `proof` for the public inputs `instances`, column by column
+    /// with `rows` rows each, up to the final pairing.
+    pub fn verify(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        instances: &[AssignedNative],
+        rows: &[usize],
+        proof: Value<Vec<u8>>,
+    ) -> Result<Deferred, Error> {
+        let protocol = self.protocol;
+        let native = self.native;
+        let transcript = &mut TranscriptChip::new(self.transcript.clone(), layouter)?;
+        let reader = &mut ProofReader { proof, offset: 0 };
+        let mut proof = Proof::default();
+
+        let repr = native.constant(layouter, protocol.transcript_repr)?;
+        transcript.common_scalar(layouter, &repr)?;
+        for instance in instances.iter() {
+            transcript.common_scalar(layouter, instance)?;
+        }
+
+        let phases = protocol
+            .advice_phases
+            .iter()
+            .max()
+            .copied()
+            .unwrap_or_default();
+        let mut advice = vec![None; protocol.advice_phases.len()];
+        let mut challenges = vec![None; protocol.challenge_phases.len()];
+        for current in 0..=phases {
+            for (phase, commitment) in protocol.advice_phases.iter().zip(advice.iter_mut()) {
+                if *phase == current {
+                    *commitment = Some(self.read_point(layouter, transcript, reader)?);
+                }
+            }
+            for (phase, challenge) in protocol.challenge_phases.iter().zip(challenges.iter_mut()) {
+                if *phase == current {
+                    *challenge = Some(transcript.squeeze_challenge(layouter)?);
+                }
+            }
+        }
+        proof.advice = advice.into_iter().map(Option::unwrap).collect();
+        proof.challenges = challenges.into_iter().map(Option::unwrap).collect();
+
+        let theta = transcript.squeeze_challenge(layouter)?;
+        for _ in protocol.lookups.iter() {
+            let input = self.read_point(layouter, transcript, reader)?;
+            let table = self.read_point(layouter, transcript, reader)?;
+            proof.lookup_permuted.push((input, table));
+        }
+        let beta = transcript.squeeze_challenge(layouter)?;
+        let gamma = transcript.squeeze_challenge(layouter)?;
+        for _ in 0..protocol.permutation_products() {
+            proof
+                .permutation_products
+                .push(self.read_point(layouter, transcript, reader)?);
+        }
+        for _ in protocol.lookups.iter() {
+            proof
+                .lookup_products
+                .push(self.read_point(layouter, transcript, reader)?);
+        }
+        proof.vanishing_random = Some(self.read_point(layouter, transcript, reader)?);
+        let y = transcript.squeeze_challenge(layouter)?;
+        for _ in 0..protocol.quotient_poly_degree {
+            proof
+                .vanishing_h
+                .push(self.read_point(layouter, transcript, reader)?);
+        }
+        let x = transcript.squeeze_challenge(layouter)?;
+
+        for _ in protocol.advice_queries.iter() {
+            proof
+                .advice_evals
+                .push(self.read_scalar(layouter, transcript, reader)?);
+        }
+        for _ in protocol.fixed_queries.iter() {
+            proof
+                .fixed_evals
+                .push(self.read_scalar(layouter, transcript, reader)?);
+        }
+        proof.vanishing_random_eval = Some(self.read_scalar(layouter, transcript, reader)?);
+        for _ in protocol.permutation_commitments.iter() {
+            proof
+                .permutation_evals
+                .push(self.read_scalar(layouter, transcript, reader)?);
+        }
+        let products = protocol.permutation_products();
+        for i in 0..products {
+            let eval = self.read_scalar(layouter, transcript, reader)?;
+            let next_eval = self.read_scalar(layouter, transcript, reader)?;
+            let last_eval = if i + 1 < products {
+                Some(self.read_scalar(layouter, transcript, reader)?)
+            } else {
+                None
+            };
+            proof
+                .permutation_product_evals
+                .push((eval, next_eval, last_eval));
+        }
+        for _ in protocol.lookups.iter() {
+            let mut evals = Vec::with_capacity(5);
+            for _ in 0..5 {
+                evals.push(self.read_scalar(layouter, transcript, reader)?);
+            }
+            proof.lookup_evals.push(evals.try_into().unwrap());
+        }
+
+        let mut xn = x.clone();
+        for _ in 0..protocol.k {
+            xn = native.mul(layouter, &xn, &xn)?;
+        }
+        proof.instance_evals = self.instance_evals(layouter, instances, rows, &x, &xn)?;
+        let challenges = Challenges {
+            theta,
+            beta,
+            gamma,
+            y,
+            x,
+            xn,
+        };
+        let expected_h = self.expected_h(layouter, &proof, &challenges)?;
+
+        let shplonk_y = transcript.squeeze_challenge(layouter)?;
+        let v = transcript.squeeze_challenge(layouter)?;
+        let h1 = self.read_point(layouter, transcript, reader)?;
+        let u = transcript.squeeze_challenge(layouter)?;
+        let h2 = self.read_point(layouter, transcript, reader)?;
+        let challenge = transcript.squeeze_challenge(layouter)?;
+
+        let terms = self.multiopen(
+            layouter,
+            &proof,
+            &challenges,
+            &expected_h,
+            [&shplonk_y, &v, &u],
+            &h1,
+            &h2,
+        )?;
+        Ok(Deferred {
+            terms,
+            h2,
+            challenge,
+        })
+    }
+
+    /// Loads a point of the proof and absorbs it.
+    fn read_point(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        transcript: &mut TranscriptChip,
+        reader: &mut ProofReader,
+    ) -> Result<AssignedPoint, Error> {
+        let (point, coordinates) = self.ecc.load_point(layouter, reader.read_point())?;
+        for coordinate in coordinates.iter() {
+            transcript.common_scalar(layouter, coordinate)?;
+        }
+        Ok(point)
+    }
+
+    /// Assigns a scalar of the proof and absorbs it.
+    fn read_scalar(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        transcript: &mut TranscriptChip,
+        reader: &mut ProofReader,
+    ) -> Result<AssignedNative, Error> {
+        let scalar = self.native.assign(layouter, reader.read_scalar())?;
+        transcript.common_scalar(layouter, &scalar)?;
+        Ok(scalar)
+    }
+
+    /// `Σ coefficient * term + constant`, a constant if all terms are.
+    fn linear(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        terms: &[(Fr, &Eval)],
+        constant: Fr,
+    ) -> Result<Eval, Error> {
+        let mut constant = constant;
+        let mut cells = vec![];
+        for (coefficient, term) in terms.iter() {
+            match term {
+                Eval::Constant(value) => constant += *coefficient * value,
+                Eval::Cell(cell) => cells.push((*coefficient, cell)),
+            }
+        }
+        Ok(match cells.as_slice() {
+            [] => Eval::Constant(constant),
+            [(coefficient, cell)] if *coefficient == Fr::one() && constant == Fr::zero() => {
+                Eval::Cell((*cell).clone())
+            }
+            _ => Eval::Cell(self.native.linear(layouter, &cells, constant)?),
+        })
+    }
+
+    fn add(&self, layouter: &mut impl Layouter<Fr>, a: &Eval, b: &Eval) -> Result<Eval, Error> {
+        self.linear(layouter, &[(Fr::one(), a), (Fr::one(), b)], Fr::zero())
+    }
+
+    fn sub(&self, layouter: &mut impl Layouter<Fr>, a: &Eval, b: &Eval) -> Result<Eval, Error> {
+        self.linear(layouter, &[(Fr::one(), a), (-Fr::one(), b)], Fr::zero())
+    }
+
+    fn mul(&self, layouter: &mut impl Layouter<Fr>, a: &Eval, b: &Eval) -> Result<Eval, Error> {
+        match (a, b) {
+            (Eval::Constant(a), Eval::Constant(b)) => Ok(Eval::Constant(*a * b)),
+            (Eval::Constant(constant), other) | (other, Eval::Constant(constant)) => {
+                self.linear(layouter, &[(*constant, other)], Fr::zero())
+            }
+            (Eval::Cell(a), Eval::Cell(b)) => Ok(Eval::Cell(self.native.mul(layouter, a, b)?)),
+        }
+    }
+
+    /// `a * b + c`.
+    fn mul_add(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        a: &Eval,
+        b: &Eval,
+        c: &Eval,
+    ) -> Result<Eval, Error> {
+        match (a, b, c) {
+            (Eval::Cell(a), Eval::Cell(b), Eval::Cell(c)) => {
+                Ok(Eval::Cell(self.native.mul_add(layouter, a, b, c)?))
+            }
+            _ => {
+                let product = self.mul(layouter, a, b)?;
+                self.add(layouter, &product, c)
+            }
+        }
+    }
+
+    /// The value as an assigned cell.
+    fn cell(&self, layouter: &mut impl Layouter<Fr>, eval: &Eval) -> Result<AssignedNative, Error> {
+        match eval {
+            Eval::Constant(value) => self.native.constant(layouter, *value),
+            Eval::Cell(cell) => Ok(cell.clone()),
+        }
+    }
+
+    fn product(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        factors: impl IntoIterator<Item = Eval>,
+    ) -> Result<Eval, Error> {
+        factors
+            .into_iter()
+            .try_fold(Eval::Constant(Fr::one()), |product, factor| {
+                self.mul(layouter, &product, &factor)
+            })
+    }
+
+    /// The Lagrange polynomial of the row `i` at `x`,
+    /// `omega^i (x^n - 1) / (n (x - omega^i))`.
+    fn lagrange(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        i: i32,
+        x: &AssignedNative,
+        xn: &AssignedNative,
+    ) -> Result<AssignedNative, Error> {
+        let point = self.protocol.omega_pow(i);
+        let scale = point * Fr::from(1u64 << self.protocol.k).invert().unwrap();
+        let numerator = self.native.linear(layouter, &[(scale, xn)], -scale)?;
+        let denominator = self.native.linear(layouter, &[(Fr::one(), x)], -point)?;
+        self.native.div(layouter, &numerator, &denominator)
+    }
+
+    /// The evaluations of the instance queries, from the public inputs.
+    fn instance_evals(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        instances: &[AssignedNative],
+        rows: &[usize],
+        x: &AssignedNative,
+        xn: &AssignedNative,
+    ) -> Result<Vec<Eval>, Error> {
+        let mut columns = vec![];
+        let mut first = 0;
+        for rows in rows.iter() {
+            columns.push(&instances[first..first + rows]);
+            first += rows;
+        }
+        let mut evals = vec![];
+        for (column, rotation) in self.protocol.instance_queries.iter() {
+            let mut eval = Eval::Constant(Fr::zero());
+            for (row, value) in columns[*column].iter().enumerate() {
+                let lagrange = self.lagrange(layouter, row as i32 - rotation, x, xn)?;
+                let term = self.native.mul(layouter, value, &lagrange)?;
+                eval = self.add(layouter, &eval, &Eval::Cell(term))?;
+            }
+            evals.push(eval);
+        }
+        Ok(evals)
+    }
+
+    fn evaluate(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        expression: &Expression<Fr>,
+        proof: &Proof,
+    ) -> Result<Eval, Error> {
+        Ok(match expression {
+            Expression::Constant(constant) => Eval::Constant(*constant),
+            Expression::Selector(_) => {
+                panic!("virtual selectors are removed during optimization")
+            }
+            Expression::Fixed(query) => Eval::from(&proof.fixed_evals[query.index()]),
+            Expression::Advice(query) => Eval::from(&proof.advice_evals[query.index()]),
+            Expression::Instance(query) => proof.instance_evals[query.index()].clone(),
+            Expression::Challenge(challenge) => Eval::from(&proof.challenges[challenge.index()]),
+            Expression::Negated(a) => {
+                let a = self.evaluate(layouter, a, proof)?;
+                self.linear(layouter, &[(-Fr::one(), &a)], Fr::zero())?
+            }
+            Expression::Sum(a, b) => {
+                let a = self.evaluate(layouter, a, proof)?;
+                let b = self.evaluate(layouter, b, proof)?;
+                self.add(layouter, &a, &b)?
+            }
+            Expression::Product(a, b) => {
+                let a = self.evaluate(layouter, a, proof)?;
+                let b = self.evaluate(layouter, b, proof)?;
+                self.mul(layouter, &a, &b)?
+            }
+            Expression::Scaled(a, scalar) => {
+                let a = self.evaluate(layouter, a, proof)?;
+                self.linear(layouter, &[(*scalar, &a)], Fr::zero())?
+            }
+        })
+    }
+
+    /// The evaluation of the quotient at `x` the constraints imply: the gates,
+    /// the permutation and the lookups folded with `y`, over the vanishing
+    /// polynomial.
+    fn expected_h(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        proof: &Proof,
+        challenges: &Challenges,
+    ) -> Result<AssignedNative, Error> {
+        let protocol = self.protocol;
+        let Challenges {
+            theta,
+            beta,
+            gamma,
+            x,
+            xn,
+            ..
+        } = challenges;
+        let (theta, beta, gamma) = (Eval::from(theta), Eval::from(beta), Eval::from(gamma));
+        let one = Eval::Constant(Fr::one());
+        let blinding_factors = protocol.blinding_factors as i32;
+        let l_last = Eval::Cell(self.lagrange(layouter, -(blinding_factors + 1), x, xn)?);
+        let mut l_blind = Eval::Constant(Fr::zero());
+        for i in -blinding_factors..0 {
+            let l = Eval::Cell(self.lagrange(layouter, i, x, xn)?);
+            l_blind = self.add(layouter, &l_blind, &l)?;
+        }
+        let l_0 = Eval::Cell(self.lagrange(layouter, 0, x, xn)?);
+        let active_rows = self.linear(
+            layouter,
+            &[(-Fr::one(), &l_last), (-Fr::one(), &l_blind)],
+            Fr::one(),
+        )?;
+
+        let mut expressions = vec![];
+        for gate in protocol.gates.iter() {
+            expressions.push(self.evaluate(layouter, gate, proof)?);
+        }
+
+        let sets = &proof.permutation_product_evals;
+        if let Some((first, _, _)) = sets.first() {
+            let term = self.sub(layouter, &one, &first.into())?;
+            expressions.push(self.mul(layouter, &l_0, &term)?);
+        }
+        if let Some((last, _, _)) = sets.last() {
+            let square = self.mul(layouter, &last.into(), &last.into())?;
+            let term = self.sub(layouter, &square, &last.into())?;
+            expressions.push(self.mul(layouter, &l_last, &term)?);
+        }
+        for (set, previous) in sets.iter().skip(1).zip(sets.iter()) {
+            let previous_last = previous.2.as_ref().unwrap();
+            let term = self.sub(layouter, &(&set.0).into(), &previous_last.into())?;
+            expressions.push(self.mul(layouter, &l_0, &term)?);
+        }
+        let beta_x = self.mul(layouter, &beta, &x.into())?;
+        for (chunk, ((set, columns), evals)) in sets
+            .iter()
+            .zip(protocol.permutation_columns.chunks(protocol.chunk_len))
+            .zip(proof.permutation_evals.chunks(protocol.chunk_len))
+            .enumerate()
+        {
+            let mut left = Eval::from(&set.1);
+            let mut right = Eval::from(&set.0);
+            let mut delta = Fr::DELTA.pow_vartime([(chunk * protocol.chunk_len) as u64]);
+            for (column, permutation_eval) in columns.iter().zip(evals.iter()) {
+                let eval = match *column {
+                    PermutationColumn::Advice(query) => Eval::from(&proof.advice_evals[query]),
+                    PermutationColumn::Fixed(query) => Eval::from(&proof.fixed_evals[query]),
+                    PermutationColumn::Instance(query) => proof.instance_evals[query].clone(),
+                };
+                let permuted = self.mul(layouter, &beta, &permutation_eval.into())?;
+                let factor = self.linear(
+                    layouter,
+                    &[
+                        (Fr::one(), &eval),
+                        (Fr::one(), &permuted),
+                        (Fr::one(), &gamma),
+                    ],
+                    Fr::zero(),
+                )?;
+                left = self.mul(layouter, &left, &factor)?;
+                let factor = self.linear(
+                    layouter,
+                    &[(Fr::one(), &eval), (delta, &beta_x), (Fr::one(), &gamma)],
+                    Fr::zero(),
+                )?;
+                right = self.mul(layouter, &right, &factor)?;
+                delta *= Fr::DELTA;
+            }
+            let term = self.sub(layouter, &left, &right)?;
+            expressions.push(self.mul(layouter, &term, &active_rows)?);
+        }
+
+        for ((input_expressions, table_expressions), evals) in
+            protocol.lookups.iter().zip(proof.lookup_evals.iter())
+        {
+            let [product, product_next, input, input_inv, table] =
+                [0, 1, 2, 3, 4].map(|i| Eval::from(&evals[i]));
+            let mut compressed = vec![];
+            for expressions in [input_expressions, table_expressions] {
+                let mut acc = Eval::Constant(Fr::zero());
+                for expression in expressions.iter() {
+                    let eval = self.evaluate(layouter, expression, proof)?;
+                    acc = self.mul_add(layouter, &acc, &theta, &eval)?;
+                }
+                compressed.push(acc);
+            }
+            let factors = [
+                self.add(layouter, &input, &beta)?,
+                self.add(layouter, &table, &gamma)?,
+            ];
+            let left = self.product(layouter, [product_next].into_iter().chain(factors))?;
+            let factors = [
+                self.add(layouter, &compressed[0], &beta)?,
+                self.add(layouter, &compressed[1], &gamma)?,
+            ];
+            let right = self.product(layouter, [product.clone()].into_iter().chain(factors))?;
+            let term = self.sub(layouter, &one, &product)?;
+            expressions.push(self.mul(layouter, &l_0, &term)?);
+            let square = self.mul(layouter, &product, &product)?;
+            let term = self.sub(layouter, &square, &product)?;
+            expressions.push(self.mul(layouter, &l_last, &term)?);
+            let term = self.sub(layouter, &left, &right)?;
+            expressions.push(self.mul(layouter, &term, &active_rows)?);
+            let input_table = self.sub(layouter, &input, &table)?;
+            expressions.push(self.mul(layouter, &l_0, &input_table)?);
+            let input_input_inv = self.sub(layouter, &input, &input_inv)?;
+            let term = self.product(
+                layouter,
+                [input_table, input_input_inv, active_rows.clone()],
+            )?;
+            expressions.push(term);
+        }
+
+        let y = Eval::from(&challenges.y);
+        let mut h = Eval::Constant(Fr::zero());
+        for eval in expressions.iter() {
+            h = self.mul_add(layouter, &h, &y, eval)?;
+        }
+        let h = self.cell(layouter, &h)?;
+        let vanishing = self
+            .native
+            .linear(layouter, &[(Fr::one(), xn)], -Fr::one())?;
+        self.native.div(layouter, &h, &vanishing)
+    }
+
+    /// The commitment and its evaluation at `x` rotated.
+    fn opening<'p>(
+        &self,
+        proof: &'p Proof,
+        commitment: Commitment,
+        rotation: i32,
+        expected_h: &'p AssignedNative,
+    ) -> (Opened<'p>, &'p AssignedNative) {
+        let protocol = self.protocol;
+        let query = |queries: &[(usize, i32)], column| {
+            queries
+                .iter()
+                .position(|query| *query == (column, rotation))
+                .expect("the proof evaluates the commitments at the rotations they are opened")
+        };
+        let last = protocol.last_rotation();
+        match (commitment, rotation) {
+            (Commitment::Advice(column), _) => (
+                Opened::Point(&proof.advice[column]),
+                &proof.advice_evals[query(&protocol.advice_queries, column)],
+            ),
+            (Commitment::Fixed(column), _) => (
+                Opened::Constant(protocol.fixed_commitments[column]),
+                &proof.fixed_evals[query(&protocol.fixed_queries, column)],
+            ),
+            (Commitment::PermutationCommon(i), _) => (
+                Opened::Constant(protocol.permutation_commitments[i]),
+                &proof.permutation_evals[i],
+            ),
+            (Commitment::PermutationProduct(i), rotation) => {
+                let (eval, next_eval, last_eval) = &proof.permutation_product_evals[i];
+                let eval = match rotation {
+                    0 => eval,
+                    1 => next_eval,
+                    rotation if rotation == last => last_eval.as_ref().unwrap(),
+                    _ => unreachable!("permutation products are opened at 0, 1 and the last row"),
+                };
+                (Opened::Point(&proof.permutation_products[i]), eval)
+            }
+            (Commitment::LookupPermutedInput(i), -1) => (
+                Opened::Point(&proof.lookup_permuted[i].0),
+                &proof.lookup_evals[i][3],
+            ),
+            (Commitment::LookupPermutedInput(i), _) => (
+                Opened::Point(&proof.lookup_permuted[i].0),
+                &proof.lookup_evals[i][2],
+            ),
+            (Commitment::LookupPermutedTable(i), _) => (
+                Opened::Point(&proof.lookup_permuted[i].1),
+                &proof.lookup_evals[i][4],
+            ),
+            (Commitment::LookupProduct(i), 1) => (
+                Opened::Point(&proof.lookup_products[i]),
+                &proof.lookup_evals[i][1],
+            ),
+            (Commitment::LookupProduct(i), _) => (
+                Opened::Point(&proof.lookup_products[i]),
+                &proof.lookup_evals[i][0],
+            ),
+            (Commitment::VanishingH, _) => (Opened::VanishingH, expected_h),
+            (Commitment::VanishingRandom, _) => (
+                Opened::Point(proof.vanishing_random.as_ref().unwrap()),
+                proof.vanishing_random_eval.as_ref().unwrap(),
+            ),
+        }
+    }
+
+    /// The terms of the point `right` of the pairing check
+    /// `e(h2, s_g2) = e(right, g2)` of the SHPLONK multiopen argument.
+    #[allow(clippy::too_many_arguments)]
+    fn multiopen(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        proof: &Proof,
+        challenges: &Challenges,
+        expected_h: &AssignedNative,
+        [y, v, u]: [&AssignedNative; 3],
+        h1: &AssignedPoint,
+        h2: &AssignedPoint,
+    ) -> Result<Vec<(AssignedNative, Base)>, Error> {
+        let protocol = self.protocol;
+        let native = self.native;
+        let x = &challenges.x;
+        let (y, v) = (Eval::from(y), Eval::from(v));
+        // `u - x * omega^rotation` for each rotation.
+        let u_minus = protocol
+            .rotations
+            .iter()
+            .map(|rotation| {
+                native
+                    .linear(
+                        layouter,
+                        &[(Fr::one(), u), (-protocol.omega_pow(*rotation), x)],
+                        Fr::zero(),
+                    )
+                    .map(Eval::Cell)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let u_minus_of =
+            |rotation: &i32| u_minus[protocol.rotations.binary_search(rotation).unwrap()].clone();
+        let x_inv = Eval::Cell(native.invert(layouter, x)?);
+
+        let mut terms = vec![];
+        let mut z_0 = Eval::Constant(Fr::zero());
+        let mut z_0_diff_inv = Eval::Constant(Fr::one());
+        let mut r_outer = Eval::Constant(Fr::zero());
+        let mut power_of_v = Eval::Constant(Fr::one());
+        for (i, (rotations, commitments)) in protocol.sets.iter().enumerate() {
+            let others = protocol
+                .rotations
+                .iter()
+                .filter(|rotation| !rotations.contains(rotation))
+                .map(u_minus_of)
+                .collect::<Vec<_>>();
+            let z_diff = self.product(layouter, others)?;
+            let z_diff = if i == 0 {
+                z_0 = self.product(layouter, rotations.iter().map(u_minus_of))?;
+                let z_diff = self.cell(layouter, &z_diff)?;
+                z_0_diff_inv = Eval::Cell(native.invert(layouter, &z_diff)?);
+                Eval::Constant(Fr::one())
+            } else {
+                self.mul(layouter, &z_diff, &z_0_diff_inv)?
+            };
+            let factor = self.mul(layouter, &power_of_v, &z_diff)?;
+            let basis = self.basis(layouter, rotations, &u_minus_of, &x_inv)?;
+
+            let mut r_inner = Eval::Constant(Fr::zero());
+            let mut power_of_y = Eval::Constant(Fr::one());
+            for commitment in commitments.iter() {
+                let mut interpolated = Eval::Constant(Fr::zero());
+                let mut opened = Opened::VanishingH;
+                for (rotation, basis) in rotations.iter().zip(basis.iter()) {
+                    let (point, eval) = self.opening(proof, *commitment, *rotation, expected_h);
+                    interpolated = self.mul_add(layouter, &eval.into(), basis, &interpolated)?;
+                    opened = point;
+                }
+                r_inner = self.mul_add(layouter, &power_of_y, &interpolated, &r_inner)?;
+                let scalar = self.mul(layouter, &factor, &power_of_y)?;
+                match opened {
+                    Opened::Point(point) => {
+                        terms.push((
+                            self.cell(layouter, &scalar)?,
+                            Base::Assigned(Box::new(point.clone())),
+                        ));
+                    }
+                    Opened::Constant(point) => {
+                        terms.push((self.cell(layouter, &scalar)?, Base::Constant(point)));
+                    }
+                    // `h = Σ xn^i * h_i`
+                    Opened::VanishingH => {
+                        let mut scalar = scalar;
+                        for (i, piece) in proof.vanishing_h.iter().enumerate() {
+                            if i > 0 {
+                                scalar = self.mul(layouter, &scalar, &(&challenges.xn).into())?;
+                            }
+                            terms.push((
+                                self.cell(layouter, &scalar)?,
+                                Base::Assigned(Box::new(piece.clone())),
+                            ));
+                        }
+                    }
+                }
+                power_of_y = self.mul(layouter, &power_of_y, &y)?;
+            }
+            r_outer = self.mul_add(layouter, &factor, &r_inner, &r_outer)?;
+            power_of_v = self.mul(layouter, &power_of_v, &v)?;
+        }
+
+        let mut negated = |eval: &Eval| {
+            self.linear(layouter, &[(-Fr::one(), eval)], Fr::zero())
+                .and_then(|eval| self.cell(layouter, &eval))
+        };
+        terms.push((negated(&r_outer)?, Base::Constant(protocol.g)));
+        terms.push((negated(&z_0)?, Base::Assigned(Box::new(h1.clone()))));
+        terms.push((u.clone(), Base::Assigned(Box::new(h2.clone()))));
+        Ok(terms)
+    }
+
+    /// The Lagrange basis of the points `x * omega^rotation` at `u`,
+    /// `Π (u - x_k) / Π (x_j - x_k)` over the other points for each `j`, with
+    /// `x_j - x_k = x * (omega^r_j - omega^r_k)`.
+    fn basis(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        rotations: &[i32],
+        u_minus_of: &dyn Fn(&i32) -> Eval,
+        x_inv: &Eval,
+    ) -> Result<Vec<Eval>, Error> {
+        let x_inv_power = self.product(
+            layouter,
+            core::iter::repeat(x_inv.clone()).take(rotations.len() - 1),
+        )?;
+        rotations
+            .iter()
+            .map(|rotation| {
+                let others = rotations.iter().filter(|other| *other != rotation);
+                let denominator = others.clone().fold(Fr::one(), |product, other| {
+                    product * (self.protocol.omega_pow(*rotation) - self.protocol.omega_pow(*other))
+                });
+                let numerator = self.product(layouter, others.map(u_minus_of))?;
+                let basis = self.mul(layouter, &numerator, &x_inv_power)?;
+                self.linear(
+                    layouter,
+                    &[(denominator.invert().unwrap(), &basis)],
+                    Fr::zero(),
+                )
+            })
+            .collect()
+    }
+}
+
+/// A commitment opened by the multiopen argument.
+enum Opened<'a> {
+    Point(&'a AssignedPoint),
+    Constant(G1Affine),
+    /// The quotient, from its pieces.
+    VanishingH,
+}
+
+struct Challenges {
+    theta: AssignedNative,
+    beta: AssignedNative,
+    gamma: AssignedNative,
+    y: AssignedNative,
+    x: AssignedNative,
+    xn: AssignedNative,
+}
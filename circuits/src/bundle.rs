@@ -0,0 +1,217 @@
+//! Self-describing proof file written by the prover.
+//!
+//! Layout (integers are little endian):
+//!
+//! ```text
+//! bundle   := magic[4] version:u8 circuit_id_len:u32 circuit_id k:u32
+//!             fingerprint[32] transcript:u8 column_count:u32 column*
+//!             proof_len:u32 proof
+//! column   := value_count:u32 value[32]*
+//! ```
+//!
+//! `fingerprint` is the [`crate::fingerprint::fingerprint`] of the verifying
+//! key, `transcript` the [`TranscriptKind`] the proof is made with and `value`
+//! the canonical representation of a public input field element.
+
+use alloc::{string::String, vec::Vec};
+use core::fmt;
+
+use crate::transcript::TranscriptKind;
+
+/// Magic bytes at the start of every proof bundle.
+pub const MAGIC: [u8; 4] = *b"BS2P";
+/// Current version of the proof bundle format.
+pub const VERSION: u8 = 4;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProofBundle {
+    pub version: u8,
+    /// Identifies the circuit the proof is for.
+    pub circuit_id: String,
+    pub k: u32,
+    /// Fingerprint of the circuit version the proof is for.
+    pub fingerprint: [u8; 32],
+    pub transcript: TranscriptKind,
+    /// Public inputs, one list per instance column.
+    pub instances: Vec<Vec<[u8; 32]>>,
+    pub proof: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BundleError {
+    UnexpectedEof,
+    InvalidMagic,
+    UnsupportedVersion(u8),
+    InvalidCircuitId,
+    UnknownTranscript(u8),
+    TrailingBytes(usize),
+}
+
+impl fmt::Display for BundleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BundleError::UnexpectedEof => write!(f, "unexpected end of proof bundle"),
+            BundleError::InvalidMagic => write!(f, "not a proof bundle"),
+            BundleError::UnsupportedVersion(v) => {
+                write!(f, "unsupported proof bundle version {}", v)
+            }
+            BundleError::InvalidCircuitId => write!(f, "circuit id is not utf-8"),
+            BundleError::UnknownTranscript(t) => write!(f, "unknown transcript {}", t),
+            BundleError::TrailingBytes(n) => write!(f, "{} trailing bytes after proof", n),
+        }
+    }
+}
+
+impl ProofBundle {
+    pub fn new(
+        circuit_id: &str,
+        k: u32,
+        fingerprint: [u8; 32],
+        transcript: TranscriptKind,
+        instances: Vec<Vec<[u8; 32]>>,
+        proof: Vec<u8>,
+    ) -> Self {
+        Self {
+            version: VERSION,
+            circuit_id: circuit_id.into(),
+            k,
+            fingerprint,
+            transcript,
+            instances,
+            proof,
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&MAGIC);
+        bytes.push(self.version);
+        bytes.extend_from_slice(&(self.circuit_id.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(self.circuit_id.as_bytes());
+        bytes.extend_from_slice(&self.k.to_le_bytes());
+        bytes.extend_from_slice(&self.fingerprint);
+        bytes.push(self.transcript as u8);
+        bytes.extend_from_slice(&(self.instances.len() as u32).to_le_bytes());
+        for column in self.instances.iter() {
+            bytes.extend_from_slice(&(column.len() as u32).to_le_bytes());
+            for value in column.iter() {
+                bytes.extend_from_slice(value);
+            }
+        }
+        bytes.extend_from_slice(&(self.proof.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&self.proof);
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, BundleError> {
+        let mut reader = Reader { bytes, pos: 0 };
+        if reader.take(4)? != MAGIC {
+            return Err(BundleError::InvalidMagic);
+        }
+        let version = reader.take(1)?[0];
+        if version != VERSION {
+            return Err(BundleError::UnsupportedVersion(version));
+        }
+        let id_len = reader.u32()? as usize;
+        let circuit_id = core::str::from_utf8(reader.take(id_len)?)
+            .map_err(|_| BundleError::InvalidCircuitId)?
+            .into();
+        let k = reader.u32()?;
+        let mut fingerprint = [0u8; 32];
+        fingerprint.copy_from_slice(reader.take(32)?);
+        let transcript = reader.take(1)?[0];
+        let transcript = TranscriptKind::from_u8(transcript)
+            .ok_or(BundleError::UnknownTranscript(transcript))?;
+        let mut instances = Vec::new();
+        for _ in 0..reader.u32()? {
+            let mut column = Vec::new();
+            for _ in 0..reader.u32()? {
+                let mut value = [0u8; 32];
+                value.copy_from_slice(reader.take(32)?);
+                column.push(value);
+            }
+            instances.push(column);
+        }
+        let proof_len = reader.u32()? as usize;
+        let proof = reader.take(proof_len)?.to_vec();
+        if reader.pos != bytes.len() {
+            return Err(BundleError::TrailingBytes(bytes.len() - reader.pos));
+        }
+        Ok(Self {
+            version,
+            circuit_id,
+            k,
+            fingerprint,
+            transcript,
+            instances,
+            proof,
+        })
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], BundleError> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .ok_or(BundleError::UnexpectedEof)?;
+        let slice = self
+            .bytes
+            .get(self.pos..end)
+            .ok_or(BundleError::UnexpectedEof)?;
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn u32(&mut self) -> Result<u32, BundleError> {
+        let mut buf = [0u8; 4];
+        buf.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(buf))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn roundtrip() {
+        let bundle = ProofBundle::new(
+            "bs2-main",
+            14,
+            [3u8; 32],
+            TranscriptKind::Poseidon,
+            vec![vec![[7u8; 32]], vec![]],
+            vec![1, 2, 3],
+        );
+        let bytes = bundle.encode();
+        assert_eq!(ProofBundle::decode(&bytes), Ok(bundle));
+        assert_eq!(
+            ProofBundle::decode(&bytes[..bytes.len() - 1]),
+            Err(BundleError::UnexpectedEof)
+        );
+        assert_eq!(ProofBundle::decode(b"BS2T"), Err(BundleError::InvalidMagic));
+    }
+
+    #[test]
+    fn roundtrip_long_circuit_id() {
+        let circuit_id = "bs2-main/".repeat(64);
+        let bundle = ProofBundle::new(
+            &circuit_id,
+            14,
+            [3u8; 32],
+            TranscriptKind::Blake2b,
+            vec![vec![[7u8; 32]]],
+            vec![1, 2, 3],
+        );
+        let decoded = ProofBundle::decode(&bundle.encode()).unwrap();
+        assert_eq!(decoded.circuit_id, circuit_id);
+        assert_eq!(decoded, bundle);
+    }
+}
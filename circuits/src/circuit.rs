@@ -0,0 +1,53 @@
+//! Circuits a proof can be for. The one of a verifying key is recorded in
+//! the witness and the key cell of the verifier, which reads the key as a key
+//! of that circuit.
+
+use core::fmt;
+
+use crate::{aggregation, main_circuit};
+
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CircuitKind {
+    #[default]
+    Main = 0,
+    /// The aggregation circuit, whose public inputs start with the
+    /// accumulator of the proofs it verifies, see [`crate::aggregation`].
+    Aggregation = 1,
+}
+
+impl CircuitKind {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(CircuitKind::Main),
+            1 => Some(CircuitKind::Aggregation),
+            _ => None,
+        }
+    }
+
+    /// The circuit of a proof bundle from its circuit id.
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            main_circuit::CIRCUIT_ID => Some(CircuitKind::Main),
+            aggregation::CIRCUIT_ID => Some(CircuitKind::Aggregation),
+            _ => None,
+        }
+    }
+
+    /// The circuit id of the proof bundles of the circuit.
+    pub fn id(&self) -> &'static str {
+        match self {
+            CircuitKind::Main => main_circuit::CIRCUIT_ID,
+            CircuitKind::Aggregation => aggregation::CIRCUIT_ID,
+        }
+    }
+}
+
+impl fmt::Display for CircuitKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CircuitKind::Main => write!(f, "main"),
+            CircuitKind::Aggregation => write!(f, "aggregation"),
+        }
+    }
+}
@@ -1,23 +1,387 @@
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
 use entry_builder::entries::Entries;
+use entry_builder::op_step::OpStep;
 use halo2_proofs::arithmetic::FieldExt;
 use halo2_proofs::{
-    circuit::Layouter,
-    plonk::{Circuit, ConstraintSystem, Error},
+    circuit::{Layouter, Region, Value},
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Fixed, Selector, VirtualCells},
+    poly::Rotation,
 };
 use runtime::trace::{InstructionType, Opcode};
-use core::marker::PhantomData;
+
+use crate::memory_table;
+use crate::program_table::is_sequential;
 
 pub mod op_configure;
+use op_configure::atype::ATypeGadget;
+use op_configure::bitwise::BitwiseGadget;
 use op_configure::btype::BTypeGadget;
+use op_configure::clmul::ClmulGadget;
+use op_configure::fused::FusedTypeGadget;
 use op_configure::itype::ITypeGadget;
-use op_configure::rtype::RTypeGadget;
-use op_configure::utype::UTypeGadget;
 use op_configure::jtype::JTypeGadget;
-use op_configure::stype::STypeGadget;
 use op_configure::others::OthersTypeGadget;
+use op_configure::rtype::RTypeGadget;
+use op_configure::shift::ShiftGadget;
+use op_configure::stype::STypeGadget;
+use op_configure::utype::UTypeGadget;
+
+/// Rows of a step, the bitwise gadget takes one per 4-bit limb of a register.
+pub const STEP_ROWS: usize = 16;
+
+/// Opcodes with constraints of their own. The gate id of an opcode is its
+/// position in the list plus one, the other opcodes are in
+/// [`UNSUPPORTED_OPCODES`].
+const GATES: [&[Opcode]; 71] = [
+    &[Opcode::ADD],
+    &[Opcode::SUB],
+    &[Opcode::ADDI],
+    &[Opcode::LRW],
+    &[Opcode::LRD],
+    &[Opcode::SCW],
+    &[Opcode::SCD],
+    &[Opcode::AMOSWAPW],
+    &[Opcode::AMOSWAPD],
+    &[Opcode::AMOADDW],
+    &[Opcode::AMOADDD],
+    &[Opcode::AMOXORW],
+    &[Opcode::AMOXORD],
+    &[Opcode::AMOANDW],
+    &[Opcode::AMOANDD],
+    &[Opcode::AMOORW],
+    &[Opcode::AMOORD],
+    &[Opcode::AMOMINW],
+    &[Opcode::AMOMIND],
+    &[Opcode::AMOMAXW],
+    &[Opcode::AMOMAXD],
+    &[Opcode::AMOMINUW],
+    &[Opcode::AMOMINUD],
+    &[Opcode::AMOMAXUW],
+    &[Opcode::AMOMAXUD],
+    &[Opcode::ANDN],
+    &[Opcode::ORN],
+    &[Opcode::XNOR],
+    &[Opcode::CPOP],
+    &[Opcode::CPOPW],
+    &[Opcode::CLZ],
+    &[Opcode::CLZW],
+    &[Opcode::CTZ],
+    &[Opcode::CTZW],
+    &[Opcode::ORCB],
+    &[Opcode::REV8],
+    &[Opcode::SEXTB],
+    &[Opcode::SEXTH],
+    &[Opcode::ZEXTH],
+    &[Opcode::MIN],
+    &[Opcode::MAX],
+    &[Opcode::MINU],
+    &[Opcode::MAXU],
+    &[Opcode::SH1ADD],
+    &[Opcode::SH2ADD],
+    &[Opcode::SH3ADD],
+    &[Opcode::ADDUW],
+    &[Opcode::SH1ADDUW],
+    &[Opcode::SH2ADDUW],
+    &[Opcode::SH3ADDUW],
+    &[Opcode::SLLIUW],
+    &[Opcode::ROL],
+    &[Opcode::ROR],
+    &[Opcode::RORI],
+    &[Opcode::ROLW],
+    &[Opcode::RORW],
+    &[Opcode::RORIW],
+    &[Opcode::BCLR],
+    &[Opcode::BCLRI],
+    &[Opcode::BEXT],
+    &[Opcode::BEXTI],
+    &[Opcode::BINV],
+    &[Opcode::BINVI],
+    &[Opcode::BSET],
+    &[Opcode::BSETI],
+    &[Opcode::CLMUL],
+    &[Opcode::CLMULH],
+    &[Opcode::CLMULR],
+    &[Opcode::CUSTOMLOADIMM, Opcode::LDSIGNEXTENDED32CONSTANT],
+    &[Opcode::FARJUMPREL],
+    &[Opcode::FARJUMPABS],
+];
+
+/// Columns of the flags, each one has the flags of [`STEP_ROWS`] gates, one
+/// per row of the step.
+const FLAG_COLUMNS: usize = (GATES.len() + STEP_ROWS - 1) / STEP_ROWS;
+
+/// Opcodes the circuit can't prove, synthesizing a trace executing one of them
+/// fails. These are all the opcodes without a gate, the steps of which would
+/// be unconstrained.
+const UNSUPPORTED_OPCODES: &[Opcode] = &[
+    Opcode::XOR,
+    Opcode::OR,
+    Opcode::AND,
+    Opcode::SLL,
+    Opcode::SRL,
+    Opcode::SRA,
+    Opcode::SLT,
+    Opcode::SLTU,
+    Opcode::LB,
+    Opcode::LH,
+    Opcode::LW,
+    Opcode::LBU,
+    Opcode::LHU,
+    Opcode::SB,
+    Opcode::SH,
+    Opcode::SW,
+    Opcode::BEQ,
+    Opcode::BNE,
+    Opcode::BLT,
+    Opcode::BGE,
+    Opcode::BLTU,
+    Opcode::BGEU,
+    Opcode::JAL,
+    Opcode::JALR,
+    Opcode::AUIPC,
+    Opcode::ECALL,
+    Opcode::EBREAK,
+    Opcode::MUL,
+    Opcode::MULH,
+    Opcode::MULHU,
+    Opcode::MULHSU,
+    Opcode::DIV,
+    Opcode::DIVU,
+    Opcode::REM,
+    Opcode::REMU,
+    Opcode::LUI,
+    Opcode::SLTI,
+    Opcode::SLTIU,
+    Opcode::XORI,
+    Opcode::ORI,
+    Opcode::ANDI,
+    Opcode::SLLI,
+    Opcode::SRLI,
+    Opcode::SRAI,
+    Opcode::FENCE,
+    Opcode::LWU,
+    Opcode::LD,
+    Opcode::SD,
+    Opcode::ADDIW,
+    Opcode::SLLIW,
+    Opcode::SRLIW,
+    Opcode::SRAIW,
+    Opcode::ADDW,
+    Opcode::SUBW,
+    Opcode::SLLW,
+    Opcode::SRLW,
+    Opcode::SRAW,
+    Opcode::MULW,
+    Opcode::DIVW,
+    Opcode::DIVUW,
+    Opcode::REMW,
+    Opcode::REMUW,
+    Opcode::UNIMP,
+];
+
+/// Gate id of the unsupported opcodes. At most one flag is set, so the gate id
+/// of a step is at most `GATES.len()` and never matches it: the instructions
+/// with this id in the program table can't be executed.
+pub const UNSUPPORTED: u64 = GATES.len() as u64 + 1;
+
+/// Gate id the program table records for an opcode, so a step can't skip the
+/// constraints of its instruction.
+pub fn gate(opcode: Opcode) -> u64 {
+    if UNSUPPORTED_OPCODES.contains(&opcode) {
+        return UNSUPPORTED;
+    }
+    GATES
+        .iter()
+        .position(|opcodes| opcodes.contains(&opcode))
+        .map_or(0, |index| index as u64 + 1)
+}
+
+/// The columns every step has. A step takes [`STEP_ROWS`] rows starting with
+/// `q_step`, its instruction and register values are on the first one:
+///
+/// | enabled | pc | opcode | length | op_a | op_b | op_c | next_pc | sequential | rs1 | rs2 | rd | aux | flags |
+///
+/// The steps are laid out in as many slots as the circuit has room for, the
+/// ones after the trace are disabled, so the fixed columns don't depend on it.
+/// The flag of the gate of the instruction is set, see [`gate`]: the flag of
+/// gate id `g` is on the row `(g - 1) % STEP_ROWS` of the flag column
+/// `(g - 1) / STEP_ROWS`.
+///
+/// Every row of a step is also a memory port, see
+/// [`crate::memory_table::MemoryTable`].
+#[derive(Clone, Debug)]
+pub struct StepConfig {
+    pub q_step: Selector,
+    /// Set on all the steps but the last one.
+    pub q_chain: Selector,
+    /// Set on the first step, whose pc is the `entry` of the program.
+    pub q_first: Selector,
+    pub entry: Column<Fixed>,
+    pub enabled: Column<Advice>,
+    pub pc: Column<Advice>,
+    pub opcode: Column<Advice>,
+    pub length: Column<Advice>,
+    pub op_a: Column<Advice>,
+    pub op_b: Column<Advice>,
+    pub op_c: Column<Advice>,
+    pub next_pc: Column<Advice>,
+    pub sequential: Column<Advice>,
+    /// Values of the registers the instruction reads and writes.
+    pub rs1: Column<Advice>,
+    pub rs2: Column<Advice>,
+    pub rd: Column<Advice>,
+    /// Cells of the gadgets, e.g. carries and signs.
+    pub aux: [Column<Advice>; 3],
+    pub flags: [Column<Advice>; FLAG_COLUMNS],
+    /// Set on all the rows of a step.
+    pub q_port: Selector,
+    /// Row of the execution region plus one, orders the memory accesses.
+    pub time: Column<Fixed>,
+    /// A byte of memory the step reads or writes, `port_time` is `time` when
+    /// the port is enabled and all the cells of a disabled port are 0.
+    pub port_enabled: Column<Advice>,
+    pub port_time: Column<Advice>,
+    pub port_address: Column<Advice>,
+    pub port_value: Column<Advice>,
+    pub port_write: Column<Advice>,
+}
+
+impl StepConfig {
+    fn configure<F: FieldExt>(cs: &mut ConstraintSystem<F>) -> Self {
+        Self {
+            q_step: cs.complex_selector(),
+            q_chain: cs.selector(),
+            q_first: cs.selector(),
+            entry: cs.fixed_column(),
+            enabled: cs.advice_column(),
+            pc: cs.advice_column(),
+            opcode: cs.advice_column(),
+            length: cs.advice_column(),
+            op_a: cs.advice_column(),
+            op_b: cs.advice_column(),
+            op_c: cs.advice_column(),
+            next_pc: cs.advice_column(),
+            sequential: cs.advice_column(),
+            rs1: cs.advice_column(),
+            rs2: cs.advice_column(),
+            rd: cs.advice_column(),
+            aux: [(); 3].map(|_| cs.advice_column()),
+            flags: [(); FLAG_COLUMNS].map(|_| cs.advice_column()),
+            q_port: cs.complex_selector(),
+            time: cs.fixed_column(),
+            port_enabled: cs.advice_column(),
+            port_time: cs.advice_column(),
+            port_address: cs.advice_column(),
+            port_value: cs.advice_column(),
+            port_write: cs.advice_column(),
+        }
+    }
+
+    pub(crate) fn columns(&self) -> Vec<Column<Advice>> {
+        let mut columns = vec![
+            self.enabled,
+            self.pc,
+            self.opcode,
+            self.length,
+            self.op_a,
+            self.op_b,
+            self.op_c,
+            self.next_pc,
+            self.sequential,
+            self.rs1,
+            self.rs2,
+            self.rd,
+        ];
+        columns.extend(self.aux);
+        columns.extend(self.flags);
+        columns.extend([
+            self.port_enabled,
+            self.port_time,
+            self.port_address,
+            self.port_value,
+            self.port_write,
+        ]);
+        columns
+    }
+
+    /// Column and row of the flag of gate id `gate`.
+    pub(crate) fn flag_cell(&self, gate: u64) -> (Column<Advice>, usize) {
+        let index = gate as usize - 1;
+        (self.flags[index / STEP_ROWS], index % STEP_ROWS)
+    }
+
+    /// The flag of the gate of `opcode` on the first row of a step, times
+    /// `q_step` so that it is 0 on the other rows.
+    pub fn flag<F: FieldExt>(&self, vc: &mut VirtualCells<'_, F>, opcode: Opcode) -> Expression<F> {
+        let gate = gate(opcode);
+        assert!(gate > 0 && gate < UNSUPPORTED, "{:?} has no gate", opcode);
+        let (column, row) = self.flag_cell(gate);
+        vc.query_selector(self.q_step) * vc.query_advice(column, Rotation(row as i32))
+    }
+
+    /// `Σ flag · value(opcode)` over the gates, without `q_step`. `value` is
+    /// the same for all the opcodes of a gate.
+    pub fn select<F: FieldExt>(
+        &self,
+        vc: &mut VirtualCells<'_, F>,
+        value: impl Fn(Opcode) -> u64,
+    ) -> Expression<F> {
+        GATES
+            .iter()
+            .enumerate()
+            .fold(Expression::Constant(F::zero()), |sum, (index, opcodes)| {
+                let (column, row) = self.flag_cell(index as u64 + 1);
+                sum + vc.query_advice(column, Rotation(row as i32))
+                    * Expression::Constant(F::from(value(opcodes[0])))
+            })
+    }
+
+    /// `Σ flag · gate id`, the gate id of the step, without `q_step`.
+    pub fn gate<F: FieldExt>(&self, vc: &mut VirtualCells<'_, F>) -> Expression<F> {
+        self.select(vc, gate)
+    }
+}
+
+/// Values of the cells of one step, the cells left out are zero.
+pub struct Slot<F> {
+    cells: BTreeMap<Column<Advice>, [F; STEP_ROWS]>,
+}
+
+impl<F: FieldExt> Slot<F> {
+    fn new(columns: &[Column<Advice>]) -> Self {
+        Self {
+            cells: columns
+                .iter()
+                .map(|column| (*column, [F::zero(); STEP_ROWS]))
+                .collect(),
+        }
+    }
+
+    pub fn assign(&mut self, column: Column<Advice>, row: usize, value: F) {
+        self.cells.get_mut(&column).expect("column of the step")[row] = value;
+    }
+
+    fn assign_region(&self, region: &mut Region<'_, F>, offset: usize) -> Result<(), Error> {
+        for (column, values) in self.cells.iter() {
+            for (row, value) in values.iter().enumerate() {
+                region.assign_advice(|| "step", *column, offset + row, || Value::known(*value))?;
+            }
+        }
+        Ok(())
+    }
+}
 
 #[derive(Clone)]
 pub struct ExecutionTable<F> {
+    pub step: StepConfig,
+    pub atype: ATypeGadget<F>,
+    pub bitwise: BitwiseGadget<F>,
+    pub shift: ShiftGadget<F>,
+    pub clmul: ClmulGadget<F>,
+    pub fused: FusedTypeGadget<F>,
     pub btype: BTypeGadget<F>,
     pub rtype: RTypeGadget<F>,
     pub itype: ITypeGadget<F>,
@@ -30,64 +394,204 @@ pub struct ExecutionTable<F> {
 
 impl<F: FieldExt> ExecutionTable<F> {
     pub fn configure(cs: &mut ConstraintSystem<F>) -> Self {
-        // Common 
-        let lhs_col = cs.advice_column();
-        let rhs_col = cs.advice_column();
-        let s_overflowing = cs.advice_column();
-
-        // RType selector
-        let s_add = cs.selector();
-        let s_sub = cs.selector();
-        
-        // BType selector
-        let s_beq = cs.selector();
+        let step = StepConfig::configure(cs);
 
-        // IType selector
-        let s_addi = cs.selector();
+        cs.create_gate("step", |vc| {
+            let q_step = vc.query_selector(step.q_step);
+            let enabled = vc.query_advice(step.enabled, Rotation::cur());
+            let one = Expression::Constant(F::one());
+            let mut constraints = vec![q_step.clone() * enabled.clone() * (one.clone() - enabled)];
+            // At most one gate applies.
+            let mut flags = Expression::Constant(F::zero());
+            for gate in 1..=GATES.len() as u64 {
+                let (column, row) = step.flag_cell(gate);
+                let flag = vc.query_advice(column, Rotation(row as i32));
+                constraints.push(q_step.clone() * flag.clone() * (one.clone() - flag.clone()));
+                flags = flags + flag;
+            }
+            constraints.push(q_step * flags.clone() * (one - flags));
+            constraints
+        });
 
-        // JType selector
-        let s_jal = cs.selector();
+        // The enabled steps come first and each one starts where the previous
+        // one continues.
+        cs.create_gate("step chain", |vc| {
+            let q_chain = vc.query_selector(step.q_chain);
+            let enabled = vc.query_advice(step.enabled, Rotation::cur());
+            let next_pc = vc.query_advice(step.next_pc, Rotation::cur());
+            let enabled_next = vc.query_advice(step.enabled, Rotation(STEP_ROWS as i32));
+            let pc_next = vc.query_advice(step.pc, Rotation(STEP_ROWS as i32));
+            vec![
+                q_chain.clone() * enabled_next.clone() * (Expression::Constant(F::one()) - enabled),
+                q_chain * enabled_next * (next_pc - pc_next),
+            ]
+        });
 
-        // SType selector
-        let s_sw = cs.selector();
-
-        // UType selector
-        let s_lui = cs.selector();
-
-        // Others selector
-        let s_lw = cs.selector();
+        // The trace starts at the entry point of the ELF.
+        cs.create_gate("entry point", |vc| {
+            let q_first = vc.query_selector(step.q_first);
+            let enabled = vc.query_advice(step.enabled, Rotation::cur());
+            let pc = vc.query_advice(step.pc, Rotation::cur());
+            let entry = vc.query_fixed(step.entry, Rotation::cur());
+            vec![q_first * enabled * (pc - entry)]
+        });
 
+        let bitwise = BitwiseGadget::configure(cs, &step);
         Self {
-            btype: BTypeGadget::configure(cs, lhs_col, rhs_col, s_beq),
-            itype: ITypeGadget::configure(cs, lhs_col, rhs_col, s_overflowing, s_addi),
-            jtype: JTypeGadget::configure(cs, lhs_col, rhs_col, s_jal),
-            rtype: RTypeGadget::configure(cs, lhs_col, rhs_col, s_add, s_sub),
-            stype: STypeGadget::configure(cs, lhs_col, rhs_col, s_sw),
-            utype: UTypeGadget::configure(cs, lhs_col, rhs_col, s_lui),
-            others: OthersTypeGadget::configure(cs, lhs_col, rhs_col, s_lw),
+            atype: ATypeGadget::configure(cs, &step, &bitwise),
+            fused: FusedTypeGadget::configure(cs, &step, &bitwise),
+            shift: ShiftGadget::configure(cs, &step, &bitwise),
+            clmul: ClmulGadget::configure(cs, &step),
+            bitwise,
+            btype: BTypeGadget::configure(cs, &step),
+            itype: ITypeGadget::configure(cs, &step),
+            jtype: JTypeGadget::configure(cs, &step),
+            rtype: RTypeGadget::configure(cs, &step),
+            stype: STypeGadget::configure(cs, &step),
+            utype: UTypeGadget::configure(cs, &step),
+            others: OthersTypeGadget::configure(cs, &step),
+            step,
             _marker: PhantomData::default(),
         }
     }
 
-    pub fn assign(&self, layouter: &mut impl Layouter<F>, entries: &Entries) -> Result<(), Error> {
+    /// The advice columns of a step.
+    pub(crate) fn columns(&self) -> Vec<Column<Advice>> {
+        let mut columns = self.step.columns();
+        columns.extend(self.bitwise.columns());
+        columns.extend(self.atype.columns());
+        columns.extend(self.shift.columns());
+        columns.extend(self.clmul.columns());
+        columns
+    }
+
+    /// Assigns the lookup tables of the gadgets.
+    pub fn assign_tables(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        self.bitwise.assign_table(layouter)?;
+        self.shift.assign_table(layouter)?;
+        self.clmul.assign_table(layouter)
+    }
+
+    /// Lays out `max_steps` steps, the first ones proving the trace, which
+    /// starts at `entry`.
+    pub fn assign(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        entries: &Entries,
+        entry: u64,
+        max_steps: usize,
+    ) -> Result<(), Error> {
         let op_steps = entries.get_op_steps();
+        if op_steps.len() > max_steps {
+            return Err(Error::Synthesis);
+        }
+        self.assign_tables(layouter)?;
+
+        let columns = self.columns();
+        layouter.assign_region(
+            || "execution",
+            |mut region| {
+                if max_steps > 0 {
+                    self.step.q_first.enable(&mut region, 0)?;
+                    region.assign_fixed(
+                        || "entry",
+                        self.step.entry,
+                        0,
+                        || Value::known(F::from(entry)),
+                    )?;
+                }
+                let mut reservation = None;
+                for index in 0..max_steps {
+                    let offset = index * STEP_ROWS;
+                    self.step.q_step.enable(&mut region, offset)?;
+                    if index + 1 < max_steps {
+                        self.step.q_chain.enable(&mut region, offset)?;
+                    }
+                    self.bitwise.enable(&mut region, offset)?;
+                    for row in offset..offset + STEP_ROWS {
+                        self.step.q_port.enable(&mut region, row)?;
+                        region.assign_fixed(
+                            || "time",
+                            self.step.time,
+                            row,
+                            || Value::known(F::from(memory_table::time(row))),
+                        )?;
+                    }
 
-        for (index, op_step) in op_steps.iter().enumerate() {
-            match op_step.instruction.opcode.into() {
-                InstructionType::BType(_) => self.btype.assign(layouter, op_step),
-                InstructionType::IType(_) => self.itype.assign(layouter, op_step),
-                InstructionType::RType(_) => self.rtype.assign(layouter, op_step),
-                InstructionType::SType(_) => self.stype.assign(layouter, op_step),
-                InstructionType::UType(_) => {
-                    let slice = &op_steps[index..=index+1];
-                    self.utype.assign(layouter, slice)
-                },
-                InstructionType::JType(_) => self.jtype.assign(layouter, op_step),
-                InstructionType::NoType(_) => self.others.assign(layouter, op_step),
-                _ => panic!("Not implemented {:?}", op_step.instruction.opcode),
-            }?;
+                    let mut slot = Slot::new(&columns);
+                    if let Some(op_step) = op_steps.get(index) {
+                        self.atype.assign_reservation(&mut slot, reservation);
+                        self.assign_step(&mut slot, op_step, reservation)?;
+                        for port in memory_table::ports(op_step) {
+                            for (column, value) in [
+                                (self.step.port_enabled, 1),
+                                (self.step.port_time, memory_table::time(offset + port.row)),
+                                (self.step.port_address, port.address),
+                                (self.step.port_value, port.value),
+                                (self.step.port_write, port.write as u64),
+                            ] {
+                                slot.assign(column, port.row, F::from(value));
+                            }
+                        }
+                        reservation = ATypeGadget::<F>::reservation_after(op_step, reservation);
+                    }
+                    slot.assign_region(&mut region, offset)?;
+                }
+                Ok(())
+            },
+        )?;
+        self.clmul.assign_products(layouter, &op_steps, max_steps)
+    }
+
+    fn assign_step(
+        &self,
+        slot: &mut Slot<F>,
+        op_step: &OpStep,
+        reservation: Option<u64>,
+    ) -> Result<(), Error> {
+        let instruction = op_step.instruction;
+        let gate = gate(instruction.opcode);
+        if gate == UNSUPPORTED {
+            return Err(Error::Synthesis);
+        }
+        for (column, value) in [
+            (self.step.enabled, 1),
+            (self.step.pc, op_step.pc),
+            (self.step.opcode, instruction.opcode as u64),
+            (self.step.length, instruction.length),
+            (self.step.op_a, instruction.op_a),
+            (self.step.op_b, instruction.op_b),
+            (self.step.op_c, instruction.op_c),
+            (self.step.next_pc, op_step.next_pc),
+            (
+                self.step.sequential,
+                is_sequential(instruction.opcode) as u64,
+            ),
+        ] {
+            slot.assign(column, 0, F::from(value));
+        }
+        if gate > 0 {
+            let (column, row) = self.step.flag_cell(gate);
+            slot.assign(column, row, F::one());
         }
 
-        Ok(())
+        if BitwiseGadget::<F>::supports(instruction.opcode) {
+            return self.bitwise.assign(slot, op_step);
+        } else if ShiftGadget::<F>::supports(instruction.opcode) {
+            return self.shift.assign(slot, op_step);
+        } else if ClmulGadget::<F>::supports(instruction.opcode) {
+            return self.clmul.assign(slot, op_step);
+        }
+        match instruction.opcode.into() {
+            InstructionType::BType(_) => self.btype.assign(slot, op_step),
+            InstructionType::IType(_) => self.itype.assign(slot, op_step),
+            InstructionType::RType(_) => self.rtype.assign(slot, op_step),
+            InstructionType::SType(_) => self.stype.assign(slot, op_step),
+            InstructionType::UType(_) => self.utype.assign(slot, op_step),
+            InstructionType::JType(_) => self.jtype.assign(slot, op_step),
+            InstructionType::AType(_) => self.atype.assign(slot, op_step, reservation),
+            InstructionType::FusedType(_) => self.fused.assign(slot, op_step),
+            InstructionType::NoType(_) => self.others.assign(slot, op_step),
+        }
     }
 }
@@ -0,0 +1,639 @@
+use core::marker::PhantomData;
+use entry_builder::op_step::OpStep;
+use runtime::trace::Opcode;
+
+use alloc::vec;
+use alloc::vec::Vec;
+use halo2_proofs::arithmetic::FieldExt;
+use halo2_proofs::plonk::*;
+use halo2_proofs::poly::Rotation;
+
+use crate::execution_table::op_configure::bitwise::{self, BitwiseGadget, TAG_SIGNS, TAG_XOR};
+use crate::execution_table::{Slot, StepConfig};
+use crate::memory_table::{READ_PORT, WRITE_PORT};
+
+/// The atomic instructions on 32 bits words.
+const WORD: [Opcode; 11] = [
+    Opcode::LRW,
+    Opcode::SCW,
+    Opcode::AMOSWAPW,
+    Opcode::AMOADDW,
+    Opcode::AMOXORW,
+    Opcode::AMOANDW,
+    Opcode::AMOORW,
+    Opcode::AMOMINW,
+    Opcode::AMOMAXW,
+    Opcode::AMOMINUW,
+    Opcode::AMOMAXUW,
+];
+
+/// The atomic instructions on 64 bits words, in the order of [`WORD`].
+const DOUBLE: [Opcode; 11] = [
+    Opcode::LRD,
+    Opcode::SCD,
+    Opcode::AMOSWAPD,
+    Opcode::AMOADDD,
+    Opcode::AMOXORD,
+    Opcode::AMOANDD,
+    Opcode::AMOORD,
+    Opcode::AMOMIND,
+    Opcode::AMOMAXD,
+    Opcode::AMOMINUD,
+    Opcode::AMOMAXUD,
+];
+
+/// Bytes of the words of [`WORD`], the ones of [`DOUBLE`] have twice as many.
+const WORD_BYTES: usize = 4;
+
+fn is_lr(opcode: Opcode) -> bool {
+    matches!(opcode, Opcode::LRW | Opcode::LRD)
+}
+
+fn is_sc(opcode: Opcode) -> bool {
+    matches!(opcode, Opcode::SCW | Opcode::SCD)
+}
+
+/// Atomic instructions access the word at `rs1` through the memory ports of
+/// the step, see [`crate::memory_table`]: its bytes are read on the ports from
+/// [`READ_PORT`], `loaded` is their value, and written on the ones from
+/// [`WRITE_PORT`], `stored` is their value. LR only reads, SC only writes and
+/// only when it succeeds.
+///
+/// | aux 0 | aux 1 | aux 2 |
+/// |-------|-------|-------|
+/// | sign of loaded | sign of rs2 | carry of AMOADD, loaded < rs2 of AMOMIN/AMOMAX |
+///
+/// The W variants sign extend `loaded` into rd and take the lower 32 bits of
+/// rs2. The operands are split into the limbs of the bitwise gadget, lhs is
+/// `loaded` and rhs is rs2: AMOXOR, AMOAND and AMOOR are linear in the limbs
+/// and their XOR. The comparisons of AMOMIN and AMOMAX read the signs in the
+/// highest bits of the limbs of the word, and the ext limbs are the difference
+/// of the operands, plus 2^32 or 2^64 when `loaded` is the lowest, which
+/// fits in the word. The other W variants use the ext limbs for
+/// `2 · (loaded - sign · 2^31)`, which fits in 32 bits, for its sign.
+///
+/// LR reserves its address. `reserved` and `reservation` are the reservation
+/// before the step, carried from step to step: SC succeeds when its address is
+/// the reserved one, its rd is then 0, and clears the reservation. SC has the
+/// inverse of `reservation - rs1` in aux 0, whether they are equal in aux 1
+/// and whether it succeeds in aux 2.
+#[derive(Clone)]
+pub struct ATypeGadget<F> {
+    pub reserved: Column<Advice>,
+    pub reservation: Column<Advice>,
+    step: StepConfig,
+    bitwise: BitwiseGadget<F>,
+    _maker: PhantomData<F>,
+}
+
+impl<F: FieldExt> ATypeGadget<F> {
+    pub fn configure(
+        cs: &mut ConstraintSystem<F>,
+        step: &StepConfig,
+        bitwise: &BitwiseGadget<F>,
+    ) -> Self {
+        let reserved = cs.advice_column();
+        let reservation = cs.advice_column();
+        let one = Expression::Constant(F::one());
+        let constant = |value: u128| Expression::Constant(F::from_u128(value));
+        let sum = |vc: &mut VirtualCells<'_, F>, opcodes: &mut dyn Iterator<Item = &Opcode>| {
+            opcodes.fold(Expression::Constant(F::zero()), |sum, opcode| {
+                sum + step.flag(vc, *opcode)
+            })
+        };
+        // The value of the bytes of the ports from `first`.
+        let bytes = |vc: &mut VirtualCells<'_, F>, first: usize| {
+            (0..2 * WORD_BYTES)
+                .rev()
+                .fold(Expression::Constant(F::zero()), |sum, byte| {
+                    sum * constant(256)
+                        + vc.query_advice(step.port_value, Rotation((first + byte) as i32))
+                })
+        };
+
+        // The words of the W variants have their first 4 ports enabled, the
+        // ones of the D variants all 8.
+        cs.create_gate("AType::ports", |vc| {
+            let atomic = sum(vc, &mut WORD.iter().chain(DOUBLE.iter()));
+            let rs1 = vc.query_advice(step.rs1, Rotation::cur());
+            let success = vc.query_advice(step.aux[2], Rotation::cur());
+            let mut flags = |opcodes: &[Opcode], write: bool| {
+                opcodes
+                    .iter()
+                    .filter(|opcode| !is_sc(**opcode) || write)
+                    .filter(|opcode| !is_lr(**opcode) || !write)
+                    .fold(Expression::Constant(F::zero()), |sum, opcode| {
+                        let (column, row) = step.flag_cell(crate::execution_table::gate(*opcode));
+                        let flag = vc.query_advice(column, Rotation(row as i32));
+                        match is_sc(*opcode) {
+                            true => sum + flag * success.clone(),
+                            false => sum + flag,
+                        }
+                    })
+            };
+            let (read_word, read_double) = (flags(&WORD, false), flags(&DOUBLE, false));
+            let (write_word, write_double) = (flags(&WORD, true), flags(&DOUBLE, true));
+
+            let mut constraints = vec![];
+            for (first, word, double, write) in [
+                (READ_PORT, read_word, read_double, false),
+                (WRITE_PORT, write_word, write_double, true),
+            ] {
+                for byte in 0..2 * WORD_BYTES {
+                    let row = Rotation((first + byte) as i32);
+                    let enabled = match byte < WORD_BYTES {
+                        true => word.clone() + double.clone(),
+                        false => double.clone(),
+                    };
+                    let address = rs1.clone() + constant(byte as u128);
+                    constraints.push(
+                        atomic.clone()
+                            * (vc.query_advice(step.port_enabled, row) - enabled.clone()),
+                    );
+                    constraints.push(
+                        atomic.clone()
+                            * (vc.query_advice(step.port_address, row) - enabled.clone() * address),
+                    );
+                    let written = if write {
+                        enabled
+                    } else {
+                        Expression::Constant(F::zero())
+                    };
+                    constraints
+                        .push(atomic.clone() * (vc.query_advice(step.port_write, row) - written));
+                }
+            }
+            constraints
+        });
+
+        let wrap = constant(1 << 64);
+        let word_wrap = constant(1 << 32);
+        let sign_extension = constant((1 << 64) - (1 << 32));
+
+        cs.create_gate("AType::rd", |vc| {
+            let loaded = bytes(vc, READ_PORT);
+            let rd = vc.query_advice(step.rd, Rotation::cur());
+            let sign = vc.query_advice(step.aux[0], Rotation::cur());
+            let success = vc.query_advice(step.aux[2], Rotation::cur());
+            let word = sum(vc, &mut WORD.iter().filter(|opcode| !is_sc(**opcode)));
+            let double = sum(vc, &mut DOUBLE.iter().filter(|opcode| !is_sc(**opcode)));
+            let sc = step.flag(vc, Opcode::SCW) + step.flag(vc, Opcode::SCD);
+            vec![
+                word * (loaded.clone() + sign * sign_extension.clone() - rd.clone()),
+                double * (loaded - rd.clone()),
+                sc * (one.clone() - success - rd),
+            ]
+        });
+
+        // lhs is loaded and rhs is rs2 when the limbs are used.
+        cs.create_gate("AType::limbs", |vc| {
+            let uses = |opcode: &Opcode| bitwise::tag(*opcode) != 0;
+            let lhs_loaded = sum(
+                vc,
+                &mut WORD
+                    .iter()
+                    .chain(DOUBLE.iter())
+                    .filter(|opcode| uses(opcode) && !is_sc(**opcode)),
+            );
+            let rhs_rs2 = sum(
+                vc,
+                &mut WORD
+                    .iter()
+                    .chain(DOUBLE.iter())
+                    .filter(|opcode| uses(opcode) && !is_lr(**opcode)),
+            );
+            let loaded = bytes(vc, READ_PORT);
+            let rs2 = vc.query_advice(step.rs2, Rotation::cur());
+            vec![
+                lhs_loaded * (bitwise.value(vc, bitwise.acc_lhs_col) - loaded),
+                rhs_rs2 * (bitwise.value(vc, bitwise.acc_rhs_col) - rs2),
+            ]
+        });
+
+        cs.create_gate("AType::sign", |vc| {
+            let loaded = bytes(vc, READ_PORT);
+            let sign = vc.query_advice(step.aux[0], Rotation::cur());
+            let rs2_sign = vc.query_advice(step.aux[1], Rotation::cur());
+            let signs = |vc: &mut VirtualCells<'_, F>, row: usize| {
+                bitwise.limb(vc, bitwise.out_col, row)
+                    - constant(2) * sign.clone()
+                    - rs2_sign.clone()
+            };
+            let ext_sign = sum(
+                vc,
+                &mut WORD
+                    .iter()
+                    .filter(|opcode| bitwise::tag(**opcode) == TAG_XOR && !is_sc(**opcode)),
+            );
+            let word_signs = sum(
+                vc,
+                &mut WORD
+                    .iter()
+                    .filter(|opcode| bitwise::tag(**opcode) == TAG_SIGNS),
+            );
+            let double_signs = sum(
+                vc,
+                &mut DOUBLE
+                    .iter()
+                    .filter(|opcode| bitwise::tag(**opcode) == TAG_SIGNS),
+            );
+            let signed = ext_sign.clone() + word_signs.clone() + double_signs.clone();
+            vec![
+                ext_sign.clone()
+                    * (bitwise.value(vc, bitwise.acc_ext_col)
+                        - constant(2) * (loaded - sign.clone() * constant(1 << 31))),
+                ext_sign * bitwise.high(vc, bitwise.acc_ext_col),
+                word_signs * signs(vc, WORD_BYTES * 2),
+                double_signs * signs(vc, 0),
+                signed.clone() * sign.clone() * (one.clone() - sign),
+                signed * rs2_sign.clone() * (one.clone() - rs2_sign),
+            ]
+        });
+
+        cs.create_gate("AType::stored", |vc| {
+            let loaded = bytes(vc, READ_PORT);
+            let stored = bytes(vc, WRITE_PORT);
+            let rs2 = vc.query_advice(step.rs2, Rotation::cur());
+            let sign = vc.query_advice(step.aux[0], Rotation::cur());
+            let rs2_sign = vc.query_advice(step.aux[1], Rotation::cur());
+            let carry = vc.query_advice(step.aux[2], Rotation::cur());
+            let success = vc.query_advice(step.aux[2], Rotation::cur());
+            let rhs_low = bitwise.low(vc, bitwise.acc_rhs_col);
+            let xor = bitwise.value(vc, bitwise.acc_out_col);
+            let xor_low = bitwise.low(vc, bitwise.acc_out_col);
+            let ext = bitwise.value(vc, bitwise.acc_ext_col);
+            let ext_high = bitwise.high(vc, bitwise.acc_ext_col);
+            let two = constant(2);
+
+            let mut constraints = vec![];
+            for (opcodes, operand, xor, wrap) in [
+                (&WORD, rhs_low, xor_low, word_wrap.clone()),
+                (&DOUBLE, rs2, xor, wrap.clone()),
+            ] {
+                let [_, sc, swap, add, xor_flag, and, or, min, max, minu, maxu] =
+                    opcodes.map(|opcode| step.flag(vc, opcode));
+                constraints.extend([
+                    sc * (stored.clone() - success.clone() * operand.clone()),
+                    swap * (stored.clone() - operand.clone()),
+                    add.clone()
+                        * (loaded.clone() + operand.clone()
+                            - stored.clone()
+                            - carry.clone() * wrap.clone()),
+                    add * carry.clone() * (one.clone() - carry.clone()),
+                    xor_flag * (stored.clone() - xor.clone()),
+                    and * (loaded.clone() + operand.clone()
+                        - xor.clone()
+                        - two.clone() * stored.clone()),
+                    or * (loaded.clone() + operand.clone() + xor - two.clone() * stored.clone()),
+                ]);
+
+                // The lowest of loaded and the operand, with the difference
+                // of their signed or unsigned values in the ext limbs.
+                let less = carry.clone();
+                let lowest = operand.clone() + less.clone() * (loaded.clone() - operand.clone());
+                let highest = loaded.clone() + operand.clone() - lowest.clone();
+                let difference = loaded.clone() - operand.clone() + less.clone() * wrap.clone();
+                let signs = (sign.clone() - rs2_sign.clone()) * wrap.clone();
+                let comparisons = min.clone() + max.clone() + minu.clone() + maxu.clone();
+                constraints.extend([
+                    comparisons.clone() * less.clone() * (one.clone() - less.clone()),
+                    (min.clone() + max.clone()) * (ext.clone() - difference.clone() + signs),
+                    (minu.clone() + maxu.clone()) * (ext.clone() - difference),
+                    (min.clone() + minu.clone()) * (stored.clone() - lowest),
+                    (max + maxu) * (stored.clone() - highest),
+                ]);
+                if opcodes == &WORD {
+                    // The difference of words fits in 32 bits.
+                    constraints.push(comparisons * ext_high.clone());
+                }
+            }
+            constraints
+        });
+
+        // SC succeeds when the address is reserved.
+        cs.create_gate("AType::SC", |vc| {
+            let reserved = vc.query_advice(reserved, Rotation::cur());
+            let difference = vc.query_advice(reservation, Rotation::cur())
+                - vc.query_advice(step.rs1, Rotation::cur());
+            let inverse = vc.query_advice(step.aux[0], Rotation::cur());
+            let equal = vc.query_advice(step.aux[1], Rotation::cur());
+            let success = vc.query_advice(step.aux[2], Rotation::cur());
+            let sc = step.flag(vc, Opcode::SCW) + step.flag(vc, Opcode::SCD);
+            vec![
+                sc.clone() * (equal.clone() - one.clone() + difference.clone() * inverse),
+                sc.clone() * difference * equal.clone(),
+                sc * (success - reserved * equal),
+            ]
+        });
+
+        // LR reserves rs1 and SC clears the reservation, the other steps keep it.
+        cs.create_gate("AType::reservation", |vc| {
+            let q_chain = vc.query_selector(step.q_chain);
+            let rows = Rotation(crate::execution_table::STEP_ROWS as i32);
+            let enabled_next = vc.query_advice(step.enabled, rows);
+            let reserved_next = vc.query_advice(reserved, rows);
+            let reservation_next = vc.query_advice(reservation, rows);
+            let reserved = vc.query_advice(reserved, Rotation::cur());
+            let reservation = vc.query_advice(reservation, Rotation::cur());
+            let rs1 = vc.query_advice(step.rs1, Rotation::cur());
+            let lr = step.select(vc, |opcode| is_lr(opcode) as u64);
+            let sc = step.select(vc, |opcode| is_sc(opcode) as u64);
+            let s = q_chain * enabled_next;
+            vec![
+                s.clone()
+                    * (reserved_next.clone()
+                        - lr.clone()
+                        - (one.clone() - lr.clone() - sc) * reserved),
+                s * reserved_next
+                    * (reservation_next - lr.clone() * rs1 - (one.clone() - lr) * reservation),
+            ]
+        });
+
+        // Nothing is reserved before the first step.
+        cs.create_gate("AType::first reservation", |vc| {
+            let q_first = vc.query_selector(step.q_first);
+            vec![q_first * vc.query_advice(reserved, Rotation::cur())]
+        });
+
+        Self {
+            reserved,
+            reservation,
+            step: step.clone(),
+            bitwise: bitwise.clone(),
+            _maker: PhantomData::default(),
+        }
+    }
+
+    pub fn columns(&self) -> Vec<Column<Advice>> {
+        vec![self.reserved, self.reservation]
+    }
+
+    /// The reservation after `step`, from the one before it.
+    pub fn reservation_after(step: &OpStep, reservation: Option<u64>) -> Option<u64> {
+        let opcode = step.instruction.opcode;
+        if is_lr(opcode) {
+            step.memory_address
+                .and_then(|ops| ops.read())
+                .map(|op| op.address)
+        } else if is_sc(opcode) {
+            None
+        } else {
+            reservation
+        }
+    }
+
+    /// Assigns the reservation before a step.
+    pub fn assign_reservation(&self, slot: &mut Slot<F>, reservation: Option<u64>) {
+        slot.assign(self.reserved, 0, F::from(reservation.is_some() as u64));
+        slot.assign(self.reservation, 0, F::from(reservation.unwrap_or(0)));
+    }
+
+    pub fn assign(
+        &self,
+        slot: &mut Slot<F>,
+        step: &OpStep,
+        reservation: Option<u64>,
+    ) -> Result<(), Error> {
+        let opcode = step.instruction.opcode;
+        let registers = step.register_indexes.ok_or(Error::Synthesis)?;
+        let rs1_value = registers
+            .read(step.instruction.op_b)
+            .ok_or(Error::Synthesis)?;
+        let rs2_value = match is_lr(opcode) {
+            true => 0,
+            false => registers
+                .read(step.instruction.op_c)
+                .ok_or(Error::Synthesis)?,
+        };
+        let rd_value = registers
+            .write(step.instruction.op_a)
+            .ok_or(Error::Synthesis)?;
+        let memory_ops = step.memory_address;
+        let loaded = memory_ops
+            .and_then(|ops| ops.read())
+            .map_or(0, |op| op.value);
+        let word = WORD.contains(&opcode);
+        let bits = if word { 32 } else { 64 };
+        let operand = if word {
+            rs2_value as u32 as u64
+        } else {
+            rs2_value
+        };
+
+        let mut aux = [0u64; 3];
+        let tag = bitwise::tag(opcode);
+        if tag != 0 {
+            let lhs = if is_sc(opcode) { 0 } else { loaded };
+            self.bitwise.assign_limbs(slot, tag, lhs, rs2_value);
+        }
+        if tag == TAG_SIGNS {
+            aux[0] = (loaded >> (bits - 1)) & 1;
+            aux[1] = (operand >> (bits - 1)) & 1;
+            let value = |value: u64, sign: u64| value as i128 - ((sign as i128) << bits);
+            let (lhs, rhs) = match opcode {
+                Opcode::AMOMINW | Opcode::AMOMAXW | Opcode::AMOMIND | Opcode::AMOMAXD => {
+                    (value(loaded, aux[0]), value(operand, aux[1]))
+                }
+                _ => (loaded as i128, operand as i128),
+            };
+            aux[2] = (lhs < rhs) as u64;
+            let difference = lhs - rhs + ((aux[2] as i128) << bits);
+            self.bitwise.assign_ext(slot, difference as u64);
+        } else if word && !is_sc(opcode) {
+            aux[0] = (loaded >> 31) & 1;
+            self.bitwise.assign_ext(slot, (loaded << 1) & 0xffff_ffff);
+        }
+        match opcode {
+            Opcode::AMOADDW => aux[2] = (loaded + operand) >> 32,
+            Opcode::AMOADDD => aux[2] = loaded.overflowing_add(rs2_value).1 as u64,
+            Opcode::SCW | Opcode::SCD => {
+                let difference = F::from(reservation.unwrap_or(0)) - F::from(rs1_value);
+                let inverse = difference.invert().unwrap_or(F::zero());
+                let equal = reservation.unwrap_or(0) == rs1_value;
+                slot.assign(self.step.aux[0], 0, inverse);
+                aux[1] = equal as u64;
+                aux[2] = (reservation == Some(rs1_value)) as u64;
+            }
+            _ => {}
+        }
+
+        for (column, value) in [
+            (self.step.rs1, rs1_value),
+            (self.step.rs2, rs2_value),
+            (self.step.rd, rd_value),
+        ] {
+            slot.assign(column, 0, F::from(value));
+        }
+        for (index, value) in aux.into_iter().enumerate() {
+            if !(is_sc(opcode) && index == 0) {
+                slot.assign(self.step.aux[index], 0, F::from(value));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::main_circuit::tests::{circuit, program, trace, verify};
+    use entry_builder::entries::{MemoryOp, RW};
+    use runtime::program::Program;
+    use runtime::trace::Trace;
+
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    const ADDRESS: u64 = 0x1000;
+
+    // amoswap.d zero, a3, (a1), the store has no gate
+    const STORE: (u64, u32) = (0x10000, 0x08d5b02f);
+
+    /// `funct5.aqrl a0, a2, (a1)` of the width of `funct3`.
+    fn atomic(funct5: u32, funct3: u32) -> u32 {
+        funct5 << 27 | 3 << 25 | 12 << 20 | 11 << 15 | funct3 << 12 | 10 << 7 | 0x2f
+    }
+
+    /// The AMOs of each width, in the order of their funct5: SWAP, ADD, XOR,
+    /// AND, OR, MIN, MAX, MINU, MAXU.
+    const FUNCT5: [u32; 9] = [
+        0b00001, 0b00000, 0b00100, 0b01100, 0b01000, 0b10000, 0b10100, 0b11000, 0b11100,
+    ];
+
+    /// `(rd, stored)` of the AMO `funct5` of `bits` on `loaded` and `rs2`.
+    fn amo(funct5: u32, bits: u32, loaded: u64, rs2: u64) -> (u64, u64) {
+        let extend = |value: u64| match bits {
+            32 => value as i32 as i64 as u64,
+            _ => value,
+        };
+        let (loaded, rs2) = (extend(loaded), extend(rs2));
+        let stored = match funct5 {
+            0b00001 => rs2,
+            0b00000 => loaded.wrapping_add(rs2),
+            0b00100 => loaded ^ rs2,
+            0b01100 => loaded & rs2,
+            0b01000 => loaded | rs2,
+            0b10000 => (loaded as i64).min(rs2 as i64) as u64,
+            0b10100 => (loaded as i64).max(rs2 as i64) as u64,
+            0b11000 => loaded.min(rs2),
+            _ => loaded.max(rs2),
+        };
+        (loaded, stored & (u64::MAX >> (64 - bits)))
+    }
+
+    /// All the AMOs after storing `a3`, each on the value the previous one
+    /// stored, with their program.
+    fn amo_trace(a3: u64, a2: u64) -> (Program, Trace) {
+        let mut code = vec![STORE];
+        let mut steps = vec![(STORE.0, vec![])];
+        let mut memory = a3;
+        for (funct3, bits) in [(2, 32), (3, 64)] {
+            for funct5 in FUNCT5 {
+                let pc = STORE.0 + 4 * code.len() as u64;
+                let (rd, stored) = amo(funct5, bits, memory & (u64::MAX >> (64 - bits)), a2);
+                memory = memory & !(u64::MAX >> (64 - bits)) | stored;
+                code.push((pc, atomic(funct5, funct3)));
+                steps.push((pc, vec![(10, rd)]));
+            }
+        }
+        let program = program(&code);
+        let steps: Vec<_> = steps
+            .iter()
+            .map(|(pc, writes)| (*pc, &writes[..]))
+            .collect();
+        let trace = trace(&program, &[(11, ADDRESS), (12, a2), (13, a3)], &steps);
+        (program, trace)
+    }
+
+    #[test]
+    fn proves_atomics() {
+        for (a3, a2) in [
+            (5, 3),
+            (u64::MAX, 2),
+            (0x8000_0000_7fff_ffff, 0x7fff_ffff_8000_0000),
+            (0x1234_5678_9abc_def0, 0x0fed_cba9_8765_4321),
+        ] {
+            let (program, trace) = amo_trace(a3, a2);
+            assert!(verify(&circuit(program, &trace)), "{:#x} {:#x}", a3, a2);
+        }
+    }
+
+    #[test]
+    fn rejects_tampered_amoadd() {
+        let (program, trace) = amo_trace(u64::MAX, 2);
+        let mut circuit = circuit(program, &trace);
+        // The stored value of AMOADD.D without the wrap around.
+        let ops = &mut circuit.entries.memory_ops.get_mut(&11).unwrap().ops;
+        ops.last_mut().unwrap().value = 2;
+        assert!(!verify(&circuit));
+    }
+
+    #[test]
+    fn rejects_read_of_another_value() {
+        let (program, trace) = amo_trace(5, 3);
+        // AMOSWAP.W loads 7 into a0 where the store wrote 5.
+        let mut circuit = circuit(program, &trace);
+        circuit.entries.memory_ops.get_mut(&1).unwrap().ops[0].value = 7;
+        let rd = circuit.entries.register_ops.get_mut(&1).unwrap();
+        rd.ops.last_mut().unwrap().value = 7;
+        assert!(!verify(&circuit));
+    }
+
+    // lr.d a0, (a1); sc.d a3, a2, (a1); sc.d a3, a2, (a1); lr.w a0, (a1);
+    // sc.w a3, a2, (a1)
+    const LR_SC: [(u64, u32); 6] = [
+        STORE,
+        (0x10004, 0x1605b52f),
+        (0x10008, 0x1ec5b6af),
+        (0x1000c, 0x1ec5b6af),
+        (0x10010, 0x1605a52f),
+        (0x10014, 0x1ec5a6af),
+    ];
+
+    fn lr_sc_trace(program: &Program, a3: u64, a2: u64) -> Trace {
+        let word = a2 as u32 as i32 as i64 as u64;
+        trace(
+            program,
+            &[(11, ADDRESS), (12, a2), (13, a3)],
+            &[
+                (0x10000, &[]),
+                (0x10004, &[(10, a3)]),
+                (0x10008, &[(13, 0)]),
+                (0x1000c, &[(13, 1)]),
+                (0x10010, &[(10, word)]),
+                (0x10014, &[(13, 0)]),
+            ],
+        )
+    }
+
+    #[test]
+    fn proves_lr_sc() {
+        let program = program(&LR_SC);
+        for (a3, a2) in [(5, 3), (1, 0xffff_ffff_8000_0000)] {
+            let trace = lr_sc_trace(&program, a3, a2);
+            assert!(verify(&circuit(program.clone(), &trace)));
+        }
+    }
+
+    #[test]
+    fn rejects_sc_without_reservation() {
+        let program = program(&LR_SC);
+        let trace = lr_sc_trace(&program, 5, 3);
+        // The second SC.D succeeds too.
+        let mut circuit = circuit(program, &trace);
+        let rd = circuit.entries.register_ops.get_mut(&3).unwrap();
+        rd.ops.last_mut().unwrap().value = 0;
+        circuit
+            .entries
+            .memory_ops
+            .entry(3)
+            .or_default()
+            .push(MemoryOp {
+                global_clk: 3,
+                rw: RW::WRITE,
+                address: ADDRESS,
+                value: 3,
+                width: 64,
+            });
+        assert!(!verify(&circuit));
+    }
+}
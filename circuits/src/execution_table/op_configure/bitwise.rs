@@ -0,0 +1,817 @@
+use alloc::collections::BTreeSet;
+use core::marker::PhantomData;
+use entry_builder::op_step::OpStep;
+use runtime::trace::Opcode;
+
+use alloc::vec;
+use alloc::vec::Vec;
+use halo2_proofs::arithmetic::FieldExt;
+use halo2_proofs::circuit::{Layouter, Region, Value};
+use halo2_proofs::plonk::*;
+use halo2_proofs::poly::Rotation;
+
+use crate::execution_table::{Slot, StepConfig, STEP_ROWS};
+
+/// Number of 4-bit limbs of a register.
+const LIMBS: usize = STEP_ROWS;
+const LIMB_BASE: u64 = 16;
+
+// Tags of the bitwise lookup table, 0 is the padding row used by the other steps.
+/// out is `lhs ^ rhs`, the AND and OR of the limbs are linear in lhs, rhs and out.
+pub const TAG_XOR: u64 = 1;
+/// out is the popcount of lhs, rhs is 0.
+pub const TAG_CPOP: u64 = 2;
+/// out is the lowest bit of lhs, rhs is 0.
+pub const TAG_RANGE: u64 = 3;
+/// out is `2 · (lhs >> 3) + (rhs >> 3)`, the highest bits of the limbs, e.g. the
+/// signs of the operands on their most significant limb.
+pub const TAG_SIGNS: u64 = 4;
+/// rhs is whether lhs is non-zero, out its leading zeros, 4 for 0.
+pub const TAG_CLZ: u64 = 5;
+/// rhs is whether lhs is non-zero, out its trailing zeros, 4 for 0.
+pub const TAG_CTZ: u64 = 6;
+const TAGS: [u64; 6] = [TAG_XOR, TAG_CPOP, TAG_RANGE, TAG_SIGNS, TAG_CLZ, TAG_CTZ];
+
+/// The rhs and out of the row of the table with `tag`, `lhs` and `rhs`. The
+/// tags of a single operand have a rhs of 0 or set from lhs.
+fn table_row(tag: u64, lhs: u64, rhs: u64) -> (u64, u64) {
+    match tag {
+        TAG_XOR => (rhs, lhs ^ rhs),
+        TAG_CPOP => (0, lhs.count_ones() as u64),
+        TAG_RANGE => (0, lhs & 1),
+        TAG_SIGNS => (rhs, 2 * (lhs >> 3) + (rhs >> 3)),
+        TAG_CLZ => ((lhs != 0) as u64, (lhs as u8).leading_zeros() as u64 - 4),
+        TAG_CTZ => (
+            (lhs != 0) as u64,
+            (lhs as u8 | 0x10).trailing_zeros() as u64,
+        ),
+        _ => (0, 0),
+    }
+}
+
+/// Tag of the limbs of the steps of `opcode`, 0 if its gadget doesn't use
+/// them.
+pub fn tag(opcode: Opcode) -> u64 {
+    match opcode {
+        Opcode::ANDN | Opcode::ORN | Opcode::XNOR => TAG_XOR,
+        Opcode::CPOP | Opcode::CPOPW => TAG_CPOP,
+        Opcode::CLZ | Opcode::CLZW | Opcode::ORCB => TAG_CLZ,
+        Opcode::CTZ | Opcode::CTZW => TAG_CTZ,
+        Opcode::REV8 | Opcode::ZEXTH => TAG_RANGE,
+        Opcode::SEXTB | Opcode::SEXTH | Opcode::MIN | Opcode::MAX | Opcode::MINU | Opcode::MAXU => {
+            TAG_SIGNS
+        }
+        Opcode::SH1ADD | Opcode::SH2ADD | Opcode::SH3ADD => TAG_RANGE,
+        Opcode::ADDUW
+        | Opcode::SH1ADDUW
+        | Opcode::SH2ADDUW
+        | Opcode::SH3ADDUW
+        | Opcode::SLLIUW
+        | Opcode::ROL
+        | Opcode::ROR
+        | Opcode::RORI => TAG_XOR,
+        Opcode::ROLW
+        | Opcode::RORW
+        | Opcode::RORIW
+        | Opcode::BCLR
+        | Opcode::BCLRI
+        | Opcode::BEXT
+        | Opcode::BEXTI
+        | Opcode::BINV
+        | Opcode::BINVI
+        | Opcode::BSET
+        | Opcode::BSETI => TAG_SIGNS,
+        Opcode::FARJUMPREL => TAG_RANGE,
+        Opcode::LRW
+        | Opcode::SCW
+        | Opcode::AMOSWAPW
+        | Opcode::AMOADDW
+        | Opcode::AMOXORW
+        | Opcode::AMOXORD
+        | Opcode::AMOANDW
+        | Opcode::AMOANDD
+        | Opcode::AMOORW
+        | Opcode::AMOORD => TAG_XOR,
+        Opcode::AMOMINW
+        | Opcode::AMOMIND
+        | Opcode::AMOMAXW
+        | Opcode::AMOMAXD
+        | Opcode::AMOMINUW
+        | Opcode::AMOMINUD
+        | Opcode::AMOMAXUW
+        | Opcode::AMOMAXUD => TAG_SIGNS,
+        _ => 0,
+    }
+}
+
+/// The rows of the limbs CLZ, CTZ and their word forms count the zeros of, in
+/// the order they count them.
+fn count_rows(opcode: Opcode) -> Vec<usize> {
+    match opcode {
+        Opcode::CLZ => (0..LIMBS).collect(),
+        Opcode::CLZW => (LIMBS / 2..LIMBS).collect(),
+        Opcode::CTZ => (0..LIMBS).rev().collect(),
+        _ => (LIMBS / 2..LIMBS).rev().collect(),
+    }
+}
+
+/// Splits the operands of bit manipulation instructions into 4-bit limbs, from
+/// the most significant one, one per row of the step, and looks each limb up
+/// in a table of `(tag, lhs, rhs, out)`, see [`tag`].
+///
+/// The tag is set on the first row from the flags of the step and copied to
+/// the other rows, it is 0 on the steps of other instructions, whose limbs
+/// are then 0 too. The accumulator columns hold the running sums of the limbs,
+/// the gadgets constrain the ones of the last row, the values of the limbs,
+/// or of the row 7 for their upper 32 bits. CPOP sums the popcount of the
+/// limbs instead of shifting them. The ext limbs are only range checked, for
+/// the values which need more than lhs and rhs.
+///
+/// The Zbb instructions are proven here. CLZ and CTZ set the ext limbs from the
+/// first non-zero limb they count, the out limbs of the limbs before it add up
+/// to the result. MIN and MAX compare their operands like the atomic
+/// instructions, see [`crate::execution_table::op_configure::atype`].
+/// FARJUMPREL splits its target into limbs of lhs with the range tag, see
+/// [`BitwiseGadget::assign_range`], the atomic instructions use the limbs for
+/// their logic operations, comparisons and signs, and the Zba and Zbs
+/// instructions and the rotations for the values their shifts split, see
+/// [`crate::execution_table::op_configure::shift`].
+#[derive(Clone)]
+pub struct BitwiseGadget<F> {
+    pub tag_col: Column<Advice>,
+    pub cpop_col: Column<Advice>,
+    pub lhs_col: Column<Advice>,
+    pub rhs_col: Column<Advice>,
+    pub out_col: Column<Advice>,
+    pub ext_col: Column<Advice>,
+    pub acc_lhs_col: Column<Advice>,
+    pub acc_rhs_col: Column<Advice>,
+    pub acc_out_col: Column<Advice>,
+    pub acc_ext_col: Column<Advice>,
+    pub tag_table: TableColumn,
+    pub lhs_table: TableColumn,
+    pub rhs_table: TableColumn,
+    pub out_table: TableColumn,
+    pub limb_table: TableColumn,
+    /// Set on all the rows of a step.
+    s_limb: Selector,
+    /// Set on all the rows of a step but the last one.
+    s_next: Selector,
+    step: StepConfig,
+    _maker: PhantomData<F>,
+}
+
+impl<F: FieldExt> BitwiseGadget<F> {
+    pub fn configure(cs: &mut ConstraintSystem<F>, step: &StepConfig) -> Self {
+        let tag_col = cs.advice_column();
+        let cpop_col = cs.advice_column();
+        let lhs_col = cs.advice_column();
+        let rhs_col = cs.advice_column();
+        let out_col = cs.advice_column();
+        let ext_col = cs.advice_column();
+        let acc_lhs_col = cs.advice_column();
+        let acc_rhs_col = cs.advice_column();
+        let acc_out_col = cs.advice_column();
+        let acc_ext_col = cs.advice_column();
+        let tag_table = cs.lookup_table_column();
+        let lhs_table = cs.lookup_table_column();
+        let rhs_table = cs.lookup_table_column();
+        let out_table = cs.lookup_table_column();
+        let limb_table = cs.lookup_table_column();
+        let s_limb = cs.complex_selector();
+        let s_next = cs.selector();
+
+        cs.lookup("bitwise limb", |vc| {
+            let s = vc.query_selector(s_limb);
+            vec![
+                (
+                    s.clone() * vc.query_advice(tag_col, Rotation::cur()),
+                    tag_table,
+                ),
+                (
+                    s.clone() * vc.query_advice(lhs_col, Rotation::cur()),
+                    lhs_table,
+                ),
+                (
+                    s.clone() * vc.query_advice(rhs_col, Rotation::cur()),
+                    rhs_table,
+                ),
+                (s * vc.query_advice(out_col, Rotation::cur()), out_table),
+            ]
+        });
+
+        cs.lookup("bitwise ext limb", |vc| {
+            let s = vc.query_selector(s_limb);
+            vec![(s * vc.query_advice(ext_col, Rotation::cur()), limb_table)]
+        });
+
+        cs.create_gate("bitwise tag", |vc| {
+            let q_step = vc.query_selector(step.q_step);
+            let expected_tag = step.select(vc, tag);
+            let expected_cpop = step.select(vc, |opcode| (tag(opcode) == TAG_CPOP) as u64);
+            vec![
+                q_step.clone() * (vc.query_advice(tag_col, Rotation::cur()) - expected_tag),
+                q_step * (vc.query_advice(cpop_col, Rotation::cur()) - expected_cpop),
+            ]
+        });
+
+        cs.create_gate("bitwise tag chain", |vc| {
+            let s_next = vc.query_selector(s_next);
+            let tag = vc.query_advice(tag_col, Rotation::cur());
+            let cpop = vc.query_advice(cpop_col, Rotation::cur());
+            vec![
+                s_next.clone() * (vc.query_advice(tag_col, Rotation::next()) - tag),
+                s_next * (vc.query_advice(cpop_col, Rotation::next()) - cpop),
+            ]
+        });
+
+        cs.create_gate("bitwise accumulate", |vc| {
+            let s_first = vc.query_selector(step.q_step);
+            let s_next = vc.query_selector(s_next);
+            let cpop = vc.query_advice(cpop_col, Rotation::cur());
+            let one = Expression::Constant(F::one());
+            let base = Expression::Constant(F::from(LIMB_BASE));
+
+            let mut constraints = vec![];
+            for (limb_col, acc_col) in [
+                (lhs_col, acc_lhs_col),
+                (rhs_col, acc_rhs_col),
+                (out_col, acc_out_col),
+                (ext_col, acc_ext_col),
+            ] {
+                let limb = vc.query_advice(limb_col, Rotation::cur());
+                let acc = vc.query_advice(acc_col, Rotation::cur());
+                let limb_next = vc.query_advice(limb_col, Rotation::next());
+                let acc_next = vc.query_advice(acc_col, Rotation::next());
+                constraints.push(s_first.clone() * (acc.clone() - limb));
+                // CPOP adds up the limbs of the output instead of shifting them.
+                let scale = if acc_col == acc_out_col {
+                    cpop.clone() + (one.clone() - cpop.clone()) * base.clone()
+                } else {
+                    base.clone()
+                };
+                constraints.push(s_next.clone() * (acc_next - acc * scale - limb_next));
+            }
+            constraints
+        });
+
+        let gadget = Self {
+            tag_col,
+            cpop_col,
+            lhs_col,
+            rhs_col,
+            out_col,
+            ext_col,
+            acc_lhs_col,
+            acc_rhs_col,
+            acc_out_col,
+            acc_ext_col,
+            tag_table,
+            lhs_table,
+            rhs_table,
+            out_table,
+            limb_table,
+            s_limb,
+            s_next,
+            step: step.clone(),
+            _maker: PhantomData::default(),
+        };
+
+        // With x = lhs ^ rhs: lhs & !rhs = (lhs - rhs + x) / 2 and
+        // lhs | !rhs = !rhs + (lhs & rhs) = max - rhs + (lhs + rhs - x) / 2.
+        cs.create_gate("bitwise operands", |vc| {
+            let two_operands = step.flag(vc, Opcode::ANDN)
+                + step.flag(vc, Opcode::ORN)
+                + step.flag(vc, Opcode::XNOR);
+            let compare = step.flag(vc, Opcode::MIN)
+                + step.flag(vc, Opcode::MAX)
+                + step.flag(vc, Opcode::MINU)
+                + step.flag(vc, Opcode::MAXU);
+            let sign_extend = step.flag(vc, Opcode::SEXTB) + step.flag(vc, Opcode::SEXTH);
+            let single_operand = [
+                Opcode::CPOP,
+                Opcode::CPOPW,
+                Opcode::CLZ,
+                Opcode::CLZW,
+                Opcode::CTZ,
+                Opcode::CTZW,
+                Opcode::ORCB,
+                Opcode::REV8,
+                Opcode::ZEXTH,
+            ]
+            .into_iter()
+            .fold(sign_extend.clone(), |sum, opcode| {
+                sum + step.flag(vc, opcode)
+            });
+            let rs1 = vc.query_advice(step.rs1, Rotation::cur());
+            let rs2 = vc.query_advice(step.rs2, Rotation::cur());
+            let rd = vc.query_advice(step.rd, Rotation::cur());
+            let lhs = gadget.value(vc, acc_lhs_col);
+            let rhs = gadget.value(vc, acc_rhs_col);
+            let out = gadget.value(vc, acc_out_col);
+            let two = Expression::Constant(F::from(2));
+            let max = Expression::Constant(F::from(u64::MAX));
+            let byte = Expression::Constant(F::from(256));
+            let nibble = Expression::Constant(F::from(LIMB_BASE));
+            // The bytes of lhs from the most significant one, and whether
+            // they are non-zero from the rhs limbs of the clz tag.
+            let bytes: Vec<_> = (0..LIMBS / 2)
+                .map(|index| {
+                    gadget.limb(vc, lhs_col, 2 * index) * nibble.clone()
+                        + gadget.limb(vc, lhs_col, 2 * index + 1)
+                })
+                .collect();
+            let nonzero_bytes: Vec<_> = (0..LIMBS / 2)
+                .map(|index| {
+                    let high = gadget.limb(vc, rhs_col, 2 * index);
+                    let low = gadget.limb(vc, rhs_col, 2 * index + 1);
+                    high.clone() + low.clone() - high * low
+                })
+                .collect();
+            let reversed = bytes
+                .iter()
+                .rev()
+                .fold(Expression::Constant(F::zero()), |sum, byte_value| {
+                    sum * byte.clone() + byte_value.clone()
+                });
+            let or_combined =
+                nonzero_bytes
+                    .iter()
+                    .fold(Expression::Constant(F::zero()), |sum, nonzero| {
+                        sum * byte.clone() + nonzero.clone() * Expression::Constant(F::from(0xff))
+                    });
+            let low_byte = bytes[LIMBS / 2 - 1].clone();
+            let low_half = bytes[LIMBS / 2 - 2].clone() * byte + low_byte.clone();
+            // The sign of the low byte or half, twice, from the signs tag.
+            let sign_b = gadget.limb(vc, out_col, LIMBS - 2);
+            let sign_h = gadget.limb(vc, out_col, LIMBS - 4);
+            vec![
+                (two_operands.clone() + compare.clone() + single_operand) * (lhs.clone() - rs1),
+                (two_operands + compare) * (rhs.clone() - rs2),
+                sign_extend * rhs.clone(),
+                step.flag(vc, Opcode::ANDN)
+                    * (lhs.clone() - rhs.clone() + out.clone() - two.clone() * rd.clone()),
+                step.flag(vc, Opcode::ORN)
+                    * (two.clone() * max.clone() + lhs
+                        - rhs
+                        - out.clone()
+                        - two.clone() * rd.clone()),
+                step.flag(vc, Opcode::XNOR) * (max - out.clone() - rd.clone()),
+                step.flag(vc, Opcode::CPOP) * (out.clone() - rd.clone()),
+                step.flag(vc, Opcode::CPOPW) * (out - gadget.high(vc, acc_out_col) - rd.clone()),
+                step.flag(vc, Opcode::REV8) * (reversed - rd.clone()),
+                step.flag(vc, Opcode::ORCB) * (or_combined - rd.clone()),
+                step.flag(vc, Opcode::SEXTB)
+                    * (two.clone() * (low_byte - rd.clone())
+                        + sign_b * Expression::Constant(F::from(u64::MAX << 8))),
+                step.flag(vc, Opcode::SEXTH)
+                    * (two * (low_half.clone() - rd.clone())
+                        + sign_h * Expression::Constant(F::from(u64::MAX << 16))),
+                step.flag(vc, Opcode::ZEXTH) * (low_half - rd),
+            ]
+        });
+
+        // The ext limb of a counted limb is set once a non-zero limb is
+        // counted, the out limbs add up until then.
+        cs.create_gate("bitwise count", |vc| {
+            let one = Expression::Constant(F::one());
+            let rd = vc.query_advice(step.rd, Rotation::cur());
+            let mut constraints = vec![];
+            for opcode in [Opcode::CLZ, Opcode::CLZW, Opcode::CTZ, Opcode::CTZW] {
+                let flag = step.flag(vc, opcode);
+                let mut seen = Expression::Constant(F::zero());
+                let mut count = Expression::Constant(F::zero());
+                for row in count_rows(opcode) {
+                    let nonzero = gadget.limb(vc, rhs_col, row);
+                    let ext = gadget.limb(vc, ext_col, row);
+                    count = count + (one.clone() - seen.clone()) * gadget.limb(vc, out_col, row);
+                    constraints.push(
+                        flag.clone()
+                            * (ext.clone() - seen.clone() - (one.clone() - seen) * nonzero),
+                    );
+                    seen = ext;
+                }
+                constraints.push(flag * (count - rd.clone()));
+            }
+            constraints
+        });
+
+        // With the signs of the signs tag in aux0 and aux1 and less in aux2,
+        // the ext limbs are the difference of the operands, plus 2^64 when
+        // less.
+        cs.create_gate("bitwise compare", |vc| {
+            let one = Expression::Constant(F::one());
+            let wrap = Expression::Constant(F::from_u128(1 << 64));
+            let signed = step.flag(vc, Opcode::MIN) + step.flag(vc, Opcode::MAX);
+            let unsigned = step.flag(vc, Opcode::MINU) + step.flag(vc, Opcode::MAXU);
+            let min = step.flag(vc, Opcode::MIN) + step.flag(vc, Opcode::MINU);
+            let max = step.flag(vc, Opcode::MAX) + step.flag(vc, Opcode::MAXU);
+            let rs1 = vc.query_advice(step.rs1, Rotation::cur());
+            let rs2 = vc.query_advice(step.rs2, Rotation::cur());
+            let rd = vc.query_advice(step.rd, Rotation::cur());
+            let aux = step.aux.map(|aux| vc.query_advice(aux, Rotation::cur()));
+            let less = aux[2].clone();
+            let difference = rs1.clone() - rs2.clone() + less.clone() * wrap.clone()
+                - gadget.value(vc, acc_ext_col);
+            let mut constraints: Vec<_> = aux
+                .iter()
+                .map(|bit| {
+                    (signed.clone() + unsigned.clone()) * bit.clone() * (one.clone() - bit.clone())
+                })
+                .collect();
+            constraints.extend([
+                signed.clone()
+                    * (gadget.limb(vc, out_col, 0)
+                        - Expression::Constant(F::from(2)) * aux[0].clone()
+                        - aux[1].clone()),
+                signed * (difference.clone() - (aux[0].clone() - aux[1].clone()) * wrap),
+                unsigned * difference,
+                min * (rd.clone()
+                    - less.clone() * rs1.clone()
+                    - (one.clone() - less.clone()) * rs2.clone()),
+                max * (rd - less.clone() * rs2 - (one - less) * rs1),
+            ]);
+            constraints
+        });
+
+        gadget
+    }
+
+    /// Returns whether the gadget proves `opcode`.
+    pub fn supports(opcode: Opcode) -> bool {
+        matches!(
+            opcode,
+            Opcode::ANDN
+                | Opcode::ORN
+                | Opcode::XNOR
+                | Opcode::CPOP
+                | Opcode::CPOPW
+                | Opcode::CLZ
+                | Opcode::CLZW
+                | Opcode::CTZ
+                | Opcode::CTZW
+                | Opcode::ORCB
+                | Opcode::REV8
+                | Opcode::SEXTB
+                | Opcode::SEXTH
+                | Opcode::ZEXTH
+                | Opcode::MIN
+                | Opcode::MAX
+                | Opcode::MINU
+                | Opcode::MAXU
+        )
+    }
+
+    pub fn columns(&self) -> Vec<Column<Advice>> {
+        vec![
+            self.tag_col,
+            self.cpop_col,
+            self.lhs_col,
+            self.rhs_col,
+            self.out_col,
+            self.ext_col,
+            self.acc_lhs_col,
+            self.acc_rhs_col,
+            self.acc_out_col,
+            self.acc_ext_col,
+        ]
+    }
+
+    /// The accumulator `acc_col` on the last row of the step, the value of its
+    /// limbs.
+    pub fn value(&self, vc: &mut VirtualCells<'_, F>, acc_col: Column<Advice>) -> Expression<F> {
+        vc.query_advice(acc_col, Rotation(LIMBS as i32 - 1))
+    }
+
+    /// The value of the limbs of the first half of the step, the upper 32
+    /// bits of [`Self::value`].
+    pub fn high(&self, vc: &mut VirtualCells<'_, F>, acc_col: Column<Advice>) -> Expression<F> {
+        vc.query_advice(acc_col, Rotation(LIMBS as i32 / 2 - 1))
+    }
+
+    /// The lower 32 bits of [`Self::value`].
+    pub fn low(&self, vc: &mut VirtualCells<'_, F>, acc_col: Column<Advice>) -> Expression<F> {
+        self.value(vc, acc_col) - self.high(vc, acc_col) * Expression::Constant(F::from(1 << 32))
+    }
+
+    /// The limb of `column` on `row`, 0 being the most significant one.
+    pub fn limb(
+        &self,
+        vc: &mut VirtualCells<'_, F>,
+        column: Column<Advice>,
+        row: usize,
+    ) -> Expression<F> {
+        vc.query_advice(column, Rotation(row as i32))
+    }
+
+    pub fn assign_table(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        layouter.assign_table(
+            || "bitwise table",
+            |mut table| {
+                let mut rows = BTreeSet::from([(0, 0, 0, 0)]);
+                for tag in TAGS {
+                    for lhs in 0..LIMB_BASE {
+                        for rhs in 0..LIMB_BASE {
+                            let (rhs, out) = table_row(tag, lhs, rhs);
+                            rows.insert((tag, lhs, rhs, out));
+                        }
+                    }
+                }
+                for (index, (tag, lhs, rhs, out)) in rows.into_iter().enumerate() {
+                    for (column, value) in [
+                        (self.tag_table, tag),
+                        (self.lhs_table, lhs),
+                        (self.rhs_table, rhs),
+                        (self.out_table, out),
+                    ] {
+                        table.assign_cell(
+                            || "bitwise table",
+                            column,
+                            index,
+                            || Value::known(F::from(value)),
+                        )?;
+                    }
+                }
+                Ok(())
+            },
+        )?;
+        layouter.assign_table(
+            || "limb table",
+            |mut table| {
+                for limb in 0..LIMB_BASE {
+                    table.assign_cell(
+                        || "limb",
+                        self.limb_table,
+                        limb as usize,
+                        || Value::known(F::from(limb)),
+                    )?;
+                }
+                Ok(())
+            },
+        )
+    }
+
+    /// Enables the limb selectors of the step at `offset`, whatever its instruction.
+    pub fn enable(&self, region: &mut Region<'_, F>, offset: usize) -> Result<(), Error> {
+        for row in 0..LIMBS {
+            self.s_limb.enable(region, offset + row)?;
+            if row + 1 < LIMBS {
+                self.s_next.enable(region, offset + row)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Splits `lhs` and `rhs` into the limbs of the step, with the out limbs of
+    /// the rows of `tag`. The rhs of the tags of a single operand is the one
+    /// of their table rows.
+    pub fn assign_limbs(&self, slot: &mut Slot<F>, tag: u64, lhs: u64, rhs: u64) {
+        let cpop = tag == TAG_CPOP;
+        let base = F::from(LIMB_BASE);
+        let (mut acc_lhs, mut acc_rhs, mut acc_out) = (F::zero(), F::zero(), F::zero());
+        for row in 0..LIMBS {
+            let shift = 4 * (LIMBS - 1 - row);
+            let lhs = (lhs >> shift) & 0xf;
+            let (rhs, out) = table_row(tag, lhs, (rhs >> shift) & 0xf);
+            acc_lhs = acc_lhs * base + F::from(lhs);
+            acc_rhs = acc_rhs * base + F::from(rhs);
+            acc_out = if cpop { acc_out } else { acc_out * base } + F::from(out);
+            for (column, value) in [
+                (self.tag_col, F::from(tag)),
+                (self.cpop_col, F::from(cpop as u64)),
+                (self.lhs_col, F::from(lhs)),
+                (self.rhs_col, F::from(rhs)),
+                (self.out_col, F::from(out)),
+                (self.acc_lhs_col, acc_lhs),
+                (self.acc_rhs_col, acc_rhs),
+                (self.acc_out_col, acc_out),
+            ] {
+                slot.assign(column, row, value);
+            }
+        }
+    }
+
+    /// Splits `value` into the ext limbs of the step.
+    pub fn assign_ext(&self, slot: &mut Slot<F>, value: u64) {
+        let mut acc = 0u64;
+        for row in 0..LIMBS {
+            let limb = (value >> (4 * (LIMBS - 1 - row))) & 0xf;
+            acc = (acc << 4) | limb;
+            slot.assign(self.ext_col, row, F::from(limb));
+            slot.assign(self.acc_ext_col, row, F::from(acc));
+        }
+    }
+
+    /// Splits `value` into the lhs limbs of the step, so that the last
+    /// accumulator is `value` and the last out cell its lowest bit.
+    pub fn assign_range(&self, slot: &mut Slot<F>, value: u64) {
+        self.assign_limbs(slot, TAG_RANGE, value, 0);
+    }
+
+    pub fn assign(&self, slot: &mut Slot<F>, step: &OpStep) -> Result<(), Error> {
+        let opcode = step.instruction.opcode;
+        let registers = step.register_indexes.ok_or(Error::Synthesis)?;
+        let rs1_value = registers
+            .read(step.instruction.op_b)
+            .ok_or(Error::Synthesis)?;
+        let rd_value = registers
+            .write(step.instruction.op_a)
+            .ok_or(Error::Synthesis)?;
+        let rs2_value = match opcode {
+            Opcode::ANDN
+            | Opcode::ORN
+            | Opcode::XNOR
+            | Opcode::MIN
+            | Opcode::MAX
+            | Opcode::MINU
+            | Opcode::MAXU => registers
+                .read(step.instruction.op_c)
+                .ok_or(Error::Synthesis)?,
+            _ => 0,
+        };
+        for (column, value) in [
+            (self.step.rs1, rs1_value),
+            (self.step.rs2, rs2_value),
+            (self.step.rd, rd_value),
+        ] {
+            slot.assign(column, 0, F::from(value));
+        }
+        self.assign_limbs(slot, tag(opcode), rs1_value, rs2_value);
+        match opcode {
+            Opcode::CLZ | Opcode::CLZW | Opcode::CTZ | Opcode::CTZW => {
+                let mut seen = false;
+                let mut ext = 0;
+                for row in count_rows(opcode) {
+                    let shift = 4 * (LIMBS - 1 - row);
+                    seen |= (rs1_value >> shift) & 0xf != 0;
+                    ext |= (seen as u64) << shift;
+                }
+                self.assign_ext(slot, ext);
+            }
+            Opcode::MIN | Opcode::MAX | Opcode::MINU | Opcode::MAXU => {
+                let signs = [rs1_value >> 63, rs2_value >> 63];
+                let (lhs, rhs) = match opcode {
+                    Opcode::MIN | Opcode::MAX => {
+                        (rs1_value as i64 as i128, rs2_value as i64 as i128)
+                    }
+                    _ => (rs1_value as i128, rs2_value as i128),
+                };
+                let less = (lhs < rhs) as u64;
+                for (aux, value) in self.step.aux.into_iter().zip([signs[0], signs[1], less]) {
+                    slot.assign(aux, 0, F::from(value));
+                }
+                self.assign_ext(slot, (lhs - rhs + ((less as i128) << 64)) as u64);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::main_circuit::tests::{circuit, program, trace, verify};
+    use runtime::program::Program;
+    use runtime::trace::Trace;
+
+    // andn a2, a0, a1; orn a3, a0, a1; xnor a4, a0, a1; cpop a5, a0
+    const BITWISE: [(u64, u32); 4] = [
+        (0x10000, 0x40b57633),
+        (0x10004, 0x40b566b3),
+        (0x10008, 0x40b54733),
+        (0x1000c, 0x60251793),
+    ];
+
+    fn bitwise_trace(program: &Program, a0: u64, a1: u64) -> Trace {
+        trace(
+            program,
+            &[(10, a0), (11, a1)],
+            &[
+                (0x10000, &[(12, a0 & !a1)]),
+                (0x10004, &[(13, a0 | !a1)]),
+                (0x10008, &[(14, !(a0 ^ a1))]),
+                (0x1000c, &[(15, a0.count_ones() as u64)]),
+            ],
+        )
+    }
+
+    #[test]
+    fn proves_bitwise() {
+        let program = program(&BITWISE);
+        for (a0, a1) in [
+            (0, 0),
+            (0xf0f0_1234_5678_9abc, 0x0ff0_ffff_0000_1111),
+            (u64::MAX, 1),
+        ] {
+            let trace = bitwise_trace(&program, a0, a1);
+            assert!(verify(&circuit(program.clone(), &trace)));
+        }
+    }
+
+    #[test]
+    fn rejects_tampered_bitwise() {
+        let program = program(&BITWISE);
+        let trace = bitwise_trace(&program, 0xf0f0_1234_5678_9abc, 0x0ff0_ffff_0000_1111);
+        // The result of each instruction, then the first operand of andn.
+        for (global_clk, op) in [(0, 2), (1, 2), (2, 2), (3, 1), (0, 0)] {
+            let mut circuit = circuit(program.clone(), &trace);
+            let ops = &mut circuit
+                .entries
+                .register_ops
+                .get_mut(&global_clk)
+                .unwrap()
+                .ops;
+            ops[op].value ^= 1 << 60;
+            assert!(!verify(&circuit), "step {} op {}", global_clk, op);
+        }
+    }
+
+    // clz a2, a0; clzw a3, a0; ctz a4, a0; ctzw a5, a0; cpopw a6, a0;
+    // orc.b a7, a0; rev8 s2, a0; sext.b s3, a0; sext.h s4, a0; zext.h s5, a0;
+    // min s6, a0, a1; max s7, a0, a1; minu s8, a0, a1; maxu s9, a0, a1
+    const ZBB: [(u64, u32); 14] = [
+        (0x10000, 0x60051613),
+        (0x10004, 0x6005169b),
+        (0x10008, 0x60151713),
+        (0x1000c, 0x6015179b),
+        (0x10010, 0x6025181b),
+        (0x10014, 0x28755893),
+        (0x10018, 0x6b855913),
+        (0x1001c, 0x60451993),
+        (0x10020, 0x60551a13),
+        (0x10024, 0x08054abb),
+        (0x10028, 0x0ab54b33),
+        (0x1002c, 0x0ab56bb3),
+        (0x10030, 0x0ab55c33),
+        (0x10034, 0x0ab57cb3),
+    ];
+
+    fn zbb_trace(program: &Program, a0: u64, a1: u64) -> Trace {
+        let orc_b = (0..8).fold(0, |value, byte| match (a0 >> (8 * byte)) & 0xff {
+            0 => value,
+            _ => value | (0xff << (8 * byte)),
+        });
+        let (lhs, rhs) = (a0 as i64, a1 as i64);
+        trace(
+            program,
+            &[(10, a0), (11, a1)],
+            &[
+                (0x10000, &[(12, a0.leading_zeros() as u64)]),
+                (0x10004, &[(13, (a0 as u32).leading_zeros() as u64)]),
+                (0x10008, &[(14, a0.trailing_zeros() as u64)]),
+                (0x1000c, &[(15, (a0 as u32).trailing_zeros() as u64)]),
+                (0x10010, &[(16, (a0 as u32).count_ones() as u64)]),
+                (0x10014, &[(17, orc_b)]),
+                (0x10018, &[(18, a0.swap_bytes())]),
+                (0x1001c, &[(19, a0 as i8 as u64)]),
+                (0x10020, &[(20, a0 as i16 as u64)]),
+                (0x10024, &[(21, a0 as u16 as u64)]),
+                (0x10028, &[(22, lhs.min(rhs) as u64)]),
+                (0x1002c, &[(23, lhs.max(rhs) as u64)]),
+                (0x10030, &[(24, a0.min(a1))]),
+                (0x10034, &[(25, a0.max(a1))]),
+            ],
+        )
+    }
+
+    const ZBB_OPERANDS: [(u64, u64); 4] = [
+        (0, 0),
+        (0x0012_0000_8000_0f00, 0xffff_ffff_ffff_fff0),
+        (0xfedc_ba98_7654_3210, 0xfedc_ba98_7654_3210),
+        (0x8000_0000_0000_0080, 1),
+    ];
+
+    #[test]
+    fn proves_zbb() {
+        let program = program(&ZBB);
+        for (a0, a1) in ZBB_OPERANDS {
+            let trace = zbb_trace(&program, a0, a1);
+            assert!(
+                verify(&circuit(program.clone(), &trace)),
+                "{:x} {:x}",
+                a0,
+                a1
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_tampered_zbb() {
+        let program = program(&ZBB);
+        let (a0, a1) = ZBB_OPERANDS[1];
+        let trace = zbb_trace(&program, a0, a1);
+        for global_clk in 0..ZBB.len() as u64 {
+            for bit in [0, 31, 63] {
+                let mut circuit = circuit(program.clone(), &trace);
+                let ops = &mut circuit
+                    .entries
+                    .register_ops
+                    .get_mut(&global_clk)
+                    .unwrap()
+                    .ops;
+                ops.last_mut().unwrap().value ^= 1 << bit;
+                assert!(!verify(&circuit), "step {} bit {}", global_clk, bit);
+            }
+        }
+    }
+}
@@ -0,0 +1,509 @@
+use core::marker::PhantomData;
+use entry_builder::op_step::OpStep;
+use runtime::trace::Opcode;
+
+use alloc::vec;
+use alloc::vec::Vec;
+use halo2_proofs::arithmetic::FieldExt;
+use halo2_proofs::circuit::{Layouter, Value};
+use halo2_proofs::plonk::*;
+use halo2_proofs::poly::Rotation;
+
+use crate::execution_table::{Slot, StepConfig, STEP_ROWS};
+
+/// Rows of a product, one per coefficient of the product of two polynomials
+/// of degree 63, from the highest one.
+pub const PRODUCT_ROWS: usize = 128;
+const HALF: usize = PRODUCT_ROWS / 2;
+
+const OPCODES: [Opcode; 3] = [Opcode::CLMUL, Opcode::CLMULH, Opcode::CLMULR];
+
+/// Number of products laid out for `max_steps` steps. They take the rows of
+/// the steps but one, where the link table has its row of zeros.
+pub fn products(max_steps: usize) -> usize {
+    (max_steps * STEP_ROWS).saturating_sub(1) / PRODUCT_ROWS
+}
+
+/// `(clmul, clmulh, clmulr)` of `a` and `b`.
+fn clmul(a: u64, b: u64) -> (u64, u64, u64) {
+    let product = (0..64)
+        .filter(|bit| (b >> bit) & 1 == 1)
+        .fold(0u128, |product, bit| product ^ ((a as u128) << bit));
+    (
+        product as u64,
+        (product >> 64) as u64,
+        (product >> 63) as u64,
+    )
+}
+
+/// Proves the carry-less multiplications. They don't fit the limbs of a
+/// step, the products are laid out apart, [`PRODUCT_ROWS`] rows each, and the
+/// steps look their operands and results up:
+///
+/// | bit | coefficient | parity | a | b | hi | lo | rev | eval | product |
+///
+/// The bits of a then the bits of b, from the most significant ones, give the
+/// polynomials A and B over GF(2) and the integer coefficients of their
+/// product over the integers, each at most 64, are looked up with their
+/// parity, the coefficients of the product over GF(2). With z a challenge
+/// drawn once the bits and the coefficients are committed, the `eval` column
+/// evaluates A(z) then B(z) and `product` the coefficients at z, on the last
+/// row `A(z) · B(z)` must be their evaluation, which fails with a negligible
+/// probability for other coefficients. a, b and the three results accumulate
+/// their bits on the way.
+#[derive(Clone)]
+pub struct ClmulGadget<F> {
+    pub bit: Column<Advice>,
+    pub coefficient: Column<Advice>,
+    pub parity: Column<Advice>,
+    pub a: Column<Advice>,
+    pub b: Column<Advice>,
+    pub hi: Column<Advice>,
+    pub lo: Column<Advice>,
+    pub rev: Column<Advice>,
+    pub eval: Column<Advice>,
+    pub product: Column<Advice>,
+    /// `(selected, rs1, rs2, clmul, clmulh, clmulr)` on the first rows of a
+    /// step, looked up in the last rows of the products.
+    pub link: Column<Advice>,
+    /// 1 on the rows of the coefficients of the upper half, those of the bits
+    /// of a.
+    pub high: Column<Fixed>,
+    pub coefficient_table: TableColumn,
+    pub parity_table: TableColumn,
+    pub z: Challenge,
+    q_row: Selector,
+    q_first: Selector,
+    q_next: Selector,
+    q_last: Selector,
+    step: StepConfig,
+    _maker: PhantomData<F>,
+}
+
+impl<F: FieldExt> ClmulGadget<F> {
+    pub fn configure(cs: &mut ConstraintSystem<F>, step: &StepConfig) -> Self {
+        let bit = cs.advice_column();
+        let coefficient = cs.advice_column();
+        let parity = cs.advice_column();
+        let a = cs.advice_column();
+        let b = cs.advice_column();
+        let hi = cs.advice_column();
+        let lo = cs.advice_column();
+        let rev = cs.advice_column();
+        let link = cs.advice_column();
+        let z = cs.challenge_usable_after(FirstPhase);
+        let eval = cs.advice_column_in(SecondPhase);
+        let product = cs.advice_column_in(SecondPhase);
+        let high = cs.fixed_column();
+        let coefficient_table = cs.lookup_table_column();
+        let parity_table = cs.lookup_table_column();
+        let q_row = cs.selector();
+        let q_first = cs.selector();
+        let q_next = cs.selector();
+        let q_last = cs.complex_selector();
+
+        cs.lookup("clmul parity", |vc| {
+            vec![
+                (
+                    vc.query_advice(coefficient, Rotation::cur()),
+                    coefficient_table,
+                ),
+                (vc.query_advice(parity, Rotation::cur()), parity_table),
+            ]
+        });
+
+        cs.create_gate("clmul bit", |vc| {
+            let q_row = vc.query_selector(q_row);
+            let bit = vc.query_advice(bit, Rotation::cur());
+            vec![q_row * bit.clone() * (Expression::Constant(F::one()) - bit)]
+        });
+
+        cs.create_gate("clmul first", |vc| {
+            let q_first = vc.query_selector(q_first);
+            let bit = vc.query_advice(bit, Rotation::cur());
+            let parity = vc.query_advice(parity, Rotation::cur());
+            vec![
+                q_first.clone() * (vc.query_advice(a, Rotation::cur()) - bit.clone()),
+                q_first.clone() * vc.query_advice(b, Rotation::cur()),
+                q_first.clone() * (vc.query_advice(hi, Rotation::cur()) - parity.clone()),
+                q_first.clone() * vc.query_advice(lo, Rotation::cur()),
+                q_first.clone() * (vc.query_advice(rev, Rotation::cur()) - parity),
+                q_first.clone() * (vc.query_advice(eval, Rotation::cur()) - bit),
+                q_first
+                    * (vc.query_advice(product, Rotation::cur())
+                        - vc.query_advice(coefficient, Rotation::cur())),
+            ]
+        });
+
+        // a and hi take the bits of the upper half, b and lo those of the
+        // lower one and rev the bits 63 to 127. eval starts over with the bits
+        // of b.
+        cs.create_gate("clmul accumulate", |vc| {
+            let q_next = vc.query_selector(q_next);
+            let one = Expression::Constant(F::one());
+            let two = Expression::Constant(F::from(2));
+            let z = vc.query_challenge(z);
+            let h = vc.query_fixed(high, Rotation::cur());
+            let h_next = vc.query_fixed(high, Rotation::next());
+            let bit_next = vc.query_advice(bit, Rotation::next());
+            let parity_next = vc.query_advice(parity, Rotation::next());
+            let [a, b, hi, lo, rev, eval, product] =
+                [a, b, hi, lo, rev, eval, product].map(|column| {
+                    (
+                        vc.query_advice(column, Rotation::cur()),
+                        vc.query_advice(column, Rotation::next()),
+                    )
+                });
+            let shift = |(value, next): (Expression<F>, Expression<F>),
+                         bit: Expression<F>,
+                         selected: Expression<F>| {
+                next - selected.clone() * (two.clone() * value.clone() + bit)
+                    - (one.clone() - selected) * value
+            };
+            vec![
+                q_next.clone() * shift(a, bit_next.clone(), h_next.clone()),
+                q_next.clone()
+                    * (b.1 - two.clone() * b.0 - (one.clone() - h_next.clone()) * bit_next.clone()),
+                q_next.clone() * shift(hi, parity_next.clone(), h_next.clone()),
+                q_next.clone()
+                    * (lo.1
+                        - two.clone() * lo.0
+                        - (one.clone() - h_next.clone()) * parity_next.clone()),
+                q_next.clone() * shift(rev, parity_next, h.clone()),
+                q_next.clone() * (eval.1 - (one + h_next - h) * z.clone() * eval.0 - bit_next),
+                q_next
+                    * (product.1 - z * product.0 - vc.query_advice(coefficient, Rotation::next())),
+            ]
+        });
+
+        cs.create_gate("clmul product", |vc| {
+            let q_last = vc.query_selector(q_last);
+            let eval_a = vc.query_advice(eval, Rotation(-(HALF as i32)));
+            let eval_b = vc.query_advice(eval, Rotation::cur());
+            let product = vc.query_advice(product, Rotation::cur());
+            vec![q_last * (eval_a * eval_b - product)]
+        });
+
+        // The steps of the other instructions look the row of zeros up.
+        cs.lookup_any("clmul link", |vc| {
+            let q_step = vc.query_selector(step.q_step);
+            let q_last = vc.query_selector(q_last);
+            [
+                q_last.clone(),
+                q_last.clone(),
+                q_last.clone(),
+                q_last.clone(),
+                q_last.clone(),
+                q_last,
+            ]
+            .into_iter()
+            .zip([None, Some(a), Some(b), Some(lo), Some(hi), Some(rev)])
+            .enumerate()
+            .map(|(row, (q_last, column))| {
+                let input = q_step.clone() * vc.query_advice(link, Rotation(row as i32));
+                let table = match column {
+                    Some(column) => vc.query_advice(column, Rotation::cur()),
+                    None => q_last,
+                };
+                (input, table)
+            })
+            .collect()
+        });
+
+        cs.create_gate("clmul link", |vc| {
+            let q_step = vc.query_selector(step.q_step);
+            let one = Expression::Constant(F::one());
+            let selected = step.select(vc, |opcode| OPCODES.contains(&opcode) as u64);
+            let link: Vec<_> = (0..6)
+                .map(|row| vc.query_advice(link, Rotation(row)))
+                .collect();
+            let rs1 = vc.query_advice(step.rs1, Rotation::cur());
+            let rs2 = vc.query_advice(step.rs2, Rotation::cur());
+            let rd = vc.query_advice(step.rd, Rotation::cur());
+            let mut constraints = vec![
+                q_step.clone() * (link[0].clone() - selected.clone()),
+                q_step.clone() * (link[1].clone() - selected.clone() * rs1),
+                q_step.clone() * (link[2].clone() - selected.clone() * rs2),
+            ];
+            for (opcode, result) in OPCODES.into_iter().zip(&link[3..]) {
+                constraints
+                    .push(q_step.clone() * (one.clone() - selected.clone()) * result.clone());
+                constraints.push(step.flag(vc, opcode) * (rd.clone() - result.clone()));
+            }
+            constraints
+        });
+
+        Self {
+            bit,
+            coefficient,
+            parity,
+            a,
+            b,
+            hi,
+            lo,
+            rev,
+            eval,
+            product,
+            link,
+            high,
+            coefficient_table,
+            parity_table,
+            z,
+            q_row,
+            q_first,
+            q_next,
+            q_last,
+            step: step.clone(),
+            _maker: PhantomData::default(),
+        }
+    }
+
+    /// Returns whether the gadget proves `opcode`.
+    pub fn supports(opcode: Opcode) -> bool {
+        OPCODES.contains(&opcode)
+    }
+
+    pub fn columns(&self) -> Vec<Column<Advice>> {
+        vec![self.link]
+    }
+
+    pub fn assign_table(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        layouter.assign_table(
+            || "parity table",
+            |mut table| {
+                for coefficient in 0..=HALF as u64 {
+                    for (column, value) in [
+                        (self.coefficient_table, coefficient),
+                        (self.parity_table, coefficient & 1),
+                    ] {
+                        table.assign_cell(
+                            || "parity table",
+                            column,
+                            coefficient as usize,
+                            || Value::known(F::from(value)),
+                        )?;
+                    }
+                }
+                Ok(())
+            },
+        )
+    }
+
+    pub fn assign(&self, slot: &mut Slot<F>, step: &OpStep) -> Result<(), Error> {
+        let registers = step.register_indexes.ok_or(Error::Synthesis)?;
+        let rs1_value = registers
+            .read(step.instruction.op_b)
+            .ok_or(Error::Synthesis)?;
+        let rs2_value = registers
+            .read(step.instruction.op_c)
+            .ok_or(Error::Synthesis)?;
+        let rd_value = registers
+            .write(step.instruction.op_a)
+            .ok_or(Error::Synthesis)?;
+        for (column, value) in [
+            (self.step.rs1, rs1_value),
+            (self.step.rs2, rs2_value),
+            (self.step.rd, rd_value),
+        ] {
+            slot.assign(column, 0, F::from(value));
+        }
+        let (clmul, clmulh, clmulr) = clmul(rs1_value, rs2_value);
+        for (row, value) in [1, rs1_value, rs2_value, clmul, clmulh, clmulr]
+            .into_iter()
+            .enumerate()
+        {
+            slot.assign(self.link, row, F::from(value));
+        }
+        Ok(())
+    }
+
+    /// Lays out the products of the carry-less multiplications of `op_steps`
+    /// in the [`products`] of `max_steps` steps.
+    pub fn assign_products(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        op_steps: &[OpStep],
+        max_steps: usize,
+    ) -> Result<(), Error> {
+        let mut operands = vec![];
+        for step in op_steps {
+            if Self::supports(step.instruction.opcode) {
+                let registers = step.register_indexes.ok_or(Error::Synthesis)?;
+                let read = |index| registers.read(index).ok_or(Error::Synthesis);
+                operands.push((read(step.instruction.op_b)?, read(step.instruction.op_c)?));
+            }
+        }
+        let products = products(max_steps);
+        if operands.len() > products {
+            return Err(Error::Synthesis);
+        }
+        let z = layouter.get_challenge(self.z);
+
+        layouter.assign_region(
+            || "clmul",
+            |mut region| {
+                for index in 0..products {
+                    let offset = index * PRODUCT_ROWS;
+                    let (a, b) = operands.get(index).copied().unwrap_or_default();
+                    let bits: Vec<u64> = (0..HALF)
+                        .rev()
+                        .map(|bit| (a >> bit) & 1)
+                        .chain((0..HALF).rev().map(|bit| (b >> bit) & 1))
+                        .collect();
+                    // The integer coefficients, from the highest one.
+                    let coefficients: Vec<u64> = (0..PRODUCT_ROWS as u64)
+                        .rev()
+                        .map(|k| {
+                            (0..HALF as u64)
+                                .filter(|i| *i <= k && k - i < HALF as u64)
+                                .map(|i| (a >> i) & (b >> (k - i)) & 1)
+                                .sum()
+                        })
+                        .collect();
+
+                    let (mut acc_a, mut acc_b, mut acc_hi, mut acc_lo, mut acc_rev) =
+                        (0u64, 0u64, 0u64, 0u64, 0u64);
+                    let mut eval = Value::known(F::zero());
+                    let mut product = Value::known(F::zero());
+                    for row in 0..PRODUCT_ROWS {
+                        let upper = row < HALF;
+                        let (bit, coefficient) = (bits[row], coefficients[row]);
+                        let parity = coefficient & 1;
+                        if upper {
+                            acc_a = 2 * acc_a + bit;
+                            acc_hi = 2 * acc_hi + parity;
+                        } else {
+                            acc_b = 2 * acc_b + bit;
+                            acc_lo = 2 * acc_lo + parity;
+                        }
+                        if row <= HALF {
+                            acc_rev = 2 * acc_rev + parity;
+                        }
+                        eval = match row == HALF {
+                            true => Value::known(F::from(bit)),
+                            false => eval * z + Value::known(F::from(bit)),
+                        };
+                        product = product * z + Value::known(F::from(coefficient));
+
+                        let row = offset + row;
+                        self.q_row.enable(&mut region, row)?;
+                        if row == offset {
+                            self.q_first.enable(&mut region, row)?;
+                        }
+                        if row + 1 < offset + PRODUCT_ROWS {
+                            self.q_next.enable(&mut region, row)?;
+                        } else {
+                            self.q_last.enable(&mut region, row)?;
+                        }
+                        region.assign_fixed(
+                            || "high",
+                            self.high,
+                            row,
+                            || Value::known(F::from(upper as u64)),
+                        )?;
+                        for (column, value) in [
+                            (self.bit, bit),
+                            (self.coefficient, coefficient),
+                            (self.parity, parity),
+                            (self.a, acc_a),
+                            (self.b, acc_b),
+                            (self.hi, acc_hi),
+                            (self.lo, acc_lo),
+                            (self.rev, acc_rev),
+                        ] {
+                            region.assign_advice(
+                                || "clmul",
+                                column,
+                                row,
+                                || Value::known(F::from(value)),
+                            )?;
+                        }
+                        region.assign_advice(|| "eval", self.eval, row, || eval)?;
+                        region.assign_advice(|| "product", self.product, row, || product)?;
+                    }
+                }
+                // The row of zeros of the link table.
+                for column in [self.a, self.b, self.hi, self.lo, self.rev] {
+                    region.assign_advice(
+                        || "zero",
+                        column,
+                        products * PRODUCT_ROWS,
+                        || Value::known(F::zero()),
+                    )?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::clmul;
+    use crate::main_circuit::tests::{circuit, program, trace, verify};
+    use runtime::program::Program;
+    use runtime::trace::Trace;
+
+    // clmul a2, a0, a1; clmulh a3, a0, a1; clmulr a4, a0, a1
+    const CLMUL: [(u64, u32); 3] = [
+        (0x10000, 0x0ab51633),
+        (0x10004, 0x0ab536b3),
+        (0x10008, 0x0ab52733),
+    ];
+
+    fn clmul_trace(program: &Program, a0: u64, a1: u64) -> Trace {
+        let (low, high, reversed) = clmul(a0, a1);
+        trace(
+            program,
+            &[(10, a0), (11, a1)],
+            &[
+                (0x10000, &[(12, low)]),
+                (0x10004, &[(13, high)]),
+                (0x10008, &[(14, reversed)]),
+            ],
+        )
+    }
+
+    #[test]
+    fn clmul_matches_the_definition() {
+        assert_eq!(clmul(0b11, 0b11), (0b101, 0, 0));
+        assert_eq!(clmul(u64::MAX, u64::MAX).0, 0x5555_5555_5555_5555);
+        assert_eq!(clmul(1 << 63, 1 << 63), (0, 1 << 62, 1 << 63));
+    }
+
+    #[test]
+    fn proves_clmul() {
+        let program = program(&CLMUL);
+        for (a0, a1) in [
+            (0, 0),
+            (u64::MAX, u64::MAX),
+            (0xfedc_ba98_7654_3210, 0x0123_4567_89ab_cdef),
+        ] {
+            let trace = clmul_trace(&program, a0, a1);
+            assert!(
+                verify(&circuit(program.clone(), &trace)),
+                "{:x} {:x}",
+                a0,
+                a1
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_tampered_clmul() {
+        let program = program(&CLMUL);
+        let trace = clmul_trace(&program, 0xfedc_ba98_7654_3210, 0x0123_4567_89ab_cdef);
+        // The result of each instruction, then the operands of clmul.
+        for (global_clk, op, bit) in [(0, 2, 0), (1, 2, 63), (2, 2, 5), (0, 0, 7), (0, 1, 3)] {
+            let mut circuit = circuit(program.clone(), &trace);
+            let ops = &mut circuit
+                .entries
+                .register_ops
+                .get_mut(&global_clk)
+                .unwrap()
+                .ops;
+            ops[op].value ^= 1 << bit;
+            assert!(!verify(&circuit), "step {} op {}", global_clk, op);
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use core::marker::PhantomData;
+use entry_builder::op_step::OpStep;
+use runtime::trace::Opcode;
+
+use alloc::vec;
+use halo2_proofs::arithmetic::FieldExt;
+use halo2_proofs::plonk::*;
+use halo2_proofs::poly::Rotation;
+
+use crate::execution_table::op_configure::bitwise::BitwiseGadget;
+use crate::execution_table::{Slot, StepConfig, STEP_ROWS};
+
+/// Proves a macro-op in one step instead of one per instruction. The value of
+/// the macro-op is `op_b`.
+///
+/// FARJUMPREL jumps to `(pc + offset) & !1`. The carry of `pc + offset`, set
+/// when it wraps around 2^64, is the first aux cell. The wrapped sum is split
+/// into the limbs of the bitwise gadget, which bounds it to 64 bits and gives
+/// its lowest bit. The target is constrained on every step, the last one of
+/// the trace too.
+#[derive(Clone)]
+pub struct FusedTypeGadget<F> {
+    step: StepConfig,
+    bitwise: BitwiseGadget<F>,
+    _maker: PhantomData<F>,
+}
+
+impl<F: FieldExt> FusedTypeGadget<F> {
+    pub fn configure(
+        cs: &mut ConstraintSystem<F>,
+        step: &StepConfig,
+        bitwise: &BitwiseGadget<F>,
+    ) -> Self {
+        cs.create_gate("FusedType::LOADIMM", |vc| {
+            let value = vc.query_advice(step.op_b, Rotation::cur());
+            let rd = vc.query_advice(step.rd, Rotation::cur());
+            let s = step.flag(vc, Opcode::CUSTOMLOADIMM);
+            vec![s * (value - rd)]
+        });
+
+        // Far jumps link to the instruction after the macro-op.
+        cs.create_gate("FusedType::FARJUMP", |vc| {
+            let pc = vc.query_advice(step.pc, Rotation::cur());
+            let rd = vc.query_advice(step.rd, Rotation::cur());
+            let length = vc.query_advice(step.length, Rotation::cur());
+            let s = step.flag(vc, Opcode::FARJUMPREL) + step.flag(vc, Opcode::FARJUMPABS);
+            vec![s * (pc + length - rd)]
+        });
+
+        cs.create_gate("FusedType::FARJUMPREL", |vc| {
+            let last = Rotation(STEP_ROWS as i32 - 1);
+            let offset = vc.query_advice(step.op_b, Rotation::cur());
+            let pc = vc.query_advice(step.pc, Rotation::cur());
+            let next_pc = vc.query_advice(step.next_pc, Rotation::cur());
+            let carry = vc.query_advice(step.aux[0], Rotation::cur());
+            let sum = vc.query_advice(bitwise.acc_lhs_col, last);
+            let low_bit = vc.query_advice(bitwise.out_col, last);
+            let s = step.flag(vc, Opcode::FARJUMPREL);
+            // 2^64 as a field element.
+            let wrap = Expression::Constant(F::from(u64::max_value()) + F::one());
+            vec![
+                s.clone() * (pc + offset - carry.clone() * wrap - sum.clone()),
+                s.clone() * carry.clone() * (Expression::Constant(F::one()) - carry),
+                s * (sum - low_bit - next_pc),
+            ]
+        });
+
+        cs.create_gate("FusedType::FARJUMPABS", |vc| {
+            let target = vc.query_advice(step.op_b, Rotation::cur());
+            let next_pc = vc.query_advice(step.next_pc, Rotation::cur());
+            let s = step.flag(vc, Opcode::FARJUMPABS);
+            vec![s * (target - next_pc)]
+        });
+
+        Self {
+            step: step.clone(),
+            bitwise: bitwise.clone(),
+            _maker: PhantomData::default(),
+        }
+    }
+
+    pub fn assign(&self, slot: &mut Slot<F>, step: &OpStep) -> Result<(), Error> {
+        let instruction = step.instruction;
+        let registers = step.register_indexes.ok_or(Error::Synthesis)?;
+        let rd_value = registers.write(instruction.op_a).ok_or(Error::Synthesis)?;
+        slot.assign(self.step.rd, 0, F::from(rd_value));
+        if instruction.opcode == Opcode::FARJUMPREL {
+            let (sum, carry) = step.pc.overflowing_add(instruction.op_b);
+            slot.assign(self.step.aux[0], 0, F::from(carry as u64));
+            self.bitwise.assign_range(slot, sum);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::main_circuit::tests::{circuit, program, trace, verify};
+    use crate::main_circuit::MainCircuit;
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    // auipc ra, 0x1; jalr ra, 7(ra) jumps to 0x11006; addi a0, a0, 1
+    const FAR_JUMP: [(u64, u32); 3] = [
+        (0x10000, 0x00001097),
+        (0x10004, 0x007080e7),
+        (0x11006, 0x00150513),
+    ];
+
+    // auipc ra, 0xfffff; jalr ra, 5(ra) jumps back to 0xf004, wrapping around
+    const FAR_JUMP_BACK: [(u64, u32); 2] = [(0x10000, 0xfffff097), (0x10004, 0x005080e7)];
+
+    /// The circuit of a trace ending with the far jump.
+    fn last_jump(code: &[(u64, u32)]) -> MainCircuit<Fr> {
+        let program = program(code);
+        let trace = trace(&program, &[], &[(0x10000, &[(1, 0x10008)])]);
+        circuit(program, &trace)
+    }
+
+    #[test]
+    fn proves_far_jump() {
+        assert!(verify(&last_jump(&FAR_JUMP)));
+
+        let program = program(&FAR_JUMP);
+        let jump_and_add = trace(
+            &program,
+            &[],
+            &[(0x10000, &[(1, 0x10008)]), (0x11006, &[(10, 1)])],
+        );
+        assert!(verify(&circuit(program, &jump_and_add)));
+
+        let circuit = last_jump(&FAR_JUMP_BACK);
+        assert_eq!(circuit.entries.next_pc, Some(0xf004));
+        assert!(verify(&circuit));
+    }
+
+    #[test]
+    fn rejects_unmasked_far_jump_target() {
+        for (code, target) in [(&FAR_JUMP[..], 0x11007), (&FAR_JUMP_BACK[..], 0xf005)] {
+            let mut circuit = last_jump(code);
+            circuit.entries.next_pc = Some(target);
+            assert!(!verify(&circuit));
+        }
+    }
+}
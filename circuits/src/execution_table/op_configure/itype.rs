@@ -3,104 +3,66 @@ use entry_builder::op_step::OpStep;
 use runtime::trace::Opcode;
 
 use halo2_proofs::arithmetic::FieldExt;
-use halo2_proofs::circuit::{AssignedCell, Layouter, Value};
 use halo2_proofs::plonk::*;
 use halo2_proofs::poly::Rotation;
 
 use alloc::vec;
 
-#[derive(Debug, Clone)]
-pub struct ACell<F: FieldExt>(pub AssignedCell<F, F>);
+use crate::execution_table::{Slot, StepConfig};
 
+/// The immediate is `op_c` of the step, sign extended to 64 bits, so
+/// `rs1 + imm` wraps around 2^64 with the carry in the first aux cell.
 #[derive(Clone)]
 pub struct ITypeGadget<F> {
-    pub lhs_col: Column<Advice>,
-    pub rhs_col: Column<Advice>,
-    pub s_overflowing: Column<Advice>,
-    s_addi: Selector,
+    step: StepConfig,
     _maker: PhantomData<F>,
 }
 
 impl<F: FieldExt> ITypeGadget<F> {
-    pub fn configure(
-        cs: &mut ConstraintSystem<F>,
-        lhs_col: Column<Advice>,
-        rhs_col: Column<Advice>,
-        s_overflowing: Column<Advice>,
-        s_addi: Selector,
-    ) -> Self {
-        cs.enable_equality(lhs_col);
-        cs.enable_equality(rhs_col);
-
-        // todo: constrain selector: s1 + s1 + .. + sn = 1
-
+    pub fn configure(cs: &mut ConstraintSystem<F>, step: &StepConfig) -> Self {
         cs.create_gate("IType::ADDI", |vc| {
-            let lhs = vc.query_advice(lhs_col, Rotation::cur());
-            let rhs = vc.query_advice(rhs_col, Rotation::cur());
-            let out = vc.query_advice(lhs_col, Rotation::next());
-            let s_overflowing = vc.query_advice(s_overflowing, Rotation::cur());
-            let s_addi = vc.query_selector(s_addi);
+            let lhs = vc.query_advice(step.rs1, Rotation::cur());
+            let rhs = vc.query_advice(step.op_c, Rotation::cur());
+            let out = vc.query_advice(step.rd, Rotation::cur());
+            let s_overflowing = vc.query_advice(step.aux[0], Rotation::cur());
+            let s_addi = step.flag(vc, Opcode::ADDI);
+            let wrap = Expression::Constant(F::from(u64::max_value()) + F::one());
             vec![
-                s_addi
-                    * (lhs + rhs
-                        - out
-                        - s_overflowing * Expression::Constant(F::from(u64::max_value()))),
+                s_addi.clone() * (lhs + rhs - out - s_overflowing.clone() * wrap),
+                s_addi * s_overflowing.clone() * (Expression::Constant(F::one()) - s_overflowing),
             ]
         });
 
         Self {
-            lhs_col,
-            rhs_col,
-            s_overflowing,
-            s_addi,
+            step: step.clone(),
             _maker: PhantomData::default(),
         }
     }
 
-    pub fn assign(&self, layouter: &mut impl Layouter<F>, step: &OpStep) -> Result<(), Error> {
-        layouter.assign_region(
-            || "IType",
-            |mut region| {
-                match step.instruction.opcode.into() {
-                    Opcode::ADDI => self.s_addi.enable(&mut region, 0)?,
-                    _ => {
-                        return Ok(());
-                    }
-                };
-                let rd = step.instruction.op_a;
-                let rs1 = step.instruction.op_b;
-                let imm = step.instruction.op_c;
-
-                let rd_value = step.register_indexes.unwrap().write(rd).unwrap();
-                let rs1_value = step.register_indexes.unwrap().read(rs1).unwrap();
-
-                let (_, is_overflowing) = rs1_value.overflowing_add(imm);
-
-                region.assign_advice(
-                    || "lhs",
-                    self.lhs_col,
-                    0,
-                    || Value::known(F::from(rs1_value)),
-                )?;
-
-                region.assign_advice(|| "rhs", self.rhs_col, 0, || Value::known(F::from(imm)))?;
-
-                region.assign_advice(
-                    || "output",
-                    self.lhs_col,
-                    1,
-                    || Value::known(F::from(rd_value)),
-                )?;
-
-                region.assign_advice(
-                    || "is_overflowing",
-                    self.s_overflowing,
-                    0,
-                    || Value::known(F::from(is_overflowing)),
-                )?;
-
-                Ok(())
-            },
-        )
+    pub fn assign(&self, slot: &mut Slot<F>, step: &OpStep) -> Result<(), Error> {
+        match step.instruction.opcode {
+            Opcode::ADDI => {}
+            _ => {
+                return Ok(());
+            }
+        };
+        let rd = step.instruction.op_a;
+        let rs1 = step.instruction.op_b;
+        let imm = step.instruction.op_c;
+
+        let registers = step.register_indexes.ok_or(Error::Synthesis)?;
+        let rd_value = registers.write(rd).ok_or(Error::Synthesis)?;
+        let rs1_value = registers.read(rs1).ok_or(Error::Synthesis)?;
+
+        let (_, is_overflowing) = rs1_value.overflowing_add(imm);
+
+        for (column, value) in [
+            (self.step.rs1, rs1_value),
+            (self.step.rd, rd_value),
+            (self.step.aux[0], is_overflowing as u64),
+        ] {
+            slot.assign(column, 0, F::from(value));
+        }
+        Ok(())
     }
 }
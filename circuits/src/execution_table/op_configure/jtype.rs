@@ -1,47 +1,28 @@
 use core::marker::PhantomData;
-use runtime::trace::Opcode;
 use entry_builder::op_step::OpStep;
 
 use halo2_proofs::arithmetic::FieldExt;
-use halo2_proofs::circuit::{AssignedCell, Layouter, Value};
 use halo2_proofs::plonk::*;
-use halo2_proofs::poly::Rotation;
 
-#[derive(Debug, Clone)]
-pub struct ACell<F: FieldExt>(pub AssignedCell<F, F>);
+use crate::execution_table::{Slot, StepConfig};
 
+/// The instructions are fetched from the program table, the link and the jump target aren't
+/// constrained yet.
 #[derive(Clone)]
 pub struct JTypeGadget<F> {
-    pub lhs_col: Column<Advice>,
-    pub rhs_col: Column<Advice>,
-    s_jal: Selector,
     _maker: PhantomData<F>,
 }
 
 impl<F: FieldExt> JTypeGadget<F> {
-    pub fn configure(
-        cs: &mut ConstraintSystem<F>,
-        lhs_col: Column<Advice>,
-        rhs_col: Column<Advice>,
-        s_jal: Selector,
-    ) -> Self {
-
+    pub fn configure(_cs: &mut ConstraintSystem<F>, _step: &StepConfig) -> Self {
+        // TODO: constrain the JType instructions.
         Self {
-            lhs_col,
-            rhs_col,
-            s_jal,
             _maker: PhantomData::default(),
         }
     }
 
-    pub fn assign(&self, layouter: &mut impl Layouter<F>, step: &OpStep) -> Result<(), Error> {
-        layouter.assign_region(
-            || "JType",
-            |mut region| {
-                // TODO
-
-                Ok(())
-            },
-        )
+    pub fn assign(&self, _slot: &mut Slot<F>, _step: &OpStep) -> Result<(), Error> {
+        // TODO
+        Ok(())
     }
 }
@@ -1,7 +1,12 @@
+pub mod atype;
+pub mod bitwise;
 pub mod btype;
-pub mod rtype;
+pub mod clmul;
+pub mod fused;
 pub mod itype;
 pub mod jtype;
+pub mod others;
+pub mod rtype;
+pub mod shift;
 pub mod stype;
 pub mod utype;
-pub mod others;
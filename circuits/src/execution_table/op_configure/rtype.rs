@@ -1,106 +1,80 @@
 use core::marker::PhantomData;
-use runtime::trace::Opcode;
 use entry_builder::op_step::OpStep;
+use runtime::trace::Opcode;
 
 use alloc::vec;
 use halo2_proofs::arithmetic::FieldExt;
-use halo2_proofs::circuit::{AssignedCell, Layouter, Value};
 use halo2_proofs::plonk::*;
 use halo2_proofs::poly::Rotation;
 
-#[derive(Debug, Clone)]
-pub struct ACell<F: FieldExt>(pub AssignedCell<F, F>);
+use crate::execution_table::{Slot, StepConfig};
 
+/// `rs1 ± rs2` wraps around 2^64, the carry (borrow for SUB) is the first aux
+/// cell of the step.
 #[derive(Clone)]
 pub struct RTypeGadget<F> {
-    pub lhs_col: Column<Advice>,
-    pub rhs_col: Column<Advice>,
-    s_add: Selector,
-    s_sub: Selector,
+    step: StepConfig,
     _maker: PhantomData<F>,
 }
 
 impl<F: FieldExt> RTypeGadget<F> {
-    pub fn configure(
-        cs: &mut ConstraintSystem<F>,
-        lhs_col: Column<Advice>,
-        rhs_col: Column<Advice>,
-        s_add: Selector,
-        s_sub: Selector,
-    ) -> Self {
-        // let lhs_col = cs.advice_column();
-        // let rhs_col = cs.advice_column();
-        cs.enable_equality(lhs_col);
-        cs.enable_equality(rhs_col);
-
-        // todo: constrain selector: s1 + s1 + .. + sn = 1
+    pub fn configure(cs: &mut ConstraintSystem<F>, step: &StepConfig) -> Self {
+        let wrap = Expression::Constant(F::from(u64::max_value()) + F::one());
 
         cs.create_gate("RType::ADD", |vc| {
-            let lhs = vc.query_advice(lhs_col, Rotation::cur());
-            let rhs = vc.query_advice(rhs_col, Rotation::cur());
-            let out = vc.query_advice(lhs_col, Rotation::next());
-            let s = vc.query_selector(s_add);
+            let lhs = vc.query_advice(step.rs1, Rotation::cur());
+            let rhs = vc.query_advice(step.rs2, Rotation::cur());
+            let out = vc.query_advice(step.rd, Rotation::cur());
+            let carry = vc.query_advice(step.aux[0], Rotation::cur());
+            let s = step.flag(vc, Opcode::ADD);
 
-            vec![s * (lhs + rhs - out)]
+            vec![
+                s.clone() * (lhs + rhs - out - carry.clone() * wrap.clone()),
+                s * carry.clone() * (Expression::Constant(F::one()) - carry),
+            ]
         });
 
         cs.create_gate("RType::SUB", |vc| {
-            let lhs = vc.query_advice(lhs_col, Rotation::cur());
-            let rhs = vc.query_advice(rhs_col, Rotation::cur());
-            let out = vc.query_advice(lhs_col, Rotation::next());
-            let s = vc.query_selector(s_sub);
+            let lhs = vc.query_advice(step.rs1, Rotation::cur());
+            let rhs = vc.query_advice(step.rs2, Rotation::cur());
+            let out = vc.query_advice(step.rd, Rotation::cur());
+            let borrow = vc.query_advice(step.aux[0], Rotation::cur());
+            let s = step.flag(vc, Opcode::SUB);
             // let (value, _) = rs1_value.overflowing_sub(rs2_value);
-            vec![s * (lhs - rhs - out)]
+            vec![
+                s.clone() * (lhs - rhs - out + borrow.clone() * wrap.clone()),
+                s * borrow.clone() * (Expression::Constant(F::one()) - borrow),
+            ]
         });
 
         Self {
-            lhs_col,
-            rhs_col,
-            s_add,
-            s_sub,
+            step: step.clone(),
             _maker: PhantomData::default(),
         }
     }
 
-    pub fn assign(&self, layouter: &mut impl Layouter<F>, step: &OpStep) -> Result<(), Error> {
-        layouter.assign_region(
-            || "rtype",
-            |mut region| {
-                let rs1 = step.instruction.op_b;
-                let rs2 = step.instruction.op_c;
-                let rd = step.instruction.op_a;
-                let rs1_value = step.register_indexes.unwrap().read(rs1).unwrap();
-                let rs2_value = step.register_indexes.unwrap().read(rs2).unwrap();
-                let rd_value = step.register_indexes.unwrap().write(rd).unwrap();
-
-                region.assign_advice(
-                    || "lhs",
-                    self.lhs_col,
-                    0,
-                    || Value::known(F::from(rs1_value)),
-                )?;
+    pub fn assign(&self, slot: &mut Slot<F>, step: &OpStep) -> Result<(), Error> {
+        let rs1 = step.instruction.op_b;
+        let rs2 = step.instruction.op_c;
+        let rd = step.instruction.op_a;
+        let registers = step.register_indexes.ok_or(Error::Synthesis)?;
+        let rs1_value = registers.read(rs1).ok_or(Error::Synthesis)?;
+        let rs2_value = registers.read(rs2).ok_or(Error::Synthesis)?;
+        let rd_value = registers.write(rd).ok_or(Error::Synthesis)?;
 
-                region.assign_advice(
-                    || "rhs",
-                    self.rhs_col,
-                    0,
-                    || Value::known(F::from(rs2_value)),
-                )?;
-
-                region.assign_advice(
-                    || "output",
-                    self.lhs_col,
-                    1,
-                    || Value::known(F::from(rd_value)),
-                )?;
-
-                match step.instruction.opcode.into() {
-                    Opcode::ADD => self.s_add.enable(&mut region, 0)?,
-                    Opcode::SUB => self.s_sub.enable(&mut region, 0)?,
-                    _ => {}, // TODO
-                };
-                Ok(())
-            },
-        )
+        let carry = match step.instruction.opcode {
+            Opcode::ADD => rs1_value.overflowing_add(rs2_value).1,
+            Opcode::SUB => rs1_value.overflowing_sub(rs2_value).1,
+            _ => false, // TODO
+        };
+        for (column, value) in [
+            (self.step.rs1, rs1_value),
+            (self.step.rs2, rs2_value),
+            (self.step.rd, rd_value),
+            (self.step.aux[0], carry as u64),
+        ] {
+            slot.assign(column, 0, F::from(value));
+        }
+        Ok(())
     }
 }
@@ -0,0 +1,558 @@
+use core::marker::PhantomData;
+use entry_builder::op_step::OpStep;
+use runtime::trace::Opcode;
+
+use alloc::vec;
+use alloc::vec::Vec;
+use halo2_proofs::arithmetic::FieldExt;
+use halo2_proofs::circuit::{Layouter, Value};
+use halo2_proofs::plonk::*;
+use halo2_proofs::poly::Rotation;
+
+use crate::execution_table::op_configure::bitwise::{self, BitwiseGadget};
+use crate::execution_table::{Slot, StepConfig};
+
+/// Kind of the rows of the pow table of the shifts of 64-bit values, whose
+/// amounts are below 64.
+const KIND_DOUBLE: u64 = 1;
+/// Kind of the rows of the pow table of the shifts of words, whose amounts
+/// are below 32.
+const KIND_WORD: u64 = 2;
+
+/// The `(n, zero extended)` of the instructions adding `rs1 << n` to `rs2`.
+fn shift_add(opcode: Opcode) -> Option<(u32, bool)> {
+    match opcode {
+        Opcode::SH1ADD => Some((1, false)),
+        Opcode::SH2ADD => Some((2, false)),
+        Opcode::SH3ADD => Some((3, false)),
+        Opcode::ADDUW => Some((0, true)),
+        Opcode::SH1ADDUW => Some((1, true)),
+        Opcode::SH2ADDUW => Some((2, true)),
+        Opcode::SH3ADDUW => Some((3, true)),
+        _ => None,
+    }
+}
+
+/// Kind of the pow table row the steps of `opcode` look up, 0 if they don't.
+fn kind(opcode: Opcode) -> u64 {
+    match opcode {
+        Opcode::SLLIUW
+        | Opcode::ROL
+        | Opcode::ROR
+        | Opcode::RORI
+        | Opcode::BCLR
+        | Opcode::BCLRI
+        | Opcode::BEXT
+        | Opcode::BEXTI
+        | Opcode::BINV
+        | Opcode::BINVI
+        | Opcode::BSET
+        | Opcode::BSETI => KIND_DOUBLE,
+        Opcode::ROLW | Opcode::RORW | Opcode::RORIW => KIND_WORD,
+        _ => 0,
+    }
+}
+
+const ROTATIONS: [Opcode; 3] = [Opcode::ROL, Opcode::ROR, Opcode::RORI];
+const WORD_ROTATIONS: [Opcode; 3] = [Opcode::ROLW, Opcode::RORW, Opcode::RORIW];
+const BITS: [Opcode; 4] = [Opcode::BCLR, Opcode::BEXT, Opcode::BINV, Opcode::BSET];
+const BITS_IMMEDIATE: [Opcode; 4] = [Opcode::BCLRI, Opcode::BEXTI, Opcode::BINVI, Opcode::BSETI];
+
+/// Proves the Zba and Zbs instructions and the rotations, which multiply a
+/// value by a power of two and split the product with the limbs of the
+/// [`BitwiseGadget`]. The first row of a step looks `(kind, shamt, pow)` up in
+/// a table of the powers `2^shamt` below 2^64, or below 2^32 for the word
+/// instructions, see [`kind`]:
+///
+/// - SHnADD: `rs1 · 2^n + rs2 = ext · 2^64 + lhs` and rd is lhs. The .UW forms
+///   and ADD.UW shift the low half of lhs, which is rs1, and rd is rhs.
+///   SLLI.UW shifts it by the power of its immediate.
+/// - Rotations: `rs1 · pow = rhs · 2^64 + lhs` and rd is `lhs + rhs`, with the
+///   amount to the left from rs2 or the immediate: rs2 is `shamt + 64 · ext`
+///   for ROL and `64 · ext - shamt` for ROR. The word forms shift the low half
+///   of lhs, which is rs1, into rhs and sign extend the sum of its halves with
+///   the sign from the signs tag on the row 8.
+/// - Single bit: with `aux0 = 2^63 / pow`, `rs1 · aux0 = rhs · 2^64 + lhs`
+///   moves the bit shamt of rs1 to the sign of lhs, which the signs tag gives
+///   on the first row since rhs is below 2^63.
+#[derive(Clone)]
+pub struct ShiftGadget<F> {
+    pub shamt: Column<Advice>,
+    pub pow: Column<Advice>,
+    pub kind_table: TableColumn,
+    pub shamt_table: TableColumn,
+    pub pow_table: TableColumn,
+    step: StepConfig,
+    bitwise: BitwiseGadget<F>,
+    _maker: PhantomData<F>,
+}
+
+impl<F: FieldExt> ShiftGadget<F> {
+    pub fn configure(
+        cs: &mut ConstraintSystem<F>,
+        step: &StepConfig,
+        bitwise: &BitwiseGadget<F>,
+    ) -> Self {
+        let shamt = cs.advice_column();
+        let pow = cs.advice_column();
+        let kind_table = cs.lookup_table_column();
+        let shamt_table = cs.lookup_table_column();
+        let pow_table = cs.lookup_table_column();
+
+        // The kind is 0 on the other rows and steps, whose shamt and pow are
+        // 0 too.
+        cs.lookup("shift pow", |vc| {
+            let q_step = vc.query_selector(step.q_step);
+            vec![
+                (q_step * step.select(vc, kind), kind_table),
+                (vc.query_advice(shamt, Rotation::cur()), shamt_table),
+                (vc.query_advice(pow, Rotation::cur()), pow_table),
+            ]
+        });
+
+        let wrap = Expression::Constant(F::from_u128(1 << 64));
+        let sum = |vc: &mut VirtualCells<'_, F>, opcodes: &[Opcode]| {
+            opcodes
+                .iter()
+                .fold(Expression::Constant(F::zero()), |sum, opcode| {
+                    sum + step.flag(vc, *opcode)
+                })
+        };
+
+        cs.create_gate("shift add", |vc| {
+            let rs1 = vc.query_advice(step.rs1, Rotation::cur());
+            let rs2 = vc.query_advice(step.rs2, Rotation::cur());
+            let rd = vc.query_advice(step.rd, Rotation::cur());
+            let imm = vc.query_advice(step.op_c, Rotation::cur());
+            let lhs = bitwise.value(vc, bitwise.acc_lhs_col);
+            let rhs = bitwise.value(vc, bitwise.acc_rhs_col);
+            let ext = bitwise.value(vc, bitwise.acc_ext_col);
+            let low = bitwise.low(vc, bitwise.acc_lhs_col);
+            let shamt = vc.query_advice(shamt, Rotation::cur());
+            let pow = vc.query_advice(pow, Rotation::cur());
+
+            let mut constraints = vec![];
+            for opcode in [
+                Opcode::SH1ADD,
+                Opcode::SH2ADD,
+                Opcode::SH3ADD,
+                Opcode::ADDUW,
+                Opcode::SH1ADDUW,
+                Opcode::SH2ADDUW,
+                Opcode::SH3ADDUW,
+            ] {
+                let (n, zero_extended) = shift_add(opcode).unwrap();
+                let flag = step.flag(vc, opcode);
+                let scale = Expression::Constant(F::from(1 << n));
+                let shifted = if zero_extended {
+                    constraints.push(flag.clone() * (lhs.clone() - rs1.clone()));
+                    constraints.push(flag.clone() * (rhs.clone() - rd.clone()));
+                    low.clone() * scale
+                } else {
+                    constraints.push(flag.clone() * (lhs.clone() - rd.clone()));
+                    rs1.clone() * scale
+                };
+                constraints
+                    .push(flag * (shifted + rs2.clone() - ext.clone() * wrap.clone() - rd.clone()));
+            }
+            let flag = step.flag(vc, Opcode::SLLIUW);
+            constraints.extend([
+                flag.clone() * (lhs - rs1),
+                flag.clone() * (rhs - rd.clone()),
+                flag.clone() * (shamt - imm),
+                flag * (low * pow - ext * wrap.clone() - rd),
+            ]);
+            constraints
+        });
+
+        cs.create_gate("shift rotate", |vc| {
+            let one = Expression::Constant(F::one());
+            let two = Expression::Constant(F::from(2));
+            let rs1 = vc.query_advice(step.rs1, Rotation::cur());
+            let rs2 = vc.query_advice(step.rs2, Rotation::cur());
+            let rd = vc.query_advice(step.rd, Rotation::cur());
+            let imm = vc.query_advice(step.op_c, Rotation::cur());
+            let aux = step.aux.map(|aux| vc.query_advice(aux, Rotation::cur()));
+            let lhs = bitwise.value(vc, bitwise.acc_lhs_col);
+            let rhs = bitwise.value(vc, bitwise.acc_rhs_col);
+            let ext = bitwise.value(vc, bitwise.acc_ext_col);
+            let shamt = vc.query_advice(shamt, Rotation::cur());
+            let pow = vc.query_advice(pow, Rotation::cur());
+
+            let rotation = sum(vc, &ROTATIONS);
+            let bits = Expression::Constant(F::from(64));
+            let mut constraints = vec![
+                rotation.clone()
+                    * (rs1.clone() * pow.clone() - rhs.clone() * wrap.clone() - lhs.clone()),
+                rotation * (rd.clone() - lhs.clone() - rhs.clone()),
+                step.flag(vc, Opcode::ROL)
+                    * (rs2.clone() - shamt.clone() - bits.clone() * ext.clone()),
+                step.flag(vc, Opcode::ROR)
+                    * (rs2.clone() + shamt.clone() - bits.clone() * ext.clone()),
+                step.flag(vc, Opcode::RORI)
+                    * (shamt.clone() + imm.clone())
+                    * (shamt.clone() + imm.clone() - bits),
+            ];
+
+            let word = sum(vc, &WORD_ROTATIONS);
+            let bits = Expression::Constant(F::from(32));
+            let high = bitwise.high(vc, bitwise.acc_rhs_col);
+            let low = bitwise.low(vc, bitwise.acc_rhs_col);
+            constraints.extend([
+                word.clone() * aux[0].clone() * (one.clone() - aux[0].clone()),
+                word.clone() * aux[1].clone() * (one - aux[1].clone()),
+                word.clone()
+                    * (bitwise.limb(vc, bitwise.out_col, 8)
+                        - two * aux[0].clone()
+                        - aux[1].clone()),
+                word.clone() * (lhs - rs1),
+                word.clone() * (bitwise.low(vc, bitwise.acc_lhs_col) * pow - rhs),
+                word * (rd
+                    - high
+                    - low
+                    - aux[1].clone() * Expression::Constant(F::from(u64::MAX << 32))),
+                step.flag(vc, Opcode::ROLW)
+                    * (rs2.clone() - shamt.clone() - bits.clone() * ext.clone()),
+                step.flag(vc, Opcode::RORW) * (rs2 + shamt.clone() - bits.clone() * ext),
+                step.flag(vc, Opcode::RORIW) * (shamt.clone() + imm.clone()) * (shamt + imm - bits),
+            ]);
+            constraints
+        });
+
+        cs.create_gate("shift bit", |vc| {
+            let one = Expression::Constant(F::one());
+            let two = Expression::Constant(F::from(2));
+            let rs1 = vc.query_advice(step.rs1, Rotation::cur());
+            let rs2 = vc.query_advice(step.rs2, Rotation::cur());
+            let rd = vc.query_advice(step.rd, Rotation::cur());
+            let imm = vc.query_advice(step.op_c, Rotation::cur());
+            let inverse = vc.query_advice(step.aux[0], Rotation::cur());
+            let lhs = bitwise.value(vc, bitwise.acc_lhs_col);
+            let rhs = bitwise.value(vc, bitwise.acc_rhs_col);
+            let ext = bitwise.value(vc, bitwise.acc_ext_col);
+            let shamt = vc.query_advice(shamt, Rotation::cur());
+            let pow = vc.query_advice(pow, Rotation::cur());
+            // Twice the bit.
+            let bit = bitwise.limb(vc, bitwise.out_col, 0);
+
+            let register = sum(vc, &BITS);
+            let immediate = sum(vc, &BITS_IMMEDIATE);
+            let single = register.clone() + immediate.clone();
+            let mut pair = |opcode: Opcode| {
+                let index = BITS.iter().position(|other| *other == opcode).unwrap();
+                step.flag(vc, opcode) + step.flag(vc, BITS_IMMEDIATE[index])
+            };
+            vec![
+                single.clone()
+                    * (pow.clone() * inverse.clone() - Expression::Constant(F::from(1 << 63))),
+                single * (rs1.clone() * inverse - rhs * wrap.clone() - lhs),
+                register * (rs2 - shamt.clone() - Expression::Constant(F::from(64)) * ext),
+                immediate * (shamt - imm),
+                pair(Opcode::BSET)
+                    * (two.clone() * (rd.clone() - rs1.clone())
+                        - (two.clone() - bit.clone()) * pow.clone()),
+                pair(Opcode::BCLR)
+                    * (two.clone() * (rd.clone() - rs1.clone()) + bit.clone() * pow.clone()),
+                pair(Opcode::BINV) * (rd.clone() - rs1 - (one - bit.clone()) * pow),
+                pair(Opcode::BEXT) * (two * rd - bit),
+            ]
+        });
+
+        Self {
+            shamt,
+            pow,
+            kind_table,
+            shamt_table,
+            pow_table,
+            step: step.clone(),
+            bitwise: bitwise.clone(),
+            _maker: PhantomData::default(),
+        }
+    }
+
+    /// Returns whether the gadget proves `opcode`.
+    pub fn supports(opcode: Opcode) -> bool {
+        shift_add(opcode).is_some() || kind(opcode) != 0
+    }
+
+    pub fn columns(&self) -> Vec<Column<Advice>> {
+        vec![self.shamt, self.pow]
+    }
+
+    pub fn assign_table(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        layouter.assign_table(
+            || "pow table",
+            |mut table| {
+                let rows = (0..64)
+                    .map(|shamt| (KIND_DOUBLE, shamt))
+                    .chain((0..32).map(|shamt| (KIND_WORD, shamt)))
+                    .map(|(kind, shamt)| (kind, shamt, 1 << shamt));
+                for (index, (kind, shamt, pow)) in [(0, 0, 0)].into_iter().chain(rows).enumerate() {
+                    for (column, value) in [
+                        (self.kind_table, kind),
+                        (self.shamt_table, shamt),
+                        (self.pow_table, pow),
+                    ] {
+                        table.assign_cell(
+                            || "pow table",
+                            column,
+                            index,
+                            || Value::known(F::from(value)),
+                        )?;
+                    }
+                }
+                Ok(())
+            },
+        )
+    }
+
+    pub fn assign(&self, slot: &mut Slot<F>, step: &OpStep) -> Result<(), Error> {
+        let opcode = step.instruction.opcode;
+        let registers = step.register_indexes.ok_or(Error::Synthesis)?;
+        let rs1_value = registers
+            .read(step.instruction.op_b)
+            .ok_or(Error::Synthesis)?;
+        let rd_value = registers
+            .write(step.instruction.op_a)
+            .ok_or(Error::Synthesis)?;
+        let imm = step.instruction.op_c;
+        let rs2_value = match step.instruction.imm_c {
+            true => 0,
+            false => registers
+                .read(step.instruction.op_c)
+                .ok_or(Error::Synthesis)?,
+        };
+        for (column, value) in [
+            (self.step.rs1, rs1_value),
+            (self.step.rs2, rs2_value),
+            (self.step.rd, rd_value),
+        ] {
+            slot.assign(column, 0, F::from(value));
+        }
+
+        let tag = bitwise::tag(opcode);
+        if let Some((n, zero_extended)) = shift_add(opcode) {
+            let shifted = match zero_extended {
+                true => rs1_value as u32 as u128,
+                false => rs1_value as u128,
+            } << n;
+            let carry = ((shifted + rs2_value as u128) >> 64) as u64;
+            match zero_extended {
+                true => self.bitwise.assign_limbs(slot, tag, rs1_value, rd_value),
+                false => self.bitwise.assign_limbs(slot, tag, rd_value, 0),
+            }
+            self.bitwise.assign_ext(slot, carry);
+            return Ok(());
+        }
+
+        // The amount of the shift to the left and the ext value.
+        let (shamt, ext) = match opcode {
+            Opcode::SLLIUW => (imm, 0),
+            Opcode::ROL => (rs2_value & 0x3f, rs2_value >> 6),
+            Opcode::ROLW => (rs2_value & 0x1f, rs2_value >> 5),
+            Opcode::ROR | Opcode::RORW => {
+                let bits = if opcode == Opcode::ROR { 64 } else { 32 };
+                let shamt = rs2_value.wrapping_neg() & (bits - 1);
+                (
+                    shamt,
+                    ((rs2_value as u128 + shamt as u128) / bits as u128) as u64,
+                )
+            }
+            Opcode::RORI => (imm.wrapping_neg() & 0x3f, 0),
+            Opcode::RORIW => (imm.wrapping_neg() & 0x1f, 0),
+            _ if step.instruction.imm_c => (imm, 0),
+            _ => (rs2_value & 0x3f, rs2_value >> 6),
+        };
+        slot.assign(self.shamt, 0, F::from(shamt));
+        slot.assign(self.pow, 0, F::from(1 << shamt));
+        match opcode {
+            Opcode::SLLIUW => {
+                let product = (rs1_value as u32 as u128) << shamt;
+                self.bitwise.assign_limbs(slot, tag, rs1_value, rd_value);
+                self.bitwise.assign_ext(slot, (product >> 64) as u64);
+            }
+            Opcode::ROLW | Opcode::RORW | Opcode::RORIW => {
+                let product = (rs1_value as u32 as u64) << shamt;
+                self.bitwise.assign_limbs(slot, tag, rs1_value, product);
+                self.bitwise.assign_ext(slot, ext);
+                for (aux, value) in self.step.aux.into_iter().zip([rs1_value, product]) {
+                    slot.assign(aux, 0, F::from((value >> 31) & 1));
+                }
+            }
+            _ => {
+                // The rotations multiply by the power, the single bit
+                // instructions by its inverse times 2^63.
+                let shift = match kind(opcode) == KIND_DOUBLE && !ROTATIONS.contains(&opcode) {
+                    true => {
+                        slot.assign(self.step.aux[0], 0, F::from(1 << (63 - shamt)));
+                        63 - shamt
+                    }
+                    false => shamt,
+                };
+                let product = (rs1_value as u128) << shift;
+                self.bitwise
+                    .assign_limbs(slot, tag, product as u64, (product >> 64) as u64);
+                self.bitwise.assign_ext(slot, ext);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::main_circuit::tests::{circuit, program, trace, verify};
+    use runtime::program::Program;
+    use runtime::trace::Trace;
+
+    // sh1add a2, a0, a1; sh2add a3, a0, a1; sh3add a4, a0, a1;
+    // add.uw a5, a0, a1; sh1add.uw a6, a0, a1; sh2add.uw a7, a0, a1;
+    // sh3add.uw s2, a0, a1; slli.uw s3, a0, 5
+    const ZBA: [(u64, u32); 8] = [
+        (0x10000, 0x20b52633),
+        (0x10004, 0x20b546b3),
+        (0x10008, 0x20b56733),
+        (0x1000c, 0x08b507bb),
+        (0x10010, 0x20b5283b),
+        (0x10014, 0x20b548bb),
+        (0x10018, 0x20b5693b),
+        (0x1001c, 0x0855199b),
+    ];
+
+    // rol a2, a0, a1; ror a3, a0, a1; rori a4, a0, 13; rolw a5, a0, a1;
+    // rorw a6, a0, a1; roriw a7, a0, 7
+    const ROTATIONS: [(u64, u32); 6] = [
+        (0x10000, 0x60b51633),
+        (0x10004, 0x60b556b3),
+        (0x10008, 0x60d55713),
+        (0x1000c, 0x60b517bb),
+        (0x10010, 0x60b5583b),
+        (0x10014, 0x6075589b),
+    ];
+
+    // bclr a2, a0, a1; bext a3, a0, a1; binv a4, a0, a1; bset a5, a0, a1;
+    // bclri a6, a0, 63; bexti a7, a0, 7; binvi s2, a0, 0; bseti s3, a0, 33
+    const ZBS: [(u64, u32); 8] = [
+        (0x10000, 0x48b51633),
+        (0x10004, 0x48b556b3),
+        (0x10008, 0x68b51733),
+        (0x1000c, 0x28b517b3),
+        (0x10010, 0x4bf51813),
+        (0x10014, 0x48755893),
+        (0x10018, 0x68051913),
+        (0x1001c, 0x2a151993),
+    ];
+
+    const OPERANDS: [(u64, u64); 4] = [
+        (0, 0),
+        (0x8000_0000_ffff_ffff, u64::MAX),
+        (0xfedc_ba98_7654_3210, 0x0123_4567_89ab_cdef),
+        (0x0000_0000_8765_4321, 0x0000_0000_0000_0040),
+    ];
+
+    fn zba_trace(program: &Program, a0: u64, a1: u64) -> Trace {
+        let word = a0 as u32 as u64;
+        trace(
+            program,
+            &[(10, a0), (11, a1)],
+            &[
+                (0x10000, &[(12, (a0 << 1).wrapping_add(a1))]),
+                (0x10004, &[(13, (a0 << 2).wrapping_add(a1))]),
+                (0x10008, &[(14, (a0 << 3).wrapping_add(a1))]),
+                (0x1000c, &[(15, word.wrapping_add(a1))]),
+                (0x10010, &[(16, (word << 1).wrapping_add(a1))]),
+                (0x10014, &[(17, (word << 2).wrapping_add(a1))]),
+                (0x10018, &[(18, (word << 3).wrapping_add(a1))]),
+                (0x1001c, &[(19, word << 5)]),
+            ],
+        )
+    }
+
+    fn rotations_trace(program: &Program, a0: u64, a1: u64) -> Trace {
+        let word = a0 as u32;
+        let shift = a1 as u32;
+        trace(
+            program,
+            &[(10, a0), (11, a1)],
+            &[
+                (0x10000, &[(12, a0.rotate_left(shift & 0x3f))]),
+                (0x10004, &[(13, a0.rotate_right(shift & 0x3f))]),
+                (0x10008, &[(14, a0.rotate_right(13))]),
+                (
+                    0x1000c,
+                    &[(15, word.rotate_left(shift & 0x1f) as i32 as u64)],
+                ),
+                (
+                    0x10010,
+                    &[(16, word.rotate_right(shift & 0x1f) as i32 as u64)],
+                ),
+                (0x10014, &[(17, word.rotate_right(7) as i32 as u64)]),
+            ],
+        )
+    }
+
+    fn zbs_trace(program: &Program, a0: u64, a1: u64) -> Trace {
+        let bit = 1 << (a1 & 0x3f);
+        trace(
+            program,
+            &[(10, a0), (11, a1)],
+            &[
+                (0x10000, &[(12, a0 & !bit)]),
+                (0x10004, &[(13, (a0 & bit != 0) as u64)]),
+                (0x10008, &[(14, a0 ^ bit)]),
+                (0x1000c, &[(15, a0 | bit)]),
+                (0x10010, &[(16, a0 & !(1 << 63))]),
+                (0x10014, &[(17, (a0 >> 7) & 1)]),
+                (0x10018, &[(18, a0 ^ 1)]),
+                (0x1001c, &[(19, a0 | (1 << 33))]),
+            ],
+        )
+    }
+
+    /// The trace of a program from `a0` and `a1`.
+    type TraceOf = fn(&Program, u64, u64) -> Trace;
+
+    const PROGRAMS: [(&[(u64, u32)], TraceOf); 3] = [
+        (&ZBA, zba_trace),
+        (&ROTATIONS, rotations_trace),
+        (&ZBS, zbs_trace),
+    ];
+
+    #[test]
+    fn proves_shifts() {
+        for (code, trace) in PROGRAMS {
+            let program = program(code);
+            for (a0, a1) in OPERANDS {
+                let trace = trace(&program, a0, a1);
+                assert!(
+                    verify(&circuit(program.clone(), &trace)),
+                    "{:x} {:x}",
+                    a0,
+                    a1
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_tampered_shifts() {
+        let (a0, a1) = OPERANDS[2];
+        for (code, trace) in PROGRAMS {
+            let program = program(code);
+            let trace = trace(&program, a0, a1);
+            for global_clk in 0..code.len() as u64 {
+                for bit in [0, 32, 63] {
+                    let mut circuit = circuit(program.clone(), &trace);
+                    let ops = &mut circuit
+                        .entries
+                        .register_ops
+                        .get_mut(&global_clk)
+                        .unwrap()
+                        .ops;
+                    ops.last_mut().unwrap().value ^= 1 << bit;
+                    assert!(!verify(&circuit), "step {} bit {}", global_clk, bit);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,509 @@
+//! Fingerprint of a circuit, identifying the circuit version a verifying key
+//! was generated for.
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use halo2_proofs::arithmetic::FieldExt;
+use halo2_proofs::circuit::Value;
+use halo2_proofs::helpers::{SerdeCurveAffine, SerdePrimeField};
+use halo2_proofs::plonk::{
+    Advice, Any, Assigned, Assignment, Challenge, Circuit, Column, ConstraintSystem, Error,
+    Expression, Fixed, FloorPlanner, Instance, Selector, VerifyingKey,
+};
+use halo2_proofs::poly::Rotation;
+use halo2_proofs::SerdeFormat;
+
+/// Blake2b-256 of the verifying key in the raw bytes format, which holds the
+/// domain size, the fixed and permutation commitments and the selector
+/// assignments, and of a canonical encoding of its constraint system, see
+/// [`encode_cs`].
+///
+/// Verifying several proofs, compute it once per key and compare it to the
+/// fingerprint of each proof with [`check_fingerprint`].
+pub fn fingerprint<C>(vk: &VerifyingKey<C>) -> [u8; 32]
+where
+    C: SerdeCurveAffine,
+    C::Scalar: SerdePrimeField,
+{
+    let mut key = Vec::new();
+    vk.write(&mut key, SerdeFormat::RawBytes)
+        .expect("writing to a vector doesn't fail");
+    let mut cs = Vec::new();
+    encode_cs(vk.cs(), &mut cs);
+
+    let mut hasher = blake2b_simd::Params::new()
+        .hash_length(32)
+        .personal(b"bs2-circuit-fp\0\0")
+        .to_state();
+    for part in [key, cs] {
+        hasher.update(&(part.len() as u64).to_le_bytes());
+        hasher.update(&part);
+    }
+    let mut fingerprint = [0u8; 32];
+    fingerprint.copy_from_slice(hasher.finalize().as_bytes());
+    fingerprint
+}
+
+/// Encodes the shape of a constraint system, what a verifier reads from it:
+/// the scalar field, the columns with the phases of the advice ones, the
+/// challenges, the queries, the gates, the permutation and the lookups. Integers are little
+/// endian `u64`, rotations `i32`, lists are prefixed with their length and
+/// expressions are written in prefix order with a tag per node.
+pub fn encode_cs<F: FieldExt>(cs: &ConstraintSystem<F>, out: &mut Vec<u8>) {
+    encode_bytes(F::MODULUS.as_bytes(), out);
+    encode_usize(cs.num_fixed_columns(), out);
+    encode_bytes(&cs.advice_column_phase(), out);
+    encode_usize(cs.num_instance_columns(), out);
+    encode_bytes(&cs.challenge_phase(), out);
+
+    encode_usize(cs.advice_queries().len(), out);
+    for (column, rotation) in cs.advice_queries().iter() {
+        encode_query(column.index(), *rotation, out);
+    }
+    encode_usize(cs.instance_queries().len(), out);
+    for (column, rotation) in cs.instance_queries().iter() {
+        encode_query(column.index(), *rotation, out);
+    }
+    encode_usize(cs.fixed_queries().len(), out);
+    for (column, rotation) in cs.fixed_queries().iter() {
+        encode_query(column.index(), *rotation, out);
+    }
+
+    encode_usize(cs.gates().len(), out);
+    for gate in cs.gates().iter() {
+        encode_expressions(gate.polynomials(), out);
+    }
+
+    let columns = cs.permutation().get_columns();
+    encode_usize(columns.len(), out);
+    for column in columns.iter() {
+        encode_column(column, out);
+    }
+
+    encode_usize(cs.lookups().len(), out);
+    for lookup in cs.lookups().iter() {
+        encode_expressions(lookup.input_expressions(), out);
+        encode_expressions(lookup.table_expressions(), out);
+    }
+}
+
+fn encode_usize(value: usize, out: &mut Vec<u8>) {
+    out.extend_from_slice(&(value as u64).to_le_bytes());
+}
+
+fn encode_bytes(bytes: &[u8], out: &mut Vec<u8>) {
+    encode_usize(bytes.len(), out);
+    out.extend_from_slice(bytes);
+}
+
+fn encode_query(column: usize, rotation: Rotation, out: &mut Vec<u8>) {
+    encode_usize(column, out);
+    out.extend_from_slice(&rotation.0.to_le_bytes());
+}
+
+fn encode_column(column: &Column<Any>, out: &mut Vec<u8>) {
+    match column.column_type() {
+        Any::Advice(advice) => out.extend_from_slice(&[0, advice.phase()]),
+        Any::Fixed => out.push(1),
+        Any::Instance => out.push(2),
+    }
+    encode_usize(column.index(), out);
+}
+
+fn encode_expressions<F: FieldExt>(expressions: &[Expression<F>], out: &mut Vec<u8>) {
+    encode_usize(expressions.len(), out);
+    for expression in expressions.iter() {
+        encode_expression(expression, out);
+    }
+}
+
+fn encode_expression<F: FieldExt>(expression: &Expression<F>, out: &mut Vec<u8>) {
+    match expression {
+        Expression::Constant(constant) => {
+            out.push(0);
+            out.extend_from_slice(constant.to_repr().as_ref());
+        }
+        Expression::Selector(selector) => {
+            out.extend_from_slice(&[1, selector.is_simple() as u8]);
+            encode_usize(selector.index(), out);
+        }
+        Expression::Fixed(query) => {
+            out.push(2);
+            encode_query(query.column_index(), query.rotation(), out);
+        }
+        Expression::Advice(query) => {
+            out.extend_from_slice(&[3, query.phase()]);
+            encode_query(query.column_index(), query.rotation(), out);
+        }
+        Expression::Instance(query) => {
+            out.push(4);
+            encode_query(query.column_index(), query.rotation(), out);
+        }
+        Expression::Challenge(challenge) => {
+            out.extend_from_slice(&[5, challenge.phase()]);
+            encode_usize(challenge.index(), out);
+        }
+        Expression::Negated(a) => {
+            out.push(6);
+            encode_expression(a, out);
+        }
+        Expression::Sum(a, b) => {
+            out.push(7);
+            encode_expression(a, out);
+            encode_expression(b, out);
+        }
+        Expression::Product(a, b) => {
+            out.push(8);
+            encode_expression(a, out);
+            encode_expression(b, out);
+        }
+        Expression::Scaled(a, scalar) => {
+            out.push(9);
+            encode_expression(a, out);
+            out.extend_from_slice(scalar.to_repr().as_ref());
+        }
+    }
+}
+
+/// Blake2b-256 of what the keys of a circuit are generated from, but the
+/// params: the shape of its constraint system, see [`encode_cs`], and the
+/// fixed cells, selectors and copy constraints its synthesis assigns, which
+/// include the lookup tables. Circuits with the same one have the same keys
+/// with the same params, and it takes a synthesis without any keygen.
+pub fn layout_fingerprint<F: FieldExt, C: Circuit<F>>(circuit: &C) -> Result<[u8; 32], Error> {
+    let mut cs = ConstraintSystem::default();
+    let config = C::configure(&mut cs);
+    let mut layout = FixedLayout::default();
+    C::FloorPlanner::synthesize(&mut layout, circuit, config, cs.constants().clone())?;
+
+    let mut out = Vec::new();
+    encode_cs(&cs, &mut out);
+    for cells in [&layout.fixed, &layout.fills] {
+        encode_usize(cells.len(), &mut out);
+        for ((column, row), value) in cells.iter() {
+            encode_usize(*column, &mut out);
+            encode_usize(*row, &mut out);
+            out.extend_from_slice(value.to_repr().as_ref());
+        }
+    }
+    encode_usize(layout.selectors.len(), &mut out);
+    for (selector, row) in layout.selectors.iter() {
+        encode_usize(*selector, &mut out);
+        encode_usize(*row, &mut out);
+    }
+    layout.copies.sort();
+    encode_usize(layout.copies.len(), &mut out);
+    for cells in layout.copies.iter() {
+        for (kind, column, row) in cells.iter() {
+            out.push(*kind);
+            encode_usize(*column, &mut out);
+            encode_usize(*row, &mut out);
+        }
+    }
+
+    let hash = blake2b_simd::Params::new()
+        .hash_length(32)
+        .personal(b"bs2-layout-fp\0\0\0")
+        .hash(&out);
+    let mut fingerprint = [0u8; 32];
+    fingerprint.copy_from_slice(hash.as_bytes());
+    Ok(fingerprint)
+}
+
+/// The cells of a synthesis the keygen reads, by column index and row.
+struct FixedLayout<F> {
+    fixed: BTreeMap<(usize, usize), F>,
+    /// Values the fixed columns are filled with from a row to the end.
+    fills: BTreeMap<(usize, usize), F>,
+    selectors: BTreeSet<(usize, usize)>,
+    /// Pairs of `(column type, column index, row)` cells.
+    copies: Vec<[(u8, usize, usize); 2]>,
+}
+
+impl<F> Default for FixedLayout<F> {
+    fn default() -> Self {
+        Self {
+            fixed: BTreeMap::new(),
+            fills: BTreeMap::new(),
+            selectors: BTreeSet::new(),
+            copies: Vec::new(),
+        }
+    }
+}
+
+/// The value of a fixed cell, which the keygen requires to be known.
+fn known<F: FieldExt>(value: Value<Assigned<F>>) -> Result<F, Error> {
+    let mut known = None;
+    let _ = value.map(|value| known = Some(value.evaluate()));
+    known.ok_or(Error::Synthesis)
+}
+
+fn cell(column: Column<Any>, row: usize) -> (u8, usize, usize) {
+    let kind = match column.column_type() {
+        Any::Advice(_) => 0,
+        Any::Fixed => 1,
+        Any::Instance => 2,
+    };
+    (kind, column.index(), row)
+}
+
+impl<F: FieldExt> Assignment<F> for FixedLayout<F> {
+    fn enter_region<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+    }
+
+    fn exit_region(&mut self) {}
+
+    fn enable_selector<A, AR>(&mut self, _: A, selector: &Selector, row: usize) -> Result<(), Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.selectors.insert((selector.index(), row));
+        Ok(())
+    }
+
+    fn query_instance(&self, _: Column<Instance>, _: usize) -> Result<Value<F>, Error> {
+        Ok(Value::unknown())
+    }
+
+    fn assign_advice<V, VR, A, AR>(
+        &mut self,
+        _: A,
+        _: Column<Advice>,
+        _: usize,
+        _: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        Ok(())
+    }
+
+    fn assign_fixed<V, VR, A, AR>(
+        &mut self,
+        _: A,
+        column: Column<Fixed>,
+        row: usize,
+        to: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let value = known(to().map(|value| value.into()))?;
+        self.fixed.insert((column.index(), row), value);
+        Ok(())
+    }
+
+    fn copy(
+        &mut self,
+        left_column: Column<Any>,
+        left_row: usize,
+        right_column: Column<Any>,
+        right_row: usize,
+    ) -> Result<(), Error> {
+        self.copies
+            .push([cell(left_column, left_row), cell(right_column, right_row)]);
+        Ok(())
+    }
+
+    fn fill_from_row(
+        &mut self,
+        column: Column<Fixed>,
+        row: usize,
+        to: Value<Assigned<F>>,
+    ) -> Result<(), Error> {
+        self.fills.insert((column.index(), row), known(to)?);
+        Ok(())
+    }
+
+    fn get_challenge(&self, _: Challenge) -> Value<F> {
+        Value::unknown()
+    }
+
+    fn annotate_column<A, AR>(&mut self, _: A, _: Column<Any>)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+    }
+
+    fn pop_namespace(&mut self, _: Option<String>) {}
+}
+
+/// The prover and the verifier were built from different circuit versions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FingerprintMismatch {
+    pub proof: [u8; 32],
+    pub vk: [u8; 32],
+}
+
+impl fmt::Display for FingerprintMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "proof is for circuit ")?;
+        write_hex(f, &self.proof)?;
+        write!(f, " but the verifying key is for circuit ")?;
+        write_hex(f, &self.vk)?;
+        write!(
+            f,
+            ", the prover and the verifier use different circuit versions"
+        )
+    }
+}
+
+/// Checks that a proof made for the circuit `expected` can be verified with
+/// the verifying key of fingerprint `vk`.
+pub fn check_fingerprint(vk: &[u8; 32], expected: &[u8; 32]) -> Result<(), FingerprintMismatch> {
+    if vk != expected {
+        return Err(FingerprintMismatch {
+            proof: *expected,
+            vk: *vk,
+        });
+    }
+    Ok(())
+}
+
+fn write_hex(f: &mut fmt::Formatter<'_>, bytes: &[u8]) -> fmt::Result {
+    bytes.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bundle::ProofBundle;
+    use crate::circuit::CircuitKind;
+    use crate::main_circuit::tests::{circuit, program, trace, K};
+    use crate::main_circuit::MainCircuit;
+    use crate::verify::read_vk;
+    use alloc::vec;
+    use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner};
+    use halo2_proofs::halo2curves::bn256::{Bn256, Fr};
+    use halo2_proofs::plonk::{keygen_vk, Circuit};
+    use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+    use rand_core::OsRng;
+
+    const VK: &[u8] = include_bytes!("../testdata/vk.bin");
+    const PROOF: &[u8] = include_bytes!("../testdata/proof.bin");
+
+    #[test]
+    fn fingerprint_is_the_one_of_the_prover() {
+        let bundle = ProofBundle::decode(PROOF).unwrap();
+        let vk = read_vk(CircuitKind::Main, VK).unwrap();
+        assert_eq!(fingerprint(&vk), bundle.fingerprint);
+        assert_eq!(fingerprint(&read_vk(CircuitKind::Main, VK).unwrap()), bundle.fingerprint);
+        assert_eq!(
+            check_fingerprint(&fingerprint(&vk), &[0; 32]),
+            Err(FingerprintMismatch {
+                proof: [0; 32],
+                vk: bundle.fingerprint
+            })
+        );
+    }
+
+    #[test]
+    fn fingerprint_changes_with_the_circuit() {
+        let params = ParamsKZG::<Bn256>::setup(K, OsRng);
+        let max_steps = MainCircuit::<Fr>::max_steps(K);
+        // addi a0, a0, 1 then addi a0, a0, 2
+        let fingerprints = [0x00150513, 0x00250513].map(|bits| {
+            let circuit = MainCircuit::<Fr>::new(program(&[(0x10000, bits)]), max_steps);
+            fingerprint(&keygen_vk(&params, &circuit).unwrap())
+        });
+        assert_ne!(fingerprints[0], fingerprints[1]);
+
+        let mut cs = ConstraintSystem::<Fr>::default();
+        MainCircuit::<Fr>::configure(&mut cs);
+        let mut shape = Vec::new();
+        encode_cs(&cs, &mut shape);
+        let mut other = Vec::new();
+        cs.instance_column();
+        encode_cs(&cs, &mut other);
+        assert_ne!(shape, other);
+    }
+
+    /// An advice cell equal to a fixed cell of the given value.
+    #[derive(Clone, Default)]
+    struct Constant(u64);
+
+    impl Circuit<Fr> for Constant {
+        type Config = (Column<Fixed>, Column<Advice>, Selector);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(cs: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let (fixed, advice, selector) = (cs.fixed_column(), cs.advice_column(), cs.selector());
+            cs.create_gate("constant", |vc| {
+                let selector = vc.query_selector(selector);
+                let fixed = vc.query_fixed(fixed, Rotation::cur());
+                vec![selector * (vc.query_advice(advice, Rotation::cur()) - fixed)]
+            });
+            (fixed, advice, selector)
+        }
+
+        fn synthesize(
+            &self,
+            (fixed, advice, selector): Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            layouter.assign_region(
+                || "constant",
+                |mut region| {
+                    selector.enable(&mut region, 0)?;
+                    let value = Value::known(Fr::from(self.0));
+                    region.assign_fixed(|| "fixed", fixed, 0, || value)?;
+                    region.assign_advice(|| "advice", advice, 0, || value)?;
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn layout_fingerprint_covers_the_fixed_cells() {
+        // Same constraint system, other fixed values.
+        let fingerprints = [1, 1, 2].map(|value| layout_fingerprint(&Constant(value)).unwrap());
+        assert_eq!(fingerprints[0], fingerprints[1]);
+        assert_ne!(fingerprints[0], fingerprints[2]);
+
+        // The trace is not part of it, the program and the steps are.
+        let program = program(&[(0x10000, 0x00150513)]);
+        let max_steps = MainCircuit::<Fr>::max_steps(K);
+        let traced = circuit(
+            program.clone(),
+            &trace(&program, &[], &[(0x10000, &[(10, 1)])]),
+        );
+        let fingerprint =
+            layout_fingerprint(&MainCircuit::<Fr>::new(program.clone(), max_steps)).unwrap();
+        assert_eq!(layout_fingerprint(&traced).unwrap(), fingerprint);
+        assert_ne!(
+            layout_fingerprint(&MainCircuit::<Fr>::new(program, max_steps - 1)).unwrap(),
+            fingerprint
+        );
+        let other = self::program(&[(0x10000, 0x00250513)]);
+        assert_ne!(
+            layout_fingerprint(&MainCircuit::<Fr>::new(other, max_steps)).unwrap(),
+            fingerprint
+        );
+    }
+}
@@ -1,6 +1,15 @@
 #![no_std]
 extern crate alloc;
 
+pub mod aggregation;
+pub mod bundle;
+pub mod circuit;
 pub mod execution_table;
+pub mod fingerprint;
 pub mod main_circuit;
 pub mod memory_table;
+pub mod program_table;
+pub mod stats;
+pub mod transcript;
+pub mod verify;
+pub mod witness;
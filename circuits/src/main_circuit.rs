@@ -1,62 +1,132 @@
-use crate::execution_table::ExecutionTable;
+use crate::execution_table::{ExecutionTable, STEP_ROWS};
 use crate::memory_table::MemoryTable;
-use entry_builder::entries::{self, Entries};
+use crate::program_table::ProgramTable;
+use entry_builder::entries::Entries;
 use halo2_proofs::arithmetic::FieldExt;
+use runtime::program::Program;
 
+use alloc::vec;
 use alloc::vec::Vec;
+use core::convert::TryInto;
 use core::marker::PhantomData;
 use halo2_proofs::{
     circuit::{Layouter, SimpleFloorPlanner},
-    plonk::{Circuit, ConstraintSystem, Error},
+    plonk::{Circuit, Column, ConstraintSystem, Error, Instance},
 };
 
+/// Circuit id recorded in the proof bundles of [`MainCircuit`].
+pub const CIRCUIT_ID: &str = "bs2-main";
+
+/// Public inputs of a proof made for the transaction of hash `tx_hash`: its
+/// two halves as little endian 128 bits integers, encoded as canonical field
+/// elements. They don't bind the proof to the transaction, see
+/// [`MainConfig::tx_hash`].
+pub fn tx_hash_instance(tx_hash: &[u8; 32]) -> Vec<[u8; 32]> {
+    tx_hash
+        .chunks(16)
+        .map(|half| {
+            let mut repr = [0u8; 32];
+            repr[..16].copy_from_slice(half);
+            repr
+        })
+        .collect()
+}
+
 #[derive(Clone)]
 pub struct MainConfig<F> {
     pub execution_table: ExecutionTable<F>,
+    pub program_table: ProgramTable<F>,
+    pub memory_table: MemoryTable<F>,
+    /// The [`tx_hash_instance`] of the proof. No gate queries it, and no
+    /// constraint ties the syscalls, among them `LOAD_TX_HASH`, or the exit
+    /// code to the trace: whoever holds the proving key proves a trace for any
+    /// hash. The KZG verifier only absorbs it in the transcript, so a proof
+    /// made for one hash doesn't verify for another.
+    pub tx_hash: Column<Instance>,
     _marker: PhantomData<F>,
 }
 
 impl<F: FieldExt> MainConfig<F> {
     fn configure(cs: &mut ConstraintSystem<F>) -> Self {
         let execution_table = ExecutionTable::configure(cs);
+        let program_table = ProgramTable::configure(cs, &execution_table.step);
+        let memory_table = MemoryTable::configure(cs, &execution_table.step);
+        let tx_hash = cs.instance_column();
 
         Self {
             execution_table,
+            program_table,
+            memory_table,
+            tx_hash,
             _marker: PhantomData::default(),
         }
     }
 
-    fn assign(&self, layouter: &mut impl Layouter<F>, entries: &Entries) -> Result<(), Error> {
-        self.execution_table.assign(layouter, entries);
+    fn assign(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        circuit: &MainCircuit<F>,
+    ) -> Result<(), Error> {
+        self.execution_table.assign(
+            layouter,
+            &circuit.entries,
+            circuit.program.entry,
+            circuit.max_steps,
+        )?;
+        self.program_table.assign(layouter, &circuit.program)?;
+        self.memory_table
+            .assign(layouter, &circuit.entries, circuit.max_steps)?;
         Ok(())
     }
 }
 
+/// Proves a trace of a program.
+///
+/// The layout, and so the keys, only depends on the program and on the number
+/// of steps the circuit has room for: the same keys prove every trace of the
+/// program up to `max_steps` steps.
 #[derive(Default, Clone)]
 pub struct MainCircuit<F> {
+    /// Program decoded from the ELF, laid out in the program table.
+    pub program: Program,
     pub entries: Entries,
+    pub max_steps: usize,
+    /// Hash of the transaction the proof is made for, which the circuit
+    /// doesn't constrain.
+    pub tx_hash: [u8; 32],
     _marker: PhantomData<F>,
 }
 
 impl<F: FieldExt> MainCircuit<F> {
-    pub fn new() -> Self {
-        Self {
-            entries: Entries::default(),
-            _marker: PhantomData::default(),
-        }
+    /// The circuit without a trace, for the keygen.
+    pub fn new(program: Program, max_steps: usize) -> Self {
+        Self::init(program, Entries::default(), max_steps)
     }
 
-    pub fn init(entries: Entries) -> Self {
+    pub fn init(program: Program, entries: Entries, max_steps: usize) -> Self {
         Self {
+            program,
             entries,
+            max_steps,
+            tx_hash: [0; 32],
             _marker: PhantomData::default(),
         }
     }
 
-    pub fn instance(&self) -> Vec<Vec<F>> {
-        let instance = Vec::new();
+    /// Steps the circuit has room for in `2^k` rows.
+    pub fn max_steps(k: u32) -> usize {
+        let mut cs = ConstraintSystem::<F>::default();
+        MainConfig::configure(&mut cs);
+        (1usize << k).saturating_sub(cs.blinding_factors() + 1) / STEP_ROWS
+    }
 
-        instance
+    pub fn instance(&self) -> Vec<Vec<F>> {
+        let tx_hash = self
+            .tx_hash
+            .chunks(16)
+            .map(|half| F::from_u128(u128::from_le_bytes(half.try_into().unwrap())))
+            .collect();
+        vec![tx_hash]
     }
 }
 
@@ -65,7 +135,7 @@ impl<F: FieldExt> Circuit<F> for MainCircuit<F> {
     type FloorPlanner = SimpleFloorPlanner;
 
     fn without_witnesses(&self) -> Self {
-        Self::default()
+        Self::new(self.program.clone(), self.max_steps)
     }
 
     fn configure(cs: &mut ConstraintSystem<F>) -> Self::Config {
@@ -80,8 +150,294 @@ impl<F: FieldExt> Circuit<F> for MainCircuit<F> {
         config: Self::Config,
         mut layouter: impl Layouter<F>,
     ) -> Result<(), Error> {
-        config.assign(&mut layouter, &self.entries);
+        config.assign(&mut layouter, self)
+    }
+}
 
-        Ok(())
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::execution_table::gate;
+    use entry_builder::builder::EntryBuilder;
+    use halo2_proofs::circuit::Value;
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::halo2curves::bn256::{Bn256, Fr};
+    use halo2_proofs::halo2curves::group::ff::PrimeField;
+    use halo2_proofs::plonk::keygen_vk;
+    use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+    use rand_core::OsRng;
+    use runtime::decoder::decode;
+    use runtime::trace::{Opcode, Step, Trace};
+
+    use alloc::vec;
+
+    /// k of the mock proofs, the bitwise table takes most of the 2^10 rows.
+    pub(crate) const K: u32 = 10;
+
+    /// The program of the `(pc, encoding)` instructions, starting at the
+    /// first one.
+    pub(crate) fn program(code: &[(u64, u32)]) -> Program {
+        let mut program = Program {
+            entry: code.first().map_or(0, |(pc, _)| *pc),
+            ..Default::default()
+        };
+        for &(pc, bits) in code.iter() {
+            program.instructions.insert(pc, decode(bits).unwrap());
+        }
+        program
+    }
+
+    /// Trace of the program from the `registers`, running the `(pc, register
+    /// writes)` steps. Steps starting a macro-op execute it.
+    pub(crate) fn trace(
+        program: &Program,
+        registers: &[(usize, u64)],
+        steps: &[(u64, &[(usize, u64)])],
+    ) -> Trace {
+        let mut state = vec![0; 32];
+        for &(index, value) in registers.iter() {
+            state[index] = value;
+        }
+        let mut trace = Trace {
+            cycles: 0,
+            return_value: 0,
+            steps: vec![],
+        };
+        for (global_clk, (pc, writes)) in steps.iter().enumerate() {
+            let instruction = program
+                .fused_instruction(*pc)
+                .or_else(|| program.instruction(*pc).cloned())
+                .expect("instruction of the program");
+            trace.steps.push(Step {
+                global_clk: global_clk as u64,
+                pc: *pc,
+                instruction,
+                registers: state.clone(),
+            });
+            for &(index, value) in writes.iter() {
+                state[index] = value;
+            }
+        }
+        trace
+    }
+
+    /// The circuit of the trace, which isn't checked against the program.
+    pub(crate) fn circuit(program: Program, trace: &Trace) -> MainCircuit<Fr> {
+        let mut entry_builder = EntryBuilder::new();
+        entry_builder.build(trace).expect("build entries");
+        MainCircuit::init(
+            program,
+            entry_builder.entries,
+            MainCircuit::<Fr>::max_steps(K),
+        )
+    }
+
+    pub(crate) fn verify(circuit: &MainCircuit<Fr>) -> bool {
+        MockProver::run(K, circuit, circuit.instance())
+            .unwrap()
+            .verify()
+            .is_ok()
+    }
+
+    // add a2, a0, a1; sub a3, a0, a1; addi a4, a0, -1; c.addi a4, 1
+    const ARITHMETIC: [(u64, u32); 4] = [
+        (0x10000, 0x00b50633),
+        (0x10004, 0x40b506b3),
+        (0x10008, 0xfff50713),
+        (0x1000c, 0x0705),
+    ];
+
+    fn arithmetic_trace(program: &Program, a0: u64, a1: u64) -> Trace {
+        trace(
+            program,
+            &[(10, a0), (11, a1)],
+            &[
+                (0x10000, &[(12, a0.wrapping_add(a1))]),
+                (0x10004, &[(13, a0.wrapping_sub(a1))]),
+                (0x10008, &[(14, a0.wrapping_sub(1))]),
+                (0x1000c, &[(14, a0)]),
+            ],
+        )
+    }
+
+    #[test]
+    fn proves_wrapping_arithmetic() {
+        let program = program(&ARITHMETIC);
+        for (a0, a1) in [(5, 3), (u64::MAX, 2), (1, 2)] {
+            let trace = arithmetic_trace(&program, a0, a1);
+            assert!(verify(&circuit(program.clone(), &trace)));
+        }
+    }
+
+    #[test]
+    fn rejects_tampered_result() {
+        let program = program(&ARITHMETIC);
+        let mut circuit = circuit(program.clone(), &arithmetic_trace(&program, 5, 3));
+        circuit.entries.register_ops.get_mut(&0).unwrap().ops[2].value = 9;
+        assert!(!verify(&circuit));
+    }
+
+    #[test]
+    fn rejects_instruction_outside_program() {
+        let program = program(&ARITHMETIC);
+        let trace = arithmetic_trace(&program, 5, 3);
+        // The trace runs sub a3, a0, a1 where the program has add a3, a0, a1.
+        let mut other = program;
+        other
+            .instructions
+            .insert(0x10004, decode(0x00b506b3).unwrap());
+        assert!(!verify(&circuit(other, &trace)));
+    }
+
+    #[test]
+    fn rejects_opcode_without_gate() {
+        // add a2, a0, a1; mul a3, a0, a1
+        let program = program(&[(0x10000, 0x00b50633), (0x10004, 0x02b506b3)]);
+        let trace = trace(
+            &program,
+            &[(10, 5), (11, 3)],
+            &[(0x10000, &[(12, 8)]), (0x10004, &[(13, 15)])],
+        );
+        let circuit = circuit(program, &trace);
+        assert!(matches!(
+            MockProver::run(K, &circuit, circuit.instance()),
+            Err(Error::Synthesis)
+        ));
+
+        for opcode in (0..=u8::MAX as u16).filter_map(Opcode::from_u16) {
+            assert_ne!(gate(opcode), 0, "{:?} has no gate", opcode);
+        }
+    }
+
+    #[test]
+    fn rejects_wrong_pc_advance() {
+        let program = program(&ARITHMETIC);
+        let trace = arithmetic_trace(&program, 5, 3);
+        let mut circuit = circuit(program, &trace);
+        circuit.entries.pcs.swap(0, 1);
+        circuit.entries.instructions.swap(0, 1);
+        assert!(!verify(&circuit));
+    }
+
+    /// A single step whose cells are all zero but `enabled`, laid out by
+    /// hand since the assignment of the execution table never gives it.
+    #[derive(Clone, Default)]
+    struct ZeroStep {
+        program: Program,
+        enabled: bool,
+    }
+
+    impl Circuit<Fr> for ZeroStep {
+        type Config = MainConfig<Fr>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(cs: &mut ConstraintSystem<Fr>) -> Self::Config {
+            MainConfig::configure(cs)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let table = &config.execution_table;
+            let step = &table.step;
+            table.assign_tables(&mut layouter)?;
+            config.program_table.assign(&mut layouter, &self.program)?;
+            config
+                .memory_table
+                .assign(&mut layouter, &Entries::default(), 0)?;
+            layouter.assign_region(
+                || "execution",
+                |mut region| {
+                    step.q_step.enable(&mut region, 0)?;
+                    step.q_first.enable(&mut region, 0)?;
+                    table.bitwise.enable(&mut region, 0)?;
+                    region.assign_fixed(|| "entry", step.entry, 0, || Value::known(Fr::zero()))?;
+                    let columns = table.columns();
+                    for column in columns {
+                        for row in 0..STEP_ROWS {
+                            let value = match column == step.enabled && row == 0 {
+                                true => Fr::from(self.enabled as u64),
+                                false => Fr::zero(),
+                            };
+                            region.assign_advice(|| "step", column, row, || Value::known(value))?;
+                        }
+                    }
+                    // The row of zeros continues anywhere.
+                    region.assign_advice(
+                        || "next_pc",
+                        step.next_pc,
+                        0,
+                        || Value::known(Fr::from(0x10004)),
+                    )?;
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn rejects_step_matching_the_row_of_zeros() {
+        let mut program = program(&ARITHMETIC);
+        program.entry = 0;
+        for enabled in [false, true] {
+            let circuit = ZeroStep {
+                program: program.clone(),
+                enabled,
+            };
+            let prover = MockProver::run(K, &circuit, vec![vec![]]).unwrap();
+            assert_eq!(prover.verify().is_ok(), !enabled);
+        }
+    }
+
+    #[test]
+    fn rejects_trace_not_starting_at_the_entry() {
+        let mut program = program(&ARITHMETIC);
+        let trace = arithmetic_trace(&program, 5, 3);
+        assert!(verify(&circuit(program.clone(), &trace)));
+        program.entry = 0x10004;
+        assert!(!verify(&circuit(program, &trace)));
+    }
+
+    #[test]
+    fn instance_is_the_tx_hash() {
+        let program = program(&ARITHMETIC);
+        let mut circuit = circuit(program.clone(), &arithmetic_trace(&program, 5, 3));
+        circuit.tx_hash = core::array::from_fn(|i| i as u8);
+        let instance = circuit.instance();
+        assert_eq!(
+            instance
+                .iter()
+                .map(|column| column.iter().map(|value| value.to_repr()).collect())
+                .collect::<Vec<Vec<_>>>(),
+            vec![tx_hash_instance(&circuit.tx_hash)]
+        );
+        assert!(verify(&circuit));
+    }
+
+    #[test]
+    fn keys_do_not_depend_on_the_trace() {
+        let params = ParamsKZG::<Bn256>::setup(K, OsRng);
+        let program = program(&ARITHMETIC);
+        let short = trace(&program, &[], &[(0x10000, &[])]);
+        let vks = [
+            keygen_vk(&params, &circuit(program.clone(), &short)),
+            keygen_vk(
+                &params,
+                &circuit(program.clone(), &arithmetic_trace(&program, 1, 2)),
+            ),
+            keygen_vk(
+                &params,
+                &MainCircuit::new(program, MainCircuit::<Fr>::max_steps(K)),
+            ),
+        ]
+        .map(|vk| vk.unwrap().transcript_repr());
+        assert_eq!(vks[0], vks[1]);
+        assert_eq!(vks[0], vks[2]);
     }
 }
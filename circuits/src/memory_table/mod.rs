@@ -1,87 +1,330 @@
+use alloc::vec;
 use alloc::vec::Vec;
+use core::marker::PhantomData;
 use entry_builder::entries::Entries;
 use entry_builder::op_step::OpStep;
 use halo2_proofs::arithmetic::FieldExt;
-use halo2_proofs::circuit::Value;
-use halo2_proofs::plonk::Advice;
-use halo2_proofs::plonk::Column;
-use halo2_proofs::plonk::Selector;
-use halo2_proofs::{
-    circuit::Layouter,
-    plonk::{Circuit, ConstraintSystem, Error},
+use halo2_proofs::circuit::{Layouter, Value};
+use halo2_proofs::plonk::{
+    Advice, Column, ConstraintSystem, Error, Expression, Selector, TableColumn,
 };
+use halo2_proofs::poly::Rotation;
 use runtime::trace::{InstructionType, Opcode};
-use core::marker::PhantomData;
 
+use crate::execution_table::{StepConfig, STEP_ROWS};
+
+/// Row of the port of the first byte a step reads.
+pub const READ_PORT: usize = 0;
+/// Row of the port of the first byte a step writes, after the read ones.
+pub const WRITE_PORT: usize = STEP_ROWS / 2;
+
+/// Bytes of the gap between two consecutive accesses.
+const GAP_BYTES: usize = 4;
+
+/// A byte a step reads or writes, on the `row` of the step.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Port {
+    pub row: usize,
+    pub address: u64,
+    pub value: u64,
+    pub write: bool,
+}
+
+/// Time of the port on the `row` of the execution region, the value of its
+/// `time` column.
+pub fn time(row: usize) -> u64 {
+    row as u64 + 1
+}
+
+/// The ports of the memory operations of a step, one per byte from the
+/// lowest address.
+pub fn ports(step: &OpStep) -> Vec<Port> {
+    let mut ports = vec![];
+    for op in step.memory_address.iter().flat_map(|ops| ops.ops.iter()) {
+        let write = op.rw.is_write();
+        let first = if write { WRITE_PORT } else { READ_PORT };
+        for byte in 0..op.width as usize / 8 {
+            ports.push(Port {
+                row: first + byte,
+                address: op.address.wrapping_add(byte as u64),
+                value: (op.value >> (8 * byte)) & 0xff,
+                write,
+            });
+        }
+    }
+    ports
+}
+
+/// Whether the gate of `opcode` constrains the memory ports of its steps. The
+/// ports of the steps of the other gates are disabled, the ones of the
+/// unconstrained steps, e.g. loads and stores, are free like their registers.
+pub fn accesses_memory(opcode: Opcode) -> bool {
+    matches!(InstructionType::from(opcode), InstructionType::AType(_))
+}
+
+/// Checks that the steps read the memory they last wrote.
+///
+/// The enabled memory ports of the steps are `(time, address, value, write)`
+/// tuples of one byte. The table lays the same tuples out sorted by address
+/// then time, each tuple is looked up in the other side: their times are
+/// unique, so both sides are the same set. A read returns the value of the
+/// previous row when it has the same address, the first access to an address
+/// reads what the memory initially holds, which isn't committed to.
+///
+/// | enabled | time | address | value | write | same | inverse | gap |
+///
+/// `same` is set when the address is the one of the previous row, `inverse` is
+/// the inverse of their difference otherwise. The `gap` of an enabled row is
+/// the difference minus one of its time and the one of the previous row at the
+/// same address, and of the addresses otherwise: its bytes make the rows
+/// strictly increasing, with addresses less than 2^32 apart, the memory of the
+/// replay is 32MB. The enabled rows come first, then rows of zeros.
 #[derive(Clone)]
 pub struct MemoryTable<F> {
-    address_col: Column<Advice>,
-    gc_col: Column<Advice>,
-    value_col: Column<Advice>,
-    rw_col: Column<Advice>,
-    s: Selector,
+    pub enabled: Column<Advice>,
+    pub time: Column<Advice>,
+    pub address: Column<Advice>,
+    pub value: Column<Advice>,
+    pub write: Column<Advice>,
+    pub same: Column<Advice>,
+    pub inverse: Column<Advice>,
+    pub gap: [Column<Advice>; GAP_BYTES],
+    pub byte_table: TableColumn,
+    /// Set on all the rows of the table.
+    q_row: Selector,
+    /// Set on all the rows of the table but the first one.
+    q_pair: Selector,
     _marker: PhantomData<F>,
 }
 
 impl<F: FieldExt> MemoryTable<F> {
-    pub fn configure(
-        cs: &mut ConstraintSystem<F>,
-        address_col: Column<Advice>,
-        gc_col: Column<Advice>,
-        value_col: Column<Advice>,
-        rw_col: Column<Advice>,
-        s: Selector,
-    ) -> Self {
-        let lhs_col = cs.advice_column();
-        let rhs_col = cs.advice_column();
+    pub fn configure(cs: &mut ConstraintSystem<F>, step: &StepConfig) -> Self {
+        let enabled = cs.advice_column();
+        let time = cs.advice_column();
+        let address = cs.advice_column();
+        let value = cs.advice_column();
+        let write = cs.advice_column();
+        let same = cs.advice_column();
+        let inverse = cs.advice_column();
+        let gap = [(); GAP_BYTES].map(|_| cs.advice_column());
+        let byte_table = cs.lookup_table_column();
+        let q_row = cs.complex_selector();
+        let q_pair = cs.selector();
+        let one = Expression::Constant(F::one());
+
+        cs.create_gate("memory port", |vc| {
+            let q_port = vc.query_selector(step.q_port);
+            let enabled = vc.query_advice(step.port_enabled, Rotation::cur());
+            let write = vc.query_advice(step.port_write, Rotation::cur());
+            let disabled = one.clone() - enabled.clone();
+            vec![
+                q_port.clone() * enabled.clone() * disabled.clone(),
+                q_port.clone() * write.clone() * (one.clone() - write.clone()),
+                q_port.clone() * disabled.clone() * write,
+                q_port.clone()
+                    * disabled.clone()
+                    * vc.query_advice(step.port_address, Rotation::cur()),
+                q_port.clone() * disabled * vc.query_advice(step.port_value, Rotation::cur()),
+                q_port
+                    * (vc.query_advice(step.port_time, Rotation::cur())
+                        - enabled * vc.query_fixed(step.time, Rotation::cur())),
+            ]
+        });
+
+        // The disabled steps and the gates without memory accesses have no
+        // enabled port.
+        cs.create_gate("memory port owner", |vc| {
+            let q_step = vc.query_selector(step.q_step);
+            let enabled = vc.query_advice(step.enabled, Rotation::cur());
+            let other_gate = step.select(vc, |opcode| !accesses_memory(opcode) as u64);
+            let without_ports = one.clone() - enabled + other_gate;
+            (0..STEP_ROWS)
+                .map(|row| {
+                    q_step.clone()
+                        * without_ports.clone()
+                        * vc.query_advice(step.port_enabled, Rotation(row as i32))
+                })
+                .collect::<Vec<_>>()
+        });
+
+        cs.create_gate("memory order", |vc| {
+            let q_pair = vc.query_selector(q_pair);
+            let enabled_prev = vc.query_advice(enabled, Rotation::prev());
+            let enabled = vc.query_advice(enabled, Rotation::cur());
+            let time_prev = vc.query_advice(time, Rotation::prev());
+            let time = vc.query_advice(time, Rotation::cur());
+            let difference = vc.query_advice(address, Rotation::cur())
+                - vc.query_advice(address, Rotation::prev());
+            let value_prev = vc.query_advice(value, Rotation::prev());
+            let value = vc.query_advice(value, Rotation::cur());
+            let write = vc.query_advice(write, Rotation::cur());
+            let same = vc.query_advice(same, Rotation::cur());
+            let inverse = vc.query_advice(inverse, Rotation::cur());
+            let gap = gap
+                .iter()
+                .rev()
+                .fold(Expression::Constant(F::zero()), |sum, byte| {
+                    sum * Expression::Constant(F::from(256))
+                        + vc.query_advice(*byte, Rotation::cur())
+                });
+            let expected_gap = same.clone() * (time - time_prev - one.clone())
+                + (one.clone() - same.clone()) * (difference.clone() - one.clone());
+            vec![
+                q_pair.clone() * enabled.clone() * (one.clone() - enabled_prev),
+                q_pair.clone() * (same.clone() - one.clone() + difference.clone() * inverse),
+                q_pair.clone() * difference * same.clone(),
+                q_pair.clone() * enabled.clone() * (gap - expected_gap),
+                q_pair * enabled * same * (one.clone() - write) * (value - value_prev),
+            ]
+        });
+
+        for column in [value].into_iter().chain(gap) {
+            cs.lookup("memory byte", |vc| {
+                let q_row = vc.query_selector(q_row);
+                vec![(q_row * vc.query_advice(column, Rotation::cur()), byte_table)]
+            });
+        }
+
+        let ports = [
+            step.port_enabled,
+            step.port_time,
+            step.port_address,
+            step.port_value,
+            step.port_write,
+        ];
+        let rows = [enabled, time, address, value, write];
+        cs.lookup_any("memory port access", |vc| {
+            let q_row = vc.query_selector(q_row);
+            ports
+                .iter()
+                .zip(rows.iter())
+                .map(|(port, row)| {
+                    (
+                        vc.query_advice(*port, Rotation::cur()),
+                        q_row.clone() * vc.query_advice(*row, Rotation::cur()),
+                    )
+                })
+                .collect()
+        });
+        cs.lookup_any("memory access port", |vc| {
+            let q_port = vc.query_selector(step.q_port);
+            rows.iter()
+                .zip(ports.iter())
+                .map(|(row, port)| {
+                    (
+                        vc.query_advice(*row, Rotation::cur()),
+                        q_port.clone() * vc.query_advice(*port, Rotation::cur()),
+                    )
+                })
+                .collect()
+        });
 
         Self {
-            address_col,
-            gc_col,
-            value_col,
-            rw_col,
-            s,
+            enabled,
+            time,
+            address,
+            value,
+            write,
+            same,
+            inverse,
+            gap,
+            byte_table,
+            q_row,
+            q_pair,
             _marker: PhantomData::default(),
         }
     }
 
-    pub fn assign(&self, layouter: &mut impl Layouter<F>, entries: &Entries) -> Result<(), Error> {
-        let mut memory_ops: Vec<_> = entries.memory_ops.iter().collect();
-        memory_ops.sort_by(|&(gc1, m1), &(gc2, m2)| {
-            m1.address.cmp(&m2.address).then_with(|| gc1.cmp(gc2))
-        });
+    /// Lays out the ports of the trace sorted, in as many rows as the
+    /// `max_steps` steps have ports.
+    pub fn assign(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        entries: &Entries,
+        max_steps: usize,
+    ) -> Result<(), Error> {
+        layouter.assign_table(
+            || "byte table",
+            |mut table| {
+                for byte in 0..256 {
+                    table.assign_cell(
+                        || "byte",
+                        self.byte_table,
+                        byte,
+                        || Value::known(F::from(byte as u64)),
+                    )?;
+                }
+                Ok(())
+            },
+        )?;
+
+        let mut accesses: Vec<_> = entries
+            .get_op_steps()
+            .iter()
+            .enumerate()
+            .flat_map(|(index, step)| {
+                ports(step).into_iter().map(move |port| {
+                    (
+                        port.address,
+                        time(index * STEP_ROWS + port.row),
+                        port.value,
+                        port.write,
+                    )
+                })
+            })
+            .collect();
+        accesses.sort();
 
         layouter.assign_region(
             || "memory table",
             |mut region| {
-                self.s.enable(&mut region, 0)?;
-                for (index, &(gc, memory_op)) in memory_ops.iter().enumerate() {
-                    region.assign_advice(
-                        || "address",
-                        self.address_col,
-                        index,
-                        || Value::known(F::from(memory_op.address)),
-                    )?;
-                    region.assign_advice(
-                        || "gc",
-                        self.gc_col,
-                        index,
-                        || Value::known(F::from(memory_op.global_clk)),
-                    )?;
-                    region.assign_advice(
-                        || "value",
-                        self.value_col,
-                        index,
-                        || Value::known(F::from(memory_op.value)),
-                    )?;
-                    region.assign_advice(
-                        || "rw",
-                        self.rw_col,
-                        index,
-                        || Value::known(F::from(memory_op.rw.is_write())),
-                    )?;
-                }
+                let mut previous: Option<(u64, u64)> = None;
+                for row in 0..max_steps * STEP_ROWS {
+                    self.q_row.enable(&mut region, row)?;
+                    if row > 0 {
+                        self.q_pair.enable(&mut region, row)?;
+                    }
+                    let access = accesses.get(row).copied();
+                    let (address, time, value, write) = access.unwrap_or_default();
+                    let (same, inverse, gap) = match previous {
+                        None => (false, F::zero(), 0),
+                        Some((address_prev, time_prev)) => {
+                            let same = address == address_prev;
+                            let inverse = (F::from(address) - F::from(address_prev))
+                                .invert()
+                                .unwrap_or(F::zero());
+                            let gap = match (access, same) {
+                                (None, _) => 0,
+                                (Some(_), true) => time - time_prev - 1,
+                                (Some(_), false) => address - address_prev - 1,
+                            };
+                            (same, inverse, gap)
+                        }
+                    };
+                    if gap >> (8 * GAP_BYTES) != 0 {
+                        return Err(Error::Synthesis);
+                    }
+                    previous = Some((address, time));
 
+                    for (column, value) in [
+                        (self.enabled, F::from(access.is_some() as u64)),
+                        (self.time, F::from(time)),
+                        (self.address, F::from(address)),
+                        (self.value, F::from(value)),
+                        (self.write, F::from(write as u64)),
+                        (self.same, F::from(same as u64)),
+                        (self.inverse, inverse),
+                    ]
+                    .into_iter()
+                    .chain(
+                        self.gap
+                            .iter()
+                            .enumerate()
+                            .map(|(byte, column)| (*column, F::from((gap >> (8 * byte)) & 0xff))),
+                    ) {
+                        region.assign_advice(|| "memory", column, row, || Value::known(value))?;
+                    }
+                }
                 Ok(())
             },
         )
@@ -0,0 +1,158 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use halo2_proofs::arithmetic::FieldExt;
+use halo2_proofs::circuit::{Layouter, Value};
+use halo2_proofs::plonk::{ConstraintSystem, Error, TableColumn};
+use halo2_proofs::poly::Rotation;
+use runtime::program::Program;
+use runtime::trace::{FusedType, IType, Instruction, InstructionType, Opcode};
+
+use crate::execution_table::{gate, StepConfig};
+
+/// The instructions of the program decoded from its ELF as a fixed
+/// `(enabled, pc, opcode, length, op_a, op_b, op_c, gate, sequential)` table,
+/// with a row for the macro-op of every pair of instructions ckb-vm fuses.
+///
+/// Every step is looked up in it, so a trace can only execute the instructions
+/// of the program, with the constraints of their gate. Only disabled steps
+/// match the row of zeros, whose `enabled` is 0. Steps which don't jump
+/// must continue at `pc + length`, so compressed instructions advance the pc
+/// by 2.
+#[derive(Clone)]
+pub struct ProgramTable<F> {
+    pub enabled: TableColumn,
+    pub pc: TableColumn,
+    pub opcode: TableColumn,
+    pub length: TableColumn,
+    pub op_a: TableColumn,
+    pub op_b: TableColumn,
+    pub op_c: TableColumn,
+    pub gate: TableColumn,
+    pub sequential: TableColumn,
+    _marker: PhantomData<F>,
+}
+
+/// Returns whether the instruction continues with the next one in memory.
+pub fn is_sequential(opcode: Opcode) -> bool {
+    !matches!(
+        opcode.into(),
+        InstructionType::BType(_)
+            | InstructionType::JType(_)
+            | InstructionType::IType(IType::JALR)
+            | InstructionType::FusedType(FusedType::FARJUMPREL)
+            | InstructionType::FusedType(FusedType::FARJUMPABS)
+    )
+}
+
+impl<F: FieldExt> ProgramTable<F> {
+    pub fn configure(cs: &mut ConstraintSystem<F>, step: &StepConfig) -> Self {
+        let table = Self {
+            enabled: cs.lookup_table_column(),
+            pc: cs.lookup_table_column(),
+            opcode: cs.lookup_table_column(),
+            length: cs.lookup_table_column(),
+            op_a: cs.lookup_table_column(),
+            op_b: cs.lookup_table_column(),
+            op_c: cs.lookup_table_column(),
+            gate: cs.lookup_table_column(),
+            sequential: cs.lookup_table_column(),
+            _marker: PhantomData::default(),
+        };
+
+        // Disabled steps look up the row of zeros.
+        cs.lookup("instruction fetch", |vc| {
+            let s = vc.query_selector(step.q_step);
+            let mut lookups = vec![];
+            for (column, table_column) in [
+                (step.enabled, table.enabled),
+                (step.pc, table.pc),
+                (step.opcode, table.opcode),
+                (step.length, table.length),
+                (step.op_a, table.op_a),
+                (step.op_b, table.op_b),
+                (step.op_c, table.op_c),
+            ] {
+                lookups.push((
+                    s.clone() * vc.query_advice(column, Rotation::cur()),
+                    table_column,
+                ));
+            }
+            lookups.push((s.clone() * step.gate(vc), table.gate));
+            lookups.push((
+                s * vc.query_advice(step.sequential, Rotation::cur()),
+                table.sequential,
+            ));
+            lookups
+        });
+
+        cs.create_gate("pc advance", |vc| {
+            let s = vc.query_selector(step.q_step);
+            let sequential = vc.query_advice(step.sequential, Rotation::cur());
+            let pc = vc.query_advice(step.pc, Rotation::cur());
+            let length = vc.query_advice(step.length, Rotation::cur());
+            let next_pc = vc.query_advice(step.next_pc, Rotation::cur());
+            vec![s * sequential * (pc + length - next_pc)]
+        });
+
+        table
+    }
+
+    /// Rows of the table: the row of zeros, the instructions of the program
+    /// and the macro-ops, by pc.
+    pub fn rows(program: &Program) -> Vec<(u64, Instruction)> {
+        let mut rows = vec![(
+            0,
+            Instruction {
+                opcode: Opcode::ADD,
+                length: 0,
+                op_a: 0,
+                op_b: 0,
+                op_c: 0,
+                imm_b: false,
+                imm_c: false,
+            },
+        )];
+        for (pc, instruction) in program.instructions.iter() {
+            rows.push((*pc, instruction.clone()));
+            if let Some(fused) = program.fused_instruction(*pc) {
+                rows.push((*pc, fused));
+            }
+        }
+        rows
+    }
+
+    pub fn assign(&self, layouter: &mut impl Layouter<F>, program: &Program) -> Result<(), Error> {
+        let rows = Self::rows(program);
+        layouter.assign_table(
+            || "program table",
+            |mut table| {
+                for (index, (pc, instruction)) in rows.iter().enumerate() {
+                    let (gate, sequential) = match instruction.length {
+                        0 => (0, false),
+                        _ => (gate(instruction.opcode), is_sequential(instruction.opcode)),
+                    };
+                    for (annotation, column, value) in [
+                        ("enabled", self.enabled, (index > 0) as u64),
+                        ("pc", self.pc, *pc),
+                        ("opcode", self.opcode, instruction.opcode as u64),
+                        ("length", self.length, instruction.length),
+                        ("op_a", self.op_a, instruction.op_a),
+                        ("op_b", self.op_b, instruction.op_b),
+                        ("op_c", self.op_c, instruction.op_c),
+                        ("gate", self.gate, gate),
+                        ("sequential", self.sequential, sequential as u64),
+                    ] {
+                        table.assign_cell(
+                            || annotation,
+                            column,
+                            index,
+                            || Value::known(F::from(value)),
+                        )?;
+                    }
+                }
+                Ok(())
+            },
+        )
+    }
+}
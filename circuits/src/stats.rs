@@ -0,0 +1,228 @@
+//! Row accounting of a circuit: lays it out like the prover does and records
+//! the rows taken by each region and lookup table.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use halo2_proofs::arithmetic::FieldExt;
+use halo2_proofs::circuit::Value;
+use halo2_proofs::plonk::{
+    Advice, Any, Assigned, Assignment, Challenge, Circuit, Column, ConstraintSystem, Error, Fixed,
+    FloorPlanner, Instance, Selector,
+};
+
+/// Regions sharing a name, e.g. all the steps of a gadget.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RegionRows {
+    pub count: usize,
+    /// Sum of the heights of the regions.
+    pub rows: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RowStats {
+    pub regions: BTreeMap<String, RegionRows>,
+    /// Rows of the lookup tables, by the name they are assigned with.
+    pub tables: BTreeMap<String, usize>,
+    /// Rows used by any column.
+    pub rows: usize,
+    /// Rows the prover keeps for blinding after the used ones.
+    pub reserved_rows: usize,
+    pub minimum_rows: usize,
+}
+
+impl RowStats {
+    pub fn measure<F: FieldExt, C: Circuit<F>>(circuit: &C) -> Result<Self, Error> {
+        let mut cs = ConstraintSystem::default();
+        let config = C::configure(&mut cs);
+        let mut counter = RowCounter {
+            stats: RowStats {
+                reserved_rows: cs.blinding_factors() + 1,
+                minimum_rows: cs.minimum_rows(),
+                ..Default::default()
+            },
+            region: None,
+        };
+        C::FloorPlanner::synthesize(&mut counter, circuit, config, cs.constants().clone())?;
+        Ok(counter.stats)
+    }
+
+    /// Smallest k whose `2^k` rows fit the circuit.
+    pub fn min_k(&self) -> u32 {
+        let rows = (self.rows + self.reserved_rows).max(self.minimum_rows);
+        rows.next_power_of_two().trailing_zeros()
+    }
+}
+
+/// The region being assigned: its name, the rows it spans and whether it
+/// only holds fixed cells, i.e. it is a lookup table.
+struct Region {
+    name: String,
+    rows: Option<(usize, usize)>,
+    fixed_only: bool,
+}
+
+struct RowCounter {
+    stats: RowStats,
+    region: Option<Region>,
+}
+
+impl RowCounter {
+    fn use_row(&mut self, row: usize, fixed: bool) {
+        self.stats.rows = self.stats.rows.max(row + 1);
+        if let Some(region) = self.region.as_mut() {
+            region.rows = Some(match region.rows {
+                Some((first, last)) => (first.min(row), last.max(row)),
+                None => (row, row),
+            });
+            region.fixed_only &= fixed;
+        }
+    }
+}
+
+impl<F: FieldExt> Assignment<F> for RowCounter {
+    fn enter_region<NR, N>(&mut self, name: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.region = Some(Region {
+            name: name().into(),
+            rows: None,
+            fixed_only: true,
+        });
+    }
+
+    fn exit_region(&mut self) {
+        let region = self.region.take().expect("exit a region after entering it");
+        let rows = region.rows.map_or(0, |(first, last)| last - first + 1);
+        if region.fixed_only && rows > 0 {
+            *self.stats.tables.entry(region.name).or_default() += rows;
+        } else {
+            let entry = self.stats.regions.entry(region.name).or_default();
+            entry.count += 1;
+            entry.rows += rows;
+        }
+    }
+
+    fn enable_selector<A, AR>(&mut self, _: A, _: &Selector, row: usize) -> Result<(), Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.use_row(row, false);
+        Ok(())
+    }
+
+    fn query_instance(&self, _: Column<Instance>, _: usize) -> Result<Value<F>, Error> {
+        Ok(Value::unknown())
+    }
+
+    fn assign_advice<V, VR, A, AR>(
+        &mut self,
+        _: A,
+        _: Column<Advice>,
+        row: usize,
+        _: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.use_row(row, false);
+        Ok(())
+    }
+
+    fn assign_fixed<V, VR, A, AR>(
+        &mut self,
+        _: A,
+        _: Column<Fixed>,
+        row: usize,
+        _: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.use_row(row, true);
+        Ok(())
+    }
+
+    fn copy(&mut self, _: Column<Any>, _: usize, _: Column<Any>, _: usize) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn fill_from_row(
+        &mut self,
+        _: Column<Fixed>,
+        _: usize,
+        _: Value<Assigned<F>>,
+    ) -> Result<(), Error> {
+        // Padding of the lookup tables up to the last row.
+        Ok(())
+    }
+
+    fn get_challenge(&self, _: Challenge) -> Value<F> {
+        Value::unknown()
+    }
+
+    fn annotate_column<A, AR>(&mut self, _: A, _: Column<Any>)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+    }
+
+    fn pop_namespace(&mut self, _: Option<String>) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::main_circuit::tests::{program, trace};
+    use crate::main_circuit::MainCircuit;
+    use entry_builder::builder::EntryBuilder;
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    /// Whether the mock prover lays out and verifies the circuit in `2^k` rows.
+    fn proves(k: u32, circuit: &MainCircuit<Fr>) -> bool {
+        MockProver::run(k, circuit, circuit.instance())
+            .map_or(false, |prover| prover.verify().is_ok())
+    }
+
+    #[test]
+    fn min_k_fits_the_circuit() {
+        // add a2, a0, a1
+        let program = program(&[(0x10000, 0x00b50633)]);
+        let trace = trace(&program, &[(10, 1), (11, 2)], &[(0x10000, &[(12, 3)])]);
+        let mut entry_builder = EntryBuilder::with_program(program.clone());
+        entry_builder.build(&trace).unwrap();
+
+        // The lookup tables set k of a few steps, the steps set it beyond.
+        let full = MainCircuit::<Fr>::max_steps(11);
+        for (max_steps, min_k) in [(1, 10), (full, 11), (full + 1, 12)] {
+            let circuit =
+                MainCircuit::init(program.clone(), entry_builder.entries.clone(), max_steps);
+            let k = RowStats::measure(&circuit).unwrap().min_k();
+            assert_eq!(k, min_k);
+            assert!(proves(k, &circuit), "{} steps at k {}", max_steps, k);
+            assert!(
+                !proves(k - 1, &circuit),
+                "{} steps at k {}",
+                max_steps,
+                k - 1
+            );
+        }
+    }
+}
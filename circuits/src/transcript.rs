@@ -0,0 +1,37 @@
+//! Fiat-Shamir transcripts a proof can be made with. The one of a proof is
+//! recorded in its bundle and in the witness of the verifier.
+
+use core::fmt;
+
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TranscriptKind {
+    #[default]
+    Blake2b = 0,
+    /// Keccak256, the transcript of EVM verifiers.
+    Keccak256 = 1,
+    /// Poseidon over the scalar field, cheap to verify in a circuit, the
+    /// transcript of proofs verified recursively.
+    Poseidon = 2,
+}
+
+impl TranscriptKind {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(TranscriptKind::Blake2b),
+            1 => Some(TranscriptKind::Keccak256),
+            2 => Some(TranscriptKind::Poseidon),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for TranscriptKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TranscriptKind::Blake2b => write!(f, "blake2b"),
+            TranscriptKind::Keccak256 => write!(f, "keccak256"),
+            TranscriptKind::Poseidon => write!(f, "poseidon"),
+        }
+    }
+}
@@ -0,0 +1,543 @@
+//! Verification of a proof from the bytes the on-chain verifier reads: the
+//! shrunk verifier params, the verifying key, the public inputs and the proof.
+//! It runs the same on the host, where it is tested, as in the verifier.
+//!
+//! A proof of the aggregation circuit only verifies the proofs it aggregates
+//! with the pairing check of the accumulator of its public inputs, which is
+//! batched with its own.
+
+use alloc::vec::Vec;
+use core::fmt;
+use halo2_proofs::halo2curves::bn256::{Bn256, Fr, G1Affine};
+use halo2_proofs::halo2curves::group::ff::PrimeField;
+use halo2_proofs::plonk::{self, verify_proof, VerifyingKey};
+use halo2_proofs::poly::kzg::batch::BatchVerifierKZG;
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsVerifierKZG};
+use halo2_proofs::poly::kzg::msm::PreparedG2KZG;
+use halo2_proofs::poly::kzg::multiopen::VerifierSHPLONK;
+use halo2_proofs::poly::kzg::strategy::DeferredStrategy;
+use halo2_proofs::poly::VerificationStrategy;
+use halo2_proofs::transcript::{
+    Blake2bRead, Challenge255, Keccak256Read, PoseidonRead, TranscriptReadBuffer,
+};
+use halo2_proofs::SerdeFormat;
+
+use crate::aggregation::{Accumulator, AggregationCircuit, ACCUMULATOR_INSTANCES};
+use crate::circuit::CircuitKind;
+use crate::fingerprint::{check_fingerprint, fingerprint, FingerprintMismatch};
+use crate::main_circuit::MainCircuit;
+use crate::transcript::TranscriptKind;
+use crate::witness::{VerifierKey, VerifierWitness};
+
+/// `shrink_k` of the verifier params written by `cli setup`: the SHPLONK
+/// verifier doesn't commit to the public inputs, it only reads `g[0]`, `g2`
+/// and `s_g2`, see `halo2_proofs::poly::kzg::shrink`.
+pub const SHRINK_K: u32 = 0;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VerifyError {
+    MalformedParams,
+    MalformedVk,
+    /// The prepared G2 points of the key can't be read.
+    MalformedPrepared,
+    FingerprintMismatch(FingerprintMismatch),
+    /// The public inputs aren't canonical field elements or don't match the
+    /// instance columns of the circuit.
+    InstanceMismatch,
+    /// The proof ends early or holds invalid points or scalars.
+    MalformedProof,
+    /// The proof doesn't verify, or some proof of a batch.
+    PairingFailure,
+    /// The witness holds no proof.
+    NoProof,
+    /// The public inputs of an aggregation proof don't start with an
+    /// accumulator: a limb is out of range or a point isn't on the curve.
+    MalformedAccumulator,
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::MalformedParams => write!(f, "malformed verifier params"),
+            VerifyError::MalformedVk => write!(f, "malformed verifying key"),
+            VerifyError::MalformedPrepared => write!(f, "malformed prepared G2 points"),
+            VerifyError::FingerprintMismatch(err) => write!(f, "{}", err),
+            VerifyError::InstanceMismatch => {
+                write!(f, "public inputs don't match the instance columns")
+            }
+            VerifyError::MalformedProof => write!(f, "malformed proof"),
+            VerifyError::PairingFailure => write!(f, "proof doesn't verify"),
+            VerifyError::NoProof => write!(f, "no proof to verify"),
+            VerifyError::MalformedAccumulator => write!(f, "malformed accumulator"),
+        }
+    }
+}
+
+impl From<plonk::Error> for VerifyError {
+    fn from(err: plonk::Error) -> Self {
+        match err {
+            plonk::Error::Transcript(_) => Self::MalformedProof,
+            plonk::Error::InvalidInstances | plonk::Error::InstanceTooLarge => {
+                Self::InstanceMismatch
+            }
+            _ => Self::PairingFailure,
+        }
+    }
+}
+
+/// Verifies `proof`, made with `transcript`, of `circuit` with the verifying
+/// key `vk` for the public inputs `instances`, one vector of canonical field
+/// elements per instance column.
+pub fn verify(
+    circuit: CircuitKind,
+    params: &[u8],
+    vk: &[u8],
+    transcript: TranscriptKind,
+    instances: &[Vec<[u8; 32]>],
+    proof: &[u8],
+) -> Result<(), VerifyError> {
+    let params = read_params(params)?;
+    let vk = read_vk(circuit, vk)?;
+    let instances = read_instances(instances)?;
+    let mut batch = BatchVerifierKZG::new(&params);
+    add_proof(
+        &mut batch, &params, circuit, &vk, transcript, &instances, proof,
+    )?;
+    finalize(batch, &PreparedG2KZG::new(&params))
+}
+
+/// Verifies the proofs of `witness` with `key`, checking first that each of
+/// them is for the circuit of the verifying key.
+///
+/// The proofs are checked with a single pairing, so a failure doesn't tell
+/// which of them is invalid.
+pub fn verify_witness(key: &VerifierKey, witness: &VerifierWitness) -> Result<(), VerifyError> {
+    if witness.proofs.is_empty() {
+        return Err(VerifyError::NoProof);
+    }
+    let params = read_params(&key.params)?;
+    let vk = read_vk(key.circuit, &key.vk)?;
+    let vk_fingerprint = fingerprint(&vk);
+    let prepared = if key.prepared.is_empty() {
+        PreparedG2KZG::new(&params)
+    } else {
+        PreparedG2KZG::<Bn256>::read(&mut &key.prepared[..])
+            .map_err(|_| VerifyError::MalformedPrepared)?
+    };
+
+    let mut batch = BatchVerifierKZG::new(&params);
+    for proof in witness.proofs.iter() {
+        check_fingerprint(&vk_fingerprint, &proof.fingerprint)
+            .map_err(VerifyError::FingerprintMismatch)?;
+        let instances = read_instances(&proof.instances)?;
+        add_proof(
+            &mut batch,
+            &params,
+            key.circuit,
+            &vk,
+            proof.transcript,
+            &instances,
+            &proof.proof,
+        )?;
+    }
+    finalize(batch, &prepared)
+}
+
+/// Adds the deferred check of `proof` to `batch`, and the pairing check of
+/// the accumulator in the public inputs of a proof of the aggregation circuit.
+fn add_proof<'params>(
+    batch: &mut BatchVerifierKZG<'params, Bn256>,
+    params: &'params ParamsVerifierKZG<Bn256>,
+    circuit: CircuitKind,
+    vk: &VerifyingKey<G1Affine>,
+    transcript: TranscriptKind,
+    instances: &[Vec<Fr>],
+    proof: &[u8],
+) -> Result<(), VerifyError> {
+    batch.add(verify_transcript(
+        params,
+        vk,
+        DeferredStrategy::new(params),
+        transcript,
+        instances,
+        proof,
+    )?);
+    if circuit == CircuitKind::Aggregation {
+        let accumulator = instances
+            .first()
+            .and_then(|column| column.get(..ACCUMULATOR_INSTANCES))
+            .and_then(Accumulator::from_instances)
+            .ok_or(VerifyError::MalformedAccumulator)?;
+        batch.add_check(accumulator.lhs, accumulator.rhs);
+    }
+    Ok(())
+}
+
+fn finalize(
+    batch: BatchVerifierKZG<'_, Bn256>,
+    prepared: &PreparedG2KZG<Bn256>,
+) -> Result<(), VerifyError> {
+    if batch.finalize_prepared(prepared) {
+        Ok(())
+    } else {
+        Err(VerifyError::PairingFailure)
+    }
+}
+
+pub(crate) fn read_params(params: &[u8]) -> Result<ParamsVerifierKZG<Bn256>, VerifyError> {
+    ParamsVerifierKZG::<Bn256>::read_shrunk(&mut &params[..])
+        .map_err(|_| VerifyError::MalformedParams)
+}
+
+/// Reads `vk` as a key of `circuit`: the constraint system isn't part of the
+/// bytes, it is the one `circuit` configures, so the key of another circuit
+/// is malformed, its bytes don't match the columns.
+pub fn read_vk(circuit: CircuitKind, vk: &[u8]) -> Result<VerifyingKey<G1Affine>, VerifyError> {
+    let reader = &mut &vk[..];
+    let vk = match circuit {
+        CircuitKind::Main => {
+            VerifyingKey::read::<_, MainCircuit<Fr>>(reader, SerdeFormat::RawBytes)
+        }
+        CircuitKind::Aggregation => {
+            VerifyingKey::read::<_, AggregationCircuit>(reader, SerdeFormat::RawBytes)
+        }
+    }
+    .map_err(|_| VerifyError::MalformedVk)?;
+    if !reader.is_empty() {
+        return Err(VerifyError::MalformedVk);
+    }
+    Ok(vk)
+}
+
+pub(crate) fn read_instances(instances: &[Vec<[u8; 32]>]) -> Result<Vec<Vec<Fr>>, VerifyError> {
+    instances
+        .iter()
+        .map(|column| {
+            column
+                .iter()
+                .map(|repr| Option::<Fr>::from(Fr::from_repr(*repr)))
+                .collect::<Option<Vec<_>>>()
+                .ok_or(VerifyError::InstanceMismatch)
+        })
+        .collect()
+}
+
+fn verify_transcript<'params, S>(
+    params: &'params ParamsVerifierKZG<Bn256>,
+    vk: &VerifyingKey<G1Affine>,
+    strategy: S,
+    transcript: TranscriptKind,
+    instances: &[Vec<Fr>],
+    proof: &[u8],
+) -> Result<S::Output, VerifyError>
+where
+    S: VerificationStrategy<'params, KZGCommitmentScheme<Bn256>, VerifierSHPLONK<'params, Bn256>>,
+{
+    let instances: Vec<&[Fr]> = instances.iter().map(Vec::as_slice).collect();
+    let output = match transcript {
+        TranscriptKind::Blake2b => {
+            verify_with::<Blake2bRead<_, _, _>, _>(params, vk, strategy, &instances, proof)
+        }
+        TranscriptKind::Keccak256 => {
+            verify_with::<Keccak256Read<_, _, _>, _>(params, vk, strategy, &instances, proof)
+        }
+        TranscriptKind::Poseidon => {
+            verify_with::<PoseidonRead<_, _, _>, _>(params, vk, strategy, &instances, proof)
+        }
+    }?;
+    Ok(output)
+}
+
+fn verify_with<'a, 'params, T, S>(
+    params: &'params ParamsVerifierKZG<Bn256>,
+    vk: &VerifyingKey<G1Affine>,
+    strategy: S,
+    instances: &[&[Fr]],
+    proof: &'a [u8],
+) -> Result<S::Output, plonk::Error>
+where
+    T: TranscriptReadBuffer<&'a [u8], G1Affine, Challenge255<G1Affine>>,
+    S: VerificationStrategy<'params, KZGCommitmentScheme<Bn256>, VerifierSHPLONK<'params, Bn256>>,
+{
+    verify_proof::<KZGCommitmentScheme<Bn256>, VerifierSHPLONK<'params, Bn256>, _, T, S>(
+        params,
+        vk,
+        strategy,
+        &[instances],
+        &mut T::init(proof),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bundle::ProofBundle;
+    use crate::main_circuit::tx_hash_instance;
+    use crate::witness::ProofWitness;
+    use alloc::vec;
+
+    // Written by `cli setup -k 11 --insecure`, `cli keygen` and `cli prove`
+    // on the first steps of a trace, in the binary format.
+    const PARAMS: &[u8] = include_bytes!("../testdata/verifier-params.bin");
+    const VK: &[u8] = include_bytes!("../testdata/vk.bin");
+    const PROOF: &[u8] = include_bytes!("../testdata/proof.bin");
+    // The same with `prove --transcript keccak256` and `--transcript poseidon`.
+    const PROOF_KECCAK256: &[u8] = include_bytes!("../testdata/proof-keccak256.bin");
+    const PROOF_POSEIDON: &[u8] = include_bytes!("../testdata/proof-poseidon.bin");
+
+    fn proof_of(bundle: &[u8]) -> ProofWitness {
+        let bundle = ProofBundle::decode(bundle).unwrap();
+        ProofWitness {
+            fingerprint: bundle.fingerprint,
+            transcript: bundle.transcript,
+            instances: bundle.instances,
+            proof: bundle.proof,
+        }
+    }
+
+    fn proof() -> ProofWitness {
+        proof_of(PROOF)
+    }
+
+    fn witness_of(proofs: &[ProofWitness]) -> VerifierWitness {
+        VerifierWitness {
+            params: vec![],
+            vk: vec![],
+            proofs: proofs.to_vec(),
+            circuit: CircuitKind::Main,
+        }
+    }
+
+    fn key() -> VerifierKey {
+        let mut prepared = vec![];
+        PreparedG2KZG::<Bn256>::new(&read_params(PARAMS).unwrap())
+            .write(&mut prepared)
+            .unwrap();
+        VerifierKey {
+            params: PARAMS.to_vec(),
+            vk: VK.to_vec(),
+            prepared,
+            circuit: CircuitKind::Main,
+        }
+    }
+
+    #[test]
+    fn verifies_cli_proof() {
+        let proof = proof();
+        assert_eq!(proof.transcript, TranscriptKind::Blake2b);
+        assert_eq!(
+            verify(
+                CircuitKind::Main,
+                PARAMS,
+                VK,
+                proof.transcript,
+                &proof.instances,
+                &proof.proof
+            ),
+            Ok(())
+        );
+        let witness = witness_of(&[proof]);
+        assert_eq!(verify_witness(&key(), &witness), Ok(()));
+        // Without the prepared G2 points.
+        let key = VerifierKey {
+            prepared: vec![],
+            ..key()
+        };
+        assert_eq!(verify_witness(&key, &witness), Ok(()));
+    }
+
+    #[test]
+    fn verifies_each_transcript() {
+        for (proof, transcript) in [
+            (PROOF_KECCAK256, TranscriptKind::Keccak256),
+            (PROOF_POSEIDON, TranscriptKind::Poseidon),
+        ] {
+            let mut proof = proof_of(proof);
+            assert_eq!(proof.transcript, transcript);
+            assert_eq!(
+                verify_witness(&key(), &witness_of(&[proof.clone()])),
+                Ok(())
+            );
+            // Other challenges than the prover's.
+            proof.transcript = TranscriptKind::Blake2b;
+            assert!(verify_witness(&key(), &witness_of(&[proof])).is_err());
+        }
+    }
+
+    #[test]
+    fn verifies_batch() {
+        let proofs = [proof(), proof_of(PROOF_KECCAK256), proof_of(PROOF_POSEIDON)];
+        assert_eq!(verify_witness(&key(), &witness_of(&proofs)), Ok(()));
+        // A single invalid proof fails the whole batch.
+        for i in 0..proofs.len() {
+            let mut proofs = proofs.clone();
+            let last = proofs[i].proof.len() - 32;
+            proofs[i].proof.copy_within(..32, last);
+            assert_eq!(
+                verify_witness(&key(), &witness_of(&proofs)),
+                Err(VerifyError::PairingFailure)
+            );
+        }
+        // The same proof twice.
+        let proofs = [proof(), proof()];
+        assert_eq!(verify_witness(&key(), &witness_of(&proofs)), Ok(()));
+        assert_eq!(
+            verify_witness(&key(), &witness_of(&[])),
+            Err(VerifyError::NoProof)
+        );
+    }
+
+    #[test]
+    fn rejects_tampered_proof() {
+        let proof = proof();
+        // A valid point in place of the last opening proof.
+        let mut bytes = proof.proof.clone();
+        let last = bytes.len() - 32;
+        bytes.copy_within(..32, last);
+        assert_eq!(
+            verify(
+                CircuitKind::Main,
+                PARAMS,
+                VK,
+                proof.transcript,
+                &proof.instances,
+                &bytes
+            ),
+            Err(VerifyError::PairingFailure)
+        );
+        // A proof cut before its evaluations.
+        assert_eq!(
+            verify(
+                CircuitKind::Main,
+                PARAMS,
+                VK,
+                proof.transcript,
+                &proof.instances,
+                &proof.proof[..proof.proof.len() / 2]
+            ),
+            Err(VerifyError::MalformedProof)
+        );
+        // Bytes that aren't a point.
+        let mut bytes = proof.proof.clone();
+        bytes[..32].copy_from_slice(&[0xff; 32]);
+        assert_eq!(
+            verify(
+                CircuitKind::Main,
+                PARAMS,
+                VK,
+                proof.transcript,
+                &proof.instances,
+                &bytes
+            ),
+            Err(VerifyError::MalformedProof)
+        );
+    }
+
+    #[test]
+    fn rejects_wrong_instances() {
+        let proof = proof();
+        // Another instance column than the circuit has.
+        let mut instances = proof.instances.clone();
+        instances.push(vec![Fr::from(1).to_repr()]);
+        assert_eq!(
+            verify(
+                CircuitKind::Main,
+                PARAMS,
+                VK,
+                proof.transcript,
+                &instances,
+                &proof.proof
+            ),
+            Err(VerifyError::InstanceMismatch)
+        );
+        // A public input that isn't a canonical field element.
+        let mut instances = proof.instances.clone();
+        instances.push(vec![[0xff; 32]]);
+        assert_eq!(
+            verify(
+                CircuitKind::Main,
+                PARAMS,
+                VK,
+                proof.transcript,
+                &instances,
+                &proof.proof
+            ),
+            Err(VerifyError::InstanceMismatch)
+        );
+    }
+
+    #[test]
+    fn rejects_other_tx_hash() {
+        let proof = proof();
+        // The testdata proofs are made for the zero hash.
+        assert_eq!(proof.instances, vec![tx_hash_instance(&[0; 32])]);
+        let mut tx_hash = [0; 32];
+        tx_hash[31] = 1;
+        assert_eq!(
+            verify(
+                CircuitKind::Main,
+                PARAMS,
+                VK,
+                proof.transcript,
+                &[tx_hash_instance(&tx_hash)],
+                &proof.proof
+            ),
+            Err(VerifyError::PairingFailure)
+        );
+    }
+
+    #[test]
+    fn verifies_with_fully_shrunk_params() {
+        // The testdata params keep `g[0]` alone and no Lagrange point for the
+        // two rows of public inputs.
+        let params = read_params(PARAMS).unwrap();
+        assert_eq!(params.g.len(), 1);
+        assert!(params.g_lagrange.is_empty());
+        let proof = proof();
+        assert_eq!(proof.instances[0].len(), 2);
+        assert_eq!(
+            verify(
+                CircuitKind::Main,
+                PARAMS,
+                VK,
+                proof.transcript,
+                &proof.instances,
+                &proof.proof
+            ),
+            Ok(())
+        );
+        assert_eq!(verify_witness(&key(), &witness_of(&[proof])), Ok(()));
+    }
+
+    #[test]
+    fn rejects_vk_of_other_circuit() {
+        assert_eq!(
+            read_vk(CircuitKind::Aggregation, VK).err(),
+            Some(VerifyError::MalformedVk)
+        );
+        let key = VerifierKey {
+            circuit: CircuitKind::Aggregation,
+            ..key()
+        };
+        assert_eq!(
+            verify_witness(&key, &witness_of(&[proof()])),
+            Err(VerifyError::MalformedVk)
+        );
+        let mut vk = VK.to_vec();
+        vk.push(0);
+        assert_eq!(
+            read_vk(CircuitKind::Main, &vk).err(),
+            Some(VerifyError::MalformedVk)
+        );
+    }
+
+    #[test]
+    fn rejects_other_circuit() {
+        let mut proof = proof();
+        proof.fingerprint[0] ^= 1;
+        assert!(matches!(
+            verify_witness(&key(), &witness_of(&[proof])),
+            Err(VerifyError::FingerprintMismatch(_))
+        ));
+    }
+}
@@ -0,0 +1,374 @@
+//! Witness of the on-chain verifier, molecule encoded:
+//!
+//! ```text
+//! vector Bytes <byte>;
+//! array Byte32 [byte; 32];
+//! vector Byte32Vec <Byte32>;
+//! vector Instances <Byte32Vec>;
+//!
+//! table ProofWitness {
+//!     fingerprint: Byte32,
+//!     transcript: byte,
+//!     instances: Instances,
+//!     proof: Bytes,
+//! }
+//!
+//! vector ProofWitnessVec <ProofWitness>;
+//!
+//! table VerifierWitness {
+//!     params: Bytes,
+//!     vk: Bytes,
+//!     proofs: ProofWitnessVec,
+//!     circuit: byte,
+//! }
+//!
+//! table VerifierKey {
+//!     params: Bytes,
+//!     vk: Bytes,
+//!     prepared: Bytes,
+//!     circuit: byte,
+//! }
+//! ```
+//!
+//! `params` are the shrunk verifier params and `vk` the verifying key in the
+//! raw bytes format, of the [`CircuitKind`] `circuit`. `proofs` are checked together with a single pairing, each
+//! of them proving a whole trace from the entry point of the program, no state
+//! is carried from one to the next. For each of them,
+//! `fingerprint` is the one of the circuit the proof is for, see
+//! [`crate::fingerprint`], `transcript` the [`TranscriptKind`] the proof is
+//! made with and `instances` the public inputs, one `Byte32Vec` of canonical
+//! field elements per instance column. The witness is carried in a field of
+//! the `WitnessArgs` of the script group.
+//!
+//! A verifier pinning its key reads `params`, `vk` and `circuit` from a cell
+//! dep holding a `VerifierKey` instead, `params` and `vk` are left empty in
+//! the witness and its `circuit` is ignored. `prepared`
+//! holds the line coefficients of the G2 points of `params` for the pairing,
+//! which the verifier computes itself when it is empty.
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::circuit::CircuitKind;
+use crate::transcript::TranscriptKind;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VerifierWitness {
+    pub params: Vec<u8>,
+    pub vk: Vec<u8>,
+    pub proofs: Vec<ProofWitness>,
+    pub circuit: CircuitKind,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProofWitness {
+    pub fingerprint: [u8; 32],
+    pub transcript: TranscriptKind,
+    pub instances: Vec<Vec<[u8; 32]>>,
+    pub proof: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WitnessError {
+    /// The bytes are shorter than the sizes they declare.
+    TotalSizeMismatch,
+    InvalidOffset,
+    FieldCountMismatch(usize),
+    InvalidItemSize,
+    UnknownTranscript(u8),
+    UnknownCircuit(u8),
+}
+
+impl fmt::Display for WitnessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WitnessError::TotalSizeMismatch => write!(f, "witness size mismatch"),
+            WitnessError::InvalidOffset => write!(f, "invalid witness field offset"),
+            WitnessError::FieldCountMismatch(n) => write!(f, "unexpected field count {}", n),
+            WitnessError::InvalidItemSize => {
+                write!(
+                    f,
+                    "fingerprint, transcript, circuit or public input has a wrong size"
+                )
+            }
+            WitnessError::UnknownTranscript(t) => write!(f, "unknown transcript {}", t),
+            WitnessError::UnknownCircuit(c) => write!(f, "unknown circuit {}", c),
+        }
+    }
+}
+
+impl VerifierWitness {
+    pub fn encode(&self) -> Vec<u8> {
+        let proofs = self
+            .proofs
+            .iter()
+            .map(ProofWitness::encode)
+            .collect::<Vec<_>>();
+        table(&[
+            bytes(&self.params),
+            bytes(&self.vk),
+            table(&proofs),
+            [self.circuit as u8].to_vec(),
+        ])
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, WitnessError> {
+        let parts = fields(data)?;
+        if parts.len() != 4 {
+            return Err(WitnessError::FieldCountMismatch(parts.len()));
+        }
+        Ok(Self {
+            params: fixvec(parts[0], 1)?.1.to_vec(),
+            vk: fixvec(parts[1], 1)?.1.to_vec(),
+            proofs: fields(parts[2])?
+                .into_iter()
+                .map(ProofWitness::decode)
+                .collect::<Result<_, _>>()?,
+            circuit: circuit(parts[3])?,
+        })
+    }
+}
+
+impl ProofWitness {
+    pub fn encode(&self) -> Vec<u8> {
+        let instances = self
+            .instances
+            .iter()
+            .map(|column| {
+                let mut bytes = (column.len() as u32).to_le_bytes().to_vec();
+                for value in column.iter() {
+                    bytes.extend_from_slice(value);
+                }
+                bytes
+            })
+            .collect::<Vec<_>>();
+        table(&[
+            self.fingerprint.to_vec(),
+            [self.transcript as u8].to_vec(),
+            table(&instances),
+            bytes(&self.proof),
+        ])
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, WitnessError> {
+        let parts = fields(data)?;
+        if parts.len() != 4 {
+            return Err(WitnessError::FieldCountMismatch(parts.len()));
+        }
+        if parts[0].len() != 32 {
+            return Err(WitnessError::InvalidItemSize);
+        }
+        let mut fingerprint = [0u8; 32];
+        fingerprint.copy_from_slice(parts[0]);
+        if parts[1].len() != 1 {
+            return Err(WitnessError::InvalidItemSize);
+        }
+        let transcript = TranscriptKind::from_u8(parts[1][0])
+            .ok_or(WitnessError::UnknownTranscript(parts[1][0]))?;
+        let instances = fields(parts[2])?
+            .into_iter()
+            .map(|column| {
+                let (count, items) = fixvec(column, 32)?;
+                Ok((0..count)
+                    .map(|i| {
+                        let mut value = [0u8; 32];
+                        value.copy_from_slice(&items[i * 32..(i + 1) * 32]);
+                        value
+                    })
+                    .collect())
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            fingerprint,
+            transcript,
+            instances,
+            proof: fixvec(parts[3], 1)?.1.to_vec(),
+        })
+    }
+}
+
+/// Params and verifying key of a circuit, the data of the cell dep a pinning
+/// verifier loads them from.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VerifierKey {
+    pub params: Vec<u8>,
+    pub vk: Vec<u8>,
+    /// Prepared G2 points of `params`, see `PreparedG2KZG::write`.
+    pub prepared: Vec<u8>,
+    pub circuit: CircuitKind,
+}
+
+impl VerifierKey {
+    pub fn encode(&self) -> Vec<u8> {
+        table(&[
+            bytes(&self.params),
+            bytes(&self.vk),
+            bytes(&self.prepared),
+            [self.circuit as u8].to_vec(),
+        ])
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, WitnessError> {
+        let parts = fields(data)?;
+        if parts.len() != 4 {
+            return Err(WitnessError::FieldCountMismatch(parts.len()));
+        }
+        Ok(Self {
+            params: fixvec(parts[0], 1)?.1.to_vec(),
+            vk: fixvec(parts[1], 1)?.1.to_vec(),
+            prepared: fixvec(parts[2], 1)?.1.to_vec(),
+            circuit: circuit(parts[3])?,
+        })
+    }
+}
+
+/// Decodes a `circuit: byte` field.
+fn circuit(data: &[u8]) -> Result<CircuitKind, WitnessError> {
+    match data {
+        [value] => CircuitKind::from_u8(*value).ok_or(WitnessError::UnknownCircuit(*value)),
+        _ => Err(WitnessError::InvalidItemSize),
+    }
+}
+
+/// Molecule `WitnessArgs` with `witness` in the field `index`: 0 for `lock`,
+/// 1 for `input_type` and 2 for `output_type`.
+pub fn witness_args(index: usize, witness: &[u8]) -> Vec<u8> {
+    let mut fields = [Vec::new(), Vec::new(), Vec::new()];
+    fields[index] = bytes(witness);
+    table(&fields)
+}
+
+/// Content of the field `index` of a molecule `WitnessArgs`, `None` if the
+/// field is absent.
+pub fn witness_args_field(data: &[u8], index: usize) -> Result<Option<&[u8]>, WitnessError> {
+    let parts = fields(data)?;
+    if parts.len() != 3 {
+        return Err(WitnessError::FieldCountMismatch(parts.len()));
+    }
+    if parts[index].is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(fixvec(parts[index], 1)?.1))
+}
+
+fn bytes(data: &[u8]) -> Vec<u8> {
+    let mut bytes = (data.len() as u32).to_le_bytes().to_vec();
+    bytes.extend_from_slice(data);
+    bytes
+}
+
+/// Molecule table, and dynamic vector: total size and the offset of each
+/// field, then the fields.
+fn table(fields: &[Vec<u8>]) -> Vec<u8> {
+    let header = 4 * (fields.len() + 1);
+    let total = header + fields.iter().map(Vec::len).sum::<usize>();
+    let mut bytes = Vec::with_capacity(total);
+    bytes.extend_from_slice(&(total as u32).to_le_bytes());
+    let mut offset = header;
+    for field in fields.iter() {
+        bytes.extend_from_slice(&(offset as u32).to_le_bytes());
+        offset += field.len();
+    }
+    for field in fields.iter() {
+        bytes.extend_from_slice(field);
+    }
+    bytes
+}
+
+fn read_u32(data: &[u8], pos: usize) -> Result<usize, WitnessError> {
+    let bytes = data
+        .get(pos..pos + 4)
+        .ok_or(WitnessError::TotalSizeMismatch)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+}
+
+/// Splits a table or a dynamic vector into its fields.
+fn fields(data: &[u8]) -> Result<Vec<&[u8]>, WitnessError> {
+    let total = read_u32(data, 0)?;
+    if total != data.len() {
+        return Err(WitnessError::TotalSizeMismatch);
+    }
+    if total == 4 {
+        return Ok(Vec::new());
+    }
+    let first = read_u32(data, 4)?;
+    if first % 4 != 0 || first < 8 || first > total {
+        return Err(WitnessError::InvalidOffset);
+    }
+    let mut offsets = (1..first / 4)
+        .map(|i| read_u32(data, 4 * i))
+        .collect::<Result<Vec<_>, _>>()?;
+    offsets.push(total);
+    offsets
+        .windows(2)
+        .map(|w| data.get(w[0]..w[1]).ok_or(WitnessError::InvalidOffset))
+        .collect()
+}
+
+/// Splits a fixed vector into its item count and items.
+fn fixvec(data: &[u8], item_size: usize) -> Result<(usize, &[u8]), WitnessError> {
+    let count = read_u32(data, 0)?;
+    let items = &data[4..];
+    if count.checked_mul(item_size) != Some(items.len()) {
+        return Err(if item_size == 1 {
+            WitnessError::TotalSizeMismatch
+        } else {
+            WitnessError::InvalidItemSize
+        });
+    }
+    Ok((count, items))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn roundtrip() {
+        let proof = ProofWitness {
+            fingerprint: [8; 32],
+            transcript: TranscriptKind::Keccak256,
+            instances: vec![vec![[5u8; 32], [6u8; 32]], vec![]],
+            proof: vec![7; 10],
+        };
+        let witness = VerifierWitness {
+            params: vec![1, 2, 3],
+            vk: vec![4; 40],
+            proofs: vec![proof.clone(), ProofWitness::default(), proof],
+            circuit: CircuitKind::Aggregation,
+        };
+        let bytes = witness.encode();
+        assert_eq!(VerifierWitness::decode(&bytes), Ok(witness));
+        assert_eq!(
+            VerifierWitness::decode(&bytes[..bytes.len() - 1]),
+            Err(WitnessError::TotalSizeMismatch)
+        );
+        assert_eq!(
+            VerifierWitness::decode(&VerifierWitness::default().encode()),
+            Ok(VerifierWitness::default())
+        );
+        // Empty WitnessArgs but the lock.
+        let args = witness_args(0, &[9]);
+        assert_eq!(
+            args[..16],
+            [21, 0, 0, 0, 16, 0, 0, 0, 21, 0, 0, 0, 21, 0, 0, 0]
+        );
+        assert_eq!(witness_args_field(&args, 0), Ok(Some(&[9u8][..])));
+        assert_eq!(witness_args_field(&args, 2), Ok(None));
+
+        let key = VerifierKey {
+            params: vec![1],
+            vk: vec![2, 3],
+            prepared: vec![4; 5],
+            circuit: CircuitKind::Aggregation,
+        };
+        assert_eq!(VerifierKey::decode(&key.encode()), Ok(key.clone()));
+        let mut bytes = key.encode();
+        *bytes.last_mut().unwrap() = 2;
+        assert_eq!(
+            VerifierKey::decode(&bytes),
+            Err(WitnessError::UnknownCircuit(2))
+        );
+    }
+}
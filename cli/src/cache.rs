@@ -0,0 +1,213 @@
+//! Proving keys cached between runs, keyed by the layout of the circuit and
+//! the params, so a cached key is found without any keygen. Next to each key
+//! the cache records the fingerprint of its verifying key, against which a
+//! proving key given on the command line is checked.
+
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use circuits::fingerprint::{fingerprint, layout_fingerprint};
+use halo2_proofs::{
+    halo2curves::{
+        bn256::{Bn256, Fr, G1Affine},
+        group::GroupEncoding,
+    },
+    io,
+    plonk::{keygen_pk, keygen_vk, Circuit, ProvingKey},
+    poly::{commitment::Params, kzg::commitment::ParamsKZG},
+    SerdeFormat,
+};
+
+pub struct PkCache {
+    dir: PathBuf,
+}
+
+/// Identifies the proving key of `circuit` with `params` without generating
+/// it: Blake2b-256 of the [`layout_fingerprint`] of the circuit, which covers
+/// its constraint system and every fixed cell, selector and copy constraint
+/// its synthesis assigns, and of `k` and the secret of the params, through
+/// `s_g2`. The trace isn't part of it, the keys don't depend on it.
+pub fn circuit_key<C: Circuit<Fr>>(
+    params: &ParamsKZG<Bn256>,
+    circuit: &C,
+) -> anyhow::Result<[u8; 32]> {
+    let layout =
+        layout_fingerprint(circuit).map_err(|e| anyhow::anyhow!("lay out circuit: {:?}", e))?;
+    let hash = blake2b_simd::Params::new()
+        .hash_length(32)
+        .personal(b"bs2-pk-cache\0\0\0\0")
+        .to_state()
+        .update(&layout)
+        .update(&params.k().to_le_bytes())
+        .update(params.s_g2.to_bytes().as_ref())
+        .finalize();
+    let mut key = [0u8; 32];
+    key.copy_from_slice(hash.as_bytes());
+    Ok(key)
+}
+
+impl PkCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, key: &[u8; 32], k: u32) -> PathBuf {
+        self.dir.join(format!("pk-{}-k{}.bin", hex::encode(key), k))
+    }
+
+    fn fingerprint_path(&self, key: &[u8; 32], k: u32) -> PathBuf {
+        self.path(key, k).with_extension("fp")
+    }
+
+    /// Returns the fingerprint of the verifying key of the circuit with
+    /// `params`, the recorded one when there is one, otherwise generated with
+    /// the verifying key and recorded.
+    pub fn fingerprint<C: Circuit<Fr>>(
+        &self,
+        params: &ParamsKZG<Bn256>,
+        circuit: &C,
+    ) -> anyhow::Result<[u8; 32]> {
+        let path = self.fingerprint_path(&circuit_key(params, circuit)?, params.k());
+        if let Some(fingerprint) = fs::read(&path).ok().and_then(|bytes| bytes.try_into().ok()) {
+            return Ok(fingerprint);
+        }
+        let vk = keygen_vk(params, circuit).map_err(|e| anyhow::anyhow!("keygen vk: {:?}", e))?;
+        let fingerprint = fingerprint(&vk);
+        self.record(&path, &fingerprint)?;
+        Ok(fingerprint)
+    }
+
+    fn record(&self, path: &Path, fingerprint: &[u8; 32]) -> anyhow::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(path, fingerprint)?;
+        Ok(())
+    }
+
+    /// Returns the cached proving key of the circuit, if any.
+    pub fn get<C: Circuit<Fr>>(
+        &self,
+        params: &ParamsKZG<Bn256>,
+        circuit: &C,
+    ) -> anyhow::Result<Option<ProvingKey<G1Affine>>> {
+        Ok(self.read::<C>(&self.path(&circuit_key(params, circuit)?, params.k())))
+    }
+
+    fn read<C: Circuit<Fr>>(&self, path: &Path) -> Option<ProvingKey<G1Affine>> {
+        let mut reader = StdReader(BufReader::new(File::open(path).ok()?));
+        match ProvingKey::<G1Affine>::read::<_, C>(&mut reader, SerdeFormat::RawBytes) {
+            Ok(pk) => {
+                println!("proving key read from {}", path.display());
+                Some(pk)
+            }
+            Err(_) => {
+                println!("ignore invalid cached proving key {}", path.display());
+                None
+            }
+        }
+    }
+
+    /// Returns the proving key of the circuit, read from the cache when it is
+    /// there, and generated and stored otherwise.
+    pub fn get_or_keygen<C: Circuit<Fr>>(
+        &self,
+        params: &ParamsKZG<Bn256>,
+        circuit: &C,
+    ) -> anyhow::Result<ProvingKey<G1Affine>> {
+        let key = circuit_key(params, circuit)?;
+        let path = self.path(&key, params.k());
+        let fingerprint_path = self.fingerprint_path(&key, params.k());
+        if let Some(pk) = self.read::<C>(&path) {
+            if !fingerprint_path.exists() {
+                self.record(&fingerprint_path, &fingerprint(pk.get_vk()))?;
+            }
+            return Ok(pk);
+        }
+
+        let vk = keygen_vk(params, circuit).map_err(|e| anyhow::anyhow!("keygen vk: {:?}", e))?;
+        let pk =
+            keygen_pk(params, vk, circuit).map_err(|e| anyhow::anyhow!("keygen pk: {:?}", e))?;
+        fs::create_dir_all(&self.dir)?;
+        // Write to a temporary file first so that an interrupted run leaves no
+        // truncated key behind.
+        let tmp = path.with_extension("tmp");
+        let mut writer = StdWriter(BufWriter::new(File::create(&tmp)?));
+        pk.write(&mut writer, SerdeFormat::RawBytes)
+            .map_err(anyhow::Error::msg)?;
+        writer.0.flush()?;
+        fs::rename(&tmp, &path)?;
+        self.record(&fingerprint_path, &fingerprint(pk.get_vk()))?;
+        println!("proving key cached in {}", path.display());
+        Ok(pk)
+    }
+}
+
+/// Streams a file into the readers of halo2, which only read slices.
+struct StdReader<R>(R);
+
+impl<R: Read> io::Read for StdReader<R> {
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.0
+            .read_exact(buf)
+            .map_err(|_| "failed to fill whole buffer")
+    }
+}
+
+struct StdWriter<W>(W);
+
+impl<W: Write> io::Write for StdWriter<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.0.write(data).map_err(|_| "failed to write")
+    }
+
+    fn write_all(&mut self, data: &[u8]) -> io::Result<()> {
+        self.0
+            .write_all(data)
+            .map_err(|_| "failed to write whole buffer")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use circuits::main_circuit::MainCircuit;
+    use rand_core::OsRng;
+    use runtime::program::Program;
+
+    const ELF: &[u8] = include_bytes!("../../circuits/testdata/loop.elf");
+
+    #[test]
+    fn reads_cached_keys_without_keygen() {
+        let dir = std::env::temp_dir().join(format!("bs2-pk-cache-{}", std::process::id()));
+        let cache = PkCache::new(&dir);
+        let params = ParamsKZG::<Bn256>::setup(10, OsRng);
+        let max_steps = MainCircuit::<Fr>::max_steps(10);
+        let circuit = MainCircuit::<Fr>::new(Program::from_elf(ELF).unwrap(), max_steps);
+
+        assert!(cache.get(&params, &circuit).unwrap().is_none());
+        let pk = cache.get_or_keygen(&params, &circuit).unwrap();
+        let cached = cache.get(&params, &circuit).unwrap().expect("cached key");
+        assert_eq!(fingerprint(cached.get_vk()), fingerprint(pk.get_vk()));
+        assert_eq!(
+            cache.fingerprint(&params, &circuit).unwrap(),
+            fingerprint(pk.get_vk())
+        );
+
+        // Another program, number of steps or params miss.
+        let mut program = circuit.program.clone();
+        program.instructions.pop_last();
+        assert!(cache
+            .get(&params, &MainCircuit::new(program, max_steps))
+            .unwrap()
+            .is_none());
+        assert!(cache
+            .get(&params, &MainCircuit::new(circuit.program.clone(), 1))
+            .unwrap()
+            .is_none());
+        assert!(cache
+            .get(&ParamsKZG::<Bn256>::setup(10, OsRng), &circuit)
+            .unwrap()
+            .is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,40 +1,26 @@
-use std::fmt::write;
-use std::io::Write;
-use std::vec;
-use std::{fs::File, io::BufReader};
+use std::fs;
+use std::path::Path;
 
+use crate::exec::aggregate::exec_aggregate;
+use crate::exec::convert::exec_convert;
+use crate::exec::export::exec_export_verifier;
+use crate::exec::keygen::exec_keygen;
+use crate::exec::prove::exec_prove;
 use crate::exec::run::exec_run;
-use clap::{command, Args, Parser, Subcommand};
-use halo2_proofs::halo2curves::pairing::Engine;
-use halo2_proofs::helpers::SerdeCurveAffine;
-use halo2_proofs::plonk::VerifyingKey;
-use halo2_proofs::poly::commitment::Params;
-use halo2_proofs::{io, SerdeFormat};
-use runtime::trace::Step;
-
+use crate::exec::setup::exec_setup;
+use crate::exec::stats::exec_stats;
+use crate::exec::validate::exec_validate;
+use crate::exec::verify::exec_verify;
+use crate::exec::witness::{exec_key_cell, exec_witness};
+use circuits::circuit::CircuitKind;
 use circuits::main_circuit::MainCircuit;
+use circuits::transcript::TranscriptKind;
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use entry_builder::builder::EntryBuilder;
-use halo2_proofs::dev::MockProver;
-use halo2_proofs::{
-    halo2curves::bn256::{Bn256, Fr, G1Affine},
-    plonk::{create_proof, keygen_pk, keygen_vk, verify_proof},
-    poly::{
-        commitment::ParamsProver,
-        kzg::{
-            commitment::{KZGCommitmentScheme, ParamsKZG, ParamsVerifierKZG},
-            multiopen::{ProverSHPLONK, VerifierSHPLONK},
-            strategy::SingleStrategy,
-        },
-    },
-    transcript::{
-        Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
-    },
-};
-use rand_core::SeedableRng;
-use rand_xorshift::XorShiftRng;
-use runtime::trace::Trace;
-
-pub const SHRINK_K: u32 = 1;
+use halo2_proofs::halo2curves::bn256::Fr;
+use runtime::program::Program;
+use runtime::trace::{binary, Step, Trace};
+use serde::{Deserialize, Serialize};
 
 #[derive(Parser)]
 pub struct Cli {
@@ -44,189 +30,524 @@ pub struct Cli {
 
 #[derive(Subcommand)]
 pub enum Commands {
+    /// Run a script of a mocked transaction and dump its trace
     Run(RunArgs),
-    Prove(RunArgs),
+    /// Generate or import the KZG params
+    Setup(SetupArgs),
+    /// Generate the verifying and proving keys of the circuit
+    Keygen(KeygenArgs),
+    /// Prove a trace with a proving key
+    Prove(ProveArgs),
+    /// Prove the aggregation circuit of poseidon proofs, a single proof verifying them all
+    Aggregate(AggregateArgs),
+    /// Verify a proof bundle, the exit code is 0 if and only if the proof is valid
+    Verify(VerifyArgs),
+    /// Encode the witness of the on-chain verifier
+    Witness(WitnessArgs),
+    /// Encode the key cell of a verifier pinning its key and print the script args pinning it
+    KeyCell(KeyCellArgs),
+    /// Describe the verifier of a verifying key in JSON, for verifiers on other chains
+    ExportVerifier(ExportVerifierArgs),
+    /// Convert a trace between the JSON and the binary format
+    Convert(ConvertArgs),
+    /// Replay a trace and report every step diverging from the replay
+    Validate(ValidateArgs),
+    /// Report the instructions of a trace and the rows and proof size it needs
+    Stats(StatsArgs),
 }
 
 #[derive(Args)]
 pub struct RunArgs {
+    /// ELF of the script
     #[arg(short, long)]
-    pub trace: Option<String>,
-    // #[arg(short, long)]
-    // pub bytecode: Option<String>,
-    // #[arg(short, long)]
-    // pub hardcode: Option<String>,
-    // #[arg(short, long)]
-    // pub file: Option<String>,
-    // // #[arg(short, long)]
-    // // pub dry_run: bool,
-}
-
-pub fn read_verifier_params<E: Engine, R: io::Read>(
-    reader: &mut R,
-) -> io::Result<ParamsVerifierKZG<E>>
-where
-    E::G1Affine: SerdeCurveAffine,
-    E::G2Affine: SerdeCurveAffine,
-{
-    let shrink_k = SHRINK_K;
-    let mut k = [0u8; 4];
-    reader.read_exact(&mut k[..])?;
-    let k = u32::from_le_bytes(k);
-    let n = 1 << k;
-    let shrink_n = 1 << shrink_k;
-
-    let format = SerdeFormat::RawBytes;
-
-    let g = (0..shrink_n)
-        .map(|_| E::G1Affine::read(reader, format))
-        .collect::<Result<Vec<_>, _>>()?;
-    let g_lagrange = (0..shrink_n)
-        .map(|_| E::G1Affine::read(reader, format))
-        .collect::<Result<Vec<_>, _>>()?;
-
-    let g2 = E::G2Affine::read(reader, format)?;
-    let s_g2 = E::G2Affine::read(reader, format)?;
-
-    Ok(ParamsVerifierKZG {
-        k,
-        n: n as u64,
-        g,
-        g_lagrange,
-        g2,
-        s_g2,
-    })
+    pub elf: String,
+    /// Mocked transaction in the JSON format of ckb-debugger
+    #[arg(long)]
+    pub tx: String,
+    /// Output trace, in JSON with the program hash, cycles and exit code of the run
+    #[arg(short, long)]
+    pub trace: String,
+    #[arg(long, value_enum, default_value = "lock")]
+    pub script_group_type: ScriptGroupTypeArg,
+    #[arg(long, value_enum, default_value = "input")]
+    pub cell_type: CellTypeArg,
+    #[arg(long, default_value_t = 0)]
+    pub cell_index: usize,
+    #[arg(long, default_value_t = 10_000_000)]
+    pub max_steps: u64,
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+pub enum ScriptGroupTypeArg {
+    Lock,
+    Type,
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+pub enum CellTypeArg {
+    Input,
+    Output,
+}
+
+/// Where and how the files produced by a command are written.
+#[derive(Args)]
+pub struct OutputArgs {
+    /// Directory of the output files
+    #[arg(long, default_value = ".")]
+    pub out_dir: String,
+    #[arg(long, value_enum, default_value = "hex")]
+    pub format: OutputFormat,
+}
+
+impl OutputArgs {
+    /// Writes `bytes` to `path`, or to `<name>.bin`/`<name>.hex` by default, in the out dir.
+    /// The extension of `path` must be the one of the format, [`read_artifact`] reads the
+    /// file in the format of its extension.
+    pub fn write(&self, name: &str, path: &Option<String>, bytes: &[u8]) -> anyhow::Result<()> {
+        let out_dir = Path::new(&self.out_dir);
+        let path = match path {
+            Some(path) => {
+                let format = OutputFormat::from_path(path)?;
+                anyhow::ensure!(
+                    format == self.format,
+                    "{} has the extension of the {} format, not of {}",
+                    path,
+                    format.extension(),
+                    self.format.extension()
+                );
+                out_dir.join(path)
+            }
+            None => out_dir.join(format!("{}.{}", name, self.format.extension())),
+        };
+        fs::create_dir_all(out_dir)?;
+        match self.format {
+            OutputFormat::Binary => fs::write(&path, bytes)?,
+            OutputFormat::Hex => fs::write(&path, hex::encode(bytes))?,
+        }
+        println!(
+            "{} written to {}, {} bytes",
+            name,
+            path.display(),
+            bytes.len()
+        );
+        Ok(())
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Binary,
+    Hex,
+}
+
+impl OutputFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Binary => "bin",
+            OutputFormat::Hex => "hex",
+        }
+    }
+
+    /// The format of a file from its extension, `.bin` or `.hex`.
+    pub fn from_path(path: &str) -> anyhow::Result<Self> {
+        match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+            Some("bin") => Ok(OutputFormat::Binary),
+            Some("hex") => Ok(OutputFormat::Hex),
+            _ => anyhow::bail!("{} has neither the .bin nor the .hex extension", path),
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct SetupArgs {
+    #[arg(short, long, default_value_t = 14)]
+    pub k: u32,
+    /// Ceremony params to use, a `.ptau` file or params in the halo2 format, downsized to k
+    #[arg(long)]
+    pub import: Option<String>,
+    /// Generate the params from a known secret, proofs made with them can be forged
+    #[arg(long)]
+    pub insecure: bool,
+    /// Params file, `params.bin` or `params.hex` by default
+    #[arg(long)]
+    pub params: Option<String>,
+    /// Verifier params file, `verifier-params.bin` or `verifier-params.hex` by default
+    #[arg(long)]
+    pub verifier_params: Option<String>,
+    /// Keep the first 2^shrink-k points of g and no point of the Lagrange basis in the verifier
+    /// params. The verifier only reads g[0], which the default 0 keeps alone
+    #[arg(long, default_value_t = circuits::verify::SHRINK_K)]
+    pub shrink_k: u32,
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(Args)]
+pub struct KeygenArgs {
+    /// Params written by `setup`, the keys prove the traces fitting in their 2^k rows
+    #[arg(long)]
+    pub params: String,
+    /// ELF of the program, its instructions are the program table of the circuit
+    #[arg(short, long)]
+    pub elf: String,
+    /// Verifying key file, `vk.bin` or `vk.hex` by default
+    #[arg(long)]
+    pub vk: Option<String>,
+    /// Proving key file, `pk.bin` or `pk.hex` by default
+    #[arg(long)]
+    pub pk: Option<String>,
+    /// Directory of the cached proving keys
+    #[arg(long, env = "BS2_CACHE_DIR", default_value = ".bs2-cache")]
+    pub cache_dir: String,
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(Args)]
+pub struct ProveArgs {
+    /// Params written by `setup`
+    #[arg(long)]
+    pub params: String,
+    /// Proving key written by `keygen`, the cached one is used or generated if not given
+    #[arg(long)]
+    pub pk: Option<String>,
+    /// Directory of the cached proving keys
+    #[arg(long, env = "BS2_CACHE_DIR", default_value = ".bs2-cache")]
+    pub cache_dir: String,
+    #[arg(short, long)]
+    pub trace: String,
+    /// ELF of the traced program, the trace is checked against its instructions
+    #[arg(short, long)]
+    pub elf: String,
+    /// Fiat-Shamir transcript of the proof, recorded in the bundle
+    #[arg(long, value_enum, default_value = "blake2b")]
+    pub transcript: TranscriptArg,
+    /// Hash of the transaction carrying the proof, its public inputs, zero by default. The circuit
+    /// doesn't constrain it
+    #[arg(long, value_parser = parse_hash)]
+    pub tx_hash: Option<[u8; 32]>,
+    /// Proof bundle file, `proof.bin` or `proof.hex` by default
+    #[arg(long)]
+    pub proof: Option<String>,
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+pub enum TranscriptArg {
+    Blake2b,
+    Keccak256,
+    /// Poseidon over the scalar field, for proofs verified in a circuit
+    Poseidon,
+}
+
+impl From<TranscriptArg> for TranscriptKind {
+    fn from(arg: TranscriptArg) -> Self {
+        match arg {
+            TranscriptArg::Blake2b => TranscriptKind::Blake2b,
+            TranscriptArg::Keccak256 => TranscriptKind::Keccak256,
+            TranscriptArg::Poseidon => TranscriptKind::Poseidon,
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct AggregateArgs {
+    /// Params written by `setup` with the secret of the params of the proofs, the circuit of one
+    /// proof of 2^11 rows takes k 18
+    #[arg(long)]
+    pub params: String,
+    /// Verifier params of the proofs
+    #[arg(long)]
+    pub verifier_params: String,
+    /// Verifying key of the proofs, written by `keygen`
+    #[arg(long)]
+    pub vk: String,
+    /// Proof bundle written by `prove --transcript poseidon`, repeated for the proofs to aggregate
+    #[arg(long, required = true)]
+    pub proof: Vec<String>,
+    /// Fiat-Shamir transcript of the aggregation proof, recorded in the bundle
+    #[arg(long, value_enum, default_value = "blake2b")]
+    pub transcript: TranscriptArg,
+    /// Directory of the cached proving keys
+    #[arg(long, env = "BS2_CACHE_DIR", default_value = ".bs2-cache")]
+    pub cache_dir: String,
+    /// Verifying key file of the aggregation circuit, `aggregation-vk.bin` or
+    /// `aggregation-vk.hex` by default
+    #[arg(long)]
+    pub aggregation_vk: Option<String>,
+    /// Proof bundle file of the aggregation proof, `aggregation-proof.bin` or
+    /// `aggregation-proof.hex` by default
+    #[arg(long)]
+    pub aggregation_proof: Option<String>,
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(Args)]
+pub struct VerifyArgs {
+    /// Verifier params written by `setup`
+    #[arg(long)]
+    pub verifier_params: String,
+    /// Verifying key written by `keygen` or `aggregate`
+    #[arg(long)]
+    pub vk: String,
+    /// Proof bundle written by `prove` or `aggregate`, holding the public inputs
+    #[arg(long)]
+    pub proof: String,
+}
+
+#[derive(Args)]
+pub struct WitnessArgs {
+    /// Verifier params written by `setup`
+    #[arg(long, required_unless_present = "pinned")]
+    pub verifier_params: Option<String>,
+    /// Verifying key written by `keygen` or `aggregate`
+    #[arg(long, required_unless_present = "pinned")]
+    pub vk: Option<String>,
+    /// Proof bundle written by `prove` or `aggregate`, repeated for the proofs of the circuit
+    /// to check together, each of a whole trace from the entry point
+    #[arg(long, required = true)]
+    pub proof: Vec<String>,
+    /// Field of the `WitnessArgs` holding the verifier witness
+    #[arg(long, value_enum, default_value = "lock")]
+    pub field: WitnessFieldArg,
+    /// Leave the params and the vk out, for a verifier loading them from its key cell
+    #[arg(long)]
+    pub pinned: bool,
+    /// Witness file, `witness.bin` or `witness.hex` by default
+    #[arg(long)]
+    pub witness: Option<String>,
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(Args)]
+pub struct KeyCellArgs {
+    /// Verifier params written by `setup`
+    #[arg(long)]
+    pub verifier_params: String,
+    /// Verifying key written by `keygen` or `aggregate`
+    #[arg(long)]
+    pub vk: String,
+    /// Circuit of the verifying key
+    #[arg(long, value_enum, default_value = "main")]
+    pub circuit: CircuitArg,
+    /// Key cell data file, `key-cell.bin` or `key-cell.hex` by default
+    #[arg(long)]
+    pub key_cell: Option<String>,
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+pub enum CircuitArg {
+    /// The circuit of `keygen`, proving traces
+    Main,
+    /// The circuit of `aggregate`, verifying proofs of the main circuit
+    Aggregation,
+}
+
+impl From<CircuitArg> for CircuitKind {
+    fn from(arg: CircuitArg) -> Self {
+        match arg {
+            CircuitArg::Main => CircuitKind::Main,
+            CircuitArg::Aggregation => CircuitKind::Aggregation,
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct ExportVerifierArgs {
+    /// Verifier params written by `setup`
+    #[arg(long)]
+    pub verifier_params: String,
+    /// Verifying key of the main circuit, written by `keygen`
+    #[arg(long)]
+    pub vk: String,
+    /// Verifier description file
+    #[arg(long, default_value = "verifier.json")]
+    pub verifier: String,
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+pub enum WitnessFieldArg {
+    Lock,
+    InputType,
+    OutputType,
+}
+
+#[derive(Args)]
+pub struct ConvertArgs {
+    /// Input trace, the format is detected from its content
+    #[arg(short, long)]
+    pub input: String,
+    #[arg(short, long)]
+    pub output: String,
+    /// ELF of the traced program, its hash is stored in the binary header
+    #[arg(short, long)]
+    pub program: Option<String>,
+}
+
+#[derive(Args)]
+pub struct ValidateArgs {
+    #[arg(short, long)]
+    pub trace: String,
+    /// ELF of the traced program, steps not executing its instructions diverge
+    #[arg(short, long)]
+    pub elf: Option<String>,
+}
+
+#[derive(Args)]
+pub struct StatsArgs {
+    #[arg(short, long)]
+    pub trace: String,
+    /// ELF of the traced program, the trace is checked against its instructions
+    #[arg(short, long)]
+    pub elf: String,
+}
+
+pub fn match_operation(cli: &Cli) {
+    let result = match &cli.command {
+        Commands::Run(args) => exec_run(args),
+        Commands::Setup(args) => exec_setup(args),
+        Commands::Keygen(args) => exec_keygen(args),
+        Commands::Prove(args) => exec_prove(args),
+        Commands::Aggregate(args) => exec_aggregate(args),
+        Commands::Verify(args) => exec_verify(args)
+            .map(|()| println!("proof verified"))
+            .map_err(|err| err.context("proof rejected")),
+        Commands::Witness(args) => exec_witness(args),
+        Commands::KeyCell(args) => exec_key_cell(args),
+        Commands::ExportVerifier(args) => exec_export_verifier(args),
+        Commands::Convert(args) => exec_convert(args),
+        Commands::Stats(args) => exec_stats(args),
+        Commands::Validate(args) => exec_validate(args),
+    };
+    if let Err(err) = result {
+        eprintln!("{:#}", err);
+        std::process::exit(1);
+    }
 }
 
-pub fn prove(steps: Vec<Step>, rng: &mut XorShiftRng) {
-    let mut entry_builder = EntryBuilder::new();
+/// A JSON trace keeping the header of a binary trace, which `convert` writes.
+#[derive(Serialize, Deserialize)]
+pub struct JsonTrace {
+    /// Hex of the hash of the traced program, all zeros if unknown.
+    pub program_hash: String,
+    pub cycles: u64,
+    pub return_value: u8,
+    pub steps: Vec<Step>,
+}
+
+/// Reads a trace in either the binary format, a [`JsonTrace`] or the JSON
+/// list of steps dumped by ckb-debugger.
+pub fn read_trace(bytes: &[u8]) -> anyhow::Result<Trace> {
+    Ok(read_trace_with_hash(bytes)?.0)
+}
+
+/// Reads a trace like [`read_trace`], with the program hash of its header.
+/// The list of steps of ckb-debugger has none.
+pub fn read_trace_with_hash(bytes: &[u8]) -> anyhow::Result<(Trace, Option<[u8; 32]>)> {
+    if binary::is_binary_trace(bytes) {
+        let (header, trace) =
+            binary::decode(bytes).map_err(|e| anyhow::anyhow!("decode binary trace: {}", e))?;
+        return Ok((trace, Some(header.program_hash)));
+    }
+    if let Ok(steps) = serde_json::from_slice::<Vec<Step>>(bytes) {
+        let trace = Trace {
+            cycles: 0,
+            return_value: 0,
+            steps,
+        };
+        return Ok((trace, None));
+    }
+    let json: JsonTrace = serde_json::from_slice(bytes)?;
+    let program_hash = parse_hash(&json.program_hash).map_err(|e| anyhow::anyhow!(e))?;
     let trace = Trace {
-        cycles: 0,
-        return_value: 0,
-        steps,
+        cycles: json.cycles,
+        return_value: json.return_value,
+        steps: json.steps,
     };
-    entry_builder.build(&trace).expect("build entry failed");
-
-    let degree = 14u32;
-
-    let circuit = MainCircuit::<Fr>::init(entry_builder.entries);
-
-    // let prover = MockProver::<Fr>::run(degree, &circuit, vec![]).unwrap();
-    // let verify_result = prover.verify();
-
-    // dbg!(verify_result);
-
-    // return;
-
-    // -----
-
-    let general_params = ParamsKZG::<Bn256>::unsafe_setup(degree);
-    let mut verifier_params: ParamsVerifierKZG<Bn256> = general_params.verifier_params().clone();
-    verifier_params.shrink(SHRINK_K);
-    let mut verifier_params_buf = alloc::vec![];
-    verifier_params
-        .write(&mut verifier_params_buf)
-        .expect("write");
-    println!("verifier parameters length : {}", verifier_params_buf.len());
-    let mut file = File::create("params.hex").expect("open file");
-    let vk_bytes_hex = hex::encode(&verifier_params_buf);
-    file.write(&vk_bytes_hex.as_bytes()).expect("write vk hex");
-
-    // check verification and serialization
-    let verifier_params: ParamsVerifierKZG<Bn256> =
-        read_verifier_params(&mut &verifier_params_buf[..verifier_params_buf.len()]).unwrap();
-
-    // Initialize the proving key
-    let vk = keygen_vk(&general_params, &circuit).expect("keygen_vk should not fail");
-    let mut vk_bytes = alloc::vec![];
-
-    vk.write(&mut vk_bytes, SerdeFormat::RawBytes)
-        .expect("write vk");
-    let mut file = File::create("vk.hex").expect("open file");
-    let vk_bytes_hex = hex::encode(&vk_bytes);
-    file.write(&vk_bytes_hex.as_bytes()).expect("write vk hex");
-
-    println!("{}", hex::encode(&vk_bytes));
-
-    VerifyingKey::<G1Affine>::read::<&[u8], MainCircuit<Fr>>(
-        &mut vk_bytes.as_slice(),
-        halo2_proofs::SerdeFormat::RawBytes,
-    )
-    .expect("Read vk");
-
-    let pk = keygen_pk(&general_params, vk, &circuit).expect("keygen_pk should not fail");
-    // Create a proof
-    let now = std::time::Instant::now();
-    println!("Begin create proof");
-
-    let mut transcript = Blake2bWrite::<_, G1Affine, Challenge255<_>>::init(vec![]);
-    create_proof::<
-        KZGCommitmentScheme<Bn256>,
-        ProverSHPLONK<'_, Bn256>,
-        Challenge255<G1Affine>,
-        &mut XorShiftRng,
-        Blake2bWrite<Vec<u8>, G1Affine, Challenge255<G1Affine>>,
-        MainCircuit<Fr>,
-    >(
-        &general_params,
-        &pk,
-        &[circuit],
-        &[&[]],
-        rng,
-        &mut transcript,
-    )
-    .expect("proof generation should not fail");
-    let proof = transcript.finalize();
-    println!("Proof created, elapsed {:?}", now.elapsed());
-    println!("{}", hex::encode(&proof));
-    let mut file = File::create("proof.hex").expect("open file");
-    let proof_hex = hex::encode(&proof);
-    file.write(&proof_hex.as_bytes()).expect("write vk hex");
-
-    // Begin verify proof
-    let now = std::time::Instant::now();
-    let mut verifier_transcript = Blake2bRead::<_, G1Affine, Challenge255<_>>::init(&proof[..]);
-    let strategy = SingleStrategy::new(&general_params);
-
-    verify_proof::<
-        KZGCommitmentScheme<Bn256>,
-        VerifierSHPLONK<'_, Bn256>,
-        Challenge255<G1Affine>,
-        Blake2bRead<&[u8], G1Affine, Challenge255<G1Affine>>,
-        SingleStrategy<'_, Bn256>,
-    >(
-        &verifier_params,
-        pk.get_vk(),
-        strategy,
-        &[&[]],
-        &mut verifier_transcript,
-    )
-    .expect("failed to verify circuit");
-    println!("Verify proof, elapsed {:?}", now.elapsed());
+    Ok((trace, Some(program_hash)))
 }
 
-pub fn match_operation(cli: &Cli) {
-    match &cli.command {
-        Commands::Run(_args) => {
-            exec_run();
-        }
-        Commands::Prove(args) => {
-            println!("create proof");
-            let trace = args.trace.as_deref();
-            let steps = get_trace_from_file(trace.unwrap());
-            let mut rng = XorShiftRng::from_seed([
-                0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
-                0xbc, 0xe5,
-            ]);
-
-            prove(steps, &mut rng);
+/// Parses a 32 bytes hash in hex, with or without a `0x` prefix.
+fn parse_hash(text: &str) -> Result<[u8; 32], String> {
+    let mut hash = [0u8; 32];
+    hex::decode_to_slice(text.strip_prefix("0x").unwrap_or(text), &mut hash)
+        .map_err(|e| format!("invalid hash {}: {}", text, e))?;
+    Ok(hash)
+}
+
+/// Reads a file written by [`OutputArgs::write`], in the format of its
+/// extension: raw bytes for `.bin` and hex for `.hex`.
+pub fn read_artifact(path: &str) -> anyhow::Result<Vec<u8>> {
+    let format = OutputFormat::from_path(path)?;
+    let bytes = fs::read(path).map_err(|e| anyhow::anyhow!("read {}: {}", path, e))?;
+    match format {
+        OutputFormat::Binary => Ok(bytes),
+        OutputFormat::Hex => {
+            let text = std::str::from_utf8(&bytes)
+                .map_err(|_| anyhow::anyhow!("decode hex {}: not utf-8", path))?;
+            hex::decode(text.trim()).map_err(|e| anyhow::anyhow!("decode hex {}: {}", path, e))
         }
     }
 }
 
-fn get_trace_from_file(path: &str) -> Vec<Step> {
-    let file = File::open(path).expect("open file");
-    let reader = BufReader::new(file);
-    let trace = serde_json::from_reader(reader).expect("read json");
-    trace
+/// Builds the circuit proving a trace of the program in `2^k` rows.
+pub(crate) fn read_circuit(trace: &str, elf: &str, k: u32) -> anyhow::Result<MainCircuit<Fr>> {
+    let trace = read_trace(&fs::read(trace)?)?;
+    let max_steps = MainCircuit::<Fr>::max_steps(k);
+    anyhow::ensure!(
+        trace.steps.len() <= max_steps,
+        "the trace has {} steps, k {} has room for {}",
+        trace.steps.len(),
+        k,
+        max_steps
+    );
+    let program = get_program_from_file(elf)?;
+    let mut entry_builder = EntryBuilder::with_program(program.clone());
+    entry_builder
+        .build(&trace)
+        .map_err(|e| anyhow::anyhow!("build entries: {}", e))?;
+    Ok(MainCircuit::init(program, entry_builder.entries, max_steps))
+}
+
+/// Decodes the program of an ELF file.
+pub(crate) fn get_program_from_file(path: &str) -> anyhow::Result<Program> {
+    let bytes = fs::read(path).map_err(|e| anyhow::anyhow!("open elf {}: {}", path, e))?;
+    Program::from_elf(&bytes).map_err(|e| anyhow::anyhow!("read elf {}: {:?}", path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_artifacts_in_the_format_of_their_extension() {
+        let dir = std::env::temp_dir().join(format!("bs2-artifact-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| dir.join(name).to_str().unwrap().to_string();
+
+        // Hex digits in a binary file stay raw bytes.
+        fs::write(path("ascii.bin"), b"00ff").unwrap();
+        assert_eq!(read_artifact(&path("ascii.bin")).unwrap(), b"00ff");
+        fs::write(path("key.hex"), "00ff\n").unwrap();
+        assert_eq!(read_artifact(&path("key.hex")).unwrap(), [0x00, 0xff]);
+        // A corrupt hex file is an error, not binary.
+        fs::write(path("corrupt.hex"), "00fg").unwrap();
+        assert!(read_artifact(&path("corrupt.hex")).is_err());
+        fs::write(path("key"), "00ff").unwrap();
+        assert!(read_artifact(&path("key")).is_err());
+
+        let output = OutputArgs {
+            out_dir: dir.to_str().unwrap().to_string(),
+            format: OutputFormat::Hex,
+        };
+        assert!(output.write("key", &Some("key.bin".into()), &[1]).is_err());
+        output.write("key", &Some("out.hex".into()), &[1]).unwrap();
+        assert_eq!(read_artifact(&path("out.hex")).unwrap(), [1]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
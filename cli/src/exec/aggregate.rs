@@ -0,0 +1,230 @@
+use circuits::aggregation::AggregationCircuit;
+use circuits::bundle::ProofBundle;
+use circuits::circuit::CircuitKind;
+use circuits::fingerprint::fingerprint;
+use circuits::transcript::TranscriptKind;
+use circuits::witness::{ProofWitness, VerifierKey};
+use halo2_proofs::{
+    halo2curves::{bn256::Bn256, group::ff::PrimeField},
+    poly::{
+        commitment::Params,
+        kzg::commitment::{ParamsKZG, ParamsVerifierKZG},
+    },
+    SerdeFormat,
+};
+
+use crate::cache::PkCache;
+use crate::command::{read_artifact, AggregateArgs};
+use crate::exec::prove::prove;
+
+/// Proves the aggregation circuit of the proof bundles with the given params,
+/// generating its proving key unless it is cached, and writes its verifying
+/// key and the proof bundle.
+pub(crate) fn exec_aggregate(args: &AggregateArgs) -> anyhow::Result<()> {
+    let params = ParamsKZG::<Bn256>::read(&mut &read_artifact(&args.params)?[..])
+        .map_err(anyhow::Error::msg)?;
+    let verifier_params = read_artifact(&args.verifier_params)?;
+    let inner = ParamsVerifierKZG::<Bn256>::read_shrunk(&mut &verifier_params[..])
+        .map_err(anyhow::Error::msg)?;
+    // The accumulator is checked with the G2 points of the params.
+    anyhow::ensure!(
+        params.g[0] == inner.g[0] && params.g2 == inner.g2 && params.s_g2 == inner.s_g2,
+        "the params and the verifier params of the proofs have different secrets"
+    );
+    let proofs = args
+        .proof
+        .iter()
+        .map(|path| {
+            let bundle = ProofBundle::decode(&read_artifact(path)?).map_err(anyhow::Error::msg)?;
+            anyhow::ensure!(
+                bundle.circuit_id == CircuitKind::Main.id(),
+                "{} is a proof of circuit {}, only {} proofs are aggregated",
+                path,
+                bundle.circuit_id,
+                CircuitKind::Main.id()
+            );
+            Ok(ProofWitness {
+                fingerprint: bundle.fingerprint,
+                transcript: bundle.transcript,
+                instances: bundle.instances,
+                proof: bundle.proof,
+            })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let key = VerifierKey {
+        params: verifier_params,
+        vk: read_artifact(&args.vk)?,
+        prepared: vec![],
+        circuit: CircuitKind::Main,
+    };
+    let circuit = AggregationCircuit::new(&key, &proofs).map_err(anyhow::Error::msg)?;
+
+    let pk = PkCache::new(&args.cache_dir).get_or_keygen(&params, &circuit)?;
+    let fingerprint = fingerprint(pk.get_vk());
+    println!("circuit fingerprint: {}", hex::encode(fingerprint));
+    let instances = circuit.instances();
+    let transcript = TranscriptKind::from(args.transcript);
+    let now = std::time::Instant::now();
+    let proof = prove(&params, &pk, circuit, &instances, transcript)?;
+    println!(
+        "{} proofs aggregated with the {} transcript, elapsed {:?}",
+        proofs.len(),
+        transcript,
+        now.elapsed()
+    );
+
+    let mut vk_bytes = vec![];
+    pk.get_vk()
+        .write(&mut vk_bytes, SerdeFormat::RawBytes)
+        .map_err(anyhow::Error::msg)?;
+    let bundle = ProofBundle::new(
+        CircuitKind::Aggregation.id(),
+        params.k(),
+        fingerprint,
+        transcript,
+        instances
+            .iter()
+            .map(|column| column.iter().map(|value| value.to_repr()).collect())
+            .collect(),
+        proof,
+    );
+    args.output
+        .write("aggregation-vk", &args.aggregation_vk, &vk_bytes)?;
+    args.output.write(
+        "aggregation-proof",
+        &args.aggregation_proof,
+        &bundle.encode(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::{
+        CircuitArg, KeyCellArgs, OutputArgs, OutputFormat, SetupArgs, TranscriptArg, VerifyArgs,
+        WitnessArgs, WitnessFieldArg,
+    };
+    use crate::exec::setup::exec_setup;
+    use crate::exec::verify::exec_verify;
+    use crate::exec::witness::{exec_key_cell, exec_witness};
+    use circuits::verify::{verify_witness, VerifyError, SHRINK_K};
+    use circuits::witness::{witness_args_field, VerifierWitness};
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    const PARAMS: &[u8] = include_bytes!("../../../circuits/testdata/verifier-params.bin");
+    const VK: &[u8] = include_bytes!("../../../circuits/testdata/vk.bin");
+    const PROOF: &[u8] = include_bytes!("../../../circuits/testdata/proof.bin");
+    const PROOF_POSEIDON: &[u8] = include_bytes!("../../../circuits/testdata/proof-poseidon.bin");
+
+    /// Aggregates the Poseidon proof of `circuits/testdata` with params of
+    /// `k = 18`, and verifies the aggregation proof like `verify` and the
+    /// on-chain verifier do. On a single core the keygen takes about 11
+    /// minutes and each of the two proofs 100, with 4 GB of memory:
+    /// `cargo test --release -p bs2-cli aggregation -- --ignored`.
+    #[test]
+    #[ignore]
+    fn verifies_the_aggregation_of_a_testdata_proof() {
+        let dir = std::env::temp_dir().join(format!("bs2-aggregate-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| dir.join(name).to_str().unwrap().to_string();
+        let mut tampered = ProofBundle::decode(PROOF_POSEIDON).unwrap();
+        let last = tampered.proof.len() - 32;
+        tampered.proof.copy_within(..32, last);
+        for (name, data) in [
+            ("verifier-params.bin", PARAMS),
+            ("vk.bin", VK),
+            ("proof.bin", PROOF),
+            ("proof-poseidon.bin", PROOF_POSEIDON),
+            ("tampered.bin", &tampered.encode()[..]),
+        ] {
+            std::fs::write(path(name), data).unwrap();
+        }
+        let output = |dir: &str| OutputArgs {
+            out_dir: path(dir),
+            format: OutputFormat::Binary,
+        };
+        exec_setup(&SetupArgs {
+            k: 18,
+            import: None,
+            insecure: true,
+            params: None,
+            verifier_params: None,
+            shrink_k: SHRINK_K,
+            output: output("k18"),
+        })
+        .unwrap();
+        let aggregate = |proof: &str| {
+            exec_aggregate(&AggregateArgs {
+                params: path("k18/params.bin"),
+                verifier_params: path("verifier-params.bin"),
+                vk: path("vk.bin"),
+                proof: vec![path(proof)],
+                transcript: TranscriptArg::Blake2b,
+                cache_dir: path("cache"),
+                aggregation_vk: None,
+                aggregation_proof: None,
+                output: output(""),
+            })
+        };
+        let verify = || {
+            exec_verify(&VerifyArgs {
+                verifier_params: path("k18/verifier-params.bin"),
+                vk: path("aggregation-vk.bin"),
+                proof: path("aggregation-proof.bin"),
+            })
+        };
+        // Only Poseidon proofs are aggregated.
+        assert!(aggregate("proof.bin").is_err());
+        aggregate("proof-poseidon.bin").unwrap();
+        verify().unwrap();
+
+        // The witness of a verifier pinning the key of the aggregation circuit.
+        exec_key_cell(&KeyCellArgs {
+            verifier_params: path("k18/verifier-params.bin"),
+            vk: path("aggregation-vk.bin"),
+            circuit: CircuitArg::Aggregation,
+            key_cell: None,
+            output: output(""),
+        })
+        .unwrap();
+        exec_witness(&WitnessArgs {
+            verifier_params: None,
+            vk: None,
+            proof: vec![path("aggregation-proof.bin")],
+            field: WitnessFieldArg::Lock,
+            pinned: true,
+            witness: None,
+            output: output(""),
+        })
+        .unwrap();
+        // The key isn't one of the main circuit.
+        assert!(exec_key_cell(&KeyCellArgs {
+            verifier_params: path("k18/verifier-params.bin"),
+            vk: path("aggregation-vk.bin"),
+            circuit: CircuitArg::Main,
+            key_cell: Some("main-key-cell.bin".into()),
+            output: output(""),
+        })
+        .is_err());
+        let read = |name: &str| std::fs::read(path(name)).unwrap();
+        let key = VerifierKey::decode(&read("key-cell.bin")).unwrap();
+        let data = read("witness.bin");
+        let witness =
+            VerifierWitness::decode(witness_args_field(&data, 0).unwrap().unwrap()).unwrap();
+        assert_eq!(witness.circuit, CircuitKind::Aggregation);
+        assert_eq!(verify_witness(&key, &witness), Ok(()));
+        // A limb of the accumulator out of range.
+        let mut malformed = witness;
+        malformed.proofs[0].instances[0][0] = Fr::from_raw([0, 1 << 4, 0, 0]).to_repr();
+        assert_eq!(
+            verify_witness(&key, &malformed),
+            Err(VerifyError::MalformedAccumulator)
+        );
+
+        // The aggregation proof of a proof which doesn't verify is valid, its
+        // accumulator fails the pairing check.
+        aggregate("tampered.bin").unwrap();
+        assert!(verify().is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
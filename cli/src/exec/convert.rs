@@ -0,0 +1,158 @@
+use std::fs::{self, File};
+use std::io::BufWriter;
+
+use runtime::trace::binary;
+
+use crate::command::{read_trace_with_hash, ConvertArgs, JsonTrace};
+
+/// Converts a JSON trace to the binary format, or a binary trace back to JSON.
+/// The header of a binary trace is kept in a [`JsonTrace`], so converting it
+/// back gives the same bytes.
+pub(crate) fn exec_convert(args: &ConvertArgs) -> anyhow::Result<()> {
+    let input = fs::read(&args.input)?;
+    let (trace, header_hash) = read_trace_with_hash(&input)?;
+    // All zeros is the hash of an unknown program.
+    let header_hash = header_hash.filter(|hash| *hash != [0; 32]);
+    let program_hash = match &args.program {
+        Some(path) => {
+            let program_hash = program_hash(&fs::read(path)?);
+            if let Some(hash) = header_hash.filter(|hash| *hash != program_hash) {
+                anyhow::bail!(
+                    "the trace is of program {}, not of {}",
+                    hex::encode(hash),
+                    path
+                );
+            }
+            program_hash
+        }
+        None => header_hash.unwrap_or_default(),
+    };
+
+    if binary::is_binary_trace(&input) {
+        let writer = BufWriter::new(File::create(&args.output)?);
+        let steps = trace.steps.len();
+        let json = JsonTrace {
+            program_hash: hex::encode(program_hash),
+            cycles: trace.cycles,
+            return_value: trace.return_value,
+            steps: trace.steps,
+        };
+        serde_json::to_writer(writer, &json)?;
+        println!(
+            "converted {} steps to json, program hash {}",
+            steps,
+            hex::encode(program_hash)
+        );
+    } else {
+        let bytes = binary::encode(&trace, program_hash)
+            .map_err(|e| anyhow::anyhow!("encode binary trace: {}", e))?;
+        fs::write(&args.output, &bytes)?;
+        println!(
+            "converted {} steps to binary, {} -> {} bytes",
+            trace.steps.len(),
+            input.len(),
+            bytes.len()
+        );
+    }
+    Ok(())
+}
+
+/// Same as the data hash of a cell in CKB.
+pub(crate) fn program_hash(program: &[u8]) -> [u8; 32] {
+    let hash = blake2b_simd::Params::new()
+        .hash_length(32)
+        .personal(b"ckb-default-hash")
+        .hash(program);
+    let mut result = [0u8; 32];
+    result.copy_from_slice(hash.as_bytes());
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::read_trace;
+    use runtime::decoder::decode;
+    use runtime::trace::{Step, Trace};
+
+    const ELF: &[u8] = include_bytes!("../../../circuits/testdata/loop.elf");
+
+    /// Converts `input` to `output` in the directory, returning the bytes of the output.
+    fn convert(dir: &std::path::Path, input: &str, output: &str, program: Option<&str>) -> Vec<u8> {
+        let path = |name: &str| dir.join(name).to_str().unwrap().to_string();
+        exec_convert(&ConvertArgs {
+            input: path(input),
+            output: path(output),
+            program: program.map(path),
+        })
+        .unwrap();
+        fs::read(dir.join(output)).unwrap()
+    }
+
+    #[test]
+    fn converts_without_losing_the_header() {
+        let dir = std::env::temp_dir().join(format!("bs2-convert-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("loop.elf"), ELF).unwrap();
+        // addi a0, a0, 1; c.addi a0, 1
+        let steps: Vec<_> = [(0x10000, 0x00150513, 0), (0x10004, 0x0505, 1)]
+            .into_iter()
+            .map(|(pc, bits, a0)| {
+                let mut registers = vec![0; 32];
+                registers[10] = a0;
+                Step {
+                    global_clk: pc - 0x10000,
+                    pc,
+                    instruction: decode(bits).unwrap(),
+                    registers,
+                }
+            })
+            .collect();
+        fs::write(dir.join("steps.json"), serde_json::to_vec(&steps).unwrap()).unwrap();
+
+        // The list of steps of ckb-debugger has no header, the program hash
+        // comes from the ELF.
+        let binary = convert(&dir, "steps.json", "trace.bin", Some("loop.elf"));
+        let (header, trace) = binary::decode(&binary).unwrap();
+        assert_eq!(header.program_hash, program_hash(ELF));
+        assert_eq!(trace.steps, steps);
+
+        // Binary to JSON and back keeps the header.
+        let mut trace = Trace {
+            cycles: 9,
+            return_value: 3,
+            steps,
+        };
+        let binary = binary::encode(&trace, program_hash(ELF)).unwrap();
+        fs::write(dir.join("trace.bin"), &binary).unwrap();
+        convert(&dir, "trace.bin", "trace.json", None);
+        assert_eq!(
+            read_trace_with_hash(&fs::read(dir.join("trace.json")).unwrap()).unwrap(),
+            (trace.clone(), Some(program_hash(ELF)))
+        );
+        assert_eq!(convert(&dir, "trace.json", "back.bin", None), binary);
+        assert_eq!(
+            convert(&dir, "trace.json", "back.bin", Some("loop.elf")),
+            binary
+        );
+
+        // A trace of another program is not relabeled.
+        trace.cycles = 0;
+        fs::write(
+            dir.join("other.bin"),
+            binary::encode(&trace, [1; 32]).unwrap(),
+        )
+        .unwrap();
+        assert!(exec_convert(&ConvertArgs {
+            input: dir.join("other.bin").to_str().unwrap().to_string(),
+            output: dir.join("other.json").to_str().unwrap().to_string(),
+            program: Some(dir.join("loop.elf").to_str().unwrap().to_string()),
+        })
+        .is_err());
+        assert_eq!(
+            read_trace(&convert(&dir, "other.bin", "other.json", None)).unwrap(),
+            trace
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
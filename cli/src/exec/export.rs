@@ -0,0 +1,35 @@
+use std::fs;
+
+use circuits::main_circuit::MainCircuit;
+use halo2_proofs::{
+    halo2curves::bn256::{Bn256, Fr, G1Affine},
+    plonk::VerifyingKey,
+    poly::kzg::commitment::ParamsVerifierKZG,
+    SerdeFormat,
+};
+
+use crate::command::{read_artifact, ExportVerifierArgs};
+use crate::export::VerifierDescription;
+
+/// Writes the JSON description of the verifier of the verifying key.
+pub(crate) fn exec_export_verifier(args: &ExportVerifierArgs) -> anyhow::Result<()> {
+    let params =
+        ParamsVerifierKZG::<Bn256>::read_shrunk(&mut &read_artifact(&args.verifier_params)?[..])
+            .map_err(anyhow::Error::msg)?;
+    let vk = VerifyingKey::<G1Affine>::read::<_, MainCircuit<Fr>>(
+        &mut &read_artifact(&args.vk)?[..],
+        SerdeFormat::RawBytes,
+    )
+    .map_err(anyhow::Error::msg)?;
+    let description = VerifierDescription::new(&params, &vk);
+    let json = description.to_json();
+    fs::write(&args.verifier, &json)?;
+    println!(
+        "verifier description written to {}, {} bytes, {} gates and {} openings",
+        args.verifier,
+        json.len(),
+        description.gates.len(),
+        description.openings.len()
+    );
+    Ok(())
+}
@@ -0,0 +1,41 @@
+use halo2_proofs::{
+    halo2curves::bn256::{Bn256, Fr},
+    poly::{commitment::Params, kzg::commitment::ParamsKZG},
+    SerdeFormat,
+};
+
+use circuits::fingerprint::fingerprint;
+use circuits::main_circuit::MainCircuit;
+
+use crate::cache::PkCache;
+use crate::command::{get_program_from_file, read_artifact, KeygenArgs};
+
+/// Writes the verifying and proving keys of the circuit of a program,
+/// generating the proving key unless it is cached.
+pub(crate) fn exec_keygen(args: &KeygenArgs) -> anyhow::Result<()> {
+    let params = ParamsKZG::<Bn256>::read(&mut &read_artifact(&args.params)?[..])
+        .map_err(anyhow::Error::msg)?;
+    let program = get_program_from_file(&args.elf)?;
+    let circuit = MainCircuit::<Fr>::new(program, MainCircuit::<Fr>::max_steps(params.k()));
+
+    let pk = PkCache::new(&args.cache_dir).get_or_keygen(&params, &circuit)?;
+    println!(
+        "circuit fingerprint: {}",
+        hex::encode(fingerprint(pk.get_vk()))
+    );
+    println!(
+        "the keys prove the traces of up to {} steps",
+        circuit.max_steps
+    );
+    let mut vk_bytes = vec![];
+    pk.get_vk()
+        .write(&mut vk_bytes, SerdeFormat::RawBytes)
+        .map_err(anyhow::Error::msg)?;
+    let mut pk_bytes = vec![];
+    pk.write(&mut pk_bytes, SerdeFormat::RawBytes)
+        .map_err(anyhow::Error::msg)?;
+
+    args.output.write("vk", &args.vk, &vk_bytes)?;
+    args.output.write("pk", &args.pk, &pk_bytes)?;
+    Ok(())
+}
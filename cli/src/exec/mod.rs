@@ -1 +1,11 @@
+pub mod aggregate;
+pub mod convert;
+pub mod export;
+pub mod keygen;
+pub mod prove;
 pub mod run;
+pub mod setup;
+pub mod stats;
+pub mod validate;
+pub mod verify;
+pub mod witness;
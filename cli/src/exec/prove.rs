@@ -0,0 +1,190 @@
+use circuits::bundle::ProofBundle;
+use circuits::fingerprint::fingerprint;
+use circuits::main_circuit::{MainCircuit, CIRCUIT_ID};
+use circuits::transcript::TranscriptKind;
+use halo2_proofs::halo2curves::group::ff::PrimeField;
+use halo2_proofs::{
+    halo2curves::bn256::{Bn256, Fr, G1Affine},
+    plonk::{create_proof, Circuit, ProvingKey},
+    poly::{
+        commitment::Params,
+        kzg::{
+            commitment::{KZGCommitmentScheme, ParamsKZG},
+            multiopen::ProverSHPLONK,
+        },
+    },
+    transcript::{
+        Blake2bWrite, Challenge255, Keccak256Write, PoseidonWrite, TranscriptWriterBuffer,
+    },
+    SerdeFormat,
+};
+use rand_core::OsRng;
+
+use crate::cache::PkCache;
+use crate::command::{read_artifact, read_circuit, ProveArgs};
+
+/// Proves a trace with the given or the cached proving key and writes the
+/// proof bundle.
+pub(crate) fn exec_prove(args: &ProveArgs) -> anyhow::Result<()> {
+    let mut params = ParamsKZG::<Bn256>::read(&mut &read_artifact(&args.params)?[..])
+        .map_err(anyhow::Error::msg)?;
+    let pk = match &args.pk {
+        Some(path) => Some(
+            ProvingKey::<G1Affine>::read::<_, MainCircuit<Fr>>(
+                &mut &read_artifact(path)?[..],
+                SerdeFormat::RawBytes,
+            )
+            .map_err(anyhow::Error::msg)?,
+        ),
+        None => None,
+    };
+    let k = pk
+        .as_ref()
+        .map_or(params.k(), |pk| pk.get_vk().get_domain().k());
+    anyhow::ensure!(
+        k <= params.k(),
+        "params have k {}, the proving key needs {}",
+        params.k(),
+        k
+    );
+    let mut circuit = read_circuit(&args.trace, &args.elf, k)?;
+    circuit.tx_hash = args.tx_hash.unwrap_or_default();
+    if k < params.k() {
+        params.downsize(k);
+    }
+    let pk = match pk {
+        Some(pk) => {
+            check_pk(&PkCache::new(&args.cache_dir), &params, &pk, &circuit)?;
+            pk
+        }
+        None => PkCache::new(&args.cache_dir).get_or_keygen(&params, &circuit)?,
+    };
+
+    let instances = circuit.instance();
+    let transcript = TranscriptKind::from(args.transcript);
+    let now = std::time::Instant::now();
+    let proof = prove(&params, &pk, circuit, &instances, transcript)?;
+    println!(
+        "proof created with the {} transcript, elapsed {:?}",
+        transcript,
+        now.elapsed()
+    );
+
+    let fingerprint = fingerprint(pk.get_vk());
+    println!("circuit fingerprint: {}", hex::encode(fingerprint));
+    let bundle = ProofBundle::new(
+        CIRCUIT_ID,
+        k,
+        fingerprint,
+        transcript,
+        instances
+            .iter()
+            .map(|column| column.iter().map(|value| value.to_repr()).collect())
+            .collect(),
+        proof,
+    );
+    args.output.write("proof", &args.proof, &bundle.encode())
+}
+
+/// Checks that `pk` is the proving key of `circuit` with `params`: the
+/// fingerprint of its verifying key must be the one the cache records for the
+/// layout of the circuit, which covers the program table and the number of
+/// steps, and the params. Only a circuit without a recorded fingerprint takes
+/// a keygen of its verifying key.
+fn check_pk(
+    cache: &PkCache,
+    params: &ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    circuit: &MainCircuit<Fr>,
+) -> anyhow::Result<()> {
+    let expected = cache.fingerprint(params, circuit)?;
+    let actual = fingerprint(pk.get_vk());
+    anyhow::ensure!(
+        actual == expected,
+        "the proving key has the circuit fingerprint {}, the circuit of the elf and the params \
+         have {}, run keygen for them",
+        hex::encode(actual),
+        hex::encode(expected)
+    );
+    Ok(())
+}
+
+/// Proves `circuit` with the public inputs `instances`, one vector per
+/// instance column, and `transcript`.
+pub(crate) fn prove<C: Circuit<Fr>>(
+    params: &ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    circuit: C,
+    instances: &[Vec<Fr>],
+    transcript: TranscriptKind,
+) -> anyhow::Result<Vec<u8>> {
+    let instances: Vec<&[Fr]> = instances.iter().map(Vec::as_slice).collect();
+    match transcript {
+        TranscriptKind::Blake2b => {
+            prove_with::<Blake2bWrite<_, _, _>, _>(params, pk, circuit, &instances)
+        }
+        TranscriptKind::Keccak256 => {
+            prove_with::<Keccak256Write<_, _, _>, _>(params, pk, circuit, &instances)
+        }
+        TranscriptKind::Poseidon => {
+            prove_with::<PoseidonWrite<_, _, _>, _>(params, pk, circuit, &instances)
+        }
+    }
+}
+
+fn prove_with<T, C>(
+    params: &ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    circuit: C,
+    instances: &[&[Fr]],
+) -> anyhow::Result<Vec<u8>>
+where
+    T: TranscriptWriterBuffer<Vec<u8>, G1Affine, Challenge255<G1Affine>>,
+    C: Circuit<Fr>,
+{
+    let mut transcript = T::init(vec![]);
+    create_proof::<
+        KZGCommitmentScheme<Bn256>,
+        ProverSHPLONK<'_, Bn256>,
+        Challenge255<G1Affine>,
+        OsRng,
+        T,
+        C,
+    >(params, pk, &[circuit], &[instances], OsRng, &mut transcript)
+    .map_err(|e| anyhow::anyhow!("create proof: {:?}", e))?;
+    Ok(transcript.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_proofs::plonk::{keygen_pk, keygen_vk};
+    use runtime::program::Program;
+
+    const ELF: &[u8] = include_bytes!("../../../circuits/testdata/loop.elf");
+
+    #[test]
+    fn rejects_the_proving_key_of_another_circuit() {
+        let dir = std::env::temp_dir().join(format!("bs2-check-pk-{}", std::process::id()));
+        let cache = PkCache::new(&dir);
+        let params = ParamsKZG::<Bn256>::setup(10, OsRng);
+        let max_steps = MainCircuit::<Fr>::max_steps(10);
+        let circuit = MainCircuit::<Fr>::new(Program::from_elf(ELF).unwrap(), max_steps);
+        let vk = keygen_vk(&params, &circuit).unwrap();
+        let pk = keygen_pk(&params, vk, &circuit).unwrap();
+        check_pk(&cache, &params, &pk, &circuit).unwrap();
+        // Against the recorded fingerprint.
+        check_pk(&cache, &params, &pk, &circuit).unwrap();
+
+        // Another program, number of steps or params.
+        let mut program = circuit.program.clone();
+        program.instructions.pop_last();
+        let other_program = MainCircuit::new(program, max_steps);
+        assert!(check_pk(&cache, &params, &pk, &other_program).is_err());
+        let circuit_with_one_step = MainCircuit::new(circuit.program.clone(), 1);
+        assert!(check_pk(&cache, &params, &pk, &circuit_with_one_step).is_err());
+        let other_params = ParamsKZG::<Bn256>::setup(10, OsRng);
+        assert!(check_pk(&cache, &other_params, &pk, &circuit).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,3 +1,115 @@
-pub(crate) fn exec_run() {
-    println!("dry run");
+use std::fs::File;
+use std::io::BufWriter;
+
+use runtime::machine::Machine;
+use runtime::syscalls::{CellType, MockSyscalls, ScriptGroupType};
+use runtime::tx::MockTransaction;
+
+use crate::command::{CellTypeArg, JsonTrace, RunArgs, ScriptGroupTypeArg};
+use crate::exec::convert::program_hash;
+
+/// Runs a script of a mocked transaction with the embedded interpreter and
+/// writes its trace, without ckb-debugger, as a [`JsonTrace`] keeping the
+/// cycles and the exit code of the run and the hash of the ELF.
+pub(crate) fn exec_run(args: &RunArgs) -> anyhow::Result<()> {
+    let tx: MockTransaction = serde_json::from_slice(&std::fs::read(&args.tx)?)?;
+    let group_type = match args.script_group_type {
+        ScriptGroupTypeArg::Lock => ScriptGroupType::Lock,
+        ScriptGroupTypeArg::Type => ScriptGroupType::Type,
+    };
+    let cell_type = match args.cell_type {
+        CellTypeArg::Input => CellType::Input,
+        CellTypeArg::Output => CellType::Output,
+    };
+    let mut syscalls = MockSyscalls::new(tx, group_type, cell_type, args.cell_index)
+        .ok_or_else(|| anyhow::anyhow!("no such script in the transaction"))?;
+
+    let elf = std::fs::read(&args.elf)?;
+    let mut machine = Machine::load_elf(&elf).map_err(|e| anyhow::anyhow!("{}", e))?;
+    let trace = machine
+        .run(&mut syscalls, args.max_steps)
+        .map_err(|e| anyhow::anyhow!("run script: {}", e))?;
+    for message in syscalls.debug.iter() {
+        println!("script debug: {}", message);
+    }
+
+    println!(
+        "run {} steps, exit code {}",
+        trace.steps.len(),
+        trace.return_value as i8
+    );
+    let writer = BufWriter::new(File::create(&args.trace)?);
+    let json = JsonTrace {
+        program_hash: hex::encode(program_hash(&elf)),
+        cycles: trace.cycles,
+        return_value: trace.return_value,
+        steps: trace.steps,
+    };
+    serde_json::to_writer(writer, &json)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::read_trace_with_hash;
+
+    const ELF: &[u8] = include_bytes!("../../../circuits/testdata/loop.elf");
+    const TX: &str = r#"{
+        "mock_info": {
+            "inputs": [
+                {
+                    "input": {"since": "0x0", "previous_output": {"tx_hash": "0x0000000000000000000000000000000000000000000000000000000000000000", "index": "0x0"}},
+                    "output": {"capacity": "0x10", "lock": {"code_hash": "0x0101010101010101010101010101010101010101010101010101010101010101", "hash_type": "data1", "args": "0x"}, "type": null},
+                    "data": "0x"
+                }
+            ],
+            "cell_deps": [],
+            "header_deps": []
+        },
+        "tx": {
+            "version": "0x0",
+            "cell_deps": [],
+            "header_deps": [],
+            "inputs": [],
+            "outputs": [],
+            "outputs_data": [],
+            "witnesses": []
+        }
+    }"#;
+
+    #[test]
+    fn writes_the_cycles_and_exit_code_of_the_run() {
+        let dir = std::env::temp_dir().join(format!("bs2-run-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| dir.join(name).to_str().unwrap().to_string();
+        std::fs::write(path("loop.elf"), ELF).unwrap();
+        std::fs::write(path("tx.json"), TX).unwrap();
+        exec_run(&RunArgs {
+            elf: path("loop.elf"),
+            tx: path("tx.json"),
+            trace: path("trace.json"),
+            script_group_type: ScriptGroupTypeArg::Lock,
+            cell_type: CellTypeArg::Input,
+            cell_index: 0,
+            max_steps: 1000,
+        })
+        .unwrap();
+
+        let tx: MockTransaction = serde_json::from_str(TX).unwrap();
+        let mut syscalls =
+            MockSyscalls::new(tx, ScriptGroupType::Lock, CellType::Input, 0).unwrap();
+        let expected = Machine::load_elf(ELF)
+            .unwrap()
+            .run(&mut syscalls, 1000)
+            .unwrap();
+        let (trace, hash) =
+            read_trace_with_hash(&std::fs::read(path("trace.json")).unwrap()).unwrap();
+        assert_eq!(hash, Some(program_hash(ELF)));
+        assert!(trace.cycles > 0);
+        assert_eq!(trace.cycles, expected.cycles);
+        assert_eq!(trace.return_value, expected.return_value);
+        assert_eq!(trace.steps.len(), expected.steps.len());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -0,0 +1,42 @@
+use std::fs;
+
+use halo2_proofs::{
+    halo2curves::bn256::Bn256,
+    poly::{
+        commitment::{Params, ParamsProver},
+        kzg::commitment::ParamsKZG,
+    },
+};
+
+use crate::command::SetupArgs;
+use crate::srs::read_params;
+
+/// Writes the KZG params of size `2^k` and the verifier params shrunk from them.
+pub(crate) fn exec_setup(args: &SetupArgs) -> anyhow::Result<()> {
+    let params = match &args.import {
+        Some(path) => read_params(&fs::read(path)?, args.k)?,
+        None if args.insecure => ParamsKZG::<Bn256>::unsafe_setup(args.k),
+        None => anyhow::bail!(
+            "refusing to generate params from a known secret, import ceremony params with \
+             --import or pass --insecure"
+        ),
+    };
+    let mut params_bytes = vec![];
+    params
+        .write(&mut params_bytes)
+        .map_err(anyhow::Error::msg)?;
+
+    let mut verifier_params_bytes = vec![];
+    params
+        .verifier_params()
+        .write_shrunk(args.shrink_k, &mut verifier_params_bytes)
+        .map_err(anyhow::Error::msg)?;
+
+    args.output.write("params", &args.params, &params_bytes)?;
+    args.output.write(
+        "verifier-params",
+        &args.verifier_params,
+        &verifier_params_bytes,
+    )?;
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use std::collections::BTreeMap;
+
+use circuits::main_circuit::MainCircuit;
+use circuits::stats::RowStats;
+use entry_builder::builder::EntryBuilder;
+use halo2_proofs::dev::cost::CircuitCost;
+use halo2_proofs::halo2curves::bn256::{Fr, G1};
+use runtime::trace::InstructionType;
+
+use crate::command::{get_program_from_file, read_trace, StatsArgs};
+
+/// Prints what proving a trace costs: the instructions and register/memory
+/// operations it has, the rows of the circuit, the minimal k and proof size.
+pub(crate) fn exec_stats(args: &StatsArgs) -> anyhow::Result<()> {
+    let trace = read_trace(&std::fs::read(&args.trace)?)?;
+    let program = get_program_from_file(&args.elf)?;
+    let mut entry_builder = EntryBuilder::with_program(program.clone());
+    entry_builder
+        .build(&trace)
+        .map_err(|e| anyhow::anyhow!("build entries: {}", e))?;
+    let entries = entry_builder.entries;
+
+    let mut instructions: BTreeMap<&str, usize> = BTreeMap::new();
+    for step in trace.steps.iter() {
+        *instructions
+            .entry(instruction_type_name(step.instruction.opcode.into()))
+            .or_default() += 1;
+    }
+    println!("{} steps", trace.steps.len());
+    for (name, count) in instructions.iter() {
+        println!("  {:<12} {:>10}", name, count);
+    }
+
+    let register_ops = entries.register_ops.values().flat_map(|ops| ops.ops.iter());
+    let (register_reads, register_writes) = register_ops.fold((0, 0), |(r, w), op| {
+        if op.rw.is_read() {
+            (r + 1, w)
+        } else {
+            (r, w + 1)
+        }
+    });
+    let memory_ops = entries.memory_ops.values().flat_map(|ops| ops.ops.iter());
+    let (memory_reads, memory_writes) = memory_ops.fold((0, 0), |(r, w), op| {
+        if op.rw.is_read() {
+            (r + 1, w)
+        } else {
+            (r, w + 1)
+        }
+    });
+    println!(
+        "register ops: {} reads, {} writes",
+        register_reads, register_writes
+    );
+    println!(
+        "memory ops: {} reads, {} writes",
+        memory_reads, memory_writes
+    );
+
+    let circuit = MainCircuit::<Fr>::init(program, entries, trace.steps.len());
+    let rows =
+        RowStats::measure(&circuit).map_err(|e| anyhow::anyhow!("lay out circuit: {:?}", e))?;
+    println!("{:<20} {:>8} {:>10}", "region", "count", "rows");
+    for (name, region) in rows.regions.iter() {
+        println!("  {:<18} {:>8} {:>10}", name, region.count, region.rows);
+    }
+    for (name, table_rows) in rows.tables.iter() {
+        println!("  {:<18} {:>8} {:>10}", name, "table", table_rows);
+    }
+    let k = rows.min_k();
+    println!(
+        "rows: {} used, {} reserved, minimal k {}",
+        rows.rows, rows.reserved_rows, k
+    );
+    println!(
+        "k {} has room for {} steps",
+        k,
+        MainCircuit::<Fr>::max_steps(k)
+    );
+
+    let cost = CircuitCost::<G1, MainCircuit<Fr>>::measure(k as usize, &circuit);
+    let proof_size: usize = cost.proof_size(1).into();
+    println!("projected proof size: {} bytes", proof_size);
+    Ok(())
+}
+
+fn instruction_type_name(instruction_type: InstructionType) -> &'static str {
+    match instruction_type {
+        InstructionType::RType(_) => "RType",
+        InstructionType::IType(_) => "IType",
+        InstructionType::SType(_) => "SType",
+        InstructionType::BType(_) => "BType",
+        InstructionType::UType(_) => "UType",
+        InstructionType::JType(_) => "JType",
+        InstructionType::AType(_) => "AType",
+        InstructionType::FusedType(_) => "FusedType",
+        InstructionType::NoType(_) => "NoType",
+    }
+}
@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+
+use entry_builder::builder::EntryBuilder;
+use entry_builder::entries::Divergence;
+use runtime::trace::{Trace, REGISTER_NAMES};
+
+use crate::command::{get_program_from_file, read_trace, ValidateArgs};
+
+/// Replays a trace through the entry builder and reports every diverging step.
+///
+/// Fails if any step diverges, after printing all of them and the opcode
+/// statistics of the trace.
+pub(crate) fn exec_validate(args: &ValidateArgs) -> anyhow::Result<()> {
+    let trace = read_trace(&std::fs::read(&args.trace)?)?;
+    let mut entry_builder = match &args.elf {
+        Some(path) => EntryBuilder::with_program(get_program_from_file(path)?),
+        None => EntryBuilder::new(),
+    };
+    let divergences = entry_builder
+        .validate(&trace)
+        .map_err(|e| anyhow::anyhow!("replay trace: {}", e))?;
+
+    for divergence in divergences.iter() {
+        print_divergence(divergence);
+    }
+    print_statistics(&trace);
+
+    if divergences.is_empty() {
+        println!("{} steps replayed, no divergence", trace.steps.len());
+        Ok(())
+    } else {
+        anyhow::bail!(
+            "{} of {} steps diverge",
+            divergences.len(),
+            trace.steps.len()
+        )
+    }
+}
+
+fn print_divergence(divergence: &Divergence) {
+    println!(
+        "step {} (clk {}) at pc {:#x}: {}",
+        divergence.index, divergence.global_clk, divergence.pc, divergence.instruction
+    );
+    if let Some(expected_pc) = divergence.expected_pc {
+        println!(
+            "    pc: expected {:#x}, actual {:#x}",
+            expected_pc, divergence.pc
+        );
+    }
+    for (index, expected, actual) in divergence.registers.iter() {
+        println!(
+            "    {} (x{}): expected {:#x}, actual {:#x}",
+            REGISTER_NAMES[*index], index, expected, actual
+        );
+    }
+    if let Some(mismatch) = &divergence.program {
+        match &mismatch.expected {
+            Some(expected) => println!("    program: expected {}", expected),
+            None => println!("    program: no instruction at pc"),
+        }
+    }
+}
+
+fn print_statistics(trace: &Trace) {
+    let mut counts: HashMap<&'static str, usize> = HashMap::new();
+    for step in trace.steps.iter() {
+        *counts.entry(step.instruction.opcode.name()).or_default() += 1;
+    }
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+    println!("{:<26} {:>10} {:>8}", "opcode", "steps", "share");
+    for (name, count) in counts {
+        println!(
+            "{:<26} {:>10} {:>7.2}%",
+            name,
+            count,
+            100.0 * count as f64 / trace.steps.len() as f64
+        );
+    }
+}
@@ -0,0 +1,37 @@
+use circuits::bundle::ProofBundle;
+use circuits::circuit::CircuitKind;
+use circuits::fingerprint::{check_fingerprint, fingerprint};
+use circuits::verify::{read_vk, verify};
+
+use crate::command::{read_artifact, VerifyArgs};
+
+/// Verifies a proof bundle against the verifying key and the verifier params,
+/// the key being one of the circuit of the bundle.
+pub(crate) fn exec_verify(args: &VerifyArgs) -> anyhow::Result<()> {
+    let params = read_artifact(&args.verifier_params)?;
+    let vk_bytes = read_artifact(&args.vk)?;
+    let bundle = ProofBundle::decode(&read_artifact(&args.proof)?).map_err(anyhow::Error::msg)?;
+    let circuit = CircuitKind::from_id(&bundle.circuit_id)
+        .ok_or_else(|| anyhow::anyhow!("proof is for unknown circuit {}", bundle.circuit_id))?;
+    let vk = read_vk(circuit, &vk_bytes).map_err(anyhow::Error::msg)?;
+    check_fingerprint(&fingerprint(&vk), &bundle.fingerprint).map_err(anyhow::Error::msg)?;
+    println!("circuit: {}", circuit);
+    println!("circuit fingerprint: {}", hex::encode(bundle.fingerprint));
+    println!("transcript: {}", bundle.transcript);
+    anyhow::ensure!(
+        bundle.k == vk.get_domain().k(),
+        "proof has k {}, the verifying key has {}",
+        bundle.k,
+        vk.get_domain().k()
+    );
+
+    verify(
+        circuit,
+        &params,
+        &vk_bytes,
+        bundle.transcript,
+        &bundle.instances,
+        &bundle.proof,
+    )
+    .map_err(anyhow::Error::msg)
+}
@@ -0,0 +1,226 @@
+use circuits::bundle::ProofBundle;
+use circuits::circuit::CircuitKind;
+use circuits::verify::read_vk;
+use circuits::witness::{witness_args, ProofWitness, VerifierKey, VerifierWitness};
+use runtime::tx::ckb_hash;
+
+use halo2_proofs::halo2curves::bn256::Bn256;
+use halo2_proofs::poly::kzg::commitment::ParamsVerifierKZG;
+use halo2_proofs::poly::kzg::msm::PreparedG2KZG;
+
+use crate::command::{read_artifact, KeyCellArgs, WitnessArgs, WitnessFieldArg};
+
+/// Writes the `WitnessArgs` carrying the params, the vk, and the public inputs
+/// and the proof of each bundle to the on-chain verifier, which checks all the
+/// proofs at once. The bundles must all be of the same circuit, the one the
+/// vk is read as.
+pub(crate) fn exec_witness(args: &WitnessArgs) -> anyhow::Result<()> {
+    let mut circuit_id = None;
+    let proofs = args
+        .proof
+        .iter()
+        .map(|path| {
+            let bundle = ProofBundle::decode(&read_artifact(path)?).map_err(anyhow::Error::msg)?;
+            let id = circuit_id.get_or_insert_with(|| bundle.circuit_id.clone());
+            anyhow::ensure!(
+                *id == bundle.circuit_id,
+                "{} is a proof of circuit {}, the first proof of {}",
+                path,
+                bundle.circuit_id,
+                id
+            );
+            Ok(ProofWitness {
+                fingerprint: bundle.fingerprint,
+                transcript: bundle.transcript,
+                instances: bundle.instances,
+                proof: bundle.proof,
+            })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let circuit_id = circuit_id.unwrap_or_default();
+    let circuit = CircuitKind::from_id(&circuit_id)
+        .ok_or_else(|| anyhow::anyhow!("proofs are for unknown circuit {}", circuit_id))?;
+    let read = |path: &Option<String>| match path {
+        Some(path) if !args.pinned => read_artifact(path),
+        _ => Ok(vec![]),
+    };
+    let witness = VerifierWitness {
+        params: read(&args.verifier_params)?,
+        vk: read(&args.vk)?,
+        proofs,
+        circuit,
+    };
+    let field = match args.field {
+        WitnessFieldArg::Lock => 0,
+        WitnessFieldArg::InputType => 1,
+        WitnessFieldArg::OutputType => 2,
+    };
+    args.output.write(
+        "witness",
+        &args.witness,
+        &witness_args(field, &witness.encode()),
+    )
+}
+
+/// Writes the data of the key cell of a verifier pinning its key, and prints
+/// the script args pinning it: the hash of the data. The cell also carries the
+/// G2 points of the params prepared for the pairing, which the verifier would
+/// otherwise compute on every run, and the circuit the vk is read as.
+pub(crate) fn exec_key_cell(args: &KeyCellArgs) -> anyhow::Result<()> {
+    let params = read_artifact(&args.verifier_params)?;
+    let mut prepared = vec![];
+    PreparedG2KZG::<Bn256>::new(
+        &ParamsVerifierKZG::<Bn256>::read_shrunk(&mut params.as_slice())
+            .map_err(anyhow::Error::msg)?,
+    )
+    .write(&mut prepared)
+    .map_err(anyhow::Error::msg)?;
+    let vk = read_artifact(&args.vk)?;
+    let circuit = CircuitKind::from(args.circuit);
+    read_vk(circuit, &vk).map_err(|e| anyhow::anyhow!("{} circuit: {}", circuit, e))?;
+    let key = VerifierKey {
+        params,
+        vk,
+        prepared,
+        circuit,
+    }
+    .encode();
+    args.output.write("key-cell", &args.key_cell, &key)?;
+    println!("script args: 0x{}", hex::encode(ckb_hash(&key)));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::{OutputArgs, OutputFormat};
+    use circuits::verify::verify_witness;
+    use circuits::witness::witness_args_field;
+    use runtime::machine::{
+        Machine, Syscalls, REGISTER_A0, REGISTER_A1, REGISTER_A2, REGISTER_A3, REGISTER_A4,
+        REGISTER_A7,
+    };
+    use runtime::syscalls::{CellType, MockSyscalls, ScriptGroupType, SYSCALL_LOAD_WITNESS};
+    use runtime::tx::MockTransaction;
+
+    const PARAMS: &[u8] = include_bytes!("../../../circuits/testdata/verifier-params.bin");
+    const VK: &[u8] = include_bytes!("../../../circuits/testdata/vk.bin");
+    const PROOF: &[u8] = include_bytes!("../../../circuits/testdata/proof.bin");
+
+    /// A transaction spending one cell with the witness of the verifier.
+    fn tx(witness: &[u8]) -> MockTransaction {
+        serde_json::from_str(&format!(
+            r#"{{
+            "mock_info": {{
+                "inputs": [
+                    {{
+                        "input": {{"since": "0x0", "previous_output": {{"tx_hash": "0x0000000000000000000000000000000000000000000000000000000000000000", "index": "0x0"}}}},
+                        "output": {{"capacity": "0x10", "lock": {{"code_hash": "0x0101010101010101010101010101010101010101010101010101010101010101", "hash_type": "data1", "args": "0x"}}, "type": null}},
+                        "data": "0x"
+                    }}
+                ],
+                "cell_deps": [],
+                "header_deps": []
+            }},
+            "tx": {{
+                "version": "0x0",
+                "cell_deps": [],
+                "header_deps": [],
+                "inputs": [],
+                "outputs": [],
+                "outputs_data": [],
+                "witnesses": ["0x{}"]
+            }}
+        }}"#,
+            hex::encode(witness)
+        ))
+        .unwrap()
+    }
+
+    /// Loads the first witness of the group inputs like the verifier does:
+    /// its length first, then the whole of it.
+    fn load_group_witness(syscalls: &mut MockSyscalls) -> Vec<u8> {
+        let mut machine = Machine::new();
+        let mut load = |size| {
+            machine.store(0x100, size, 8).unwrap();
+            for (register, value) in [
+                (REGISTER_A0, 0x200),
+                (REGISTER_A1, 0x100),
+                (REGISTER_A2, 0),
+                (REGISTER_A3, 0),
+                (REGISTER_A4, 0x0100000000000001),
+                (REGISTER_A7, SYSCALL_LOAD_WITNESS),
+            ] {
+                machine.set_register(register, value);
+            }
+            assert_eq!(syscalls.ecall(&mut machine), Ok(None));
+            assert_eq!(machine.register(REGISTER_A0), 0);
+            machine.load(0x100, 8).unwrap()
+        };
+        let size = load(0);
+        load(size);
+        machine.read_bytes(0x200, size).unwrap().to_vec()
+    }
+
+    #[test]
+    fn verifies_the_witness_loaded_from_the_mocked_transaction() {
+        let dir = std::env::temp_dir().join(format!("bs2-witness-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| dir.join(name).to_str().unwrap().to_string();
+        for (name, data) in [
+            ("verifier-params.bin", PARAMS),
+            ("vk.bin", VK),
+            ("proof.bin", PROOF),
+        ] {
+            std::fs::write(path(name), data).unwrap();
+        }
+        let witness = |field| {
+            exec_witness(&WitnessArgs {
+                verifier_params: Some(path("verifier-params.bin")),
+                vk: Some(path("vk.bin")),
+                proof: vec![path("proof.bin")],
+                field,
+                pinned: false,
+                witness: None,
+                output: OutputArgs {
+                    out_dir: dir.to_str().unwrap().into(),
+                    format: OutputFormat::Binary,
+                },
+            })
+            .unwrap();
+            std::fs::read(path("witness.bin")).unwrap()
+        };
+
+        let mut syscalls = MockSyscalls::new(
+            tx(&witness(WitnessFieldArg::Lock)),
+            ScriptGroupType::Lock,
+            CellType::Input,
+            0,
+        )
+        .unwrap();
+        let data = load_group_witness(&mut syscalls);
+        let mut witness_of_lock =
+            VerifierWitness::decode(witness_args_field(&data, 0).unwrap().unwrap()).unwrap();
+        let key = VerifierKey {
+            params: core::mem::take(&mut witness_of_lock.params),
+            vk: core::mem::take(&mut witness_of_lock.vk),
+            prepared: vec![],
+            circuit: witness_of_lock.circuit,
+        };
+        verify_witness(&key, &witness_of_lock).unwrap();
+        witness_of_lock.proofs[0].proof[0] ^= 1;
+        assert!(verify_witness(&key, &witness_of_lock).is_err());
+
+        // A lock reads its witness from the lock field only.
+        let mut syscalls = MockSyscalls::new(
+            tx(&witness(WitnessFieldArg::InputType)),
+            ScriptGroupType::Lock,
+            CellType::Input,
+            0,
+        )
+        .unwrap();
+        let data = load_group_witness(&mut syscalls);
+        assert_eq!(witness_args_field(&data, 0), Ok(None));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
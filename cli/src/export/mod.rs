@@ -0,0 +1,575 @@
+//! Self-contained description of the verifier of a circuit, in JSON, from
+//! which verifiers for other chains than CKB are generated.
+//!
+//! It holds everything `verify_proof` with SHPLONK reads from the verifier
+//! params and the verifying key: the KZG points, the domain, the fixed and
+//! permutation commitments, the gates and lookups as expression trees over
+//! the queried evaluations, and the openings checked by the multiopen
+//! argument. [`verifier::verify`] is the reference verifier consuming it.
+//!
+//! Field elements are big-endian hex strings of their canonical value,
+//! `0x`-prefixed with 64 digits. A G1 point is `{"x", "y"}`, a G2 point
+//! `{"x": [c0, c1], "y": [c0, c1]}` with `c0` the real part, and the point at
+//! infinity has zero coordinates.
+
+pub mod verifier;
+
+use std::collections::BTreeSet;
+use std::convert::TryFrom;
+
+use halo2_proofs::arithmetic::{CurveAffine, FieldExt};
+use halo2_proofs::halo2curves::bn256::{Bn256, Fq, Fq2, Fr, G1Affine, G2Affine};
+use halo2_proofs::halo2curves::group::ff::PrimeField;
+use halo2_proofs::plonk::{self, Any, Column, VerifyingKey};
+use halo2_proofs::poly::kzg::commitment::ParamsVerifierKZG;
+use halo2_proofs::poly::Rotation;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Version of the format, bumped on any change of the meaning of a field.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// The verifier of the circuit of a verifying key, for proofs made with
+/// SHPLONK over BN256 and the public inputs committed by their evaluations.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VerifierDescription {
+    pub version: u32,
+    pub curve: String,
+    /// The domain has `2^k` rows.
+    pub k: u32,
+    /// Generator of the domain and its inverse, `x` rotated by `r` is
+    /// `x * omega^r`.
+    pub omega: Hex<Fr>,
+    pub omega_inv: Hex<Fr>,
+    /// Generator of the cosets of the permutation argument.
+    pub delta: Hex<Fr>,
+    /// First scalar absorbed by the transcript.
+    pub transcript_repr: Hex<Fr>,
+    /// Rows at the end of the domain not constrained by the gates.
+    pub blinding_factors: usize,
+    /// Number of pieces the quotient polynomial is committed in.
+    pub quotient_poly_degree: usize,
+    pub params: KzgParams,
+    pub num_instance_columns: usize,
+    /// Phase of each advice column, its commitment is read in that phase.
+    pub advice_phases: Vec<u8>,
+    /// Phase of each challenge, squeezed at the end of that phase.
+    pub challenge_phases: Vec<u8>,
+    pub advice_queries: Vec<Query>,
+    pub fixed_queries: Vec<Query>,
+    pub instance_queries: Vec<Query>,
+    pub fixed_commitments: Vec<G1>,
+    pub permutation: Permutation,
+    pub lookups: Vec<Lookup>,
+    /// The polynomials of all the gates, each vanishing on the usable rows.
+    pub gates: Vec<Expression>,
+    /// The openings of the multiopen argument, in order.
+    pub openings: Vec<Opening>,
+}
+
+/// The points of the verifier params the verifier uses.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct KzgParams {
+    /// The first point of the SRS, the generator of G1.
+    pub g: G1,
+    pub g2: G2,
+    pub s_g2: G2,
+}
+
+/// An evaluation of the column of the given index at `x` rotated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Query {
+    pub column: usize,
+    pub rotation: i32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Permutation {
+    /// Columns per permutation product, the last one may have fewer.
+    pub chunk_len: usize,
+    pub columns: Vec<PermutationColumn>,
+    /// Commitment of the permutation of each column.
+    pub commitments: Vec<G1>,
+}
+
+/// A column of the permutation, evaluated by the query of the given index
+/// at the current row.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PermutationColumn {
+    Advice(usize),
+    Fixed(usize),
+    Instance(usize),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Lookup {
+    pub input: Vec<Expression>,
+    pub table: Vec<Expression>,
+}
+
+/// An expression over the evaluations of the queries, by query index.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Expression {
+    Constant(Hex<Fr>),
+    Advice(usize),
+    Fixed(usize),
+    Instance(usize),
+    Challenge(usize),
+    Negated(Box<Expression>),
+    Sum(Vec<Expression>),
+    Product(Vec<Expression>),
+    Scaled(Box<Expression>, Hex<Fr>),
+}
+
+/// A commitment opened by the multiopen argument. Openings of the same
+/// commitment at several points are grouped together.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Commitment {
+    /// Of the advice column of the given index.
+    Advice(usize),
+    Fixed(usize),
+    /// Of the permutation of the given column of the permutation.
+    PermutationCommon(usize),
+    /// Of the given permutation product.
+    PermutationProduct(usize),
+    LookupPermutedInput(usize),
+    LookupPermutedTable(usize),
+    LookupProduct(usize),
+    /// The pieces of the quotient polynomial, recombined with powers of
+    /// `x^n`.
+    VanishingH,
+    VanishingRandom,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Opening {
+    pub commitment: Commitment,
+    pub rotation: i32,
+}
+
+impl VerifierDescription {
+    /// Describes the verifier of the circuit of `vk` with `params`.
+    pub fn new(params: &ParamsVerifierKZG<Bn256>, vk: &VerifyingKey<G1Affine>) -> Self {
+        let cs = vk.cs();
+        let domain = vk.get_domain();
+        let queries = |queries: Vec<(usize, Rotation)>| {
+            queries
+                .into_iter()
+                .map(|(column, rotation)| Query {
+                    column,
+                    rotation: rotation.0,
+                })
+                .collect::<Vec<_>>()
+        };
+        let advice_queries = queries(
+            cs.advice_queries()
+                .iter()
+                .map(|(column, rotation)| (column.index(), *rotation))
+                .collect(),
+        );
+        let fixed_queries = queries(
+            cs.fixed_queries()
+                .iter()
+                .map(|(column, rotation)| (column.index(), *rotation))
+                .collect(),
+        );
+        let instance_queries = queries(
+            cs.instance_queries()
+                .iter()
+                .map(|(column, rotation)| (column.index(), *rotation))
+                .collect(),
+        );
+        let current = |queries: &[Query], column: &Column<Any>| {
+            queries
+                .iter()
+                .position(|query| query.column == column.index() && query.rotation == 0)
+                .expect("permutation columns are queried at the current row")
+        };
+        let columns = cs
+            .permutation()
+            .get_columns()
+            .iter()
+            .map(|column| match column.column_type() {
+                Any::Advice(_) => PermutationColumn::Advice(current(&advice_queries, column)),
+                Any::Fixed => PermutationColumn::Fixed(current(&fixed_queries, column)),
+                Any::Instance => PermutationColumn::Instance(current(&instance_queries, column)),
+            })
+            .collect::<Vec<_>>();
+        let permutation = Permutation {
+            chunk_len: cs.degree() - 2,
+            columns,
+            commitments: vk
+                .permutation()
+                .commitments()
+                .iter()
+                .copied()
+                .map(G1)
+                .collect(),
+        };
+        let lookups = cs
+            .lookups()
+            .iter()
+            .map(|lookup| Lookup {
+                input: lookup.input_expressions().iter().map(expression).collect(),
+                table: lookup.table_expressions().iter().map(expression).collect(),
+            })
+            .collect::<Vec<_>>();
+        let gates = cs
+            .gates()
+            .iter()
+            .flat_map(|gate| gate.polynomials().iter().map(expression))
+            .collect();
+
+        let mut description = Self {
+            version: FORMAT_VERSION,
+            curve: "bn256".into(),
+            k: domain.k(),
+            omega: Hex(domain.get_omega()),
+            omega_inv: Hex(domain.get_omega_inv()),
+            delta: Hex(Fr::DELTA),
+            transcript_repr: Hex(vk.transcript_repr()),
+            blinding_factors: cs.blinding_factors(),
+            quotient_poly_degree: domain.get_quotient_poly_degree(),
+            params: KzgParams {
+                g: G1(params.g[0]),
+                g2: G2(params.g2),
+                s_g2: G2(params.s_g2),
+            },
+            num_instance_columns: cs.num_instance_columns(),
+            advice_phases: cs.advice_column_phase(),
+            challenge_phases: cs.challenge_phase(),
+            advice_queries,
+            fixed_queries,
+            instance_queries,
+            fixed_commitments: vk.fixed_commitments().iter().copied().map(G1).collect(),
+            permutation,
+            lookups,
+            gates,
+            openings: vec![],
+        };
+        description.openings = description.layout_openings();
+        description
+    }
+
+    /// Parses and checks a description.
+    pub fn from_json(bytes: &[u8]) -> anyhow::Result<Self> {
+        let description: Self = serde_json::from_slice(bytes)?;
+        description.check()?;
+        Ok(description)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("serialize verifier description")
+    }
+
+    /// Number of permutation products, one per chunk of columns.
+    pub fn permutation_products(&self) -> usize {
+        self.permutation
+            .columns
+            .chunks(self.permutation.chunk_len)
+            .count()
+    }
+
+    /// The openings in the order of `verify_proof`: the advice queries, the
+    /// permutation products at `x`, `x * omega` and, but for the last one,
+    /// the last usable row, the lookups, the fixed queries, the permutations
+    /// and the vanishing argument.
+    fn layout_openings(&self) -> Vec<Opening> {
+        let opening = |commitment, rotation| Opening {
+            commitment,
+            rotation,
+        };
+        let last = self.last_rotation();
+        let products = self.permutation_products();
+        let mut openings = vec![];
+        openings.extend(
+            self.advice_queries
+                .iter()
+                .map(|query| opening(Commitment::Advice(query.column), query.rotation)),
+        );
+        for i in 0..products {
+            openings.push(opening(Commitment::PermutationProduct(i), 0));
+            openings.push(opening(Commitment::PermutationProduct(i), 1));
+        }
+        for i in (0..products).rev().skip(1) {
+            openings.push(opening(Commitment::PermutationProduct(i), last));
+        }
+        for i in 0..self.lookups.len() {
+            openings.push(opening(Commitment::LookupProduct(i), 0));
+            openings.push(opening(Commitment::LookupPermutedInput(i), 0));
+            openings.push(opening(Commitment::LookupPermutedTable(i), 0));
+            openings.push(opening(Commitment::LookupPermutedInput(i), -1));
+            openings.push(opening(Commitment::LookupProduct(i), 1));
+        }
+        openings.extend(
+            self.fixed_queries
+                .iter()
+                .map(|query| opening(Commitment::Fixed(query.column), query.rotation)),
+        );
+        openings.extend(
+            (0..self.permutation.commitments.len())
+                .map(|i| opening(Commitment::PermutationCommon(i), 0)),
+        );
+        openings.push(opening(Commitment::VanishingH, 0));
+        openings.push(opening(Commitment::VanishingRandom, 0));
+        openings
+    }
+
+    /// Rotation of the last usable row.
+    fn last_rotation(&self) -> i32 {
+        -(self.blinding_factors as i32 + 1)
+    }
+
+    /// Checks that the indices of the description are in range and that it
+    /// only opens commitments at points the proof has evaluations at.
+    fn check(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.version == FORMAT_VERSION && self.curve == "bn256",
+            "unsupported verifier description version {} on {}",
+            self.version,
+            self.curve
+        );
+        anyhow::ensure!(
+            self.k < Fr::S && self.quotient_poly_degree > 0 && self.permutation.chunk_len > 0,
+            "invalid domain"
+        );
+        let phases = self.advice_phases.iter().max().copied().unwrap_or_default();
+        anyhow::ensure!(
+            self.challenge_phases.iter().all(|phase| *phase <= phases),
+            "challenge squeezed after the last phase"
+        );
+        let num_advice_columns = self.advice_phases.len();
+        let columns = [
+            (&self.advice_queries, num_advice_columns),
+            (&self.fixed_queries, self.fixed_commitments.len()),
+            (&self.instance_queries, self.num_instance_columns),
+        ];
+        for (queries, num_columns) in columns {
+            anyhow::ensure!(
+                queries.iter().all(|query| query.column < num_columns),
+                "query of a missing column"
+            );
+        }
+        for column in self.permutation.columns.iter() {
+            let (queries, index) = match *column {
+                PermutationColumn::Advice(index) => (&self.advice_queries, index),
+                PermutationColumn::Fixed(index) => (&self.fixed_queries, index),
+                PermutationColumn::Instance(index) => (&self.instance_queries, index),
+            };
+            anyhow::ensure!(
+                queries.get(index).map(|query| query.rotation) == Some(0),
+                "permutation column not queried at the current row"
+            );
+        }
+        anyhow::ensure!(
+            self.permutation.columns.len() == self.permutation.commitments.len(),
+            "a permutation commitment per column is expected"
+        );
+        for lookup in self.lookups.iter() {
+            anyhow::ensure!(
+                lookup.input.len() == lookup.table.len(),
+                "lookup input and table of different widths"
+            );
+        }
+        let expressions = self.gates.iter().chain(
+            self.lookups
+                .iter()
+                .flat_map(|lookup| lookup.input.iter().chain(lookup.table.iter())),
+        );
+        for expression in expressions {
+            self.check_expression(expression)?;
+        }
+        let points = self
+            .openings
+            .iter()
+            .map(|opening| (opening.commitment, opening.rotation))
+            .collect::<BTreeSet<_>>();
+        anyhow::ensure!(
+            points.len() == self.openings.len(),
+            "commitment opened twice at the same point"
+        );
+        for opening in self.openings.iter() {
+            anyhow::ensure!(
+                self.evaluated(opening),
+                "opening of {:?} at {} without evaluation",
+                opening.commitment,
+                opening.rotation
+            );
+        }
+        Ok(())
+    }
+
+    fn check_expression(&self, expression: &Expression) -> anyhow::Result<()> {
+        let in_range = match expression {
+            Expression::Constant(_) => true,
+            Expression::Advice(index) => *index < self.advice_queries.len(),
+            Expression::Fixed(index) => *index < self.fixed_queries.len(),
+            Expression::Instance(index) => *index < self.instance_queries.len(),
+            Expression::Challenge(index) => *index < self.challenge_phases.len(),
+            Expression::Negated(expression) | Expression::Scaled(expression, _) => {
+                return self.check_expression(expression)
+            }
+            Expression::Sum(terms) | Expression::Product(terms) => {
+                anyhow::ensure!(!terms.is_empty(), "empty sum or product");
+                return terms
+                    .iter()
+                    .try_for_each(|term| self.check_expression(term));
+            }
+        };
+        anyhow::ensure!(in_range, "expression of a missing query: {:?}", expression);
+        Ok(())
+    }
+
+    /// Whether the proof holds the evaluation of `opening`.
+    fn evaluated(&self, opening: &Opening) -> bool {
+        let query = |queries: &[Query], column| {
+            queries
+                .iter()
+                .any(|query| query.column == column && query.rotation == opening.rotation)
+        };
+        let products = self.permutation_products();
+        match (opening.commitment, opening.rotation) {
+            (Commitment::Advice(column), _) => query(&self.advice_queries, column),
+            (Commitment::Fixed(column), _) => query(&self.fixed_queries, column),
+            (Commitment::PermutationCommon(i), 0) => i < self.permutation.commitments.len(),
+            (Commitment::PermutationProduct(i), 0 | 1) => i < products,
+            (Commitment::PermutationProduct(i), rotation) => {
+                rotation == self.last_rotation() && i + 1 < products
+            }
+            (Commitment::LookupPermutedInput(i), 0 | -1)
+            | (Commitment::LookupPermutedTable(i), 0)
+            | (Commitment::LookupProduct(i), 0 | 1) => i < self.lookups.len(),
+            (Commitment::VanishingH | Commitment::VanishingRandom, 0) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Converts an expression of the constraint system, flattening the nested
+/// sums and products.
+fn expression(source: &plonk::Expression<Fr>) -> Expression {
+    let flatten = |terms: [&plonk::Expression<Fr>; 2], sum: bool| {
+        terms
+            .into_iter()
+            .flat_map(|term| match (expression(term), sum) {
+                (Expression::Sum(terms), true) | (Expression::Product(terms), false) => terms,
+                (term, _) => vec![term],
+            })
+            .collect()
+    };
+    match source {
+        plonk::Expression::Constant(constant) => Expression::Constant(Hex(*constant)),
+        plonk::Expression::Selector(_) => {
+            panic!("virtual selectors are removed during optimization")
+        }
+        plonk::Expression::Fixed(query) => Expression::Fixed(query.index()),
+        plonk::Expression::Advice(query) => Expression::Advice(query.index()),
+        plonk::Expression::Instance(query) => Expression::Instance(query.index()),
+        plonk::Expression::Challenge(challenge) => Expression::Challenge(challenge.index()),
+        plonk::Expression::Negated(a) => Expression::Negated(Box::new(expression(a))),
+        plonk::Expression::Sum(a, b) => Expression::Sum(flatten([a, b], true)),
+        plonk::Expression::Product(a, b) => Expression::Product(flatten([a, b], false)),
+        plonk::Expression::Scaled(a, scalar) => {
+            Expression::Scaled(Box::new(expression(a)), Hex(*scalar))
+        }
+    }
+}
+
+/// A field element, serialized in big-endian hex.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Hex<F>(pub F);
+
+impl<F: PrimeField<Repr = [u8; 32]>> Serialize for Hex<F> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut bytes = self.0.to_repr();
+        bytes.reverse();
+        serializer.serialize_str(&format!("0x{}", hex::encode(bytes)))
+    }
+}
+
+impl<'de, F: PrimeField<Repr = [u8; 32]>> Deserialize<'de> for Hex<F> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        let mut bytes = [0u8; 32];
+        text.strip_prefix("0x")
+            .and_then(|digits| hex::decode_to_slice(digits, &mut bytes).ok())
+            .ok_or_else(|| D::Error::custom(format!("invalid field element {}", text)))?;
+        bytes.reverse();
+        Option::from(F::from_repr(bytes))
+            .map(Hex)
+            .ok_or_else(|| D::Error::custom(format!("non canonical field element {}", text)))
+    }
+}
+
+/// A G1 point, checked to be on the curve when deserialized.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "G1Coordinates", into = "G1Coordinates")]
+pub struct G1(pub G1Affine);
+
+#[derive(Serialize, Deserialize)]
+struct G1Coordinates {
+    x: Hex<Fq>,
+    y: Hex<Fq>,
+}
+
+impl From<G1> for G1Coordinates {
+    fn from(point: G1) -> Self {
+        Self {
+            x: Hex(point.0.x),
+            y: Hex(point.0.y),
+        }
+    }
+}
+
+impl TryFrom<G1Coordinates> for G1 {
+    type Error = String;
+
+    fn try_from(coordinates: G1Coordinates) -> Result<Self, Self::Error> {
+        let point = G1Affine {
+            x: coordinates.x.0,
+            y: coordinates.y.0,
+        };
+        bool::from(point.is_on_curve())
+            .then_some(G1(point))
+            .ok_or_else(|| "G1 point not on the curve".into())
+    }
+}
+
+/// A G2 point, checked to be on the curve when deserialized.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "G2Coordinates", into = "G2Coordinates")]
+pub struct G2(pub G2Affine);
+
+#[derive(Serialize, Deserialize)]
+struct G2Coordinates {
+    x: [Hex<Fq>; 2],
+    y: [Hex<Fq>; 2],
+}
+
+impl From<G2> for G2Coordinates {
+    fn from(point: G2) -> Self {
+        Self {
+            x: [Hex(point.0.x.c0), Hex(point.0.x.c1)],
+            y: [Hex(point.0.y.c0), Hex(point.0.y.c1)],
+        }
+    }
+}
+
+impl TryFrom<G2Coordinates> for G2 {
+    type Error = String;
+
+    fn try_from(coordinates: G2Coordinates) -> Result<Self, Self::Error> {
+        let [x0, x1] = coordinates.x;
+        let [y0, y1] = coordinates.y;
+        let point = G2Affine {
+            x: Fq2 { c0: x0.0, c1: x1.0 },
+            y: Fq2 { c0: y0.0, c1: y1.0 },
+        };
+        bool::from(point.is_on_curve())
+            .then_some(G2(point))
+            .ok_or_else(|| "G2 point not on the curve".into())
+    }
+}
@@ -0,0 +1,652 @@
+//! Reference verifier of a [`VerifierDescription`], following `verify_proof`
+//! with SHPLONK step by step with nothing but the description, the proof
+//! and the public inputs. Verifiers generated from the description compute
+//! the same.
+//!
+//! The proof is read from the transcript in this order, challenges being
+//! squeezed in between:
+//!
+//! 1. absorbed: `transcript_repr`, then the public inputs column by column;
+//! 2. per phase, the advice commitments of the phase, then its challenges;
+//! 3. `theta`, the permuted input and table commitments of each lookup;
+//! 4. `beta`, `gamma`, the permutation products, the lookup products;
+//! 5. the vanishing random commitment, `y`, the `quotient_poly_degree`
+//!    pieces of the quotient, `x`;
+//! 6. the advice and fixed evaluations by query, the vanishing random
+//!    evaluation, the permutation evaluations by column, for each
+//!    permutation product its evaluations at `x`, `x * omega` and, but for
+//!    the last one, the last usable row, and for each lookup the product
+//!    at `x` and `x * omega`, the permuted input at `x` and `x / omega` and
+//!    the permuted table at `x`;
+//! 7. SHPLONK: `y`, `v`, `h1`, `u`, `h2`.
+
+use std::collections::BTreeSet;
+
+use circuits::transcript::TranscriptKind;
+use circuits::verify::VerifyError;
+use halo2_proofs::arithmetic::Field;
+use halo2_proofs::halo2curves::bn256::{Bn256, Fr, G1Affine, G2Prepared, G1};
+use halo2_proofs::halo2curves::group::ff::PrimeField;
+use halo2_proofs::halo2curves::group::prime::PrimeCurveAffine;
+use halo2_proofs::halo2curves::group::{Curve, Group};
+use halo2_proofs::halo2curves::pairing::{MillerLoopResult, MultiMillerLoop};
+use halo2_proofs::io;
+use halo2_proofs::transcript::{
+    Blake2bRead, Challenge255, EncodedChallenge, Keccak256Read, PoseidonRead, TranscriptReadBuffer,
+};
+
+use super::{Commitment, Expression, PermutationColumn, VerifierDescription};
+
+/// Verifies `proof`, made with `transcript`, for the public inputs
+/// `instances` against a checked description.
+pub fn verify(
+    description: &VerifierDescription,
+    transcript: TranscriptKind,
+    instances: &[Vec<[u8; 32]>],
+    proof: &[u8],
+) -> Result<(), VerifyError> {
+    let instances = instances
+        .iter()
+        .map(|column| {
+            column
+                .iter()
+                .map(|repr| Option::<Fr>::from(Fr::from_repr(*repr)))
+                .collect::<Option<Vec<_>>>()
+        })
+        .collect::<Option<Vec<_>>>()
+        .ok_or(VerifyError::InstanceMismatch)?;
+    if instances.len() != description.num_instance_columns {
+        return Err(VerifyError::InstanceMismatch);
+    }
+    let valid = match transcript {
+        TranscriptKind::Blake2b => {
+            verify_with::<Blake2bRead<_, _, _>>(description, &instances, proof)
+        }
+        TranscriptKind::Keccak256 => {
+            verify_with::<Keccak256Read<_, _, _>>(description, &instances, proof)
+        }
+        TranscriptKind::Poseidon => {
+            verify_with::<PoseidonRead<_, _, _>>(description, &instances, proof)
+        }
+    }
+    .map_err(|_| VerifyError::MalformedProof)?;
+    if valid {
+        Ok(())
+    } else {
+        Err(VerifyError::PairingFailure)
+    }
+}
+
+/// The commitments and evaluations read from a proof.
+#[derive(Default)]
+struct Proof {
+    advice: Vec<G1Affine>,
+    lookup_permuted: Vec<(G1Affine, G1Affine)>,
+    permutation_products: Vec<G1Affine>,
+    lookup_products: Vec<G1Affine>,
+    vanishing_random: G1Affine,
+    vanishing_h: Vec<G1Affine>,
+    advice_evals: Vec<Fr>,
+    fixed_evals: Vec<Fr>,
+    instance_evals: Vec<Fr>,
+    vanishing_random_eval: Fr,
+    permutation_evals: Vec<Fr>,
+    /// At `x`, `x * omega` and the last usable row.
+    permutation_product_evals: Vec<(Fr, Fr, Option<Fr>)>,
+    /// The product at `x` and `x * omega`, the permuted input at `x` and
+    /// `x / omega` and the permuted table at `x`.
+    lookup_evals: Vec<[Fr; 5]>,
+}
+
+/// The challenges of the plonk part.
+struct Challenges {
+    challenges: Vec<Fr>,
+    theta: Fr,
+    beta: Fr,
+    gamma: Fr,
+    y: Fr,
+    x: Fr,
+}
+
+fn verify_with<'a, T>(
+    description: &VerifierDescription,
+    instances: &[Vec<Fr>],
+    proof: &'a [u8],
+) -> io::Result<bool>
+where
+    T: TranscriptReadBuffer<&'a [u8], G1Affine, Challenge255<G1Affine>>,
+{
+    let transcript = &mut T::init(proof);
+    let squeeze = |transcript: &mut T| transcript.squeeze_challenge().get_scalar();
+    let read_points = |transcript: &mut T, n: usize| {
+        (0..n)
+            .map(|_| transcript.read_point())
+            .collect::<io::Result<Vec<_>>>()
+    };
+    let read_scalars = |transcript: &mut T, n: usize| {
+        (0..n)
+            .map(|_| transcript.read_scalar())
+            .collect::<io::Result<Vec<_>>>()
+    };
+    let mut proof = Proof::default();
+
+    transcript.common_scalar(description.transcript_repr.0)?;
+    for value in instances.iter().flatten() {
+        transcript.common_scalar(*value)?;
+    }
+
+    let phases = description
+        .advice_phases
+        .iter()
+        .max()
+        .copied()
+        .unwrap_or_default();
+    proof.advice = vec![G1Affine::identity(); description.advice_phases.len()];
+    let mut challenges = vec![Fr::zero(); description.challenge_phases.len()];
+    for current in 0..=phases {
+        for (phase, commitment) in description
+            .advice_phases
+            .iter()
+            .zip(proof.advice.iter_mut())
+        {
+            if *phase == current {
+                *commitment = transcript.read_point()?;
+            }
+        }
+        for (phase, challenge) in description
+            .challenge_phases
+            .iter()
+            .zip(challenges.iter_mut())
+        {
+            if *phase == current {
+                *challenge = squeeze(transcript);
+            }
+        }
+    }
+
+    let theta = squeeze(transcript);
+    for _ in description.lookups.iter() {
+        let input = transcript.read_point()?;
+        proof
+            .lookup_permuted
+            .push((input, transcript.read_point()?));
+    }
+    let beta = squeeze(transcript);
+    let gamma = squeeze(transcript);
+    proof.permutation_products = read_points(transcript, description.permutation_products())?;
+    proof.lookup_products = read_points(transcript, description.lookups.len())?;
+    proof.vanishing_random = transcript.read_point()?;
+    let y = squeeze(transcript);
+    proof.vanishing_h = read_points(transcript, description.quotient_poly_degree)?;
+    let x = squeeze(transcript);
+
+    proof.advice_evals = read_scalars(transcript, description.advice_queries.len())?;
+    proof.fixed_evals = read_scalars(transcript, description.fixed_queries.len())?;
+    proof.vanishing_random_eval = transcript.read_scalar()?;
+    proof.permutation_evals = read_scalars(transcript, description.permutation.commitments.len())?;
+    for i in 0..proof.permutation_products.len() {
+        let eval = transcript.read_scalar()?;
+        let next_eval = transcript.read_scalar()?;
+        let last_eval = if i + 1 < proof.permutation_products.len() {
+            Some(transcript.read_scalar()?)
+        } else {
+            None
+        };
+        proof
+            .permutation_product_evals
+            .push((eval, next_eval, last_eval));
+    }
+    for _ in description.lookups.iter() {
+        let evals = read_scalars(transcript, 5)?;
+        proof.lookup_evals.push(evals.try_into().unwrap());
+    }
+
+    let verifier = Verifier { description };
+    let xn = x.pow_vartime([1u64 << description.k]);
+    proof.instance_evals = verifier.instance_evals(instances, x, xn);
+    let challenges = Challenges {
+        challenges,
+        theta,
+        beta,
+        gamma,
+        y,
+        x,
+    };
+    let expected_h = verifier.expected_h(&proof, &challenges, xn);
+
+    let shplonk_y = squeeze(transcript);
+    let v = squeeze(transcript);
+    let h1 = transcript.read_point()?;
+    let u = squeeze(transcript);
+    let h2 = transcript.read_point()?;
+
+    let (left, right) = verifier.multiopen(&proof, x, xn, expected_h, shplonk_y, v, u, h1, h2);
+    let params = &description.params;
+    Ok(bool::from(
+        Bn256::multi_miller_loop(&[
+            (&left, &G2Prepared::from(params.s_g2.0)),
+            (&right, &G2Prepared::from(-params.g2.0)),
+        ])
+        .final_exponentiation()
+        .is_identity(),
+    ))
+}
+
+struct Verifier<'a> {
+    description: &'a VerifierDescription,
+}
+
+impl Verifier<'_> {
+    /// `x` rotated by `rotation` rows.
+    fn rotate(&self, x: Fr, rotation: i32) -> Fr {
+        let (omega, exponent) = if rotation >= 0 {
+            (self.description.omega.0, rotation as u64)
+        } else {
+            (self.description.omega_inv.0, rotation.unsigned_abs() as u64)
+        };
+        x * omega.pow_vartime([exponent])
+    }
+
+    /// The Lagrange polynomial of the row `i`, `omega^i (x^n - 1) / (n (x - omega^i))`.
+    fn lagrange(&self, i: i32, x: Fr, xn: Fr) -> Fr {
+        let point = self.rotate(Fr::one(), i);
+        let n = Fr::from(1u64 << self.description.k);
+        point * (xn - Fr::one()) * (n * (x - point)).invert().unwrap()
+    }
+
+    /// The evaluations of the instance queries, from the public inputs.
+    fn instance_evals(&self, instances: &[Vec<Fr>], x: Fr, xn: Fr) -> Vec<Fr> {
+        self.description
+            .instance_queries
+            .iter()
+            .map(|query| {
+                instances[query.column]
+                    .iter()
+                    .enumerate()
+                    .map(|(row, value)| *value * self.lagrange(row as i32 - query.rotation, x, xn))
+                    .fold(Fr::zero(), |sum, term| sum + term)
+            })
+            .collect()
+    }
+
+    /// The evaluation of the quotient at `x` the constraints imply: the gates,
+    /// the permutation and the lookups folded with `y`, over the vanishing
+    /// polynomial.
+    fn expected_h(&self, proof: &Proof, challenges: &Challenges, xn: Fr) -> Fr {
+        let description = self.description;
+        let Challenges {
+            theta,
+            beta,
+            gamma,
+            x,
+            ..
+        } = *challenges;
+        let blinding_factors = description.blinding_factors as i32;
+        let l_last = self.lagrange(-(blinding_factors + 1), x, xn);
+        let l_blind = (-blinding_factors..0)
+            .map(|i| self.lagrange(i, x, xn))
+            .fold(Fr::zero(), |sum, l| sum + l);
+        let l_0 = self.lagrange(0, x, xn);
+        let active_rows = Fr::one() - (l_last + l_blind);
+
+        let mut expressions = vec![];
+        for gate in description.gates.iter() {
+            expressions.push(evaluate(gate, proof, &challenges.challenges));
+        }
+
+        let sets = &proof.permutation_product_evals;
+        if let Some((first, _, _)) = sets.first() {
+            expressions.push(l_0 * (Fr::one() - first));
+        }
+        if let Some((last, _, _)) = sets.last() {
+            expressions.push(l_last * (last.square() - last));
+        }
+        for (set, previous) in sets.iter().skip(1).zip(sets.iter()) {
+            expressions.push(l_0 * (set.0 - previous.2.unwrap()));
+        }
+        let permutation = &description.permutation;
+        for (chunk, ((set, columns), evals)) in sets
+            .iter()
+            .zip(permutation.columns.chunks(permutation.chunk_len))
+            .zip(proof.permutation_evals.chunks(permutation.chunk_len))
+            .enumerate()
+        {
+            let mut left = set.1;
+            let mut right = set.0;
+            let mut delta = beta
+                * x
+                * description
+                    .delta
+                    .0
+                    .pow_vartime([(chunk * permutation.chunk_len) as u64]);
+            for (column, permutation_eval) in columns.iter().zip(evals.iter()) {
+                let eval = match *column {
+                    PermutationColumn::Advice(query) => proof.advice_evals[query],
+                    PermutationColumn::Fixed(query) => proof.fixed_evals[query],
+                    PermutationColumn::Instance(query) => proof.instance_evals[query],
+                };
+                left *= eval + beta * permutation_eval + gamma;
+                right *= eval + delta + gamma;
+                delta *= description.delta.0;
+            }
+            expressions.push((left - right) * active_rows);
+        }
+
+        for (lookup, evals) in description.lookups.iter().zip(proof.lookup_evals.iter()) {
+            let [product, product_next, input, input_inv, table] = *evals;
+            let compress = |expressions: &[Expression]| {
+                expressions
+                    .iter()
+                    .map(|expression| evaluate(expression, proof, &challenges.challenges))
+                    .fold(Fr::zero(), |acc, eval| acc * theta + eval)
+            };
+            let left = product_next * (input + beta) * (table + gamma);
+            let right =
+                product * (compress(&lookup.input) + beta) * (compress(&lookup.table) + gamma);
+            expressions.push(l_0 * (Fr::one() - product));
+            expressions.push(l_last * (product.square() - product));
+            expressions.push((left - right) * active_rows);
+            expressions.push(l_0 * (input - table));
+            expressions.push((input - table) * (input - input_inv) * active_rows);
+        }
+
+        let h = expressions
+            .into_iter()
+            .fold(Fr::zero(), |h, eval| h * challenges.y + eval);
+        h * (xn - Fr::one()).invert().unwrap()
+    }
+
+    /// The commitment and its evaluation at `x` rotated, `None` if the proof
+    /// doesn't hold it.
+    fn opening(
+        &self,
+        proof: &Proof,
+        commitment: Commitment,
+        rotation: i32,
+        xn: Fr,
+        expected_h: Fr,
+    ) -> Option<(G1, Fr)> {
+        let description = self.description;
+        let query = |queries: &[super::Query], column| {
+            queries
+                .iter()
+                .position(|query| query.column == column && query.rotation == rotation)
+        };
+        let last = -(description.blinding_factors as i32 + 1);
+        let (point, eval) = match (commitment, rotation) {
+            (Commitment::Advice(column), _) => (
+                proof.advice[column],
+                proof.advice_evals[query(&description.advice_queries, column)?],
+            ),
+            (Commitment::Fixed(column), _) => (
+                description.fixed_commitments[column].0,
+                proof.fixed_evals[query(&description.fixed_queries, column)?],
+            ),
+            (Commitment::PermutationCommon(i), 0) => (
+                description.permutation.commitments[i].0,
+                proof.permutation_evals[i],
+            ),
+            (Commitment::PermutationProduct(i), rotation) => {
+                let (eval, next_eval, last_eval) = proof.permutation_product_evals[i];
+                let eval = match rotation {
+                    0 => eval,
+                    1 => next_eval,
+                    rotation if rotation == last => last_eval?,
+                    _ => return None,
+                };
+                (proof.permutation_products[i], eval)
+            }
+            (Commitment::LookupPermutedInput(i), 0) => {
+                (proof.lookup_permuted[i].0, proof.lookup_evals[i][2])
+            }
+            (Commitment::LookupPermutedInput(i), -1) => {
+                (proof.lookup_permuted[i].0, proof.lookup_evals[i][3])
+            }
+            (Commitment::LookupPermutedTable(i), 0) => {
+                (proof.lookup_permuted[i].1, proof.lookup_evals[i][4])
+            }
+            (Commitment::LookupProduct(i), 0) => {
+                (proof.lookup_products[i], proof.lookup_evals[i][0])
+            }
+            (Commitment::LookupProduct(i), 1) => {
+                (proof.lookup_products[i], proof.lookup_evals[i][1])
+            }
+            (Commitment::VanishingH, 0) => {
+                let h = proof
+                    .vanishing_h
+                    .iter()
+                    .rev()
+                    .fold(G1::identity(), |acc, piece| acc * xn + piece);
+                return Some((h, expected_h));
+            }
+            (Commitment::VanishingRandom, 0) => {
+                (proof.vanishing_random, proof.vanishing_random_eval)
+            }
+            _ => return None,
+        };
+        Some((point.into(), eval))
+    }
+
+    /// The points `left` and `right` of the pairing check
+    /// `e(left, s_g2) = e(right, g2)` of the SHPLONK multiopen argument.
+    #[allow(clippy::too_many_arguments)]
+    fn multiopen(
+        &self,
+        proof: &Proof,
+        x: Fr,
+        xn: Fr,
+        expected_h: Fr,
+        y: Fr,
+        v: Fr,
+        u: Fr,
+        h1: G1Affine,
+        h2: G1Affine,
+    ) -> (G1Affine, G1Affine) {
+        let openings = &self.description.openings;
+        // The rotations each commitment is opened at, then the commitments
+        // opened at each set of rotations, in the order of the openings.
+        let mut commitments: Vec<(Commitment, BTreeSet<i32>)> = vec![];
+        for opening in openings.iter() {
+            match commitments
+                .iter_mut()
+                .find(|(commitment, _)| *commitment == opening.commitment)
+            {
+                Some((_, rotations)) => {
+                    rotations.insert(opening.rotation);
+                }
+                None => commitments.push((opening.commitment, BTreeSet::from([opening.rotation]))),
+            }
+        }
+        let mut sets: Vec<(BTreeSet<i32>, Vec<Commitment>)> = vec![];
+        for (commitment, rotations) in commitments {
+            match sets.iter_mut().find(|(set, _)| *set == rotations) {
+                Some((_, commitments)) => commitments.push(commitment),
+                None => sets.push((rotations, vec![commitment])),
+            }
+        }
+        let all = openings
+            .iter()
+            .map(|opening| opening.rotation)
+            .collect::<BTreeSet<_>>();
+        let vanishing = |rotations: &mut dyn Iterator<Item = &i32>| {
+            rotations.fold(Fr::one(), |acc, rotation| {
+                acc * (u - self.rotate(x, *rotation))
+            })
+        };
+
+        let (mut z_0, mut z_0_diff_inv) = (Fr::zero(), Fr::zero());
+        let (mut outer, mut r_outer) = (G1::identity(), Fr::zero());
+        let mut power_of_v = Fr::one();
+        for (i, (rotations, commitments)) in sets.iter().enumerate() {
+            let mut z_diff = vanishing(&mut all.difference(rotations));
+            if i == 0 {
+                z_0 = vanishing(&mut rotations.iter());
+                z_0_diff_inv = z_diff.invert().unwrap();
+                z_diff = Fr::one();
+            } else {
+                z_diff *= z_0_diff_inv;
+            }
+            let points = rotations
+                .iter()
+                .map(|rotation| self.rotate(x, *rotation))
+                .collect::<Vec<_>>();
+            let (mut inner, mut r_inner) = (G1::identity(), Fr::zero());
+            let mut power_of_y = Fr::one();
+            for commitment in commitments.iter() {
+                let mut evals = vec![];
+                let mut point = G1::identity();
+                for rotation in rotations.iter() {
+                    let (commitment, eval) = self
+                        .opening(proof, *commitment, *rotation, xn, expected_h)
+                        .expect("openings of a checked description are evaluated");
+                    point = commitment;
+                    evals.push(eval);
+                }
+                inner += point * power_of_y;
+                r_inner += power_of_y * interpolate(&points, &evals, u);
+                power_of_y *= y;
+            }
+            outer += inner * (power_of_v * z_diff);
+            r_outer += power_of_v * r_inner * z_diff;
+            power_of_v *= v;
+        }
+        let g = self.description.params.g.0;
+        let right = outer - g * r_outer - h1 * z_0 + h2 * u;
+        (h2, right.to_affine())
+    }
+}
+
+/// The value of `expression` at `x` from the evaluations of the proof.
+fn evaluate(expression: &Expression, proof: &Proof, challenges: &[Fr]) -> Fr {
+    let evaluate = |expression| evaluate(expression, proof, challenges);
+    match expression {
+        Expression::Constant(constant) => constant.0,
+        Expression::Advice(index) => proof.advice_evals[*index],
+        Expression::Fixed(index) => proof.fixed_evals[*index],
+        Expression::Instance(index) => proof.instance_evals[*index],
+        Expression::Challenge(index) => challenges[*index],
+        Expression::Negated(a) => -evaluate(a),
+        Expression::Sum(terms) => terms.iter().map(evaluate).fold(Fr::zero(), |a, b| a + b),
+        Expression::Product(terms) => terms.iter().map(evaluate).fold(Fr::one(), |a, b| a * b),
+        Expression::Scaled(a, scalar) => evaluate(a) * scalar.0,
+    }
+}
+
+/// The polynomial through `evals` at `points`, evaluated at `u`.
+fn interpolate(points: &[Fr], evals: &[Fr], u: Fr) -> Fr {
+    points
+        .iter()
+        .zip(evals.iter())
+        .enumerate()
+        .map(|(j, (point, eval))| {
+            let (numerator, denominator) = points.iter().enumerate().filter(|(k, _)| *k != j).fold(
+                (Fr::one(), Fr::one()),
+                |(numerator, denominator), (_, other)| {
+                    (numerator * (u - other), denominator * (*point - other))
+                },
+            );
+            *eval * numerator * denominator.invert().unwrap()
+        })
+        .fold(Fr::zero(), |sum, term| sum + term)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::Opening;
+    use circuits::bundle::ProofBundle;
+    use circuits::main_circuit::MainCircuit;
+    use halo2_proofs::plonk::VerifyingKey;
+    use halo2_proofs::poly::kzg::commitment::ParamsVerifierKZG;
+    use halo2_proofs::SerdeFormat;
+
+    const PARAMS: &[u8] = include_bytes!("../../../circuits/testdata/verifier-params.bin");
+    const VK: &[u8] = include_bytes!("../../../circuits/testdata/vk.bin");
+    const PROOF: &[u8] = include_bytes!("../../../circuits/testdata/proof.bin");
+    const PROOF_KECCAK256: &[u8] = include_bytes!("../../../circuits/testdata/proof-keccak256.bin");
+    const PROOF_POSEIDON: &[u8] = include_bytes!("../../../circuits/testdata/proof-poseidon.bin");
+
+    /// The description of the test circuit, through its JSON.
+    fn description() -> VerifierDescription {
+        let params = ParamsVerifierKZG::<Bn256>::read_shrunk(&mut &PARAMS[..]).unwrap();
+        let vk = VerifyingKey::<G1Affine>::read::<_, MainCircuit<Fr>>(
+            &mut &VK[..],
+            SerdeFormat::RawBytes,
+        )
+        .unwrap();
+        let description = VerifierDescription::new(&params, &vk);
+        let parsed = VerifierDescription::from_json(description.to_json().as_bytes()).unwrap();
+        assert_eq!(parsed, description);
+        parsed
+    }
+
+    #[test]
+    fn verifies_each_transcript() {
+        let description = description();
+        for (bundle, transcript) in [
+            (PROOF, TranscriptKind::Blake2b),
+            (PROOF_KECCAK256, TranscriptKind::Keccak256),
+            (PROOF_POSEIDON, TranscriptKind::Poseidon),
+        ] {
+            let bundle = ProofBundle::decode(bundle).unwrap();
+            assert_eq!(bundle.transcript, transcript);
+            assert_eq!(
+                verify(&description, transcript, &bundle.instances, &bundle.proof),
+                Ok(())
+            );
+            // Other challenges than the prover's.
+            let other = match transcript {
+                TranscriptKind::Blake2b => TranscriptKind::Poseidon,
+                _ => TranscriptKind::Blake2b,
+            };
+            assert!(verify(&description, other, &bundle.instances, &bundle.proof).is_err());
+        }
+    }
+
+    #[test]
+    fn rejects_tampered_proof() {
+        let description = description();
+        let bundle = ProofBundle::decode(PROOF).unwrap();
+        let verify = |instances: &[Vec<[u8; 32]>], proof: &[u8]| {
+            verify(&description, bundle.transcript, instances, proof)
+        };
+        // A valid point in place of the last opening proof.
+        let mut bytes = bundle.proof.clone();
+        let last = bytes.len() - 32;
+        bytes.copy_within(..32, last);
+        assert_eq!(
+            verify(&bundle.instances, &bytes),
+            Err(VerifyError::PairingFailure)
+        );
+        assert_eq!(
+            verify(&bundle.instances, &bundle.proof[..bundle.proof.len() / 2]),
+            Err(VerifyError::MalformedProof)
+        );
+        let mut bytes = bundle.proof.clone();
+        bytes[..32].copy_from_slice(&[0xff; 32]);
+        assert_eq!(
+            verify(&bundle.instances, &bytes),
+            Err(VerifyError::MalformedProof)
+        );
+
+        let mut instances = bundle.instances.clone();
+        instances.push(vec![Fr::from(1).to_repr()]);
+        assert_eq!(
+            verify(&instances, &bundle.proof),
+            Err(VerifyError::InstanceMismatch)
+        );
+    }
+
+    #[test]
+    fn rejects_inconsistent_description() {
+        let mut inconsistent = description();
+        inconsistent.openings.push(Opening {
+            commitment: Commitment::VanishingRandom,
+            rotation: 1,
+        });
+        assert!(VerifierDescription::from_json(inconsistent.to_json().as_bytes()).is_err());
+
+        let json = description().to_json().replacen("0x", "0xff", 1);
+        assert!(VerifierDescription::from_json(json.as_bytes()).is_err());
+    }
+}
@@ -1,8 +1,11 @@
 use clap::Parser;
 use command::Cli;
 
+pub mod cache;
 pub mod command;
 mod exec;
+pub mod export;
+pub mod srs;
 
 fn main() {
     let cli = Cli::parse();
@@ -0,0 +1,275 @@
+//! Structured reference strings of powers-of-tau ceremonies, read into the
+//! KZG params of halo2.
+
+use halo2_proofs::arithmetic::{best_multiexp, CurveAffine, FieldExt};
+use halo2_proofs::halo2curves::bn256::{Bn256, Fr, G1Affine, G2Affine};
+use halo2_proofs::halo2curves::group::cofactor::CofactorGroup;
+use halo2_proofs::halo2curves::group::prime::PrimeCurveAffine;
+use halo2_proofs::halo2curves::group::{Curve, GroupEncoding};
+use halo2_proofs::halo2curves::pairing::Engine;
+use halo2_proofs::helpers::SerdeCurveAffine;
+use halo2_proofs::poly::commitment::Params;
+use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+use halo2_proofs::SerdeFormat;
+
+/// Magic bytes of the `.ptau` files of snarkjs.
+const PTAU_MAGIC: &[u8; 4] = b"ptau";
+const PTAU_SECTION_HEADER: u32 = 1;
+const PTAU_SECTION_TAU_G1: u32 = 2;
+const PTAU_SECTION_TAU_G2: u32 = 3;
+/// Base field modulus of BN254, little endian, as found in the `.ptau` header.
+const BN254_Q: [u8; 32] = [
+    0x47, 0xfd, 0x7c, 0xd8, 0x16, 0x8c, 0x20, 0x3c, 0x8d, 0xca, 0x71, 0x68, 0x91, 0x6a, 0x81, 0x97,
+    0x5d, 0x58, 0x81, 0x81, 0xb6, 0x45, 0x50, 0xb8, 0x29, 0xa0, 0x31, 0xe1, 0x72, 0x4e, 0x64, 0x30,
+];
+
+/// Reads ceremony params, either a `.ptau` file or params in the halo2
+/// format, checks them and downsizes them to `2^k` points.
+pub fn read_params(bytes: &[u8], k: u32) -> anyhow::Result<ParamsKZG<Bn256>> {
+    let mut params = if bytes.starts_with(PTAU_MAGIC) {
+        read_ptau(bytes, k)?
+    } else {
+        ParamsKZG::<Bn256>::read(&mut &bytes[..]).map_err(anyhow::Error::msg)?
+    };
+    anyhow::ensure!(
+        params.k() >= k,
+        "params have k {}, {} is required",
+        params.k(),
+        k
+    );
+    check_params(&params, 1 << k)?;
+    // Always derive the lagrange basis from the checked powers, the `.ptau`
+    // powers are read without it.
+    params.downsize(k);
+    Ok(params)
+}
+
+/// Reads the first `2^k` powers of tau of a `.ptau` file, whose points are
+/// stored uncompressed with coordinates in little endian Montgomery form.
+///
+/// The lagrange basis is left empty, [`read_params`] derives it once the
+/// powers are checked.
+fn read_ptau(bytes: &[u8], k: u32) -> anyhow::Result<ParamsKZG<Bn256>> {
+    let mut reader = &bytes[PTAU_MAGIC.len()..];
+    let _version = read_u32(&mut reader)?;
+    let mut sections = vec![];
+    for _ in 0..read_u32(&mut reader)? {
+        let id = read_u32(&mut reader)?;
+        let size = read_u64(&mut reader)? as usize;
+        anyhow::ensure!(size <= reader.len(), "ptau section {} is truncated", id);
+        sections.push((id, &reader[..size]));
+        reader = &reader[size..];
+    }
+    let section = |id: u32| {
+        sections
+            .iter()
+            .find(|(section, _)| *section == id)
+            .map(|(_, data)| *data)
+            .ok_or_else(|| anyhow::anyhow!("ptau section {} is missing", id))
+    };
+
+    let mut header = section(PTAU_SECTION_HEADER)?;
+    let n8 = read_u32(&mut header)? as usize;
+    anyhow::ensure!(
+        n8 == BN254_Q.len() && header.get(..n8) == Some(&BN254_Q[..]),
+        "ptau is not over BN254"
+    );
+    header = &header[n8..];
+    let power = read_u32(&mut header)?;
+    anyhow::ensure!(
+        power >= k,
+        "ptau has 2^{} powers, 2^{} are required",
+        power,
+        k
+    );
+
+    let n = 1usize << k;
+    let mut tau_g1 = section(PTAU_SECTION_TAU_G1)?;
+    let g = (0..n)
+        .map(|_| G1Affine::read(&mut tau_g1, SerdeFormat::RawBytes))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(anyhow::Error::msg)?;
+    let mut tau_g2 = section(PTAU_SECTION_TAU_G2)?;
+    let g2 = G2Affine::read(&mut tau_g2, SerdeFormat::RawBytes).map_err(anyhow::Error::msg)?;
+    let s_g2 = G2Affine::read(&mut tau_g2, SerdeFormat::RawBytes).map_err(anyhow::Error::msg)?;
+
+    Ok(ParamsKZG {
+        k,
+        n: n as u64,
+        g,
+        g_lagrange: vec![],
+        g2,
+        s_g2,
+    })
+}
+
+/// Checks that the first `n` points of `g` are successive powers of the
+/// secret of `s_g2`: `e(g[i + 1], g2) == e(g[i], s_g2)` for every `i`, batched
+/// in a random linear combination whose coefficients are derived from the
+/// points themselves.
+///
+/// The points are read without validation, so they are first checked to be on
+/// the curve, and `s_g2` to be in the subgroup of prime order, which `G2`
+/// isn't. `G1` has cofactor 1.
+pub fn check_params(params: &ParamsKZG<Bn256>, n: usize) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        params.g.len() >= n,
+        "params have {} powers, {} are required",
+        params.g.len(),
+        n
+    );
+    if let Some(i) = params.g[..n]
+        .iter()
+        .position(|point| !bool::from(point.is_on_curve()))
+    {
+        anyhow::bail!("power {} of the params is not on the curve", i);
+    }
+    for (name, point) in [("g2", params.g2), ("s_g2", params.s_g2)] {
+        anyhow::ensure!(
+            bool::from(point.is_on_curve()) && bool::from(point.to_curve().is_torsion_free()),
+            "{} of the params is not in the subgroup of G2",
+            name
+        );
+    }
+    anyhow::ensure!(
+        params.g[0] == G1Affine::generator() && params.g2 == G2Affine::generator(),
+        "params don't start from the generators"
+    );
+    anyhow::ensure!(
+        !bool::from(params.s_g2.is_identity()),
+        "params have no secret in G2"
+    );
+
+    let mut hasher = blake2b_simd::Params::new()
+        .hash_length(64)
+        .personal(b"bs2-srs-check\0\0\0")
+        .to_state();
+    for point in params.g[..n].iter() {
+        hasher.update(point.to_bytes().as_ref());
+    }
+    hasher.update(params.s_g2.to_bytes().as_ref());
+    let mut seed = [0u8; 64];
+    seed.copy_from_slice(hasher.finalize().as_bytes());
+    let r = Fr::from_bytes_wide(&seed);
+
+    let mut coeffs = Vec::with_capacity(n - 1);
+    let mut power = Fr::one();
+    for _ in 1..n {
+        coeffs.push(power);
+        power *= r;
+    }
+    let lhs = best_multiexp(&coeffs, &params.g[1..n]).to_affine();
+    let rhs = best_multiexp(&coeffs, &params.g[..n - 1]).to_affine();
+    anyhow::ensure!(
+        Bn256::pairing(&lhs, &params.g2) == Bn256::pairing(&rhs, &params.s_g2),
+        "params fail the pairing check, they are not powers of one secret"
+    );
+    Ok(())
+}
+
+fn read_u32(reader: &mut &[u8]) -> anyhow::Result<u32> {
+    let mut bytes = [0u8; 4];
+    std::io::Read::read_exact(reader, &mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut &[u8]) -> anyhow::Result<u64> {
+    let mut bytes = [0u8; 8];
+    std::io::Read::read_exact(reader, &mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_proofs::arithmetic::{g_to_lagrange, Field};
+    use halo2_proofs::halo2curves::bn256::{Fq, Fq2, G1, G2};
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    /// `.ptau` in the snarkjs layout, with every section, of a ceremony of
+    /// 2^4 powers of tau = 5.
+    const PTAU: &[u8] = include_bytes!("../testdata/tau5-k4.ptau");
+
+    /// A point of the G2 curve outside of its subgroup of prime order.
+    fn torsion_point() -> G2Affine {
+        let generator = G2Affine::generator();
+        let b = generator.y.square() - generator.x.square() * generator.x;
+        let mut x = Fq2::one();
+        loop {
+            let y = (x.square() * x + b).sqrt();
+            if bool::from(y.is_some()) {
+                let point = G2Affine { x, y: y.unwrap() };
+                if !bool::from(point.to_curve().is_torsion_free()) {
+                    return point;
+                }
+            }
+            x += Fq2::one();
+        }
+    }
+
+    #[test]
+    fn check_params_rejects_tampered_powers() {
+        let params = ParamsKZG::<Bn256>::setup(4, XorShiftRng::seed_from_u64(7));
+        let mut bytes = vec![];
+        params.write(&mut bytes).unwrap();
+        let downsized = read_params(&bytes, 3).unwrap();
+        assert_eq!(downsized.k(), 3);
+        assert_eq!(downsized.g_lagrange, {
+            let mut expected = params.clone();
+            expected.downsize(3);
+            expected.g_lagrange
+        });
+
+        let mut tampered = params;
+        tampered.g.swap(2, 3);
+        assert!(check_params(&tampered, 16).is_err());
+    }
+
+    #[test]
+    fn reads_ptau() {
+        let tau = Fr::from(5);
+        let params = read_params(PTAU, 3).unwrap();
+        assert_eq!(params.k(), 3);
+        let mut power = Fr::one();
+        for point in params.g.iter() {
+            assert_eq!(*point, (G1::generator() * power).to_affine());
+            power *= tau;
+        }
+        assert_eq!(params.g.len(), 8);
+        assert_eq!(params.s_g2, (G2::generator() * tau).to_affine());
+        assert_eq!(
+            params.g_lagrange,
+            g_to_lagrange(params.g.iter().map(|g| g.to_curve()).collect(), 3)
+        );
+
+        assert_eq!(read_params(PTAU, 4).unwrap().k(), 4);
+        assert!(read_params(PTAU, 5).is_err());
+    }
+
+    #[test]
+    fn rejects_points_outside_of_the_subgroup() {
+        let torsion = torsion_point();
+        assert!(bool::from(torsion.is_on_curve()));
+
+        // tauG2 of the `.ptau` starts 2076 bytes in, with g2 then s_g2.
+        let mut ptau = PTAU.to_vec();
+        let mut s_g2 = vec![];
+        torsion.write(&mut s_g2, SerdeFormat::RawBytes).unwrap();
+        ptau[2076 + 128..2076 + 256].copy_from_slice(&s_g2);
+        let error = read_params(&ptau, 3).unwrap_err().to_string();
+        assert!(error.contains("subgroup"), "{}", error);
+
+        let mut params = read_params(PTAU, 4).unwrap();
+        params.s_g2 = torsion;
+        let mut bytes = vec![];
+        params.write(&mut bytes).unwrap();
+        let error = read_params(&bytes, 3).unwrap_err().to_string();
+        assert!(error.contains("subgroup"), "{}", error);
+
+        let mut params = read_params(PTAU, 4).unwrap();
+        params.g[1].y += Fq::one();
+        let error = check_params(&params, 16).unwrap_err().to_string();
+        assert!(error.contains("not on the curve"), "{}", error);
+    }
+}
@@ -1,9 +1,12 @@
-use crate::entries::Entries;
-use core::fmt::Error;
+use crate::entries::{Divergence, Entries, StepError};
+use alloc::vec::Vec;
+use runtime::program::Program;
 use runtime::trace::Trace;
 
 pub struct EntryBuilder {
     pub entries: Entries,
+    /// Program decoded from the ELF file, every step of the trace is checked against it.
+    pub program: Option<Program>,
 }
 
 impl Default for EntryBuilder {
@@ -16,11 +19,31 @@ impl EntryBuilder {
     pub fn new() -> EntryBuilder {
         Self {
             entries: Entries::new(),
+            program: None,
         }
     }
 
-    pub fn build(&mut self, trace: &Trace) -> Result<(), Error> {
-        for (_index, step) in trace.steps.iter().enumerate() {
+    pub fn with_program(program: Program) -> EntryBuilder {
+        Self {
+            entries: Entries::new(),
+            program: Some(program),
+        }
+    }
+
+    /// Replays the trace, rejecting the steps which don't execute an
+    /// instruction of the program and a trace which doesn't start at its entry.
+    pub fn build(&mut self, trace: &Trace) -> Result<(), StepError> {
+        for step in trace.steps.iter() {
+            if let Some(program) = &self.program {
+                if self.entries.next_pc.is_none() && step.pc != program.entry {
+                    return Err(StepError::Pc {
+                        global_clk: step.global_clk,
+                        expected: program.entry,
+                        actual: step.pc,
+                    });
+                }
+                program.check_step(step).map_err(StepError::Program)?;
+            }
             // match step.instruction.opcode
             // TODO: store rw operations to container
             self.entries.step(step)?;
@@ -28,15 +51,53 @@ impl EntryBuilder {
 
         Ok(())
     }
+
+    /// Replays the whole trace like [`build`](Self::build), but returns every
+    /// diverging step instead of stopping at the first one.
+    pub fn validate(&mut self, trace: &Trace) -> Result<Vec<Divergence>, StepError> {
+        let mut divergences = Vec::new();
+        for (index, step) in trace.steps.iter().enumerate() {
+            let mut divergence = self.entries.divergence(index, step);
+            if let Some(program) = &self.program {
+                if let Err(mismatch) = program.check_step(step) {
+                    let divergence = divergence.get_or_insert_with(|| Divergence {
+                        index,
+                        global_clk: step.global_clk,
+                        pc: step.pc,
+                        instruction: step.instruction.clone(),
+                        expected_pc: None,
+                        registers: Vec::new(),
+                        program: None,
+                    });
+                    divergence.program = Some(mismatch);
+                }
+            }
+            if let Some(divergence) = divergence {
+                self.entries.resync(step);
+                divergences.push(divergence);
+            }
+            self.entries.step(step)?;
+        }
+
+        Ok(divergences)
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    extern crate std;
+
+    use alloc::{vec, vec::Vec};
     use std::{fs::File, io::BufReader};
 
-    use runtime::trace::{Step, Trace};
+    use runtime::decoder::decode;
+    use runtime::fusion::fuse;
+    use runtime::machine::{Machine, MachineError, Syscalls, REGISTER_A0};
+    use runtime::program::Program;
+    use runtime::trace::{Instruction, Step, Trace};
 
     use super::EntryBuilder;
+    use crate::entries::StepError;
 
     fn get_trace_from_file(path: &str) -> Vec<Step> {
         let file = File::open(path).expect("open file");
@@ -48,7 +109,7 @@ mod tests {
     #[test]
     fn deserialize_trace() {
         let steps = get_trace_from_file("../trace.json");
-        dbg!(&steps);
+        std::dbg!(&steps);
     }
 
     #[test]
@@ -61,6 +122,433 @@ mod tests {
             steps,
         };
         entry_builder.build(&trace).expect("build entry");
-        dbg!(entry_builder.entries.get_op_steps());
+        std::dbg!(entry_builder.entries.get_op_steps());
+    }
+
+    /// Builds a trace running the `(pc, encoding, register writes)` steps.
+    fn build_trace(code: &[(u64, u32, &[(usize, u64)])]) -> Trace {
+        let code: Vec<_> = code
+            .iter()
+            .map(|&(pc, bits, writes)| (pc, decode(bits).unwrap(), writes))
+            .collect();
+        build_trace_from(&code)
+    }
+
+    fn build_trace_from(code: &[(u64, Instruction, &[(usize, u64)])]) -> Trace {
+        let mut registers = vec![0; 32];
+        let mut steps = Vec::new();
+        for (global_clk, (pc, instruction, writes)) in code.iter().enumerate() {
+            steps.push(Step {
+                global_clk: global_clk as u64,
+                pc: *pc,
+                instruction: instruction.clone(),
+                registers: registers.clone(),
+            });
+            for &(index, value) in writes.iter() {
+                registers[index] = value;
+            }
+        }
+        Trace {
+            cycles: 0,
+            return_value: 0,
+            steps,
+        }
+    }
+
+    /// Steps of `c.li a0, 5; c.addi a0, 1; jal ra, 8; ...; c.jr ra; c.mv a1, a0`,
+    /// with the pc of the second step replaced by `second_pc`.
+    fn compressed_trace(second_pc: u64) -> Trace {
+        build_trace(&[
+            (0x10000, 0x4515, &[(10, 5)]),
+            (second_pc, 0x0505, &[(10, 6)]),
+            (0x10004, 0x008000ef, &[(1, 0x10008)]),
+            (0x1000c, 0x8082, &[]),
+            (0x10008, 0x85aa, &[(11, 6)]),
+        ])
+    }
+
+    #[test]
+    fn test_compressed_pc_advance() {
+        let mut entry_builder = EntryBuilder::new();
+        entry_builder
+            .build(&compressed_trace(0x10002))
+            .expect("build entry");
+        assert_eq!(entry_builder.entries.next_pc, Some(0x1000a));
+    }
+
+    #[test]
+    fn test_wrong_pc_advance() {
+        let mut entry_builder = EntryBuilder::new();
+        assert_eq!(
+            entry_builder.build(&compressed_trace(0x10004)),
+            Err(StepError::Pc {
+                global_clk: 1,
+                expected: 0x10002,
+                actual: 0x10004,
+            })
+        );
+    }
+
+    #[test]
+    fn test_validate_reports_divergences() {
+        let mut trace = compressed_trace(0x10004);
+        trace.steps[4].registers[11] = 1;
+        let mut entry_builder = EntryBuilder::new();
+        let divergences = entry_builder.validate(&trace).expect("validate trace");
+
+        // The replay resyncs after a divergence, the wrong pc of step 1 only
+        // shifts the next step.
+        let indexes: Vec<_> = divergences.iter().map(|d| d.index).collect();
+        assert_eq!(indexes, vec![1, 2, 4]);
+        assert_eq!(divergences[0].expected_pc, Some(0x10002));
+        assert_eq!(divergences[1].expected_pc, Some(0x10006));
+        assert_eq!(divergences[2].registers, vec![(11, 0, 1)]);
+    }
+
+    #[test]
+    fn test_atomic_read_modify_write() {
+        // li a1, 0x1000; li a2, 5; sd a2, 0(a1); amoadd.d a0, a2, (a1);
+        // lr.d a0, (a1); sc.d a3, a2, (a1)
+        let trace = build_trace(&[
+            (0x10000, 0x000015b7, &[(11, 0x1000)]),
+            (0x10004, 0x4615, &[(12, 5)]),
+            (0x10006, 0x00c5b023, &[]),
+            (0x1000a, 0x00c5b52f, &[(10, 5)]),
+            (0x1000e, 0x1005b52f, &[(10, 10)]),
+            (0x10012, 0x18c5b6af, &[(13, 0)]),
+        ]);
+        let mut entry_builder = EntryBuilder::new();
+        entry_builder.build(&trace).expect("build entry");
+
+        let entries = &entry_builder.entries;
+        let amo = &entries.memory_ops[&3];
+        assert_eq!(amo.read().map(|op| op.value), Some(5));
+        assert_eq!(amo.write().map(|op| op.value), Some(10));
+        assert_eq!(entries.register_ops[&3].write(10), Some(5));
+        assert_eq!(entries.register_ops[&4].write(10), Some(10));
+        // The store conditional succeeds on the reservation of the load.
+        assert_eq!(entries.register_ops[&5].write(13), Some(0));
+        assert_eq!(entries.memory_ops[&5].write().map(|op| op.value), Some(5));
+        assert_eq!(entries.reservation, None);
+    }
+
+    #[test]
+    fn test_fused_macro_ops() {
+        // lui a0, 0x12345; addi a0, a0, -1; auipc ra, 0x1; jalr ra, -16(ra)
+        let code = [0x12345537, 0xfff50513, 0x00001097, 0xff0080e7];
+        let mut program = Program {
+            entry: 0x10000,
+            ..Default::default()
+        };
+        for (index, bits) in code.into_iter().enumerate() {
+            let pc = 0x10000 + 4 * index as u64;
+            program.instructions.insert(pc, decode(bits).unwrap());
+        }
+        program
+            .instructions
+            .insert(0x10ff8, decode(0x85aa).unwrap());
+
+        let load_imm = fuse(&decode(code[0]).unwrap(), &decode(code[1]).unwrap()).unwrap();
+        let far_jump = fuse(&decode(code[2]).unwrap(), &decode(code[3]).unwrap()).unwrap();
+        let trace = build_trace_from(&[
+            (0x10000, load_imm, &[(10, 0x12344fff)]),
+            (0x10008, far_jump, &[(1, 0x10010)]),
+            (0x10ff8, decode(0x85aa).unwrap(), &[(11, 0x12344fff)]),
+        ]);
+
+        let mut entry_builder = EntryBuilder::with_program(program);
+        entry_builder.build(&trace).expect("build entry");
+        assert_eq!(
+            entry_builder.entries.register_ops[&1].write(1),
+            Some(0x10010)
+        );
+        assert_eq!(entry_builder.entries.next_pc, Some(0x10ffa));
+    }
+
+    #[test]
+    fn test_rejects_instruction_not_in_program() {
+        let trace = compressed_trace(0x10002);
+        let mut program = Program {
+            entry: 0x10000,
+            ..Default::default()
+        };
+        for step in trace.steps.iter().skip(1) {
+            program
+                .instructions
+                .insert(step.pc, step.instruction.clone());
+        }
+        // c.li a0, 6 instead of c.li a0, 5
+        program
+            .instructions
+            .insert(0x10000, decode(0x4519).unwrap());
+
+        let mut entry_builder = EntryBuilder::with_program(program);
+        match entry_builder.build(&trace) {
+            Err(StepError::Program(mismatch)) => assert_eq!(mismatch.pc, 0x10000),
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert!(entry_builder.entries.instructions.is_empty());
+    }
+
+    #[test]
+    fn test_rejects_trace_not_starting_at_the_entry() {
+        let trace = compressed_trace(0x10002);
+        let mut program = Program {
+            entry: 0x10002,
+            ..Default::default()
+        };
+        for step in trace.steps.iter() {
+            program
+                .instructions
+                .insert(step.pc, step.instruction.clone());
+        }
+
+        let mut entry_builder = EntryBuilder::with_program(program);
+        assert_eq!(
+            entry_builder.build(&trace),
+            Err(StepError::Pc {
+                global_clk: trace.steps[0].global_clk,
+                expected: 0x10002,
+                actual: 0x10000,
+            })
+        );
+    }
+
+    #[test]
+    fn test_checks_registers_after_load() {
+        // li a1, 0x1000; ld a0, 0(a1); c.mv a1, a0
+        let mut trace = build_trace(&[
+            (0x10000, 0x000015b7, &[(11, 0x1000)]),
+            (0x10004, 0x0005b503, &[(10, 7)]),
+            (0x10008, 0x85aa, &[(11, 7)]),
+        ]);
+        // The loaded value comes from the next step.
+        let mut entry_builder = EntryBuilder::new();
+        entry_builder.build(&trace).expect("build entry");
+        assert_eq!(entry_builder.entries.register_buffer[11], 7);
+
+        // The other registers are still checked.
+        trace.steps[2].registers[12] = 1;
+        assert_eq!(
+            EntryBuilder::new().build(&trace),
+            Err(StepError::Register {
+                global_clk: 2,
+                index: 12,
+                expected: 0,
+                actual: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn test_rejects_malformed_steps() {
+        // ebreak
+        let trace = build_trace(&[(0x10000, 0x00100073, &[])]);
+        assert!(matches!(
+            EntryBuilder::new().build(&trace),
+            Err(StepError::Trap { pc: 0x10000, .. })
+        ));
+
+        // lui a1, 0x80000; ld a0, 0(a1) loads from outside of the memory.
+        let trace = build_trace(&[
+            (0x10000, 0x800005b7, &[(11, 0xffffffff80000000)]),
+            (0x10004, 0x0005b503, &[(10, 0)]),
+        ]);
+        assert_eq!(
+            EntryBuilder::new().build(&trace),
+            Err(StepError::Memory {
+                global_clk: 1,
+                address: 0xffffffff80000000,
+                width: 64,
+            })
+        );
+
+        // add a2, a0, a1 with 31 registers, then with rs1 x40.
+        let mut trace = build_trace(&[(0x10000, 0x00b50633, &[])]);
+        trace.steps[0].registers.pop();
+        assert_eq!(
+            EntryBuilder::new().build(&trace),
+            Err(StepError::RegisterIndex {
+                global_clk: 0,
+                index: 31,
+            })
+        );
+        let mut add = decode(0x00b50633).unwrap();
+        add.op_b = 40;
+        let trace = build_trace_from(&[(0x10000, add, &[])]);
+        assert_eq!(
+            EntryBuilder::new().build(&trace),
+            Err(StepError::RegisterIndex {
+                global_clk: 0,
+                index: 40,
+            })
+        );
+    }
+
+    /// Exits on the first ecall with the code in a0.
+    struct Exit;
+
+    impl Syscalls for Exit {
+        fn ecall(&mut self, machine: &mut Machine) -> Result<Option<i8>, MachineError> {
+            Ok(Some(machine.register(REGISTER_A0) as i8))
+        }
+    }
+
+    /// `op a2, a0, a1` of the R-type `(funct7, funct3, opcode)`.
+    fn rtype((funct7, funct3, opcode): (u32, u32, u32)) -> u32 {
+        funct7 << 25 | 11 << 20 | 10 << 15 | funct3 << 12 | 12 << 7 | opcode
+    }
+
+    /// `op a2, a0, imm` of the I-type `(imm, funct3, opcode)`.
+    fn itype((imm, funct3, opcode): (u32, u32, u32)) -> u32 {
+        imm << 20 | 10 << 15 | funct3 << 12 | 12 << 7 | opcode
+    }
+
+    #[test]
+    fn test_replays_machine_traces() {
+        let rtypes = [
+            // add, sub, sll, slt, sltu, xor, srl, sra, or, and
+            (0x00, 0, 0x33),
+            (0x20, 0, 0x33),
+            (0x00, 1, 0x33),
+            (0x00, 2, 0x33),
+            (0x00, 3, 0x33),
+            (0x00, 4, 0x33),
+            (0x00, 5, 0x33),
+            (0x20, 5, 0x33),
+            (0x00, 6, 0x33),
+            (0x00, 7, 0x33),
+            // mul, mulh, mulhsu, mulhu, div, divu, rem, remu
+            (0x01, 0, 0x33),
+            (0x01, 1, 0x33),
+            (0x01, 2, 0x33),
+            (0x01, 3, 0x33),
+            (0x01, 4, 0x33),
+            (0x01, 5, 0x33),
+            (0x01, 6, 0x33),
+            (0x01, 7, 0x33),
+            // addw, subw, sllw, srlw, sraw, mulw, divw, divuw, remw, remuw
+            (0x00, 0, 0x3b),
+            (0x20, 0, 0x3b),
+            (0x00, 1, 0x3b),
+            (0x00, 5, 0x3b),
+            (0x20, 5, 0x3b),
+            (0x01, 0, 0x3b),
+            (0x01, 4, 0x3b),
+            (0x01, 5, 0x3b),
+            (0x01, 6, 0x3b),
+            (0x01, 7, 0x3b),
+            // andn, orn, xnor, min, minu, max, maxu, rol, ror, rolw, rorw
+            (0x20, 7, 0x33),
+            (0x20, 6, 0x33),
+            (0x20, 4, 0x33),
+            (0x05, 4, 0x33),
+            (0x05, 5, 0x33),
+            (0x05, 6, 0x33),
+            (0x05, 7, 0x33),
+            (0x30, 1, 0x33),
+            (0x30, 5, 0x33),
+            (0x30, 1, 0x3b),
+            (0x30, 5, 0x3b),
+            // sh1add, sh2add, sh3add, sh1add.uw, sh2add.uw, sh3add.uw, add.uw
+            (0x10, 2, 0x33),
+            (0x10, 4, 0x33),
+            (0x10, 6, 0x33),
+            (0x10, 2, 0x3b),
+            (0x10, 4, 0x3b),
+            (0x10, 6, 0x3b),
+            (0x04, 0, 0x3b),
+            // clmul, clmulr, clmulh, bclr, bext, binv, bset
+            (0x05, 1, 0x33),
+            (0x05, 2, 0x33),
+            (0x05, 3, 0x33),
+            (0x24, 1, 0x33),
+            (0x24, 5, 0x33),
+            (0x34, 1, 0x33),
+            (0x14, 1, 0x33),
+        ];
+        let mut itypes = vec![
+            // clz, ctz, cpop, sext.b, sext.h, clzw, ctzw, cpopw, orc.b, rev8
+            (0x600, 1, 0x13),
+            (0x601, 1, 0x13),
+            (0x602, 1, 0x13),
+            (0x604, 1, 0x13),
+            (0x605, 1, 0x13),
+            (0x600, 1, 0x1b),
+            (0x601, 1, 0x1b),
+            (0x602, 1, 0x1b),
+            (0x287, 5, 0x13),
+            (0x6b8, 5, 0x13),
+        ];
+        for imm in [0, 1, 0x7ff, 0x800, 0xfff] {
+            // addi, slti, sltiu, xori, ori, andi, addiw
+            for (funct3, opcode) in [(0, 0x13), (2, 0x13), (3, 0x13), (4, 0x13), (6, 0x13)] {
+                itypes.push((imm, funct3, opcode));
+            }
+            itypes.push((imm, 7, 0x13));
+            itypes.push((imm, 0, 0x1b));
+        }
+        for shamt in [0, 1, 31, 63] {
+            // slli, srli, srai, rori, bclri, bexti, binvi, bseti, slli.uw
+            for (funct6, funct3) in [
+                (0x00, 1),
+                (0x00, 5),
+                (0x10, 5),
+                (0x18, 5),
+                (0x12, 1),
+                (0x12, 5),
+                (0x1a, 1),
+                (0x0a, 1),
+            ] {
+                itypes.push((funct6 << 6 | shamt, funct3, 0x13));
+            }
+            itypes.push((0x02 << 6 | shamt, 1, 0x1b));
+        }
+        for shamt in [0, 1, 31] {
+            // slliw, srliw, sraiw, roriw
+            for (funct7, funct3) in [(0x00, 1), (0x00, 5), (0x20, 5), (0x30, 5)] {
+                itypes.push((funct7 << 5 | shamt, funct3, 0x1b));
+            }
+        }
+        // zext.h
+        let mut code: Vec<u32> = vec![0x0800_0000 | 10 << 15 | 4 << 12 | 12 << 7 | 0x3b];
+        code.extend(rtypes.into_iter().map(rtype));
+        code.extend(itypes.into_iter().map(itype));
+        // li a7, 93; ecall
+        code.extend([0x05d00893, 0x00000073]);
+
+        let values = [
+            0,
+            1,
+            2,
+            63,
+            64,
+            u64::MAX,
+            i64::MIN as u64,
+            i64::MAX as u64,
+            0x8000_0000,
+            0xffff_ffff,
+            0x1_0000_0001,
+            0x0123_4567_89ab_cdef,
+        ];
+        for a0 in values {
+            for a1 in values {
+                let mut machine = Machine::new();
+                machine.pc = 0x1000;
+                for (index, bits) in code.iter().enumerate() {
+                    machine
+                        .store(0x1000 + 4 * index as u64, *bits as u64, 4)
+                        .unwrap();
+                }
+                machine.registers[10] = a0;
+                machine.registers[11] = a1;
+                let trace = machine.run(&mut Exit, code.len() as u64).unwrap();
+
+                let mut entry_builder = EntryBuilder::new();
+                assert_eq!(entry_builder.build(&trace), Ok(()), "a0 {a0:#x} a1 {a1:#x}");
+                let divergences = EntryBuilder::new().validate(&trace).unwrap();
+                assert!(divergences.is_empty(), "{:?}", divergences);
+            }
+        }
     }
 }
@@ -1,11 +1,12 @@
 use crate::{op_step::OpStep, Register};
 use alloc::{vec, vec::Vec};
 use byteorder::{ByteOrder, LittleEndian};
-use core::fmt::Error;
-use core::ops::Shr;
+use core::fmt;
 use hashbrown::HashMap;
+use runtime::program::ProgramMismatch;
 use runtime::trace::{
-    BType, IType, Instruction, InstructionType, JType, NoType, RType, SType, Step, UType,
+    AType, BType, FusedType, IType, Instruction, InstructionType, JType, NoType, RType, SType,
+    Step, UType,
 };
 
 /// Marker that defines whether an Operation performs a `READ` or a `WRITE`.
@@ -18,6 +19,8 @@ pub enum RW {
 }
 
 const SHIFT_MASK: u64 = 0x3f;
+/// Shift amounts of the word instructions.
+const SHIFT_MASK_W: u64 = 0x1f;
 
 impl RW {
     /// Returns true if the RW corresponds internally to a [`READ`](RW::READ).
@@ -60,6 +63,30 @@ pub struct RegisterOps {
     pub ops: Vec<RegisterOp>,
 }
 
+/// Memory operations of one global_clk, a read followed by a write for atomic
+/// instructions.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MemoryOps {
+    pub ops: Vec<MemoryOp>,
+}
+
+impl MemoryOps {
+    pub fn push(&mut self, op: MemoryOp) {
+        if let Some(first) = self.ops.first() {
+            assert_eq!(op.global_clk, first.global_clk);
+        }
+        self.ops.push(op);
+    }
+
+    pub fn read(&self) -> Option<&MemoryOp> {
+        self.ops.iter().find(|op| op.rw.is_read())
+    }
+
+    pub fn write(&self) -> Option<&MemoryOp> {
+        self.ops.iter().rev().find(|op| op.rw.is_write())
+    }
+}
+
 impl RegisterOps {
     pub fn new() -> Self {
         Self { ops: Vec::new() }
@@ -92,14 +119,131 @@ impl RegisterOps {
     }
 }
 
+/// A step whose state is not the one the replay of the previous steps expects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// Index of the step in the trace.
+    pub index: usize,
+    pub global_clk: u64,
+    pub pc: u64,
+    pub instruction: Instruction,
+    /// Pc the previous step continues at, if the step starts somewhere else.
+    pub expected_pc: Option<u64>,
+    /// `(index, expected, actual)` of every register which differs.
+    pub registers: Vec<(usize, u64, u64)>,
+    /// Set if the step doesn't execute the instruction of the program.
+    pub program: Option<ProgramMismatch>,
+}
+
+impl Divergence {
+    pub fn is_empty(&self) -> bool {
+        self.expected_pc.is_none() && self.registers.is_empty() && self.program.is_none()
+    }
+}
+
+/// A step the replay can't go on with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StepError {
+    /// The step doesn't execute the instruction of the program at its pc.
+    Program(ProgramMismatch),
+    /// The step doesn't start at the pc the previous one continues at.
+    Pc {
+        global_clk: u64,
+        expected: u64,
+        actual: u64,
+    },
+    /// A register of the step differs from the replayed one.
+    Register {
+        global_clk: u64,
+        index: usize,
+        expected: u64,
+        actual: u64,
+    },
+    /// The step doesn't have the 32 registers, or its instruction names one
+    /// which doesn't exist.
+    RegisterIndex { global_clk: u64, index: u64 },
+    /// The step accesses memory outside of the memory of the replay.
+    Memory {
+        global_clk: u64,
+        address: u64,
+        width: u8,
+    },
+    /// The step executes an instruction which stops the program, EBREAK or
+    /// UNIMP, so no step can follow.
+    Trap {
+        global_clk: u64,
+        pc: u64,
+        instruction: Instruction,
+    },
+}
+
+impl fmt::Display for StepError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StepError::Program(mismatch) => {
+                write!(
+                    f,
+                    "step {} at pc {:#x} executes {}, ",
+                    mismatch.global_clk, mismatch.pc, mismatch.actual
+                )?;
+                match &mismatch.expected {
+                    Some(expected) => write!(f, "the program has {}", expected),
+                    None => write!(f, "the program has no instruction there"),
+                }
+            }
+            StepError::Pc {
+                global_clk,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "step {} starts at pc {:#x}, expected {:#x}",
+                global_clk, actual, expected
+            ),
+            StepError::Register {
+                global_clk,
+                index,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "register x{} is {:#x} in step {}, expected {:#x}",
+                index, actual, global_clk, expected
+            ),
+            StepError::RegisterIndex { global_clk, index } => {
+                write!(f, "step {} has no register x{}", global_clk, index)
+            }
+            StepError::Memory {
+                global_clk,
+                address,
+                width,
+            } => write!(
+                f,
+                "step {} accesses {} bits at {:#x}, out of the memory",
+                global_clk, width, address
+            ),
+            StepError::Trap {
+                global_clk,
+                pc,
+                instruction,
+            } => write!(
+                f,
+                "step {} at pc {:#x} executes {}, which stops the program",
+                global_clk, pc, instruction
+            ),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Entries {
-    // TODO: this should be read from ELF file
-    pub pc_instructions: HashMap<u64, Instruction>,
     /// Program counter for each global_clk
     pub pcs: Vec<(u64, u64)>,
+    /// Instruction executed at each global_clk, in the order of `pcs`. The
+    /// circuit looks them up in the program, they are never added to it.
+    pub instructions: Vec<Instruction>,
     /// Operations of memory for each global_clk
-    pub memory_ops: HashMap<u64, MemoryOp>,
+    pub memory_ops: HashMap<u64, MemoryOps>,
     /// Operations of register for each global_clk
     pub register_ops: HashMap<u64, RegisterOps>,
 
@@ -108,7 +252,17 @@ pub struct Entries {
     pub memory_buffer: Vec<u8>,
     pub register_buffer: Vec<u64>,
 
+    /// Set before the first step, whose registers the replay takes over.
     pub should_copy_registers: bool,
+    /// Register the last step loaded from memory or got from a syscall, the
+    /// replay takes its value from the next step and checks the others.
+    pub loaded_register: Option<u64>,
+
+    /// Pc the next step has to start at, `None` before the first step.
+    pub next_pc: Option<u64>,
+
+    /// Address reserved by the last LR, consumed by the next SC.
+    pub reservation: Option<u64>,
 
     /// Temporary register to store the counter for operations within an instruction.
     pub rwc: u64,
@@ -124,16 +278,22 @@ impl Entries {
     pub fn get_op_steps(&self) -> Vec<OpStep> {
         self.pcs
             .iter()
-            .map(|(global_clk, pc)| {
+            .zip(self.instructions.iter())
+            .enumerate()
+            .map(|(index, ((global_clk, pc), instruction))| {
                 let r = self.register_ops.get(global_clk);
                 let m = self.memory_ops.get(global_clk);
-
-                let instruction = self.pc_instructions.get(pc).expect("get instructions");
+                // The last step continues where the replay says.
+                let next_pc = match self.pcs.get(index + 1) {
+                    Some((_, next_pc)) => *next_pc,
+                    None => self.next_pc.unwrap_or_default(),
+                };
 
                 OpStep {
                     global_clk: *global_clk,
                     pc: *pc,
-                    instruction: instruction,
+                    next_pc,
+                    instruction,
                     register_indexes: r,
                     memory_address: m,
                 }
@@ -143,27 +303,53 @@ impl Entries {
 
     pub fn new() -> Self {
         Self {
-            pc_instructions: HashMap::new(),
             pcs: Vec::new(),
+            instructions: Vec::new(),
             memory_ops: HashMap::new(),
             register_ops: HashMap::new(),
             // Some registers has initial state, so we need to copy them at first.
             should_copy_registers: true,
+            loaded_register: None,
             memory_buffer: vec![0; 1024 * 1024 * 32],
             register_buffer: vec![0; 32],
+            next_pc: None,
+            reservation: None,
             rwc: 0,
         }
     }
 
-    pub fn update_pc_register(&mut self, _gc: u64, _value: u64) {
+    pub fn update_pc_register(&mut self, _gc: u64, value: u64) {
         // TODO: save pc register in a separated table.
+        self.next_pc = Some(value);
+    }
+
+    /// The value of register `index` in the step.
+    pub fn register(step: &Step, index: u64) -> Result<u64, StepError> {
+        step.registers
+            .get(index as usize)
+            .copied()
+            .ok_or(StepError::RegisterIndex {
+                global_clk: step.global_clk,
+                index,
+            })
     }
 
-    pub fn read_register(&mut self, gc: u64, index: u64, value: u64) {
-        assert_eq!(
-            self.register_buffer[index as usize], value,
-            "Read the wrong register value"
-        );
+    pub fn read_register(&mut self, gc: u64, index: u64, value: u64) -> Result<(), StepError> {
+        let replayed =
+            self.register_buffer
+                .get(index as usize)
+                .ok_or(StepError::RegisterIndex {
+                    global_clk: gc,
+                    index,
+                })?;
+        if *replayed != value {
+            return Err(StepError::Register {
+                global_clk: gc,
+                index: index as usize,
+                expected: *replayed,
+                actual: value,
+            });
+        }
         let read_op = RegisterOp {
             global_clk: gc,
             rwc: self.rwc,
@@ -174,11 +360,19 @@ impl Entries {
 
         self.register_ops.entry(gc).or_default().push(read_op);
         self.rwc += 1;
+        Ok(())
     }
 
-    pub fn write_register(&mut self, gc: u64, index: u64, value: u64) {
+    pub fn write_register(&mut self, gc: u64, index: u64, value: u64) -> Result<(), StepError> {
+        let register =
+            self.register_buffer
+                .get_mut(index as usize)
+                .ok_or(StepError::RegisterIndex {
+                    global_clk: gc,
+                    index,
+                })?;
         if index != 0 {
-            self.register_buffer[index as usize] = value;
+            *register = value;
         }
         let write_op = RegisterOp {
             global_clk: gc,
@@ -189,10 +383,26 @@ impl Entries {
         };
         self.register_ops.entry(gc).or_default().push(write_op);
         self.rwc += 1;
+        Ok(())
     }
 
-    pub fn read_memory(&mut self, gc: u64, address: u64, width: u8) -> u64 {
-        let mut reader = &self.memory_buffer[address as usize..];
+    /// The bytes of the memory buffer accessed by `width` bits at `address`.
+    fn memory(&mut self, gc: u64, address: u64, width: u8) -> Result<&mut [u8], StepError> {
+        let error = StepError::Memory {
+            global_clk: gc,
+            address,
+            width,
+        };
+        let start = usize::try_from(address).map_err(|_| error.clone())?;
+        let end = start
+            .checked_add(width as usize / 8)
+            .filter(|end| *end <= self.memory_buffer.len())
+            .ok_or(error)?;
+        Ok(&mut self.memory_buffer[start..end])
+    }
+
+    pub fn read_memory(&mut self, gc: u64, address: u64, width: u8) -> Result<u64, StepError> {
+        let reader = &*self.memory(gc, address, width)?;
         let value = match width {
             8 => reader[0] as u64,
             16 => LittleEndian::read_u16(reader) as u64,
@@ -208,21 +418,18 @@ impl Entries {
             value,
             width,
         };
-        self.memory_ops.insert(gc, read_op);
-        value
+        self.memory_ops.entry(gc).or_default().push(read_op);
+        Ok(value)
     }
 
-    pub fn write_memory(&mut self, gc: u64, address: u64, value: u64, width: u8) {
-        let write_op = MemoryOp {
-            global_clk: gc,
-            rw: RW::WRITE,
-            address,
-            value,
-            width,
-        };
-        self.memory_ops.insert(gc, write_op);
-        let writer = &mut self.memory_buffer[address as usize..];
-
+    pub fn write_memory(
+        &mut self,
+        gc: u64,
+        address: u64,
+        value: u64,
+        width: u8,
+    ) -> Result<(), StepError> {
+        let writer = self.memory(gc, address, width)?;
         match width {
             8 => writer[0] = value as u8,
             16 => LittleEndian::write_u16(writer, value as u16),
@@ -230,11 +437,21 @@ impl Entries {
             64 => LittleEndian::write_u64(writer, value as u64),
             _ => panic!("Not implemented {:?}", width),
         }
+        let write_op = MemoryOp {
+            global_clk: gc,
+            rw: RW::WRITE,
+            address,
+            // The bytes written, like the value of a read.
+            value: value & (u64::MAX >> (64 - width as u32)),
+            width,
+        };
+        self.memory_ops.entry(gc).or_default().push(write_op);
+        Ok(())
     }
 
-    pub fn step_rtype(&mut self, rtype: RType, step: &Step) -> Result<(), Error> {
-        let rs1_value = step.registers[step.instruction.op_b as usize];
-        let rs2_value = step.registers[step.instruction.op_c as usize];
+    pub fn step_rtype(&mut self, rtype: RType, step: &Step) -> Result<(), StepError> {
+        let rs1_value = Self::register(step, step.instruction.op_b)?;
+        let rs2_value = Self::register(step, step.instruction.op_c)?;
         let result = match rtype {
             RType::ADD => {
                 let (value, _) = rs1_value.overflowing_add(rs2_value);
@@ -256,8 +473,8 @@ impl Entries {
                 let shift_value = rs2_value.clone() & SHIFT_MASK;
                 rs1_value.clone() >> shift_value
             }
-            RType::SRA => rs1_value.clone() >> rs2_value,
-            RType::SLT => (rs1_value < rs2_value).into(),
+            RType::SRA => rs1_value.signed_shr(&(rs2_value & SHIFT_MASK)),
+            RType::SLT => rs1_value.lt_s(&rs2_value),
             RType::SLTU => (rs1_value < rs2_value).into(),
             RType::XOR => rs1_value ^ rs2_value,
             RType::OR => rs1_value | rs2_value,
@@ -281,116 +498,130 @@ impl Entries {
                 let (value, _) = a.overflowing_mul(b);
                 (value >> 64) as u64
             }
-            RType::DIV => {
-                // rs1_value.overflowing_div_signed(rs2_value);
-                if rs1_value == 0 {
-                    u64::max_value()
-                } else {
-                    rs1_value.overflowing_div(rs2_value).0
-                }
-            }
-            RType::DIVU => {
-                // rs1_value.overflowing_div(rs2_value);
-                if rs2_value == 0 {
-                    (-1i64) as u64
-                } else {
-                    let (v, o) = (rs1_value as i64).overflowing_div(rs2_value as i64);
-                    if o {
-                        // -2**(L-1) implemented using (-1) << (L - 1)
-                        ((-1i64) as u64) << (64 - 1)
-                    } else {
-                        v as u64
-                    }
-                }
-            }
-            RType::REM => {
-                // rs1_value.overflowing_rem_signed(rs2_value);
-                if rs2_value == 0 {
-                    rs1_value
-                } else {
-                    (rs1_value).overflowing_rem(rs2_value).0
-                }
-            }
-            RType::REMU => {
-                // rs1_value.overflowing_rem(rs2_value);
-                if rs2_value == 0 {
-                    rs1_value
-                } else {
-                    let (v, o) = (rs1_value as i64).overflowing_rem(rs2_value as i64);
-                    if o {
-                        0
-                    } else {
-                        v as u64
-                    }
-                }
-            }
-            RType::ADDW => (rs1_value + rs2_value).sign_extend(&32),
+            RType::DIV => rs1_value.overflowing_div_signed(&rs2_value),
+            RType::DIVU => Register::overflowing_div(&rs1_value, &rs2_value),
+            RType::REM => rs1_value.overflowing_rem_signed(&rs2_value),
+            RType::REMU => Register::overflowing_rem(&rs1_value, &rs2_value),
+            RType::ADDW => Register::overflowing_add(&rs1_value, &rs2_value).sign_extend(&32),
             RType::SLLW => {
-                let shift_value = rs2_value.clone() & SHIFT_MASK;
-                let result = rs1_value.clone() << shift_value;
+                let shift_value = rs2_value & SHIFT_MASK_W;
+                let result = rs1_value << shift_value;
                 result.sign_extend(&32)
             }
             RType::SRLW => {
-                let shift_value = rs2_value.clone() & SHIFT_MASK;
-                let result = rs1_value.clone() >> shift_value;
+                let shift_value = rs2_value & SHIFT_MASK_W;
+                let result = rs1_value.zero_extend(&32) >> shift_value;
                 result.sign_extend(&32)
             }
             RType::SRAW => {
-                let result = rs1_value.clone() >> rs2_value;
+                let shift_value = rs2_value & SHIFT_MASK_W;
+                let result = rs1_value.sign_extend(&32).signed_shr(&shift_value);
                 result.sign_extend(&32)
             }
+            RType::MULW => {
+                let result = Register::overflowing_mul(&(rs1_value as u32), &(rs2_value as u32));
+                (result as u64).sign_extend(&32)
+            }
+            RType::DIVW => {
+                let result =
+                    Register::overflowing_div_signed(&(rs1_value as u32), &(rs2_value as u32));
+                (result as u64).sign_extend(&32)
+            }
+            RType::DIVUW => {
+                let result = Register::overflowing_div(&(rs1_value as u32), &(rs2_value as u32));
+                (result as u64).sign_extend(&32)
+            }
+            RType::REMW => {
+                let result =
+                    Register::overflowing_rem_signed(&(rs1_value as u32), &(rs2_value as u32));
+                (result as u64).sign_extend(&32)
+            }
+            RType::REMUW => {
+                let result = Register::overflowing_rem(&(rs1_value as u32), &(rs2_value as u32));
+                (result as u64).sign_extend(&32)
+            }
+            RType::ADDUW => Register::overflowing_add(&rs2_value, &rs1_value.zero_extend(&32)),
+            RType::ANDN => rs1_value & !rs2_value,
+            RType::ORN => rs1_value | !rs2_value,
+            RType::XNOR => !(rs1_value ^ rs2_value),
+            RType::MAX => (rs1_value as i64).max(rs2_value as i64) as u64,
+            RType::MAXU => rs1_value.max(rs2_value),
+            RType::MIN => (rs1_value as i64).min(rs2_value as i64) as u64,
+            RType::MINU => rs1_value.min(rs2_value),
+            RType::ROL => rs1_value.rol(&rs2_value),
+            RType::ROLW => ((rs1_value as u32).rol(&(rs2_value as u32)) as u64).sign_extend(&32),
+            RType::ROR => rs1_value.ror(&rs2_value),
+            RType::RORW => ((rs1_value as u32).ror(&(rs2_value as u32)) as u64).sign_extend(&32),
+            RType::SH1ADD => Register::overflowing_add(&rs2_value, &(rs1_value << 1)),
+            RType::SH1ADDUW => {
+                Register::overflowing_add(&rs2_value, &(rs1_value.zero_extend(&32) << 1))
+            }
+            RType::SH2ADD => Register::overflowing_add(&rs2_value, &(rs1_value << 2)),
+            RType::SH2ADDUW => {
+                Register::overflowing_add(&rs2_value, &(rs1_value.zero_extend(&32) << 2))
+            }
+            RType::SH3ADD => Register::overflowing_add(&rs2_value, &(rs1_value << 3)),
+            RType::SH3ADDUW => {
+                Register::overflowing_add(&rs2_value, &(rs1_value.zero_extend(&32) << 3))
+            }
+            RType::CLMUL => rs1_value.clmul(&rs2_value),
+            RType::CLMULH => rs1_value.clmulh(&rs2_value),
+            RType::CLMULR => rs1_value.clmulr(&rs2_value),
+            RType::BCLR => rs1_value & !(1 << (rs2_value & SHIFT_MASK)),
+            RType::BEXT => (rs1_value >> (rs2_value & SHIFT_MASK)) & 1,
+            RType::BINV => rs1_value ^ (1 << (rs2_value & SHIFT_MASK)),
+            RType::BSET => rs1_value | (1 << (rs2_value & SHIFT_MASK)),
         };
         // read rs1
-        self.read_register(step.global_clk, step.instruction.op_b, rs1_value);
+        self.read_register(step.global_clk, step.instruction.op_b, rs1_value)?;
 
         // read rs2
-        self.read_register(step.global_clk, step.instruction.op_c, rs2_value);
+        self.read_register(step.global_clk, step.instruction.op_c, rs2_value)?;
 
         // write rd
-        self.write_register(step.global_clk, step.instruction.op_a, result);
+        self.write_register(step.global_clk, step.instruction.op_a, result)?;
 
         Ok(())
     }
 
-    pub fn step_stype_or_btype(&mut self, step: &Step) -> (u64, u64, i64) {
-        let rs1 = step.registers[step.instruction.op_a as usize];
-        let rs2 = step.registers[step.instruction.op_b as usize];
+    pub fn step_stype_or_btype(&mut self, step: &Step) -> Result<(u64, u64, i64), StepError> {
+        let rs1 = Self::register(step, step.instruction.op_a)?;
+        let rs2 = Self::register(step, step.instruction.op_b)?;
         // Immediate is always signed here.
         let imm = step.instruction.op_c.sign_extend(&32) as i64;
-        self.read_register(step.global_clk, step.instruction.op_a, rs1);
-        self.read_register(step.global_clk, step.instruction.op_b, rs2);
-        (rs1, rs2, imm)
+        self.read_register(step.global_clk, step.instruction.op_a, rs1)?;
+        self.read_register(step.global_clk, step.instruction.op_b, rs2)?;
+        Ok((rs1, rs2, imm))
     }
 
-    pub fn step_stype(&mut self, stype: SType, step: &Step) -> Result<(), Error> {
+    pub fn step_stype(&mut self, stype: SType, step: &Step) -> Result<(), StepError> {
         // Note that the convention to store rs1/rs2/imm is different from the btype.
-        let (rs2, rs1, imm) = self.step_stype_or_btype(step);
+        let (rs2, rs1, imm) = self.step_stype_or_btype(step)?;
 
         let (addr, _) = (rs1 as i64).overflowing_add(imm);
         let addr = addr as u64;
         let value = rs2;
         match stype {
             SType::SB => {
-                self.write_memory(step.global_clk, addr, value, 8);
+                self.write_memory(step.global_clk, addr, value, 8)?;
             }
             SType::SH => {
-                self.write_memory(step.global_clk, addr, value, 16);
+                self.write_memory(step.global_clk, addr, value, 16)?;
             }
             SType::SW => {
-                self.write_memory(step.global_clk, addr, value, 32);
+                self.write_memory(step.global_clk, addr, value, 32)?;
             }
             SType::SD => {
-                self.write_memory(step.global_clk, addr, value, 64);
+                self.write_memory(step.global_clk, addr, value, 64)?;
             }
         }
         Ok(())
     }
 
-    pub fn step_itype(&mut self, itype: IType, step: &Step) -> Result<(), Error> {
+    pub fn step_itype(&mut self, itype: IType, step: &Step) -> Result<(), StepError> {
         let rd_index = step.instruction.op_a;
-        let rs1_index: usize = step.instruction.op_b as usize;
-        let rs1 = step.registers[rs1_index];
-        self.read_register(step.global_clk, step.instruction.op_b, rs1);
+        let rs1 = Self::register(step, step.instruction.op_b)?;
+        self.read_register(step.global_clk, step.instruction.op_b, rs1)?;
         let imm = step.instruction.op_c;
 
         let addr = Register::overflowing_add(&rs1, &u64::from_i32(imm as i32));
@@ -398,128 +629,152 @@ impl Entries {
         // TODO: we didn't consider word width while doing some arithematic operation.
         match itype {
             IType::JALR => {
-                let result = step.pc + step.instruction.get_instruction_length();
-                let next_pc = rs1 + imm;
-                let next_pc = next_pc;
-                self.write_register(step.global_clk, rd_index, result);
+                let result =
+                    Register::overflowing_add(&step.pc, &step.instruction.get_instruction_length());
+                let next_pc = addr & !1;
+                self.write_register(step.global_clk, rd_index, result)?;
                 self.update_pc_register(step.global_clk, next_pc);
             }
             IType::ADDI => {
-                let rs1 = rs1 as i64;
-                let imm = imm.sign_extend(&32) as i64;
-                let result = rs1 + imm;
-                self.write_register(step.global_clk, rd_index, result as u64);
+                self.write_register(step.global_clk, rd_index, addr)?;
             }
             IType::SLTI => {
                 let result = (rs1 as i64) < (imm as i64);
-                self.write_register(step.global_clk, rd_index, result as u64);
+                self.write_register(step.global_clk, rd_index, result as u64)?;
             }
             IType::SLTIU => {
                 let result = (rs1 as u64) < (imm as u64);
-                self.write_register(step.global_clk, rd_index, result as u64);
+                self.write_register(step.global_clk, rd_index, result as u64)?;
             }
             IType::XORI => {
                 let rs1 = rs1 as i64;
                 let imm = imm as i64;
                 let result = rs1 ^ imm;
-                self.write_register(step.global_clk, rd_index, result as u64);
+                self.write_register(step.global_clk, rd_index, result as u64)?;
             }
             IType::ORI => {
                 let rs1 = rs1 as i64;
                 let imm = imm as i64;
                 let result = rs1 | imm;
-                self.write_register(step.global_clk, rd_index, result as u64);
+                self.write_register(step.global_clk, rd_index, result as u64)?;
             }
             IType::ANDI => {
                 let rs1 = rs1 as i64;
                 let imm = imm as i64;
                 let result = rs1 & imm;
-                self.write_register(step.global_clk, rd_index, result as u64);
+                self.write_register(step.global_clk, rd_index, result as u64)?;
             }
             IType::SLLI => {
-                let rs1 = rs1 as u64;
-                let imm = imm as u64;
-                let result = rs1 << imm;
-                self.write_register(step.global_clk, rd_index, result as u64);
+                let result = rs1 << (imm & SHIFT_MASK);
+                self.write_register(step.global_clk, rd_index, result)?;
             }
             IType::SRLI => {
-                let rs1 = rs1 as u64;
-                let imm = imm as u64;
-                let result = rs1 >> imm;
-                self.write_register(step.global_clk, rd_index, result as u64);
+                let result = rs1 >> (imm & SHIFT_MASK);
+                self.write_register(step.global_clk, rd_index, result)?;
             }
             IType::SRAI => {
-                let rs1 = rs1 as i64;
-                let imm = imm as u64;
-                let result = rs1.shr(imm);
-                self.write_register(step.global_clk, rd_index, result as u64);
+                let result = rs1.signed_shr(&(imm & SHIFT_MASK));
+                self.write_register(step.global_clk, rd_index, result)?;
             }
             IType::SLLIW => {
-                let rs1 = rs1 as u64;
-                let imm = imm as u64;
-                let result = rs1 << imm;
-                let result = result.sign_extend(&32);
-                self.write_register(step.global_clk, rd_index, result as u64);
+                let result = rs1 << (imm & SHIFT_MASK_W);
+                self.write_register(step.global_clk, rd_index, result.sign_extend(&32))?;
             }
             IType::ADDIW => {
-                let rs1 = rs1 as i64;
-                let imm = imm as i64;
-                let result = rs1 + imm;
-                let result = (result as u64).sign_extend(&32);
-                self.write_register(step.global_clk, rd_index, result as u64);
+                self.write_register(step.global_clk, rd_index, addr.sign_extend(&32))?;
             }
             IType::SRLIW => {
-                let rs1 = rs1 as u64;
-                let imm = imm as u64;
-                let result = rs1 >> imm;
-                let result = (result as u64).sign_extend(&32);
-                self.write_register(step.global_clk, rd_index, result as u64);
+                let result = rs1.zero_extend(&32) >> (imm & SHIFT_MASK_W);
+                self.write_register(step.global_clk, rd_index, result.sign_extend(&32))?;
             }
             IType::SRAIW => {
-                let rs1 = rs1 as i64;
-                let imm = imm as u64;
-                let result = rs1.shr(imm);
-                let result = (result as u64).sign_extend(&32);
-                self.write_register(step.global_clk, rd_index, result as u64);
-            }
+                let result = rs1.sign_extend(&32).signed_shr(&(imm & SHIFT_MASK_W));
+                self.write_register(step.global_clk, rd_index, result.sign_extend(&32))?;
+            }
+            IType::RORI => self.write_register(step.global_clk, rd_index, rs1.ror(&imm))?,
+            IType::RORIW => {
+                let result = (rs1 as u32).ror(&(imm as u32)) as u64;
+                self.write_register(step.global_clk, rd_index, result.sign_extend(&32))?;
+            }
+            IType::SLLIUW => {
+                let result = rs1.zero_extend(&32) << (imm & SHIFT_MASK);
+                self.write_register(step.global_clk, rd_index, result)?;
+            }
+            IType::BCLRI => {
+                let result = rs1 & !(1 << (imm & SHIFT_MASK));
+                self.write_register(step.global_clk, rd_index, result)?;
+            }
+            IType::BEXTI => {
+                let result = (rs1 >> (imm & SHIFT_MASK)) & 1;
+                self.write_register(step.global_clk, rd_index, result)?;
+            }
+            IType::BINVI => {
+                let result = rs1 ^ (1 << (imm & SHIFT_MASK));
+                self.write_register(step.global_clk, rd_index, result)?;
+            }
+            IType::BSETI => {
+                let result = rs1 | (1 << (imm & SHIFT_MASK));
+                self.write_register(step.global_clk, rd_index, result)?;
+            }
+            IType::CLZ => self.write_register(step.global_clk, rd_index, rs1.clz())?,
+            IType::CLZW => {
+                let result = (rs1 as u32).clz() as u64;
+                self.write_register(step.global_clk, rd_index, result)?;
+            }
+            IType::CTZ => self.write_register(step.global_clk, rd_index, rs1.ctz())?,
+            IType::CTZW => {
+                let result = (rs1 as u32).ctz() as u64;
+                self.write_register(step.global_clk, rd_index, result)?;
+            }
+            IType::CPOP => self.write_register(step.global_clk, rd_index, rs1.cpop())?,
+            IType::CPOPW => {
+                let result = (rs1 as u32).cpop() as u64;
+                self.write_register(step.global_clk, rd_index, result)?;
+            }
+            IType::ORCB => self.write_register(step.global_clk, rd_index, rs1.orcb())?,
+            IType::REV8 => self.write_register(step.global_clk, rd_index, rs1.rev8())?,
+            IType::SEXTB => self.write_register(step.global_clk, rd_index, rs1.sign_extend(&8))?,
+            IType::SEXTH => self.write_register(step.global_clk, rd_index, rs1.sign_extend(&16))?,
+            IType::ZEXTH => self.write_register(step.global_clk, rd_index, rs1.zero_extend(&16))?,
             IType::LB | IType::LBU => {
-                self.should_copy_registers = true;
-                self.read_memory(step.global_clk, addr, 8);
+                self.loaded_register = Some(rd_index);
+                self.read_memory(step.global_clk, addr, 8)?;
             }
             IType::LH | IType::LHU => {
-                self.should_copy_registers = true;
-                self.read_memory(step.global_clk, addr, 16);
+                self.loaded_register = Some(rd_index);
+                self.read_memory(step.global_clk, addr, 16)?;
             }
             IType::LW | IType::LWU => {
-                self.should_copy_registers = true;
-                self.read_memory(step.global_clk, addr, 32);
+                self.loaded_register = Some(rd_index);
+                self.read_memory(step.global_clk, addr, 32)?;
             }
             IType::LD => {
-                self.should_copy_registers = true;
-                self.read_memory(step.global_clk, addr, 64);
+                self.loaded_register = Some(rd_index);
+                self.read_memory(step.global_clk, addr, 64)?;
             }
         }
         Ok(())
     }
 
-    pub fn step_jtype(&mut self, jtype: JType, step: &Step) -> Result<(), Error> {
+    pub fn step_jtype(&mut self, jtype: JType, step: &Step) -> Result<(), StepError> {
         let rd_index = step.instruction.op_a;
         let imm = step.instruction.op_b as i32;
 
         match jtype {
             JType::JAL => {
-                let result = step.pc + step.instruction.get_instruction_length();
+                let result =
+                    Register::overflowing_add(&step.pc, &step.instruction.get_instruction_length());
                 let next_pc = Register::overflowing_add(&step.pc, &u64::from_i32(imm.clone()));
-                self.write_register(step.global_clk, rd_index, result);
+                self.write_register(step.global_clk, rd_index, result)?;
                 self.update_pc_register(step.global_clk, next_pc);
             }
         }
         Ok(())
     }
 
-    pub fn step_btype(&mut self, btype: BType, step: &Step) -> Result<(), Error> {
+    pub fn step_btype(&mut self, btype: BType, step: &Step) -> Result<(), StepError> {
         // Note that the convention to store rs1/rs2/imm is different from the stype.
-        let (rs1, rs2, imm) = self.step_stype_or_btype(step);
+        let (rs1, rs2, imm) = self.step_stype_or_btype(step)?;
 
         let new_pc = if match btype {
             BType::BEQ => rs1 as i64 == rs2 as i64,
@@ -529,70 +784,219 @@ impl Entries {
             BType::BLT => (rs1 as i64) < (rs2 as i64),
             BType::BLTU => (rs1 as u64) < (rs2 as u64),
         } {
-            (step.pc as i64 + imm) as u64
+            Register::overflowing_add(&step.pc, &(imm as u64))
         } else {
-            step.pc + step.instruction.get_instruction_length() as u64
+            Register::overflowing_add(&step.pc, &step.instruction.get_instruction_length())
         };
         self.update_pc_register(step.global_clk, new_pc);
 
         Ok(())
     }
 
-    pub fn step_utype(&mut self, u: UType, step: &Step) -> Result<(), Error> {
+    pub fn step_utype(&mut self, u: UType, step: &Step) -> Result<(), StepError> {
         let imm = step.instruction.op_b as u64;
 
         let result = match u {
             UType::LUI => imm as u64,
-            UType::AUIPC => {
-                let new_pc = (step.pc + imm) as u64;
-                self.update_pc_register(step.global_clk, new_pc);
-                new_pc
-            }
+            UType::AUIPC => Register::overflowing_add(&step.pc, &imm),
         };
 
         // write rd
-        self.write_register(step.global_clk, step.instruction.op_a, result);
+        self.write_register(step.global_clk, step.instruction.op_a, result)?;
 
         Ok(())
     }
 
-    pub fn step_notype(&mut self, n: NoType, _step: &Step) -> Result<(), Error> {
+    pub fn step_atype(&mut self, a: AType, step: &Step) -> Result<(), StepError> {
+        let rd_index = step.instruction.op_a;
+        let addr = Self::register(step, step.instruction.op_b)?;
+        let rs2 = Self::register(step, step.instruction.op_c)?;
+        self.read_register(step.global_clk, step.instruction.op_b, addr)?;
+
+        let width = match a {
+            AType::LRW
+            | AType::SCW
+            | AType::AMOSWAPW
+            | AType::AMOADDW
+            | AType::AMOXORW
+            | AType::AMOANDW
+            | AType::AMOORW
+            | AType::AMOMINW
+            | AType::AMOMAXW
+            | AType::AMOMINUW
+            | AType::AMOMAXUW => 32,
+            _ => 64,
+        };
+
+        match a {
+            AType::LRW | AType::LRD => {
+                // The loaded value isn't known to the buffer, see the loads.
+                self.loaded_register = Some(rd_index);
+                let loaded = self.read_memory(step.global_clk, addr, width)?;
+                let loaded = if width == 32 {
+                    loaded.sign_extend(&32)
+                } else {
+                    loaded
+                };
+                self.write_register(step.global_clk, rd_index, loaded)?;
+                self.reservation = Some(addr);
+            }
+            AType::SCW | AType::SCD => {
+                self.read_register(step.global_clk, step.instruction.op_c, rs2)?;
+                let success = self.reservation.take() == Some(addr);
+                if success {
+                    self.write_memory(step.global_clk, addr, rs2, width)?;
+                }
+                self.write_register(step.global_clk, rd_index, !success as u64)?;
+            }
+            _ => {
+                self.read_register(step.global_clk, step.instruction.op_c, rs2)?;
+                self.loaded_register = Some(rd_index);
+                let loaded = self.read_memory(step.global_clk, addr, width)?;
+                let (loaded, rs2) = if width == 32 {
+                    (loaded.sign_extend(&32), rs2.sign_extend(&32))
+                } else {
+                    (loaded, rs2)
+                };
+                let value = match a {
+                    AType::AMOSWAPW | AType::AMOSWAPD => rs2,
+                    AType::AMOADDW | AType::AMOADDD => Register::overflowing_add(&loaded, &rs2),
+                    AType::AMOXORW | AType::AMOXORD => loaded ^ rs2,
+                    AType::AMOANDW | AType::AMOANDD => loaded & rs2,
+                    AType::AMOORW | AType::AMOORD => loaded | rs2,
+                    AType::AMOMINW | AType::AMOMIND => (loaded as i64).min(rs2 as i64) as u64,
+                    AType::AMOMAXW | AType::AMOMAXD => (loaded as i64).max(rs2 as i64) as u64,
+                    AType::AMOMINUW | AType::AMOMINUD => loaded.min(rs2),
+                    AType::AMOMAXUW | AType::AMOMAXUD => loaded.max(rs2),
+                    _ => unreachable!(),
+                };
+                self.write_memory(step.global_clk, addr, value, width)?;
+                self.write_register(step.global_clk, rd_index, loaded)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Executes a macro-op in one step, the intermediate value of the first
+    /// instruction is overwritten by the second one.
+    pub fn step_fusedtype(&mut self, f: FusedType, step: &Step) -> Result<(), StepError> {
+        let rd_index = step.instruction.op_a;
+        let value = step.instruction.op_b;
+        let link = Register::overflowing_add(&step.pc, &step.instruction.get_instruction_length());
+
+        match f {
+            FusedType::CUSTOMLOADIMM | FusedType::LDSIGNEXTENDED32CONSTANT => {
+                self.write_register(step.global_clk, rd_index, value)?;
+            }
+            FusedType::FARJUMPREL => {
+                let next_pc = Register::overflowing_add(&step.pc, &value) & !1;
+                self.write_register(step.global_clk, rd_index, link)?;
+                self.update_pc_register(step.global_clk, next_pc);
+            }
+            FusedType::FARJUMPABS => {
+                self.write_register(step.global_clk, rd_index, link)?;
+                self.update_pc_register(step.global_clk, value);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn step_notype(&mut self, n: NoType, step: &Step) -> Result<(), StepError> {
         match n {
             NoType::FENCE => (),
             NoType::ECALL => {
-                self.should_copy_registers = true;
+                // The syscalls return in a0.
+                self.loaded_register = Some(10);
+            }
+            NoType::EBREAK | NoType::UNIMP => {
+                return Err(StepError::Trap {
+                    global_clk: step.global_clk,
+                    pc: step.pc,
+                    instruction: step.instruction.clone(),
+                })
             }
-            NoType::EBREAK => todo!(),
-            NoType::UNIMP => todo!(),
         };
 
         Ok(())
     }
 
-    pub fn step(&mut self, step: &Step) -> Result<(), Error> {
+    /// Compares the step with the replayed state, `None` if they agree.
+    pub fn divergence(&self, index: usize, step: &Step) -> Option<Divergence> {
+        let registers = self.diverging_registers(step);
+        let divergence = Divergence {
+            index,
+            global_clk: step.global_clk,
+            pc: step.pc,
+            instruction: step.instruction.clone(),
+            expected_pc: self.next_pc.filter(|pc| *pc != step.pc),
+            registers,
+            program: None,
+        };
+        (!divergence.is_empty()).then_some(divergence)
+    }
+
+    /// `(index, expected, actual)` of the registers of the step which differ
+    /// from the replayed ones, but the one the last step loaded.
+    fn diverging_registers(&self, step: &Step) -> Vec<(usize, u64, u64)> {
+        if self.should_copy_registers {
+            return Vec::new();
+        }
+        let loaded = self.loaded_register.filter(|index| *index != 0);
+        self.register_buffer
+            .iter()
+            .zip(step.registers.iter())
+            .enumerate()
+            .filter(|(index, (expected, actual))| {
+                expected != actual && loaded != Some(*index as u64)
+            })
+            .map(|(index, (expected, actual))| (index, *expected, *actual))
+            .collect()
+    }
+
+    /// Takes over the state of the step, so the replay goes on after a divergence.
+    pub fn resync(&mut self, step: &Step) {
+        self.register_buffer = step.registers.clone();
+        self.next_pc = Some(step.pc);
+    }
+
+    pub fn step(&mut self, step: &Step) -> Result<(), StepError> {
         self.rwc = 0;
         let opcode = step.instruction.opcode;
-        if self.should_copy_registers {
-            self.register_buffer = step.registers.clone();
-            self.should_copy_registers = false;
-        } else {
-            for (index, (left, right)) in step
-                .registers
-                .iter()
-                .zip(self.register_buffer.clone())
-                .enumerate()
-            {
-                assert_eq!(
-                    *left, right,
-                    "Register {} is not the same in step {}",
-                    index, step.global_clk
-                )
-            }
+        if step.registers.len() != self.register_buffer.len() {
+            return Err(StepError::RegisterIndex {
+                global_clk: step.global_clk,
+                index: step.registers.len().min(self.register_buffer.len()) as u64,
+            });
+        }
+        if let Some(&(index, expected, actual)) = self.diverging_registers(step).first() {
+            return Err(StepError::Register {
+                global_clk: step.global_clk,
+                index,
+                expected,
+                actual,
+            });
+        }
+        // The other registers are the replayed ones.
+        self.register_buffer = step.registers.clone();
+        self.should_copy_registers = false;
+        self.loaded_register = None;
+
+        if let Some(next_pc) = self.next_pc.filter(|pc| *pc != step.pc) {
+            return Err(StepError::Pc {
+                global_clk: step.global_clk,
+                expected: next_pc,
+                actual: step.pc,
+            });
         }
+        // Instructions which don't jump move to the next one, 2 bytes ahead for
+        // compressed instructions.
+        self.next_pc = Some(Register::overflowing_add(
+            &step.pc,
+            &step.instruction.get_instruction_length(),
+        ));
 
-        self.pc_instructions
-            .insert(step.pc, step.instruction.clone());
         self.pcs.push((step.global_clk, step.pc));
+        self.instructions.push(step.instruction.clone());
 
         match opcode.into() {
             InstructionType::RType(r) => self.step_rtype(r, step),
@@ -601,6 +1005,8 @@ impl Entries {
             InstructionType::IType(i) => self.step_itype(i, step),
             InstructionType::JType(j) => self.step_jtype(j, step),
             InstructionType::UType(u) => self.step_utype(u, step),
+            InstructionType::AType(a) => self.step_atype(a, step),
+            InstructionType::FusedType(f) => self.step_fusedtype(f, step),
             InstructionType::NoType(n) => self.step_notype(n, step),
         }
     }
@@ -1,12 +1,14 @@
 use runtime::trace::Instruction;
 
-use crate::entries::{MemoryOp, RegisterOps};
+use crate::entries::{MemoryOps, RegisterOps};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct OpStep<'a> {
     pub global_clk: u64,
     pub pc: u64,
+    /// Pc of the next step, or the one the replay continues at after the last step.
+    pub next_pc: u64,
     pub instruction: &'a Instruction,
     pub register_indexes: Option<&'a RegisterOps>,
-    pub memory_address: Option<&'a MemoryOp>,
+    pub memory_address: Option<&'a MemoryOps>,
 }
@@ -27,10 +27,12 @@ pub struct Pow5Config<F: FieldExt, const WIDTH: usize, const RATE: usize> {
     rc_b: [Column<Fixed>; WIDTH],
     s_full: Selector,
     s_partial: Selector,
+    s_partial_single: Selector,
     s_pad_and_add: Selector,
 
     half_full_rounds: usize,
     half_partial_rounds: usize,
+    odd_partial_round: bool,
     alpha: [u64; 4],
     round_constants: Vec<[F; WIDTH]>,
     m_reg: Mds<F, WIDTH>,
@@ -65,11 +67,12 @@ impl<F: FieldExt, const WIDTH: usize, const RATE: usize> Pow5Chip<F, WIDTH, RATE
     ) -> Pow5Config<F, WIDTH, RATE> {
         assert_eq!(RATE, WIDTH - 1);
         // Generate constants for the Poseidon permutation.
-        // This gadget requires R_F and R_P to be even.
+        // This gadget requires R_F to be even. With an odd R_P, the last partial round
+        // takes a row of its own.
         assert!(S::full_rounds() & 1 == 0);
-        assert!(S::partial_rounds() & 1 == 0);
         let half_full_rounds = S::full_rounds() / 2;
         let half_partial_rounds = S::partial_rounds() / 2;
+        let odd_partial_round = S::partial_rounds() & 1 == 1;
         let (round_constants, m_reg, m_inv) = S::constants();
 
         // This allows state words to be initialized (by constraining them equal to fixed
@@ -86,6 +89,7 @@ impl<F: FieldExt, const WIDTH: usize, const RATE: usize> Pow5Chip<F, WIDTH, RATE
 
         let s_full = meta.selector();
         let s_partial = meta.selector();
+        let s_partial_single = meta.selector();
         let s_pad_and_add = meta.selector();
 
         let alpha = [5, 0, 0, 0];
@@ -162,6 +166,33 @@ impl<F: FieldExt, const WIDTH: usize, const RATE: usize> Pow5Chip<F, WIDTH, RATE
             )
         });
 
+        meta.create_gate("single partial round", |meta| {
+            let s_partial_single = meta.query_selector(s_partial_single);
+
+            Constraints::with_selector(
+                s_partial_single,
+                (0..WIDTH)
+                    .map(|next_idx| {
+                        let state_next = meta.query_advice(state[next_idx], Rotation::next());
+                        let expr = (0..WIDTH)
+                            .map(|idx| {
+                                let state_cur = meta.query_advice(state[idx], Rotation::cur());
+                                let rc_a = meta.query_fixed(rc_a[idx], Rotation::cur());
+                                let word = if idx == 0 {
+                                    pow_5(state_cur + rc_a)
+                                } else {
+                                    state_cur + rc_a
+                                };
+                                word * m_reg[next_idx][idx]
+                            })
+                            .reduce(|acc, term| acc + term)
+                            .expect("WIDTH > 0");
+                        expr - state_next
+                    })
+                    .collect::<Vec<_>>(),
+            )
+        });
+
         meta.create_gate("pad-and-add", |meta| {
             let initial_state_rate = meta.query_advice(state[RATE], Rotation::prev());
             let output_state_rate = meta.query_advice(state[RATE], Rotation::next());
@@ -195,9 +226,11 @@ impl<F: FieldExt, const WIDTH: usize, const RATE: usize> Pow5Chip<F, WIDTH, RATE
             rc_b,
             s_full,
             s_partial,
+            s_partial_single,
             s_pad_and_add,
             half_full_rounds,
             half_partial_rounds,
+            odd_partial_round,
             alpha,
             round_constants,
             m_reg,
@@ -257,13 +290,25 @@ impl<F: FieldExt, S: Spec<F, WIDTH, RATE>, const WIDTH: usize, const RATE: usize
                     })
                 })?;
 
+                let state = if config.odd_partial_round {
+                    state.single_partial_round(
+                        &mut region,
+                        config,
+                        config.half_full_rounds + 2 * config.half_partial_rounds,
+                        config.half_full_rounds + config.half_partial_rounds,
+                    )?
+                } else {
+                    state
+                };
+                let odd = config.odd_partial_round as usize;
+
                 let state = (0..config.half_full_rounds).fold(Ok(state), |res, r| {
                     res.and_then(|state| {
                         state.full_round(
                             &mut region,
                             config,
-                            config.half_full_rounds + 2 * config.half_partial_rounds + r,
-                            config.half_full_rounds + config.half_partial_rounds + r,
+                            config.half_full_rounds + 2 * config.half_partial_rounds + odd + r,
+                            config.half_full_rounds + config.half_partial_rounds + odd + r,
                         )
                     })
                 })?;
@@ -460,6 +505,49 @@ impl<F: FieldExt, const WIDTH: usize> Pow5State<F, WIDTH> {
         })
     }
 
+    fn single_partial_round<const RATE: usize>(
+        self,
+        region: &mut Region<F>,
+        config: &Pow5Config<F, WIDTH, RATE>,
+        round: usize,
+        offset: usize,
+    ) -> Result<Self, Error> {
+        Self::round(
+            region,
+            config,
+            round,
+            offset,
+            config.s_partial_single,
+            |_| {
+                let q = self.0.iter().enumerate().map(|(idx, word)| {
+                    word.0
+                        .value()
+                        .map(|v| *v + config.round_constants[round][idx])
+                });
+                let r: Value<Vec<F>> = q
+                    .enumerate()
+                    .map(|(idx, q)| {
+                        if idx == 0 {
+                            q.map(|q| q.pow(&config.alpha))
+                        } else {
+                            q
+                        }
+                    })
+                    .collect();
+                let m = &config.m_reg;
+                let state = m.iter().map(|m_i| {
+                    r.as_ref().map(|r| {
+                        r.iter()
+                            .enumerate()
+                            .fold(F::zero(), |acc, (j, r_j)| acc + m_i[j] * r_j)
+                    })
+                });
+
+                Ok((round + 1, state.collect::<Vec<_>>().try_into().unwrap()))
+            },
+        )
+    }
+
     fn partial_round<const RATE: usize>(
         self,
         region: &mut Region<F>,
@@ -0,0 +1,80 @@
+//! Benchmarks of the Fiat-Shamir transcripts on the reads of a verifier: the
+//! points, scalars and challenges of a proof of the size of the bs2 proofs.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use group::{Curve, Group};
+use halo2_proofs::transcript::{
+    Blake2bRead, Blake2bWrite, Challenge255, Keccak256Read, Keccak256Write, PoseidonRead,
+    PoseidonWrite, TranscriptRead, TranscriptReadBuffer, TranscriptWrite, TranscriptWriterBuffer,
+};
+use halo2curves::bn256::{Fr, G1Affine, G1};
+
+const POINTS: usize = 30;
+const SCALARS: usize = 50;
+const CHALLENGES: usize = 8;
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let points = (0..POINTS)
+        .map(|i| (G1::generator() * Fr::from(i as u64 + 1)).to_affine())
+        .collect::<Vec<_>>();
+    let scalars = (0..SCALARS)
+        .map(|i| Fr::from(i as u64 + 1))
+        .collect::<Vec<_>>();
+
+    let blake2b = write::<Blake2bWrite<_, _, _>>(&points, &scalars);
+    let keccak256 = write::<Keccak256Write<_, _, _>>(&points, &scalars);
+    let poseidon = write::<PoseidonWrite<_, _, _>>(&points, &scalars);
+
+    let mut group = c.benchmark_group("transcript");
+    group.bench_function("Blake2b", |b| {
+        b.iter(|| read::<Blake2bRead<_, _, _>>(&blake2b))
+    });
+    group.bench_function("Keccak256", |b| {
+        b.iter(|| read::<Keccak256Read<_, _, _>>(&keccak256))
+    });
+    group.bench_function("Poseidon", |b| {
+        b.iter(|| read::<PoseidonRead<_, _, _>>(&poseidon))
+    });
+    // The Poseidon constants are derived when the transcript is created.
+    group.bench_function("Poseidon init", |b| {
+        b.iter(|| PoseidonRead::<_, G1Affine, Challenge255<_>>::init(&poseidon[..]))
+    });
+    group.finish();
+}
+
+fn write<W>(points: &[G1Affine], scalars: &[Fr]) -> Vec<u8>
+where
+    W: TranscriptWriterBuffer<Vec<u8>, G1Affine, Challenge255<G1Affine>>,
+{
+    let mut writer = W::init(vec![]);
+    for (i, point) in points.iter().enumerate() {
+        writer.write_point(*point).unwrap();
+        if i % (POINTS / CHALLENGES) == 0 {
+            writer.squeeze_challenge();
+        }
+    }
+    for scalar in scalars.iter() {
+        writer.write_scalar(*scalar).unwrap();
+    }
+    writer.finalize()
+}
+
+fn read<'a, R>(proof: &'a [u8])
+where
+    R: TranscriptReadBuffer<&'a [u8], G1Affine, Challenge255<G1Affine>>,
+{
+    let mut reader = R::init(proof);
+    for i in 0..POINTS {
+        reader.read_point().unwrap();
+        if i % (POINTS / CHALLENGES) == 0 {
+            reader.squeeze_challenge();
+        }
+    }
+    for _ in 0..SCALARS {
+        reader.read_scalar().unwrap();
+    }
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
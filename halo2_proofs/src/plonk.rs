@@ -119,9 +119,10 @@ where
         let mut length = [0u8; 4];
         reader.read_exact(&mut length)?;
         let length = u32::from_be_bytes(length) as usize;
+        // Read one by one, the length isn't trusted to allocate them.
         let selector_assignments = (0..length)
-            .map(|_| SelectorAssignment::read(reader).unwrap())
-            .collect::<Vec<_>>();
+            .map(|_| SelectorAssignment::read(reader))
+            .collect::<io::Result<Vec<_>>>()?;
         let cs = cs.ckb_recreate_side_effect(selector_assignments.clone());
 
         Ok(Self::from_parts(
@@ -232,6 +233,11 @@ impl<C: CurveAffine> VerifyingKey<C> {
     pub fn cs(&self) -> &ConstraintSystem<C::Scalar> {
         &self.cs
     }
+
+    /// Returns the representative of this `VerifyingKey` in transcripts
+    pub fn transcript_repr(&self) -> C::Scalar {
+        self.transcript_repr
+    }
 }
 
 /// Minimal representation of a verification key that can be used to identify
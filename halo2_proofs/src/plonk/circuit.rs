@@ -392,6 +392,11 @@ impl Selector {
     pub fn is_simple(&self) -> bool {
         self.1
     }
+
+    /// Index of this selector in the constraint system.
+    pub fn index(&self) -> usize {
+        self.0
+    }
 }
 
 /// Query of fixed column at a certain relative location
@@ -782,7 +787,7 @@ impl<F: SerdePrimeField> Expression<F> {
         reader.read_exact(&mut typ)?;
         let expression = match typ[0] {
             0x00 => {
-                let scalar = F::read(reader, format).unwrap();
+                let scalar = F::read(reader, format)?;
                 Ok(Expression::Constant(scalar))
             }
             0x01 => {
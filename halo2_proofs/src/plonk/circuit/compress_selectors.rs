@@ -49,7 +49,7 @@ impl<F: SerdePrimeField> SelectorAssignment<F> {
         let mut combination_index = [0u8; 4];
         reader.read_exact(&mut combination_index)?;
         let combination_index = u32::from_be_bytes(combination_index) as usize;
-        let expression = Expression::read(reader, SerdeFormat::RawBytes).unwrap();
+        let expression = Expression::read(reader, SerdeFormat::RawBytes)?;
         Ok(Self {
             selector,
             combination_index,
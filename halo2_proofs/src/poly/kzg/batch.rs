@@ -0,0 +1,91 @@
+use core::fmt::Debug;
+
+use super::commitment::ParamsKZG;
+use super::msm::{DualMSM, PreparedG2KZG};
+use crate::arithmetic::FieldExt;
+use crate::poly::commitment::MSM;
+use crate::Vec;
+use ff::Field;
+use group::GroupEncoding;
+use halo2curves::pairing::{Engine, MillerLoopResult, MultiMillerLoop};
+
+/// A verifier that checks many KZG proofs with a single pairing check.
+///
+/// The deferred checks of the proofs, see
+/// [`DeferredStrategy`](super::strategy::DeferredStrategy), are summed with the
+/// powers of a challenge hashed from all of them, so unlike the
+/// `AccumulatorStrategy` it needs no source of randomness: a prover can't
+/// choose the proofs after the challenge.
+#[derive(Debug)]
+pub struct BatchVerifierKZG<'params, E: Engine> {
+    params: &'params ParamsKZG<E>,
+    checks: Vec<(E::G1Affine, E::G1Affine)>,
+}
+
+impl<'params, E: MultiMillerLoop + Debug> BatchVerifierKZG<'params, E> {
+    /// Constructs an empty batch
+    pub fn new(params: &'params ParamsKZG<E>) -> Self {
+        Self {
+            params,
+            checks: Vec::new(),
+        }
+    }
+
+    /// Adds the deferred check of a proof
+    pub fn add(&mut self, msm: DualMSM<'params, E>) {
+        self.checks.push(msm.eval());
+    }
+
+    /// Adds a check `e(left, s_g2) = e(right, g2)` of points already
+    /// evaluated, such as the accumulator of an aggregation proof
+    pub fn add_check(&mut self, left: E::G1Affine, right: E::G1Affine) {
+        self.checks.push((left, right));
+    }
+
+    /// Number of proofs in the batch
+    pub fn len(&self) -> usize {
+        self.checks.len()
+    }
+
+    /// Returns `true` if no proof was added
+    pub fn is_empty(&self) -> bool {
+        self.checks.is_empty()
+    }
+
+    /// Checks all the proofs of the batch, `true` for an empty one.
+    ///
+    /// Returns `false` if *some* proof is invalid. If the caller needs to
+    /// identify the failing proofs, it must check them separately.
+    pub fn finalize(self) -> bool {
+        let prepared = PreparedG2KZG::new(self.params);
+        self.finalize_prepared(&prepared)
+    }
+
+    /// Checks all the proofs of the batch with the G2 points of the params
+    /// prepared ahead of time
+    pub fn finalize_prepared(self, prepared: &PreparedG2KZG<E>) -> bool {
+        let challenge = self.challenge();
+        let mut batch = DualMSM::new(self.params);
+        let mut power = E::Scalar::one();
+        for (left, right) in self.checks.iter() {
+            batch.left.append_term(power, (*left).into());
+            batch.right.append_term(power, (*right).into());
+            power *= challenge;
+        }
+        batch.check_prepared(prepared)
+    }
+
+    fn challenge(&self) -> E::Scalar {
+        let mut hasher = blake2b_simd::Params::new()
+            .hash_length(64)
+            .personal(b"Halo2-KZG-Batch\0")
+            .to_state();
+        for (left, right) in self.checks.iter() {
+            hasher.update(left.to_bytes().as_ref());
+            hasher.update(right.to_bytes().as_ref());
+        }
+        let mut bytes = [0u8; 64];
+        bytes.copy_from_slice(hasher.finalize().as_bytes());
+        E::Scalar::from_bytes_wide(&bytes)
+    }
+}
@@ -1,7 +1,11 @@
+/// Batch verification of KZG proofs
+pub mod batch;
 /// KZG commitment scheme
 pub mod commitment;
 /// Multiscalar multiplication engines
 pub mod msm;
+/// Verifier params shrunk to the points a verifier uses
+pub mod shrink;
 /// KZG multi-open scheme
 pub mod multiopen;
 /// Strategies used with KZG scheme
@@ -5,9 +5,10 @@ use crate::{
     arithmetic::{best_multiexp, parallelize, CurveAffine},
     poly::commitment::MSM,
 };
-use crate::{vec, Vec};
+use crate::{io, vec, Vec};
 use group::{Curve, Group};
 use halo2curves::pairing::{Engine, MillerLoopResult, MultiMillerLoop};
+use halo2curves::serde::SerdeObject;
 
 /// A multiscalar multiplication in the polynomial commitment scheme
 #[derive(Clone, Default, Debug)]
@@ -148,15 +149,26 @@ impl<'a, E: MultiMillerLoop + Debug> DualMSM<'a, E> {
         self.right.add_msm(&other.right);
     }
 
+    /// Evaluates both channels, the points `left` and `right` of the pairing
+    /// check `e(left, s_g2) = e(right, g2)`
+    pub fn eval(&self) -> (E::G1Affine, E::G1Affine) {
+        (self.left.eval().into(), self.right.eval().into())
+    }
+
     /// Performs final pairing check with given verifier params and two channel linear combination
     pub fn check(self) -> bool {
-        let s_g2_prepared = E::G2Prepared::from(self.params.s_g2);
-        let n_g2_prepared = E::G2Prepared::from(-self.params.g2);
+        let prepared = PreparedG2KZG::new(self.params);
+        self.check_prepared(&prepared)
+    }
 
+    /// Performs the final pairing check with the line coefficients of `s_g2`
+    /// and `-g2` computed ahead of time, a single multi Miller loop and final
+    /// exponentiation
+    pub fn check_prepared(self, prepared: &PreparedG2KZG<E>) -> bool {
         let left: <E as Engine>::G1Affine = self.left.eval().into();
         let right: <E as Engine>::G1Affine = self.right.eval().into();
 
-        let (term_1, term_2) = ((&left, &s_g2_prepared), (&right, &n_g2_prepared));
+        let (term_1, term_2) = ((&left, &prepared.s_g2), (&right, &prepared.neg_g2));
         let terms = &[term_1, term_2];
 
         bool::from(
@@ -166,3 +178,48 @@ impl<'a, E: MultiMillerLoop + Debug> DualMSM<'a, E> {
         )
     }
 }
+
+/// The G2 points of verifier params prepared for the pairing check. They only
+/// depend on the params, so a verifier can compute them once, or read them
+/// precomputed, instead of on every check.
+#[derive(Clone)]
+pub struct PreparedG2KZG<E: MultiMillerLoop> {
+    pub s_g2: E::G2Prepared,
+    pub neg_g2: E::G2Prepared,
+}
+
+impl<E: MultiMillerLoop> Debug for PreparedG2KZG<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("PreparedG2KZG").finish_non_exhaustive()
+    }
+}
+
+impl<E: MultiMillerLoop + Debug> PreparedG2KZG<E> {
+    /// Prepares `s_g2` and `-g2` of `params`
+    pub fn new(params: &ParamsKZG<E>) -> Self {
+        Self {
+            s_g2: E::G2Prepared::from(params.s_g2),
+            neg_g2: E::G2Prepared::from(-params.g2),
+        }
+    }
+}
+
+impl<E: MultiMillerLoop> PreparedG2KZG<E>
+where
+    E::G2Prepared: SerdeObject,
+{
+    /// Writes the line coefficients of `s_g2` then `-g2`
+    pub fn write<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.s_g2.write_raw(writer)?;
+        self.neg_g2.write_raw(writer)
+    }
+
+    /// Reads prepared points written by [`PreparedG2KZG::write`]. Nothing
+    /// ties them to the params, they must come from a trusted source.
+    pub fn read<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        Ok(Self {
+            s_g2: E::G2Prepared::read_raw(reader)?,
+            neg_g2: E::G2Prepared::read_raw(reader)?,
+        })
+    }
+}
@@ -0,0 +1,150 @@
+//! Verifier params shrunk to the points a verifier uses.
+//!
+//! A KZG verifier needs `g[0]` for the opening and `g2` and `s_g2` for the
+//! pairing. The multiopen verifiers of this crate, SHPLONK and GWC, don't
+//! query the instance columns (`QUERY_INSTANCE` is false): the verifier
+//! evaluates the public inputs itself and never commits to them, so it reads
+//! no point of the Lagrange basis. Shrunk params keep the first `2^shrink_k`
+//! points of `g` and none of `g_lagrange`, `shrink_k = 0` is enough. A
+//! verifier querying the instance columns commits to the public inputs with
+//! `g_lagrange`, [`ParamsKZG::check_instance_rows`] rejects shrunk params for
+//! it.
+//!
+//! They are written as [`ParamsKZG::write`] does, with `shrink_k` as a
+//! little endian `u32` after `k`, `2^shrink_k` points of `g` and no
+//! `g_lagrange`.
+
+use core::fmt::{self, Debug};
+
+use ff::PrimeField;
+use halo2curves::pairing::Engine;
+
+use super::commitment::ParamsKZG;
+use crate::helpers::SerdeCurveAffine;
+use crate::io;
+use crate::{SerdeFormat, Vec};
+
+/// The params lack the points of the Lagrange basis a verifier querying the
+/// instance columns commits to the public inputs with.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParamsTooShrunk {
+    /// Points kept of the Lagrange basis.
+    pub points: usize,
+    pub instance_rows: usize,
+}
+
+impl fmt::Display for ParamsTooShrunk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "verifier params keep {} points of the Lagrange basis, the public inputs have {} rows",
+            self.points, self.instance_rows
+        )
+    }
+}
+
+impl<E: Engine + Debug> ParamsKZG<E>
+where
+    E::G1Affine: SerdeCurveAffine,
+    E::G2Affine: SerdeCurveAffine,
+{
+    /// Writes the params shrunk to the first `2^shrink_k` points of `g`.
+    pub fn write_shrunk<W: io::Write>(&self, shrink_k: u32, writer: &mut W) -> io::Result<()> {
+        if shrink_k > self.k {
+            return Err("params shrunk to more points than they have");
+        }
+        let n = 1 << shrink_k;
+        let format = SerdeFormat::RawBytes;
+        writer.write_all(&self.k.to_le_bytes())?;
+        writer.write_all(&shrink_k.to_le_bytes())?;
+        for point in self.g[..n].iter() {
+            point.write(writer, format)?;
+        }
+        self.g2.write(writer, format)?;
+        self.s_g2.write(writer, format)
+    }
+
+    /// Reads params written by [`Self::write_shrunk`].
+    pub fn read_shrunk<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        let mut k = [0u8; 4];
+        reader.read_exact(&mut k[..])?;
+        let k = u32::from_le_bytes(k);
+        let mut shrink_k = [0u8; 4];
+        reader.read_exact(&mut shrink_k[..])?;
+        let shrink_k = u32::from_le_bytes(shrink_k);
+        if k > E::Scalar::S || shrink_k > k {
+            return Err("invalid size of shrunk params");
+        }
+        let n = 1 << shrink_k;
+
+        let format = SerdeFormat::RawBytes;
+        let g = (0..n)
+            .map(|_| E::G1Affine::read(reader, format))
+            .collect::<Result<Vec<_>, _>>()?;
+        let g2 = E::G2Affine::read(reader, format)?;
+        let s_g2 = E::G2Affine::read(reader, format)?;
+
+        Ok(Self {
+            k,
+            n: 1 << k,
+            g,
+            g_lagrange: Vec::new(),
+            g2,
+            s_g2,
+        })
+    }
+
+    /// Checks that the params keep a point of the Lagrange basis for each of
+    /// `instance_rows` rows of public inputs, which only a verifier querying
+    /// the instance columns needs. Shrunk params keep none.
+    pub fn check_instance_rows(&self, instance_rows: usize) -> Result<(), ParamsTooShrunk> {
+        let points = self.g_lagrange.len();
+        if points < instance_rows {
+            return Err(ParamsTooShrunk {
+                points,
+                instance_rows,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poly::commitment::ParamsProver;
+    use crate::vec;
+    use halo2curves::bn256::Bn256;
+
+    #[test]
+    fn shrunk_params_roundtrip() {
+        let params = ParamsKZG::<Bn256>::new(4);
+        let mut bytes = vec![];
+        params.write_shrunk(2, &mut bytes).unwrap();
+        let shrunk = ParamsKZG::<Bn256>::read_shrunk(&mut &bytes[..]).unwrap();
+        assert_eq!((shrunk.k, shrunk.n), (4, 16));
+        assert_eq!(shrunk.g[..], params.g[..4]);
+        assert!(shrunk.g_lagrange.is_empty());
+        assert_eq!((shrunk.g2, shrunk.s_g2), (params.g2, params.s_g2));
+
+        // Fully shrunk params keep `g[0]` alone.
+        let mut fully_shrunk = vec![];
+        params.write_shrunk(0, &mut fully_shrunk).unwrap();
+        let fully_shrunk = ParamsKZG::<Bn256>::read_shrunk(&mut &fully_shrunk[..]).unwrap();
+        assert_eq!(fully_shrunk.g[..], params.g[..1]);
+
+        // Only a verifier querying the instance columns needs the Lagrange basis.
+        assert_eq!(shrunk.check_instance_rows(0), Ok(()));
+        assert_eq!(
+            shrunk.check_instance_rows(2),
+            Err(ParamsTooShrunk {
+                points: 0,
+                instance_rows: 2
+            })
+        );
+        assert_eq!(params.check_instance_rows(16), Ok(()));
+        assert!(params.write_shrunk(5, &mut vec![]).is_err());
+        // A truncated file.
+        assert!(ParamsKZG::<Bn256>::read_shrunk(&mut &bytes[..bytes.len() - 1]).is_err());
+    }
+}
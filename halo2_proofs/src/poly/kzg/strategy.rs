@@ -80,6 +80,23 @@ impl<'params, E: MultiMillerLoop + Debug> SingleStrategy<'params, E> {
     }
 }
 
+/// A verifier that defers the pairing check of a proof and yields it as a
+/// `DualMSM`, to check it later in a batch, see
+/// [`BatchVerifierKZG`](super::batch::BatchVerifierKZG)
+#[derive(Clone, Debug)]
+pub struct DeferredStrategy<'params, E: Engine> {
+    pub(crate) msm: DualMSM<'params, E>,
+}
+
+impl<'params, E: MultiMillerLoop + Debug> DeferredStrategy<'params, E> {
+    /// Constructs a verifier of a single proof
+    pub fn new(params: &'params ParamsKZG<E>) -> Self {
+        DeferredStrategy {
+            msm: DualMSM::new(params),
+        }
+    }
+}
+
 impl<
         'params,
         E: MultiMillerLoop + Debug,
@@ -157,3 +174,36 @@ where
         unreachable!();
     }
 }
+
+impl<
+        'params,
+        E: MultiMillerLoop + Debug,
+        V: Verifier<
+            'params,
+            KZGCommitmentScheme<E>,
+            MSMAccumulator = DualMSM<'params, E>,
+            Guard = GuardKZG<'params, E>,
+        >,
+    > VerificationStrategy<'params, KZGCommitmentScheme<E>, V> for DeferredStrategy<'params, E>
+where
+    E::G1Affine: SerdeCurveAffine,
+    E::G2Affine: SerdeCurveAffine,
+{
+    type Output = DualMSM<'params, E>;
+
+    fn new(params: &'params ParamsKZG<E>) -> Self {
+        Self::new(params)
+    }
+
+    fn process(
+        self,
+        f: impl FnOnce(V::MSMAccumulator) -> Result<V::Guard, Error>,
+    ) -> Result<Self::Output, Error> {
+        let guard = f(self.msm)?;
+        Ok(guard.msm_accumulator)
+    }
+
+    fn finalize(self) -> bool {
+        unreachable!();
+    }
+}
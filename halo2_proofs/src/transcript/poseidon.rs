@@ -1,4 +1,7 @@
-use super::{Challenge255, EncodedChallenge, Transcript, TranscriptRead, TranscriptWrite};
+use super::{
+    Challenge255, EncodedChallenge, Transcript, TranscriptRead, TranscriptReadBuffer,
+    TranscriptWrite, TranscriptWriterBuffer,
+};
 use crate::helpers::base_to_scalar;
 use crate::io::{self, Read, Write};
 use core::convert::TryInto;
@@ -32,6 +35,14 @@ impl<R: Read, C: CurveAffine, E: EncodedChallenge<C>> PoseidonRead<R, C, E> {
     }
 }
 
+impl<R: Read, C: CurveAffine> TranscriptReadBuffer<R, C, Challenge255<C>>
+    for PoseidonRead<R, C, Challenge255<C>>
+{
+    fn init(reader: R) -> Self {
+        PoseidonRead::init(reader)
+    }
+}
+
 impl<R: Read, C: CurveAffine> TranscriptRead<C, Challenge255<C>>
     for PoseidonRead<R, C, Challenge255<C>>
 {
@@ -111,6 +122,18 @@ impl<W: Write, C: CurveAffine, E: EncodedChallenge<C>> PoseidonWrite<W, C, E> {
     }
 }
 
+impl<W: Write, C: CurveAffine> TranscriptWriterBuffer<W, C, Challenge255<C>>
+    for PoseidonWrite<W, C, Challenge255<C>>
+{
+    fn init(writer: W) -> Self {
+        PoseidonWrite::init(writer)
+    }
+
+    fn finalize(self) -> W {
+        PoseidonWrite::finalize(self)
+    }
+}
+
 impl<W: Write, C: CurveAffine> TranscriptWrite<C, Challenge255<C>>
     for PoseidonWrite<W, C, Challenge255<C>>
 {
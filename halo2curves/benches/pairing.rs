@@ -0,0 +1,59 @@
+//! Costs of the pieces of the KZG pairing check of the verifier: the field
+//! multiplication, the preparation of G2 points, the multi Miller loop and the
+//! final exponentiation.
+//!
+//! To count cycles rather than time, build the bench for the target with the
+//! `asm` feature off, as the verifier is, and run it under the VM.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use ff::Field;
+use group::prime::PrimeCurveAffine;
+use group::Group;
+use halo2curves::bn256::{multi_miller_loop, pairing, Fq, G1Affine, G2Affine, G2Prepared, G1, G2};
+use halo2curves::pairing::MillerLoopResult;
+use halo2curves::serde::SerdeObject;
+use rand_core::SeedableRng;
+use rand_xorshift::XorShiftRng;
+
+pub fn criterion_benchmark(c: &mut Criterion) {
+    let mut rng = XorShiftRng::seed_from_u64(0x5962be5d);
+    let left = G1Affine::from(G1::random(&mut rng));
+    let right = G1Affine::from(G1::random(&mut rng));
+    let s_g2 = G2Affine::from(G2::random(&mut rng));
+    let neg_g2 = -G2Affine::generator();
+    let s_g2_prepared = G2Prepared::from(s_g2);
+    let neg_g2_prepared = G2Prepared::from(neg_g2);
+    let s_g2_raw = s_g2_prepared.to_raw_bytes();
+
+    let mut group = c.benchmark_group("bn256 pairing");
+    let x = Fq::random(&mut rng);
+    let y = Fq::random(&mut rng);
+    group.bench_function("fq mul", |b| b.iter(|| black_box(x) * black_box(y)));
+    group.bench_function("fq square", |b| b.iter(|| black_box(x).square()));
+    group.bench_function("prepare g2", |b| {
+        b.iter(|| G2Prepared::from(black_box(s_g2)))
+    });
+    group.bench_function("read prepared g2", |b| {
+        b.iter(|| G2Prepared::from_raw_bytes(black_box(&s_g2_raw)).unwrap())
+    });
+    group.bench_function("pairing", |b| {
+        b.iter(|| pairing(black_box(&left), black_box(&s_g2)))
+    });
+    group.bench_function("kzg check", |b| {
+        b.iter(|| {
+            let s_g2 = G2Prepared::from(s_g2);
+            let neg_g2 = G2Prepared::from(neg_g2);
+            multi_miller_loop(&[(&left, &s_g2), (&right, &neg_g2)]).final_exponentiation()
+        })
+    });
+    group.bench_function("kzg check prepared", |b| {
+        b.iter(|| {
+            multi_miller_loop(&[(&left, &s_g2_prepared), (&right, &neg_g2_prepared)])
+                .final_exponentiation()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -5,7 +5,9 @@ use crate::bn256::fq12::*;
 use crate::bn256::fq2::*;
 use crate::bn256::fq6::FROBENIUS_COEFF_FQ6_C1;
 use crate::bn256::fr::*;
+use crate::io::{self, Read, Write};
 use crate::pairing::{Engine, MillerLoopResult, MultiMillerLoop, PairingCurveAffine};
+use crate::serde::SerdeObject;
 use alloc::{vec, vec::Vec};
 use core::borrow::Borrow;
 use core::iter::Sum;
@@ -449,6 +451,74 @@ impl G2Prepared {
     }
 }
 
+/// Number of line coefficients of a prepared point other than the identity:
+/// two per non-zero digit of `6u + 2` in NAF, one per other digit, and the two
+/// Frobenius additions.
+pub const G2_PREPARED_COEFFS: usize = 91;
+
+/// Raw form of the line coefficients, prefixed by their count as a `u32` in
+/// little endian, so that the coefficients of fixed points like `g2` and
+/// `s_g2` can be computed once and shipped to the verifier.
+impl SerdeObject for G2Prepared {
+    fn from_raw_bytes_unchecked(bytes: &[u8]) -> Self {
+        Self::read_raw_unchecked(&mut &bytes[..])
+    }
+    fn from_raw_bytes(bytes: &[u8]) -> Option<Self> {
+        let mut reader = bytes;
+        let prepared = Self::read_raw(&mut reader).ok()?;
+        reader.is_empty().then_some(prepared)
+    }
+    fn to_raw_bytes(&self) -> Vec<u8> {
+        let mut res = Vec::with_capacity(4 + self.coeffs.len() * 192);
+        self.write_raw(&mut res).unwrap();
+        res
+    }
+    fn read_raw_unchecked<R: Read>(reader: &mut R) -> Self {
+        let mut count = [0u8; 4];
+        reader.read_exact(&mut count).unwrap();
+        let coeffs = (0..u32::from_le_bytes(count))
+            .map(|_| {
+                let [c0, c1, c2] = [(); 3].map(|_| Fq2::read_raw_unchecked(reader));
+                (c0, c1, c2)
+            })
+            .collect::<Vec<_>>();
+        G2Prepared {
+            infinity: coeffs.is_empty(),
+            coeffs,
+        }
+    }
+    fn read_raw<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut count = [0u8; 4];
+        reader.read_exact(&mut count)?;
+        let count = u32::from_le_bytes(count) as usize;
+        if count != 0 && count != G2_PREPARED_COEFFS {
+            return Err("invalid number of prepared line coefficients");
+        }
+        let coeffs = (0..count)
+            .map(|_| {
+                Ok((
+                    Fq2::read_raw(reader)?,
+                    Fq2::read_raw(reader)?,
+                    Fq2::read_raw(reader)?,
+                ))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        Ok(G2Prepared {
+            infinity: coeffs.is_empty(),
+            coeffs,
+        })
+    }
+    fn write_raw<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&(self.coeffs.len() as u32).to_le_bytes())?;
+        for (c0, c1, c2) in self.coeffs.iter() {
+            c0.write_raw(writer)?;
+            c1.write_raw(writer)?;
+            c2.write_raw(writer)?;
+        }
+        Ok(())
+    }
+}
+
 impl From<G2Affine> for G2Prepared {
     fn from(q: G2Affine) -> G2Prepared {
         G2Prepared::from_affine(q)
@@ -837,3 +907,28 @@ fn random_miller_loop_tests() {
         assert_eq!(abcd, abcd_with_double_loop);
     }
 }
+
+#[test]
+fn g2_prepared_raw_roundtrip() {
+    let mut rng = XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ]);
+
+    let a = G1Affine::from(G1::random(&mut rng));
+    let b = G2Affine::from(G2::random(&mut rng));
+    let prepared = G2Prepared::from(b);
+    assert_eq!(prepared.coeffs.len(), G2_PREPARED_COEFFS);
+
+    let bytes = prepared.to_raw_bytes();
+    let read = G2Prepared::from_raw_bytes(&bytes).unwrap();
+    assert_eq!(
+        multi_miller_loop(&[(&a, &read)]).final_exponentiation(),
+        pairing(&a, &b)
+    );
+    assert!(G2Prepared::from_raw_bytes(&bytes[..bytes.len() - 1]).is_none());
+
+    let identity =
+        G2Prepared::from_raw_bytes(&G2Prepared::from(G2Affine::identity()).to_raw_bytes());
+    assert!(identity.unwrap().is_zero());
+}
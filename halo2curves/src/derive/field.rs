@@ -420,34 +420,6 @@ macro_rules! field_arithmetic {
                 $field::montgomery_reduce(&[r0, r1, r2, r3, r4, r5, r6, r7])
             }
 
-            /// Multiplies `rhs` by `self`, returning the result.
-            #[inline]
-            pub const fn mul(&self, rhs: &Self) -> $field {
-                // Schoolbook multiplication
-
-                let (r0, carry) = mac(0, self.0[0], rhs.0[0], 0);
-                let (r1, carry) = mac(0, self.0[0], rhs.0[1], carry);
-                let (r2, carry) = mac(0, self.0[0], rhs.0[2], carry);
-                let (r3, r4) = mac(0, self.0[0], rhs.0[3], carry);
-
-                let (r1, carry) = mac(r1, self.0[1], rhs.0[0], 0);
-                let (r2, carry) = mac(r2, self.0[1], rhs.0[1], carry);
-                let (r3, carry) = mac(r3, self.0[1], rhs.0[2], carry);
-                let (r4, r5) = mac(r4, self.0[1], rhs.0[3], carry);
-
-                let (r2, carry) = mac(r2, self.0[2], rhs.0[0], 0);
-                let (r3, carry) = mac(r3, self.0[2], rhs.0[1], carry);
-                let (r4, carry) = mac(r4, self.0[2], rhs.0[2], carry);
-                let (r5, r6) = mac(r5, self.0[2], rhs.0[3], carry);
-
-                let (r3, carry) = mac(r3, self.0[3], rhs.0[0], 0);
-                let (r4, carry) = mac(r4, self.0[3], rhs.0[1], carry);
-                let (r5, carry) = mac(r5, self.0[3], rhs.0[2], carry);
-                let (r6, r7) = mac(r6, self.0[3], rhs.0[3], carry);
-
-                $field::montgomery_reduce(&[r0, r1, r2, r3, r4, r5, r6, r7])
-            }
-
             /// Subtracts `rhs` from `self`, returning the result.
             #[inline]
             pub const fn sub(&self, rhs: &Self) -> Self {
@@ -492,6 +464,41 @@ macro_rules! field_arithmetic {
 macro_rules! field_specific {
     ($field:ident, $modulus:ident, $inv:ident, sparse) => {
         impl $field {
+            /// Multiplies `rhs` by `self`, returning the result.
+            #[inline]
+            pub const fn mul(&self, rhs: &Self) -> $field {
+                // Montgomery multiplication interleaving the products and the
+                // reduction (CIOS). It keeps six words live instead of the
+                // eight of the schoolbook product, which fits the registers of
+                // RV64 without the `asm` feature. The carry word `t4` is needed
+                // for operands which aren't reduced, e.g. the 256-bit digits
+                // `from_u512` multiplies by R2 and R3.
+                let mut t = [0u64; 4];
+                let mut t4 = 0;
+                let mut i = 0;
+                while i < 4 {
+                    let (t0, mut a) = mac(t[0], self.0[0], rhs.0[i], 0);
+                    let m = t0.wrapping_mul($inv);
+                    let (_, mut c) = mac(t0, m, $modulus.0[0], 0);
+                    let mut j = 1;
+                    while j < 4 {
+                        let (tj, carry) = mac(t[j], self.0[j], rhs.0[i], a);
+                        a = carry;
+                        let (tj, carry) = mac(tj, m, $modulus.0[j], c);
+                        c = carry;
+                        t[j - 1] = tj;
+                        j += 1;
+                    }
+                    let (t3, carry) = adc(t4, a, c);
+                    t[3] = t3;
+                    t4 = carry;
+                    i += 1;
+                }
+
+                // Result may be within MODULUS of the correct value
+                (&$field(t)).sub(&$modulus)
+            }
+
             /// Adds `rhs` to `self`, returning the result.
             #[inline]
             pub const fn add(&self, rhs: &Self) -> Self {
@@ -546,6 +553,34 @@ macro_rules! field_specific {
     };
     ($field:ident, $modulus:ident, $inv:ident, dense) => {
         impl $field {
+            /// Multiplies `rhs` by `self`, returning the result.
+            #[inline]
+            pub const fn mul(&self, rhs: &Self) -> $field {
+                // Schoolbook multiplication
+
+                let (r0, carry) = mac(0, self.0[0], rhs.0[0], 0);
+                let (r1, carry) = mac(0, self.0[0], rhs.0[1], carry);
+                let (r2, carry) = mac(0, self.0[0], rhs.0[2], carry);
+                let (r3, r4) = mac(0, self.0[0], rhs.0[3], carry);
+
+                let (r1, carry) = mac(r1, self.0[1], rhs.0[0], 0);
+                let (r2, carry) = mac(r2, self.0[1], rhs.0[1], carry);
+                let (r3, carry) = mac(r3, self.0[1], rhs.0[2], carry);
+                let (r4, r5) = mac(r4, self.0[1], rhs.0[3], carry);
+
+                let (r2, carry) = mac(r2, self.0[2], rhs.0[0], 0);
+                let (r3, carry) = mac(r3, self.0[2], rhs.0[1], carry);
+                let (r4, carry) = mac(r4, self.0[2], rhs.0[2], carry);
+                let (r5, r6) = mac(r5, self.0[2], rhs.0[3], carry);
+
+                let (r3, carry) = mac(r3, self.0[3], rhs.0[0], 0);
+                let (r4, carry) = mac(r4, self.0[3], rhs.0[1], carry);
+                let (r5, carry) = mac(r5, self.0[3], rhs.0[2], carry);
+                let (r6, r7) = mac(r6, self.0[3], rhs.0[3], carry);
+
+                $field::montgomery_reduce(&[r0, r1, r2, r3, r4, r5, r6, r7])
+            }
+
             /// Adds `rhs` to `self`, returning the result.
             #[inline]
             pub const fn add(&self, rhs: &Self) -> Self {
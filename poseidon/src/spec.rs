@@ -175,7 +175,7 @@ impl<F: FieldExt, const T: usize, const RATE: usize> MDSMatrix<F, T, RATE> {
     }
 
     /// Inverts the MDS matrix
-    fn invert(&self) -> Self {
+    pub fn invert(&self) -> Self {
         Self(self.0.invert())
     }
 
@@ -318,6 +318,12 @@ impl<F: FieldExt, const T: usize, const RATE: usize> Spec<F, T, RATE> {
         }
     }
 
+    /// Round constants and MDS matrix of the unoptimized permutation, as
+    /// generated with the reference `Grain`, for circuit implementations
+    pub fn reference(r_f: usize, r_p: usize) -> (Vec<[F; T]>, MDSMatrix<F, T, RATE>) {
+        Grain::generate(r_f, r_p)
+    }
+
     fn calculate_optimized_constants(
         r_f: usize,
         r_p: usize,
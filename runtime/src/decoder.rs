@@ -0,0 +1,730 @@
+//! Decoder from raw RV64IMC encodings to [`Instruction`]s.
+//!
+//! The operands follow the convention of the ckb-debugger traces consumed by
+//! the entry builder:
+//!
+//! | type | op_a | op_b | op_c |
+//! |------|------|------|------|
+//! | R, A | rd   | rs1  | rs2  |
+//! | I    | rd   | rs1  | imm  |
+//! | S    | rs2  | rs1  | imm  |
+//! | B    | rs1  | rs2  | imm  |
+//! | J, U | rd   | imm  | 0    |
+//!
+//! Immediates are sign extended to 64 bits. Compressed instructions are
+//! expanded to their base instruction with a length of 2.
+
+use crate::trace::{Instruction, Opcode};
+use core::fmt;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// Not a valid encoding.
+    Invalid(u32),
+    /// A valid encoding of an extension we don't support yet.
+    Unsupported(u32),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Invalid(bits) => write!(f, "invalid instruction {:#010x}", bits),
+            DecodeError::Unsupported(bits) => write!(f, "unsupported instruction {:#010x}", bits),
+        }
+    }
+}
+
+/// Returns the length in bytes of the instruction starting with the half word `low`.
+pub fn instruction_length(low: u16) -> u64 {
+    if low & 0b11 == 0b11 {
+        4
+    } else {
+        2
+    }
+}
+
+/// Decodes the instruction at the start of `bytes` (little endian).
+pub fn decode_bytes(bytes: &[u8]) -> Result<Instruction, DecodeError> {
+    if bytes.len() < 2 {
+        return Err(DecodeError::Invalid(0));
+    }
+    let low = u16::from_le_bytes([bytes[0], bytes[1]]);
+    if instruction_length(low) == 2 {
+        return decode(low as u32);
+    }
+    if bytes.len() < 4 {
+        return Err(DecodeError::Invalid(low as u32));
+    }
+    decode(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Decodes a 32-bit instruction, or a compressed one held in the low 16 bits.
+pub fn decode(bits: u32) -> Result<Instruction, DecodeError> {
+    if instruction_length(bits as u16) == 2 {
+        decode_compressed(bits as u16)
+    } else {
+        decode_base(bits)
+    }
+}
+
+fn rd(bits: u32) -> u64 {
+    ((bits >> 7) & 0x1f) as u64
+}
+
+fn rs1(bits: u32) -> u64 {
+    ((bits >> 15) & 0x1f) as u64
+}
+
+fn rs2(bits: u32) -> u64 {
+    ((bits >> 20) & 0x1f) as u64
+}
+
+fn funct3(bits: u32) -> u32 {
+    (bits >> 12) & 0b111
+}
+
+fn funct7(bits: u32) -> u32 {
+    bits >> 25
+}
+
+fn imm_i(bits: u32) -> i64 {
+    ((bits as i32) >> 20) as i64
+}
+
+fn imm_s(bits: u32) -> i64 {
+    ((((bits as i32) >> 25) << 5) | ((bits >> 7) & 0x1f) as i32) as i64
+}
+
+fn imm_b(bits: u32) -> i64 {
+    ((((bits as i32) >> 31) << 12)
+        | (((bits >> 7) & 0x1) << 11) as i32
+        | (((bits >> 25) & 0x3f) << 5) as i32
+        | (((bits >> 8) & 0xf) << 1) as i32) as i64
+}
+
+fn imm_u(bits: u32) -> i64 {
+    (bits & 0xffff_f000) as i32 as i64
+}
+
+fn imm_j(bits: u32) -> i64 {
+    ((((bits as i32) >> 31) << 20)
+        | (bits & 0xff000) as i32
+        | (((bits >> 20) & 0x1) << 11) as i32
+        | (((bits >> 21) & 0x3ff) << 1) as i32) as i64
+}
+
+/// Sign extends the lowest `width` bits of `value`.
+fn sign_extend(value: u32, width: u32) -> i64 {
+    let shift = 32 - width;
+    (((value << shift) as i32) >> shift) as i64
+}
+
+pub(crate) fn rtype(opcode: Opcode, length: u64, rd: u64, rs1: u64, rs2: u64) -> Instruction {
+    Instruction {
+        opcode,
+        length,
+        op_a: rd,
+        op_b: rs1,
+        op_c: rs2,
+        imm_b: false,
+        imm_c: false,
+    }
+}
+
+pub(crate) fn itype(opcode: Opcode, length: u64, rd: u64, rs1: u64, imm: i64) -> Instruction {
+    Instruction {
+        opcode,
+        length,
+        op_a: rd,
+        op_b: rs1,
+        op_c: imm as u64,
+        imm_b: false,
+        imm_c: true,
+    }
+}
+
+pub(crate) fn stype(opcode: Opcode, length: u64, rs1: u64, rs2: u64, imm: i64) -> Instruction {
+    Instruction {
+        opcode,
+        length,
+        op_a: rs2,
+        op_b: rs1,
+        op_c: imm as u64,
+        imm_b: false,
+        imm_c: true,
+    }
+}
+
+pub(crate) fn btype(opcode: Opcode, length: u64, rs1: u64, rs2: u64, imm: i64) -> Instruction {
+    Instruction {
+        opcode,
+        length,
+        op_a: rs1,
+        op_b: rs2,
+        op_c: imm as u64,
+        imm_b: false,
+        imm_c: true,
+    }
+}
+
+pub(crate) fn utype(opcode: Opcode, length: u64, rd: u64, imm: i64) -> Instruction {
+    Instruction {
+        opcode,
+        length,
+        op_a: rd,
+        op_b: imm as u64,
+        op_c: 0,
+        imm_b: true,
+        imm_c: false,
+    }
+}
+
+fn notype(opcode: Opcode, length: u64) -> Instruction {
+    Instruction {
+        opcode,
+        length,
+        op_a: 0,
+        op_b: 0,
+        op_c: 0,
+        imm_b: false,
+        imm_c: false,
+    }
+}
+
+fn decode_base(bits: u32) -> Result<Instruction, DecodeError> {
+    let invalid = Err(DecodeError::Invalid(bits));
+    let instruction = match bits & 0x7f {
+        0b0110111 => utype(Opcode::LUI, 4, rd(bits), imm_u(bits)),
+        0b0010111 => utype(Opcode::AUIPC, 4, rd(bits), imm_u(bits)),
+        0b1101111 => utype(Opcode::JAL, 4, rd(bits), imm_j(bits)),
+        0b1100111 if funct3(bits) == 0 => itype(Opcode::JALR, 4, rd(bits), rs1(bits), imm_i(bits)),
+        0b1100011 => {
+            let opcode = match funct3(bits) {
+                0b000 => Opcode::BEQ,
+                0b001 => Opcode::BNE,
+                0b100 => Opcode::BLT,
+                0b101 => Opcode::BGE,
+                0b110 => Opcode::BLTU,
+                0b111 => Opcode::BGEU,
+                _ => return invalid,
+            };
+            btype(opcode, 4, rs1(bits), rs2(bits), imm_b(bits))
+        }
+        0b0000011 => {
+            let opcode = match funct3(bits) {
+                0b000 => Opcode::LB,
+                0b001 => Opcode::LH,
+                0b010 => Opcode::LW,
+                0b011 => Opcode::LD,
+                0b100 => Opcode::LBU,
+                0b101 => Opcode::LHU,
+                0b110 => Opcode::LWU,
+                _ => return invalid,
+            };
+            itype(opcode, 4, rd(bits), rs1(bits), imm_i(bits))
+        }
+        0b0100011 => {
+            let opcode = match funct3(bits) {
+                0b000 => Opcode::SB,
+                0b001 => Opcode::SH,
+                0b010 => Opcode::SW,
+                0b011 => Opcode::SD,
+                _ => return invalid,
+            };
+            stype(opcode, 4, rs1(bits), rs2(bits), imm_s(bits))
+        }
+        0b0010011 => {
+            let shamt = ((bits >> 20) & 0x3f) as i64;
+            let (opcode, imm) = match (funct3(bits), bits >> 26) {
+                (0b000, _) => (Opcode::ADDI, imm_i(bits)),
+                (0b010, _) => (Opcode::SLTI, imm_i(bits)),
+                (0b011, _) => (Opcode::SLTIU, imm_i(bits)),
+                (0b100, _) => (Opcode::XORI, imm_i(bits)),
+                (0b110, _) => (Opcode::ORI, imm_i(bits)),
+                (0b111, _) => (Opcode::ANDI, imm_i(bits)),
+                (0b001, 0b000000) => (Opcode::SLLI, shamt),
+                (0b101, 0b000000) => (Opcode::SRLI, shamt),
+                (0b101, 0b010000) => (Opcode::SRAI, shamt),
+                (0b001, 0b010010) => (Opcode::BCLRI, shamt),
+                (0b001, 0b011010) => (Opcode::BINVI, shamt),
+                (0b001, 0b001010) => (Opcode::BSETI, shamt),
+                (0b101, 0b010010) => (Opcode::BEXTI, shamt),
+                (0b101, 0b011000) => (Opcode::RORI, shamt),
+                (0b001, 0b011000) => match bits >> 20 {
+                    0x600 => (Opcode::CLZ, 0),
+                    0x601 => (Opcode::CTZ, 0),
+                    0x602 => (Opcode::CPOP, 0),
+                    0x604 => (Opcode::SEXTB, 0),
+                    0x605 => (Opcode::SEXTH, 0),
+                    _ => return invalid,
+                },
+                (0b101, _) if bits >> 20 == 0x287 => (Opcode::ORCB, 0),
+                (0b101, _) if bits >> 20 == 0x6b8 => (Opcode::REV8, 0),
+                _ => return invalid,
+            };
+            itype(opcode, 4, rd(bits), rs1(bits), imm)
+        }
+        0b0011011 => {
+            let shamt = ((bits >> 20) & 0x1f) as i64;
+            let (opcode, imm) = match (funct3(bits), funct7(bits)) {
+                (0b000, _) => (Opcode::ADDIW, imm_i(bits)),
+                (0b001, 0b0000000) => (Opcode::SLLIW, shamt),
+                (0b101, 0b0000000) => (Opcode::SRLIW, shamt),
+                (0b101, 0b0100000) => (Opcode::SRAIW, shamt),
+                (0b101, 0b0110000) => (Opcode::RORIW, shamt),
+                (0b001, 0b0110000) => match bits >> 20 {
+                    0x600 => (Opcode::CLZW, 0),
+                    0x601 => (Opcode::CTZW, 0),
+                    0x602 => (Opcode::CPOPW, 0),
+                    _ => return invalid,
+                },
+                (0b001, _) if bits >> 26 == 0b000010 => {
+                    (Opcode::SLLIUW, ((bits >> 20) & 0x3f) as i64)
+                }
+                _ => return invalid,
+            };
+            itype(opcode, 4, rd(bits), rs1(bits), imm)
+        }
+        0b0110011 => {
+            let opcode = match (funct7(bits), funct3(bits)) {
+                (0b0000000, 0b000) => Opcode::ADD,
+                (0b0100000, 0b000) => Opcode::SUB,
+                (0b0000000, 0b001) => Opcode::SLL,
+                (0b0000000, 0b010) => Opcode::SLT,
+                (0b0000000, 0b011) => Opcode::SLTU,
+                (0b0000000, 0b100) => Opcode::XOR,
+                (0b0000000, 0b101) => Opcode::SRL,
+                (0b0100000, 0b101) => Opcode::SRA,
+                (0b0000000, 0b110) => Opcode::OR,
+                (0b0000000, 0b111) => Opcode::AND,
+                (0b0000001, 0b000) => Opcode::MUL,
+                (0b0000001, 0b001) => Opcode::MULH,
+                (0b0000001, 0b010) => Opcode::MULHSU,
+                (0b0000001, 0b011) => Opcode::MULHU,
+                (0b0000001, 0b100) => Opcode::DIV,
+                (0b0000001, 0b101) => Opcode::DIVU,
+                (0b0000001, 0b110) => Opcode::REM,
+                (0b0000001, 0b111) => Opcode::REMU,
+                (0b0010000, 0b010) => Opcode::SH1ADD,
+                (0b0010000, 0b100) => Opcode::SH2ADD,
+                (0b0010000, 0b110) => Opcode::SH3ADD,
+                (0b0100000, 0b111) => Opcode::ANDN,
+                (0b0100000, 0b110) => Opcode::ORN,
+                (0b0100000, 0b100) => Opcode::XNOR,
+                (0b0000101, 0b001) => Opcode::CLMUL,
+                (0b0000101, 0b010) => Opcode::CLMULR,
+                (0b0000101, 0b011) => Opcode::CLMULH,
+                (0b0000101, 0b100) => Opcode::MIN,
+                (0b0000101, 0b101) => Opcode::MINU,
+                (0b0000101, 0b110) => Opcode::MAX,
+                (0b0000101, 0b111) => Opcode::MAXU,
+                (0b0110000, 0b001) => Opcode::ROL,
+                (0b0110000, 0b101) => Opcode::ROR,
+                (0b0100100, 0b001) => Opcode::BCLR,
+                (0b0100100, 0b101) => Opcode::BEXT,
+                (0b0110100, 0b001) => Opcode::BINV,
+                (0b0010100, 0b001) => Opcode::BSET,
+                _ => return invalid,
+            };
+            rtype(opcode, 4, rd(bits), rs1(bits), rs2(bits))
+        }
+        0b0111011 => {
+            let opcode = match (funct7(bits), funct3(bits)) {
+                (0b0000000, 0b000) => Opcode::ADDW,
+                (0b0100000, 0b000) => Opcode::SUBW,
+                (0b0000000, 0b001) => Opcode::SLLW,
+                (0b0000000, 0b101) => Opcode::SRLW,
+                (0b0100000, 0b101) => Opcode::SRAW,
+                (0b0000001, 0b000) => Opcode::MULW,
+                (0b0000001, 0b100) => Opcode::DIVW,
+                (0b0000001, 0b101) => Opcode::DIVUW,
+                (0b0000001, 0b110) => Opcode::REMW,
+                (0b0000001, 0b111) => Opcode::REMUW,
+                (0b0000100, 0b000) => Opcode::ADDUW,
+                (0b0000100, 0b100) if rs2(bits) == 0 => {
+                    return Ok(itype(Opcode::ZEXTH, 4, rd(bits), rs1(bits), 0));
+                }
+                (0b0010000, 0b010) => Opcode::SH1ADDUW,
+                (0b0010000, 0b100) => Opcode::SH2ADDUW,
+                (0b0010000, 0b110) => Opcode::SH3ADDUW,
+                (0b0110000, 0b001) => Opcode::ROLW,
+                (0b0110000, 0b101) => Opcode::RORW,
+                _ => return invalid,
+            };
+            rtype(opcode, 4, rd(bits), rs1(bits), rs2(bits))
+        }
+        0b0101111 => {
+            let opcode = match (bits >> 27, funct3(bits)) {
+                (0b00010, 0b010) if rs2(bits) == 0 => Opcode::LRW,
+                (0b00011, 0b010) => Opcode::SCW,
+                (0b00001, 0b010) => Opcode::AMOSWAPW,
+                (0b00000, 0b010) => Opcode::AMOADDW,
+                (0b00100, 0b010) => Opcode::AMOXORW,
+                (0b01100, 0b010) => Opcode::AMOANDW,
+                (0b01000, 0b010) => Opcode::AMOORW,
+                (0b10000, 0b010) => Opcode::AMOMINW,
+                (0b10100, 0b010) => Opcode::AMOMAXW,
+                (0b11000, 0b010) => Opcode::AMOMINUW,
+                (0b11100, 0b010) => Opcode::AMOMAXUW,
+                (0b00010, 0b011) if rs2(bits) == 0 => Opcode::LRD,
+                (0b00011, 0b011) => Opcode::SCD,
+                (0b00001, 0b011) => Opcode::AMOSWAPD,
+                (0b00000, 0b011) => Opcode::AMOADDD,
+                (0b00100, 0b011) => Opcode::AMOXORD,
+                (0b01100, 0b011) => Opcode::AMOANDD,
+                (0b01000, 0b011) => Opcode::AMOORD,
+                (0b10000, 0b011) => Opcode::AMOMIND,
+                (0b10100, 0b011) => Opcode::AMOMAXD,
+                (0b11000, 0b011) => Opcode::AMOMINUD,
+                (0b11100, 0b011) => Opcode::AMOMAXUD,
+                _ => return invalid,
+            };
+            rtype(opcode, 4, rd(bits), rs1(bits), rs2(bits))
+        }
+        // FENCE and FENCE.I are both no-ops for ckb-vm.
+        0b0001111 if funct3(bits) <= 0b001 => notype(Opcode::FENCE, 4),
+        0b1110011 => match bits {
+            0x0000_0073 => notype(Opcode::ECALL, 4),
+            0x0010_0073 => notype(Opcode::EBREAK, 4),
+            // `csrrw x0, cycle, x0`, the canonical encoding of `unimp`.
+            0xc000_1073 => notype(Opcode::UNIMP, 4),
+            // CSR instructions
+            _ => return Err(DecodeError::Unsupported(bits)),
+        },
+        _ => return invalid,
+    };
+    Ok(instruction)
+}
+
+fn decode_compressed(half: u16) -> Result<Instruction, DecodeError> {
+    let bits = half as u32;
+    // All zeros is defined to be an illegal instruction.
+    if bits == 0 {
+        return Err(DecodeError::Invalid(bits));
+    }
+
+    let funct3 = (bits >> 13) & 0b111;
+    let rd = ((bits >> 7) & 0x1f) as u64;
+    let rs2 = ((bits >> 2) & 0x1f) as u64;
+    // Registers x8 - x15 of the CIW, CL, CS, CA and CB formats.
+    let rd_short = (((bits >> 2) & 0b111) + 8) as u64;
+    let rs1_short = (((bits >> 7) & 0b111) + 8) as u64;
+    // 6-bit immediate of the CI format.
+    let imm_ci = sign_extend(((bits >> 7) & 0x20) | ((bits >> 2) & 0x1f), 6);
+
+    let instruction = match (bits & 0b11, funct3) {
+        // C.ADDI4SPN
+        (0b00, 0b000) => {
+            let imm = ((bits >> 7) & 0x30)
+                | ((bits >> 1) & 0x3c0)
+                | ((bits >> 4) & 0x4)
+                | ((bits >> 2) & 0x8);
+            if imm == 0 {
+                return Err(DecodeError::Invalid(bits));
+            }
+            itype(Opcode::ADDI, 2, rd_short, 2, imm as i64)
+        }
+        // C.LW
+        (0b00, 0b010) => {
+            let imm = ((bits >> 7) & 0x38) | ((bits << 1) & 0x40) | ((bits >> 4) & 0x4);
+            itype(Opcode::LW, 2, rd_short, rs1_short, imm as i64)
+        }
+        // C.LD
+        (0b00, 0b011) => {
+            let imm = ((bits >> 7) & 0x38) | ((bits << 1) & 0xc0);
+            itype(Opcode::LD, 2, rd_short, rs1_short, imm as i64)
+        }
+        // C.SW
+        (0b00, 0b110) => {
+            let imm = ((bits >> 7) & 0x38) | ((bits << 1) & 0x40) | ((bits >> 4) & 0x4);
+            stype(Opcode::SW, 2, rs1_short, rd_short, imm as i64)
+        }
+        // C.SD
+        (0b00, 0b111) => {
+            let imm = ((bits >> 7) & 0x38) | ((bits << 1) & 0xc0);
+            stype(Opcode::SD, 2, rs1_short, rd_short, imm as i64)
+        }
+        // C.ADDI, C.NOP when rd is zero
+        (0b01, 0b000) => itype(Opcode::ADDI, 2, rd, rd, imm_ci),
+        // C.ADDIW, C.JAL only exists on RV32
+        (0b01, 0b001) if rd != 0 => itype(Opcode::ADDIW, 2, rd, rd, imm_ci),
+        (0b01, 0b001) => return Err(DecodeError::Invalid(bits)),
+        // C.LI
+        (0b01, 0b010) => itype(Opcode::ADDI, 2, rd, 0, imm_ci),
+        // C.ADDI16SP
+        (0b01, 0b011) if rd == 2 => {
+            let imm = ((bits >> 3) & 0x200)
+                | ((bits >> 2) & 0x10)
+                | ((bits << 1) & 0x40)
+                | ((bits << 4) & 0x180)
+                | ((bits << 3) & 0x20);
+            if imm == 0 {
+                return Err(DecodeError::Invalid(bits));
+            }
+            itype(Opcode::ADDI, 2, 2, 2, sign_extend(imm, 10))
+        }
+        // C.LUI
+        (0b01, 0b011) if rd != 0 && imm_ci != 0 => utype(Opcode::LUI, 2, rd, imm_ci << 12),
+        (0b01, 0b011) => return Err(DecodeError::Invalid(bits)),
+        (0b01, 0b100) => {
+            // 6-bit shift amount, the upper bit lives in bit 12.
+            let shamt = (((bits >> 7) & 0x20) | ((bits >> 2) & 0x1f)) as i64;
+            match ((bits >> 10) & 0b11, (bits >> 12) & 1, (bits >> 5) & 0b11) {
+                (0b00, _, _) => itype(Opcode::SRLI, 2, rs1_short, rs1_short, shamt),
+                (0b01, _, _) => itype(Opcode::SRAI, 2, rs1_short, rs1_short, shamt),
+                (0b10, _, _) => itype(Opcode::ANDI, 2, rs1_short, rs1_short, imm_ci),
+                (0b11, 0, funct2) => {
+                    let opcode = match funct2 {
+                        0b00 => Opcode::SUB,
+                        0b01 => Opcode::XOR,
+                        0b10 => Opcode::OR,
+                        _ => Opcode::AND,
+                    };
+                    rtype(opcode, 2, rs1_short, rs1_short, rd_short)
+                }
+                (0b11, _, 0b00) => rtype(Opcode::SUBW, 2, rs1_short, rs1_short, rd_short),
+                (0b11, _, 0b01) => rtype(Opcode::ADDW, 2, rs1_short, rs1_short, rd_short),
+                _ => return Err(DecodeError::Invalid(bits)),
+            }
+        }
+        // C.J
+        (0b01, 0b101) => utype(Opcode::JAL, 2, 0, imm_cj(bits)),
+        // C.BEQZ, C.BNEZ
+        (0b01, 0b110) => btype(Opcode::BEQ, 2, rs1_short, 0, imm_cb(bits)),
+        (0b01, 0b111) => btype(Opcode::BNE, 2, rs1_short, 0, imm_cb(bits)),
+        // C.SLLI
+        (0b10, 0b000) => {
+            let shamt = ((bits >> 7) & 0x20) | ((bits >> 2) & 0x1f);
+            itype(Opcode::SLLI, 2, rd, rd, shamt as i64)
+        }
+        // C.LWSP
+        (0b10, 0b010) if rd != 0 => {
+            let imm = ((bits >> 7) & 0x20) | ((bits >> 2) & 0x1c) | ((bits << 4) & 0xc0);
+            itype(Opcode::LW, 2, rd, 2, imm as i64)
+        }
+        (0b10, 0b010) => return Err(DecodeError::Invalid(bits)),
+        // C.LDSP
+        (0b10, 0b011) if rd != 0 => {
+            let imm = ((bits >> 7) & 0x20) | ((bits >> 2) & 0x18) | ((bits << 4) & 0x1c0);
+            itype(Opcode::LD, 2, rd, 2, imm as i64)
+        }
+        (0b10, 0b011) => return Err(DecodeError::Invalid(bits)),
+        (0b10, 0b100) => match ((bits >> 12) & 1, rd, rs2) {
+            (0, 0, 0) => return Err(DecodeError::Invalid(bits)),
+            // C.EBREAK
+            (_, 0, 0) => notype(Opcode::EBREAK, 2),
+            // C.JR
+            (0, rs1, 0) => itype(Opcode::JALR, 2, 0, rs1, 0),
+            // C.MV
+            (0, rd, rs2) => rtype(Opcode::ADD, 2, rd, 0, rs2),
+            // C.JALR
+            (_, rs1, 0) => itype(Opcode::JALR, 2, 1, rs1, 0),
+            // C.ADD
+            (_, rd, rs2) => rtype(Opcode::ADD, 2, rd, rd, rs2),
+        },
+        // C.SWSP
+        (0b10, 0b110) => {
+            let imm = ((bits >> 7) & 0x3c) | ((bits >> 1) & 0xc0);
+            stype(Opcode::SW, 2, 2, rs2, imm as i64)
+        }
+        // C.SDSP
+        (0b10, 0b111) => {
+            let imm = ((bits >> 7) & 0x38) | ((bits >> 1) & 0x1c0);
+            stype(Opcode::SD, 2, 2, rs2, imm as i64)
+        }
+        (0b00, 0b100) => return Err(DecodeError::Invalid(bits)),
+        // The floating point loads and stores.
+        _ => return Err(DecodeError::Unsupported(bits)),
+    };
+    Ok(instruction)
+}
+
+/// Jump target offset of the CJ format.
+fn imm_cj(bits: u32) -> i64 {
+    let imm = ((bits >> 1) & 0x800)
+        | ((bits << 2) & 0x400)
+        | ((bits >> 1) & 0x300)
+        | ((bits << 1) & 0x80)
+        | ((bits >> 1) & 0x40)
+        | ((bits << 3) & 0x20)
+        | ((bits >> 7) & 0x10)
+        | ((bits >> 2) & 0xe);
+    sign_extend(imm, 12)
+}
+
+/// Branch offset of the CB format.
+fn imm_cb(bits: u32) -> i64 {
+    let imm = ((bits >> 4) & 0x100)
+        | ((bits << 1) & 0xc0)
+        | ((bits << 3) & 0x20)
+        | ((bits >> 7) & 0x18)
+        | ((bits >> 2) & 0x6);
+    sign_extend(imm, 9)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use alloc::format;
+
+    #[test]
+    fn decode_base_instructions() {
+        // addi sp, sp, -32
+        assert_eq!(
+            decode(0xfe010113),
+            Ok(itype(Opcode::ADDI, 4, 2, 2, -32))
+        );
+        // sd ra, 24(sp)
+        assert_eq!(decode(0x00113c23), Ok(stype(Opcode::SD, 4, 2, 1, 24)));
+        // bne a0, a1, -16
+        assert_eq!(decode(0xfeb518e3), Ok(btype(Opcode::BNE, 4, 10, 11, -16)));
+        // jal ra, 2048
+        assert_eq!(decode(0x001000ef), Ok(utype(Opcode::JAL, 4, 1, 2048)));
+        // lui a0, 0x80000
+        assert_eq!(
+            decode(0x80000537),
+            Ok(utype(Opcode::LUI, 4, 10, -0x8000_0000))
+        );
+        // srai a0, a0, 63
+        assert_eq!(decode(0x43f55513), Ok(itype(Opcode::SRAI, 4, 10, 10, 63)));
+        // divuw a0, a0, a1
+        assert_eq!(decode(0x02b5553b), Ok(rtype(Opcode::DIVUW, 4, 10, 10, 11)));
+        assert_eq!(decode(0x00000073), Ok(notype(Opcode::ECALL, 4)));
+        // andn a0, a0, a1
+        assert_eq!(decode(0x40b57533), Ok(rtype(Opcode::ANDN, 4, 10, 10, 11)));
+        // sh3add.uw a0, a0, a1
+        assert_eq!(decode(0x20b5653b), Ok(rtype(Opcode::SH3ADDUW, 4, 10, 10, 11)));
+        // cpop a0, a1
+        assert_eq!(decode(0x60259513), Ok(itype(Opcode::CPOP, 4, 10, 11, 0)));
+        // rev8 a0, a1
+        assert_eq!(decode(0x6b85d513), Ok(itype(Opcode::REV8, 4, 10, 11, 0)));
+        // zext.h a0, a1
+        assert_eq!(decode(0x0805c53b), Ok(itype(Opcode::ZEXTH, 4, 10, 11, 0)));
+        // bseti a0, a0, 63
+        assert_eq!(decode(0x2bf51513), Ok(itype(Opcode::BSETI, 4, 10, 10, 63)));
+        // lr.w.aq a0, (a1)
+        assert_eq!(decode(0x1405a52f), Ok(rtype(Opcode::LRW, 4, 10, 11, 0)));
+        // sc.d a0, a2, (a1)
+        assert_eq!(decode(0x18c5b52f), Ok(rtype(Opcode::SCD, 4, 10, 11, 12)));
+        // amoadd.d.aqrl a0, a2, (a1)
+        assert_eq!(decode(0x06c5b52f), Ok(rtype(Opcode::AMOADDD, 4, 10, 11, 12)));
+        assert_eq!(decode(0xffffffff), Err(DecodeError::Invalid(0xffffffff)));
+    }
+
+    #[test]
+    fn disassemble_instructions() {
+        let disassemble = |bits| format!("{}", decode(bits).unwrap());
+        assert_eq!(disassemble(0xfe010113), "addi sp, sp, -32");
+        assert_eq!(disassemble(0x00113c23), "sd ra, 24(sp)");
+        assert_eq!(disassemble(0xfeb518e3), "bne a0, a1, -16");
+        assert_eq!(disassemble(0x6588), "ld a0, 8(a1)");
+        assert_eq!(disassemble(0x60259513), "cpop a0, a1");
+        assert_eq!(disassemble(0x00000073), "ecall");
+    }
+
+    #[test]
+    fn decode_compressed_instructions() {
+        // c.li a0, -1
+        assert_eq!(decode(0x557d), Ok(itype(Opcode::ADDI, 2, 10, 0, -1)));
+        // c.ld a0, 8(a1)
+        assert_eq!(decode(0x6588), Ok(itype(Opcode::LD, 2, 10, 11, 8)));
+        // c.sdsp ra, 8(sp)
+        assert_eq!(decode(0xe406), Ok(stype(Opcode::SD, 2, 2, 1, 8)));
+        // c.beqz a0, -4
+        assert_eq!(decode(0xdd75), Ok(btype(Opcode::BEQ, 2, 10, 0, -4)));
+        // c.j -2
+        assert_eq!(decode(0xbffd), Ok(utype(Opcode::JAL, 2, 0, -2)));
+        // c.jr ra
+        assert_eq!(decode(0x8082), Ok(itype(Opcode::JALR, 2, 0, 1, 0)));
+        // c.mv a0, a1
+        assert_eq!(decode(0x852e), Ok(rtype(Opcode::ADD, 2, 10, 0, 11)));
+        // c.addi16sp sp, -64
+        assert_eq!(decode(0x7139), Ok(itype(Opcode::ADDI, 2, 2, 2, -64)));
+        // sext.w a0, a0 (c.addiw on RV64, not c.jal)
+        assert_eq!(decode(0x2501), Ok(itype(Opcode::ADDIW, 2, 10, 10, 0)));
+        // c.srli a0, 1
+        assert_eq!(decode(0x8105), Ok(itype(Opcode::SRLI, 2, 10, 10, 1)));
+        // c.slli a0, 3
+        assert_eq!(decode(0x050e), Ok(itype(Opcode::SLLI, 2, 10, 10, 3)));
+        // c.sub a0, a1
+        assert_eq!(decode(0x8d0d), Ok(rtype(Opcode::SUB, 2, 10, 10, 11)));
+        // c.addw a0, a1
+        assert_eq!(decode(0x9d2d), Ok(rtype(Opcode::ADDW, 2, 10, 10, 11)));
+        assert_eq!(decode(0x9002), Ok(notype(Opcode::EBREAK, 2)));
+        assert_eq!(decode(0x0000), Err(DecodeError::Invalid(0)));
+        // c.fld
+        assert_eq!(decode(0x2008), Err(DecodeError::Unsupported(0x2008)));
+    }
+
+    #[test]
+    fn reject_reserved_compressed_instructions() {
+        // c.lui a0, 0
+        assert_eq!(decode(0x6501), Err(DecodeError::Invalid(0x6501)));
+        // c.lui zero, 1
+        assert_eq!(decode(0x6005), Err(DecodeError::Invalid(0x6005)));
+        // c.lui zero, 0
+        assert_eq!(decode(0x6001), Err(DecodeError::Invalid(0x6001)));
+        // c.lwsp zero, 0(sp)
+        assert_eq!(decode(0x4002), Err(DecodeError::Invalid(0x4002)));
+        // c.ldsp zero, 8(sp)
+        assert_eq!(decode(0x6022), Err(DecodeError::Invalid(0x6022)));
+        // c.addiw zero, 1
+        assert_eq!(decode(0x2005), Err(DecodeError::Invalid(0x2005)));
+        // The reserved quadrant 0 encoding with funct3 = 100.
+        assert_eq!(decode(0x8000), Err(DecodeError::Invalid(0x8000)));
+        // c.lui a0, 1 and c.lwsp a0, 0(sp) stay valid.
+        assert_eq!(decode(0x6505), Ok(utype(Opcode::LUI, 2, 10, 0x1000)));
+        assert_eq!(decode(0x4502), Ok(itype(Opcode::LW, 2, 10, 2, 0)));
+    }
+
+    fn decode_all(mut code: &[u8]) -> Vec<Instruction> {
+        let mut instructions = Vec::new();
+        while !code.is_empty() {
+            let instruction = decode_bytes(code).unwrap();
+            code = &code[instruction.length as usize..];
+            instructions.push(instruction);
+        }
+        instructions
+    }
+
+    #[test]
+    fn decode_ckb_script_snippets() {
+        // `_start` of ckb-c-stdlib, compiled with riscv64-unknown-elf-gcc -march=rv64imac.
+        let start = [
+            0x02, 0x45, 0x2c, 0x00, 0x01, 0x46, 0xef, 0x00, 0xc0, 0x00, 0x93, 0x08, 0xd0, 0x05,
+            0x73, 0x00, 0x00, 0x00,
+        ];
+        assert_eq!(
+            decode_all(&start),
+            vec![
+                itype(Opcode::LW, 2, 10, 2, 0),
+                itype(Opcode::ADDI, 2, 11, 2, 8),
+                itype(Opcode::ADDI, 2, 12, 0, 0),
+                utype(Opcode::JAL, 4, 1, 12),
+                itype(Opcode::ADDI, 4, 17, 0, 93),
+                notype(Opcode::ECALL, 4),
+            ]
+        );
+
+        // Prologue and epilogue of a non-leaf function.
+        let function = [
+            0x41, 0x11, 0x06, 0xe4, 0x22, 0xe0, 0x00, 0x08, 0xa2, 0x60, 0x02, 0x64, 0x41, 0x01,
+            0x82, 0x80,
+        ];
+        assert_eq!(
+            decode_all(&function),
+            vec![
+                itype(Opcode::ADDI, 2, 2, 2, -16),
+                stype(Opcode::SD, 2, 2, 1, 8),
+                stype(Opcode::SD, 2, 2, 8, 0),
+                itype(Opcode::ADDI, 2, 8, 2, 16),
+                itype(Opcode::LD, 2, 1, 2, 8),
+                itype(Opcode::LD, 2, 8, 2, 0),
+                itype(Opcode::ADDI, 2, 2, 2, 16),
+                itype(Opcode::JALR, 2, 0, 1, 0),
+            ]
+        );
+    }
+}
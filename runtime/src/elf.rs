@@ -0,0 +1,149 @@
+//! Minimal reader for the little endian RISC-V ELF64 files run by ckb-vm.
+
+use alloc::vec::Vec;
+use core::fmt;
+
+const ELF_MAGIC: [u8; 4] = [0x7f, b'E', b'L', b'F'];
+const ELFCLASS64: u8 = 2;
+const ELFDATA2LSB: u8 = 1;
+const EM_RISCV: u16 = 0xf3;
+
+const PT_LOAD: u32 = 1;
+pub const PF_X: u32 = 0x1;
+pub const PF_W: u32 = 0x2;
+pub const PF_R: u32 = 0x4;
+
+const SHT_NOBITS: u32 = 8;
+const SHF_EXECINSTR: u64 = 0x4;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ElfError {
+    InvalidMagic,
+    /// Only 64-bit little endian RISC-V files are supported.
+    UnsupportedFormat,
+    /// A header or a segment points outside of the file.
+    OutOfBounds,
+}
+
+impl fmt::Display for ElfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ElfError::InvalidMagic => write!(f, "not an ELF file"),
+            ElfError::UnsupportedFormat => write!(f, "not a RISC-V ELF64 little endian file"),
+            ElfError::OutOfBounds => write!(f, "ELF header points outside of the file"),
+        }
+    }
+}
+
+/// A `PT_LOAD` program header together with its file content.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Segment<'a> {
+    pub vaddr: u64,
+    pub memsz: u64,
+    pub flags: u32,
+    pub data: &'a [u8],
+}
+
+impl<'a> Segment<'a> {
+    pub fn is_executable(&self) -> bool {
+        self.flags & PF_X != 0
+    }
+}
+
+/// A section holding code (`SHF_EXECINSTR`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CodeSection<'a> {
+    pub addr: u64,
+    pub data: &'a [u8],
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Elf<'a> {
+    pub entry: u64,
+    pub segments: Vec<Segment<'a>>,
+    pub code_sections: Vec<CodeSection<'a>>,
+}
+
+impl<'a> Elf<'a> {
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, ElfError> {
+        if bytes.len() < 64 || bytes[..4] != ELF_MAGIC {
+            return Err(ElfError::InvalidMagic);
+        }
+        if bytes[4] != ELFCLASS64 || bytes[5] != ELFDATA2LSB || u16_at(bytes, 0x12)? != EM_RISCV
+        {
+            return Err(ElfError::UnsupportedFormat);
+        }
+
+        let entry = u64_at(bytes, 0x18)?;
+        let phoff = u64_at(bytes, 0x20)? as usize;
+        let shoff = u64_at(bytes, 0x28)? as usize;
+        let phentsize = u16_at(bytes, 0x36)? as usize;
+        let phnum = u16_at(bytes, 0x38)? as usize;
+        let shentsize = u16_at(bytes, 0x3a)? as usize;
+        let shnum = u16_at(bytes, 0x3c)? as usize;
+
+        let mut segments = Vec::new();
+        for i in 0..phnum {
+            let header = phoff + i * phentsize;
+            if u32_at(bytes, header)? != PT_LOAD {
+                continue;
+            }
+            let flags = u32_at(bytes, header + 0x4)?;
+            let offset = u64_at(bytes, header + 0x8)? as usize;
+            let vaddr = u64_at(bytes, header + 0x10)?;
+            let filesz = u64_at(bytes, header + 0x20)? as usize;
+            let memsz = u64_at(bytes, header + 0x28)?;
+            segments.push(Segment {
+                vaddr,
+                memsz,
+                flags,
+                data: slice(bytes, offset, filesz)?,
+            });
+        }
+
+        let mut code_sections = Vec::new();
+        for i in 0..shnum {
+            let header = shoff + i * shentsize;
+            let sh_type = u32_at(bytes, header + 0x4)?;
+            let sh_flags = u64_at(bytes, header + 0x8)?;
+            if sh_type == SHT_NOBITS || sh_flags & SHF_EXECINSTR == 0 {
+                continue;
+            }
+            let addr = u64_at(bytes, header + 0x10)?;
+            let offset = u64_at(bytes, header + 0x18)? as usize;
+            let size = u64_at(bytes, header + 0x20)? as usize;
+            code_sections.push(CodeSection {
+                addr,
+                data: slice(bytes, offset, size)?,
+            });
+        }
+
+        Ok(Self {
+            entry,
+            segments,
+            code_sections,
+        })
+    }
+}
+
+fn slice(bytes: &[u8], offset: usize, len: usize) -> Result<&[u8], ElfError> {
+    let end = offset.checked_add(len).ok_or(ElfError::OutOfBounds)?;
+    bytes.get(offset..end).ok_or(ElfError::OutOfBounds)
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> Result<u16, ElfError> {
+    let b = slice(bytes, offset, 2)?;
+    Ok(u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> Result<u32, ElfError> {
+    let b = slice(bytes, offset, 4)?;
+    Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn u64_at(bytes: &[u8], offset: usize) -> Result<u64, ElfError> {
+    let b = slice(bytes, offset, 8)?;
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(b);
+    Ok(u64::from_le_bytes(buf))
+}
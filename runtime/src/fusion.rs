@@ -0,0 +1,78 @@
+//! Macro-op fusion of ckb-vm version 1.
+//!
+//! ckb-vm executes some common instruction pairs as a single macro-op, the
+//! traces then hold one step of the fused opcode covering both instructions:
+//!
+//! | pair                          | fused                                        |
+//! |-------------------------------|----------------------------------------------|
+//! | `lui rd, a; addi rd, rd, b`   | `CUSTOMLOADIMM rd, a + b`                    |
+//! | `lui rd, a; addiw rd, rd, b`  | `LDSIGNEXTENDED32CONSTANT rd, sext32(a + b)` |
+//! | `auipc rd, a; jalr rd, b(rd)` | `FARJUMPREL rd, a + b`                       |
+//! | `lui rd, a; jalr rd, b(rd)`   | `FARJUMPABS rd, (a + b) & !1`                |
+
+use crate::program::Program;
+use crate::trace::{Instruction, Opcode};
+
+/// Fuses two consecutive instructions, `None` if ckb-vm doesn't fuse them.
+pub fn fuse(first: &Instruction, second: &Instruction) -> Option<Instruction> {
+    let rd = first.op_a;
+    if rd == 0 || second.op_a != rd || second.op_b != rd {
+        return None;
+    }
+    let value = first.op_b.wrapping_add(second.op_c);
+    let (opcode, value) = match (first.opcode, second.opcode) {
+        (Opcode::LUI, Opcode::ADDI) => (Opcode::CUSTOMLOADIMM, value),
+        (Opcode::LUI, Opcode::ADDIW) => (
+            Opcode::LDSIGNEXTENDED32CONSTANT,
+            value as u32 as i32 as i64 as u64,
+        ),
+        (Opcode::AUIPC, Opcode::JALR) => (Opcode::FARJUMPREL, value),
+        (Opcode::LUI, Opcode::JALR) => (Opcode::FARJUMPABS, value & !1),
+        _ => return None,
+    };
+    Some(Instruction {
+        opcode,
+        length: first.length + second.length,
+        op_a: rd,
+        op_b: value,
+        op_c: 0,
+        imm_b: true,
+        imm_c: false,
+    })
+}
+
+impl Program {
+    /// Returns the macro-op ckb-vm executes at `pc`, if the instructions there fuse.
+    pub fn fused_instruction(&self, pc: u64) -> Option<Instruction> {
+        let first = self.instruction(pc)?;
+        let second = self.instruction(pc + first.length)?;
+        fuse(first, second)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder::decode;
+
+    #[test]
+    fn fuse_pairs() {
+        // lui a0, 0x12345; addi a0, a0, -1
+        let lui = decode(0x12345537).unwrap();
+        let addi = decode(0xfff50513).unwrap();
+        let fused = fuse(&lui, &addi).unwrap();
+        assert_eq!(fused.opcode, Opcode::CUSTOMLOADIMM);
+        assert_eq!((fused.length, fused.op_a, fused.op_b), (8, 10, 0x12344fff));
+
+        // auipc ra, 0x1; jalr ra, -16(ra)
+        let auipc = decode(0x00001097).unwrap();
+        let jalr = decode(0xff0080e7).unwrap();
+        let fused = fuse(&auipc, &jalr).unwrap();
+        assert_eq!(fused.opcode, Opcode::FARJUMPREL);
+        assert_eq!((fused.op_a, fused.op_b), (1, 0xff0));
+
+        // The second instruction has to use the result of the first one.
+        let addi_a1 = decode(0xfff50593).unwrap();
+        assert_eq!(fuse(&lui, &addi_a1), None);
+    }
+}
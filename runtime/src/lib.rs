@@ -1,4 +1,11 @@
 #![no_std]
 extern crate alloc;
 
+pub mod decoder;
+pub mod elf;
+pub mod fusion;
+pub mod machine;
+pub mod program;
+pub mod syscalls;
 pub mod trace;
+pub mod tx;
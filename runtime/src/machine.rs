@@ -0,0 +1,574 @@
+//! A RV64IMC interpreter recording the executed steps as a [`Trace`].
+//!
+//! It follows ckb-vm closely enough to trace CKB scripts offline: the ELF is
+//! loaded into a flat memory of [`MEMORY_SIZE`] bytes, the stack starts at the
+//! top of it and `ecall` is served by a [`Syscalls`] implementation. Macro-op
+//! fusion is not applied, every instruction is one step.
+
+use crate::decoder::{decode_bytes, DecodeError};
+use crate::elf::{Elf, ElfError};
+use crate::trace::{
+    AType, BType, IType, Instruction, InstructionType, JType, NoType, RType, SType, Step, Trace,
+    UType,
+};
+use alloc::{vec, vec::Vec};
+use core::fmt;
+
+/// Memory size of ckb-vm.
+pub const MEMORY_SIZE: usize = 4 * 1024 * 1024;
+
+pub const REGISTER_SP: usize = 2;
+pub const REGISTER_A0: usize = 10;
+pub const REGISTER_A1: usize = 11;
+pub const REGISTER_A2: usize = 12;
+pub const REGISTER_A3: usize = 13;
+pub const REGISTER_A4: usize = 14;
+pub const REGISTER_A5: usize = 15;
+pub const REGISTER_A7: usize = 17;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MachineError {
+    Elf(ElfError),
+    Decode {
+        pc: u64,
+        error: DecodeError,
+    },
+    /// An access of the given address is outside of the memory.
+    OutOfBounds(u64),
+    /// The instruction at pc can't be executed, e.g. `ebreak` or `unimp`.
+    IllegalInstruction(u64),
+    UnsupportedSyscall(u64),
+    /// The program didn't exit within the given number of steps.
+    StepLimitExceeded(u64),
+}
+
+impl fmt::Display for MachineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MachineError::Elf(error) => write!(f, "load elf: {}", error),
+            MachineError::Decode { pc, error } => write!(f, "decode at {:#x}: {}", pc, error),
+            MachineError::OutOfBounds(address) => {
+                write!(f, "memory access out of bounds at {:#x}", address)
+            }
+            MachineError::IllegalInstruction(pc) => write!(f, "illegal instruction at {:#x}", pc),
+            MachineError::UnsupportedSyscall(number) => write!(f, "unsupported syscall {}", number),
+            MachineError::StepLimitExceeded(limit) => {
+                write!(f, "program didn't exit within {} steps", limit)
+            }
+        }
+    }
+}
+
+impl From<ElfError> for MachineError {
+    fn from(error: ElfError) -> Self {
+        MachineError::Elf(error)
+    }
+}
+
+/// The environment calls of a program.
+pub trait Syscalls {
+    /// Serves the syscall numbered by a7, with its arguments in a0 to a5.
+    ///
+    /// Returns the exit code if the syscall ends the program.
+    fn ecall(&mut self, machine: &mut Machine) -> Result<Option<i8>, MachineError>;
+}
+
+#[derive(Clone, Debug)]
+pub struct Machine {
+    pub registers: [u64; 32],
+    pub pc: u64,
+    pub memory: Vec<u8>,
+    /// Address reserved by the last LR, consumed by the next SC.
+    pub reservation: Option<u64>,
+}
+
+impl Default for Machine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Machine {
+    pub fn new() -> Self {
+        Self {
+            registers: [0; 32],
+            pc: 0,
+            memory: vec![0; MEMORY_SIZE],
+            reservation: None,
+        }
+    }
+
+    /// Loads the segments of the ELF and points the stack to the top of the
+    /// memory, with `argc` and the `argv` terminator of an empty argument list.
+    pub fn load_elf(bytes: &[u8]) -> Result<Self, MachineError> {
+        let elf = Elf::parse(bytes)?;
+        let mut machine = Machine::new();
+        machine.pc = elf.entry;
+        for segment in elf.segments.iter() {
+            machine.write_bytes(segment.vaddr, segment.data)?;
+            // The rest of the segment (.bss) is already zeroed, but has to fit.
+            machine.check_range(segment.vaddr, segment.memsz)?;
+        }
+        machine.registers[REGISTER_SP] = MEMORY_SIZE as u64 - 16;
+        Ok(machine)
+    }
+
+    pub fn register(&self, index: usize) -> u64 {
+        self.registers[index]
+    }
+
+    pub fn set_register(&mut self, index: usize, value: u64) {
+        if index != 0 {
+            self.registers[index] = value;
+        }
+    }
+
+    fn check_range(&self, address: u64, len: u64) -> Result<core::ops::Range<usize>, MachineError> {
+        match address.checked_add(len) {
+            Some(end) if end <= self.memory.len() as u64 => Ok(address as usize..end as usize),
+            _ => Err(MachineError::OutOfBounds(address)),
+        }
+    }
+
+    pub fn read_bytes(&self, address: u64, len: u64) -> Result<&[u8], MachineError> {
+        let range = self.check_range(address, len)?;
+        Ok(&self.memory[range])
+    }
+
+    pub fn write_bytes(&mut self, address: u64, bytes: &[u8]) -> Result<(), MachineError> {
+        let range = self.check_range(address, bytes.len() as u64)?;
+        self.memory[range].copy_from_slice(bytes);
+        Ok(())
+    }
+
+    /// Reads `width` bytes as a little endian value, zero extended.
+    pub fn load(&self, address: u64, width: u64) -> Result<u64, MachineError> {
+        let mut buf = [0u8; 8];
+        buf[..width as usize].copy_from_slice(self.read_bytes(address, width)?);
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    pub fn store(&mut self, address: u64, value: u64, width: u64) -> Result<(), MachineError> {
+        self.write_bytes(address, &value.to_le_bytes()[..width as usize])
+    }
+
+    /// Runs until the program exits, or fails after `max_steps` steps.
+    pub fn run(
+        &mut self,
+        syscalls: &mut impl Syscalls,
+        max_steps: u64,
+    ) -> Result<Trace, MachineError> {
+        let mut steps = Vec::new();
+        loop {
+            if steps.len() as u64 >= max_steps {
+                return Err(MachineError::StepLimitExceeded(max_steps));
+            }
+            let instruction = self.fetch()?;
+            steps.push(Step {
+                global_clk: steps.len() as u64,
+                pc: self.pc,
+                instruction: instruction.clone(),
+                registers: self.registers.to_vec(),
+            });
+            if let Some(exit_code) = self.execute(&instruction, syscalls)? {
+                // One cycle per instruction, ckb-vm charges more for some of them.
+                return Ok(Trace {
+                    cycles: steps.len() as u64,
+                    return_value: exit_code as u8,
+                    steps,
+                });
+            }
+        }
+    }
+
+    fn fetch(&self) -> Result<Instruction, MachineError> {
+        // The last instruction of the memory may be a compressed one.
+        let available = (self.memory.len() as u64).saturating_sub(self.pc).min(4);
+        let bytes = self.read_bytes(self.pc, available)?;
+        decode_bytes(bytes).map_err(|error| MachineError::Decode { pc: self.pc, error })
+    }
+
+    /// Executes one instruction, returns the exit code if it ends the program.
+    pub fn execute(
+        &mut self,
+        instruction: &Instruction,
+        syscalls: &mut impl Syscalls,
+    ) -> Result<Option<i8>, MachineError> {
+        let pc = self.pc;
+        let mut next_pc = pc.wrapping_add(instruction.length);
+        let rd = instruction.op_a as usize;
+        match instruction.opcode.into() {
+            InstructionType::RType(r) => {
+                let rs1 = self.registers[instruction.op_b as usize];
+                let rs2 = self.registers[instruction.op_c as usize];
+                self.set_register(rd, rtype(r, rs1, rs2));
+            }
+            InstructionType::IType(IType::JALR) => {
+                let rs1 = self.registers[instruction.op_b as usize];
+                next_pc = rs1.wrapping_add(instruction.op_c) & !1;
+                self.set_register(rd, pc.wrapping_add(instruction.length));
+            }
+            InstructionType::IType(i) => {
+                let rs1 = self.registers[instruction.op_b as usize];
+                let value = match load_width(i) {
+                    Some((width, signed)) => {
+                        let value = self.load(rs1.wrapping_add(instruction.op_c), width)?;
+                        if signed {
+                            sign_extend(value, width * 8)
+                        } else {
+                            value
+                        }
+                    }
+                    None => itype(i, rs1, instruction.op_c),
+                };
+                self.set_register(rd, value);
+            }
+            InstructionType::SType(s) => {
+                let rs2 = self.registers[instruction.op_a as usize];
+                let rs1 = self.registers[instruction.op_b as usize];
+                let width = match s {
+                    SType::SB => 1,
+                    SType::SH => 2,
+                    SType::SW => 4,
+                    SType::SD => 8,
+                };
+                self.store(rs1.wrapping_add(instruction.op_c), rs2, width)?;
+            }
+            InstructionType::BType(b) => {
+                let rs1 = self.registers[instruction.op_a as usize];
+                let rs2 = self.registers[instruction.op_b as usize];
+                let taken = match b {
+                    BType::BEQ => rs1 == rs2,
+                    BType::BNE => rs1 != rs2,
+                    BType::BLT => (rs1 as i64) < (rs2 as i64),
+                    BType::BGE => (rs1 as i64) >= (rs2 as i64),
+                    BType::BLTU => rs1 < rs2,
+                    BType::BGEU => rs1 >= rs2,
+                };
+                if taken {
+                    next_pc = pc.wrapping_add(instruction.op_c);
+                }
+            }
+            InstructionType::UType(UType::LUI) => self.set_register(rd, instruction.op_b),
+            InstructionType::UType(UType::AUIPC) => {
+                self.set_register(rd, pc.wrapping_add(instruction.op_b))
+            }
+            InstructionType::JType(JType::JAL) => {
+                self.set_register(rd, pc.wrapping_add(instruction.length));
+                next_pc = pc.wrapping_add(instruction.op_b);
+            }
+            InstructionType::AType(a) => self.execute_atype(a, instruction)?,
+            InstructionType::FusedType(_) => return Err(MachineError::IllegalInstruction(pc)),
+            InstructionType::NoType(NoType::FENCE) => {}
+            InstructionType::NoType(NoType::ECALL) => {
+                if let Some(exit_code) = syscalls.ecall(self)? {
+                    return Ok(Some(exit_code));
+                }
+            }
+            InstructionType::NoType(NoType::EBREAK | NoType::UNIMP) => {
+                return Err(MachineError::IllegalInstruction(pc))
+            }
+        }
+        self.pc = next_pc;
+        Ok(None)
+    }
+
+    fn execute_atype(&mut self, a: AType, instruction: &Instruction) -> Result<(), MachineError> {
+        let rd = instruction.op_a as usize;
+        let address = self.registers[instruction.op_b as usize];
+        let rs2 = self.registers[instruction.op_c as usize];
+        let word = matches!(
+            a,
+            AType::LRW
+                | AType::SCW
+                | AType::AMOSWAPW
+                | AType::AMOADDW
+                | AType::AMOXORW
+                | AType::AMOANDW
+                | AType::AMOORW
+                | AType::AMOMINW
+                | AType::AMOMAXW
+                | AType::AMOMINUW
+                | AType::AMOMAXUW
+        );
+        let width = if word { 4 } else { 8 };
+        match a {
+            AType::LRW | AType::LRD => {
+                let loaded = sign_extend(self.load(address, width)?, width * 8);
+                self.reservation = Some(address);
+                self.set_register(rd, loaded);
+            }
+            AType::SCW | AType::SCD => {
+                let success = self.reservation.take() == Some(address);
+                if success {
+                    self.store(address, rs2, width)?;
+                }
+                self.set_register(rd, (!success).into());
+            }
+            _ => {
+                let loaded = sign_extend(self.load(address, width)?, width * 8);
+                let (lhs, rhs) = if word {
+                    (loaded as u32 as u64, rs2 as u32 as u64)
+                } else {
+                    (loaded, rs2)
+                };
+                let (signed_lhs, signed_rhs) = if word {
+                    (lhs as i32 as i64, rhs as i32 as i64)
+                } else {
+                    (lhs as i64, rhs as i64)
+                };
+                let stored = match a {
+                    AType::AMOSWAPW | AType::AMOSWAPD => rs2,
+                    AType::AMOADDW | AType::AMOADDD => loaded.wrapping_add(rs2),
+                    AType::AMOXORW | AType::AMOXORD => loaded ^ rs2,
+                    AType::AMOANDW | AType::AMOANDD => loaded & rs2,
+                    AType::AMOORW | AType::AMOORD => loaded | rs2,
+                    AType::AMOMINW | AType::AMOMIND if signed_lhs <= signed_rhs => loaded,
+                    AType::AMOMAXW | AType::AMOMAXD if signed_lhs >= signed_rhs => loaded,
+                    AType::AMOMINW | AType::AMOMIND | AType::AMOMAXW | AType::AMOMAXD => rs2,
+                    AType::AMOMINUW | AType::AMOMINUD if lhs <= rhs => loaded,
+                    AType::AMOMAXUW | AType::AMOMAXUD if lhs >= rhs => loaded,
+                    _ => rs2,
+                };
+                self.store(address, stored, width)?;
+                self.set_register(rd, loaded);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn sign_extend(value: u64, bits: u64) -> u64 {
+    let shift = 64 - bits;
+    (((value << shift) as i64) >> shift) as u64
+}
+
+fn sext32(value: u64) -> u64 {
+    value as i32 as i64 as u64
+}
+
+/// Width in bytes and signedness of the load instructions.
+fn load_width(i: IType) -> Option<(u64, bool)> {
+    match i {
+        IType::LB => Some((1, true)),
+        IType::LH => Some((2, true)),
+        IType::LW => Some((4, true)),
+        IType::LD => Some((8, true)),
+        IType::LBU => Some((1, false)),
+        IType::LHU => Some((2, false)),
+        IType::LWU => Some((4, false)),
+        _ => None,
+    }
+}
+
+fn rtype(r: RType, rs1: u64, rs2: u64) -> u64 {
+    let shamt = rs2 & 0x3f;
+    let shamt_w = (rs2 & 0x1f) as u32;
+    match r {
+        RType::ADD => rs1.wrapping_add(rs2),
+        RType::SUB => rs1.wrapping_sub(rs2),
+        RType::SLL => rs1 << shamt,
+        RType::SLT => ((rs1 as i64) < (rs2 as i64)).into(),
+        RType::SLTU => (rs1 < rs2).into(),
+        RType::XOR => rs1 ^ rs2,
+        RType::SRL => rs1 >> shamt,
+        RType::SRA => ((rs1 as i64) >> shamt) as u64,
+        RType::OR => rs1 | rs2,
+        RType::AND => rs1 & rs2,
+        RType::MUL => rs1.wrapping_mul(rs2),
+        RType::MULH => ((rs1 as i64 as i128 * rs2 as i64 as i128) >> 64) as u64,
+        RType::MULHU => ((rs1 as u128 * rs2 as u128) >> 64) as u64,
+        RType::MULHSU => ((rs1 as i64 as i128).wrapping_mul(rs2 as i128) >> 64) as u64,
+        RType::DIV => match rs2 {
+            0 => u64::MAX,
+            _ => (rs1 as i64).wrapping_div(rs2 as i64) as u64,
+        },
+        RType::DIVU => rs1.checked_div(rs2).unwrap_or(u64::MAX),
+        RType::REM => match rs2 {
+            0 => rs1,
+            _ => (rs1 as i64).wrapping_rem(rs2 as i64) as u64,
+        },
+        RType::REMU => rs1.checked_rem(rs2).unwrap_or(rs1),
+        RType::ADDW => sext32(rs1.wrapping_add(rs2)),
+        RType::SUBW => sext32(rs1.wrapping_sub(rs2)),
+        RType::SLLW => sext32(((rs1 as u32) << shamt_w) as u64),
+        RType::SRLW => sext32(((rs1 as u32) >> shamt_w) as u64),
+        RType::SRAW => ((rs1 as i32) >> shamt_w) as i64 as u64,
+        RType::MULW => sext32(rs1.wrapping_mul(rs2)),
+        RType::DIVW => match rs2 as i32 {
+            0 => u64::MAX,
+            divisor => (rs1 as i32).wrapping_div(divisor) as i64 as u64,
+        },
+        RType::DIVUW => match rs2 as u32 {
+            0 => u64::MAX,
+            divisor => sext32(((rs1 as u32) / divisor) as u64),
+        },
+        RType::REMW => match rs2 as i32 {
+            0 => sext32(rs1),
+            divisor => (rs1 as i32).wrapping_rem(divisor) as i64 as u64,
+        },
+        RType::REMUW => match rs2 as u32 {
+            0 => sext32(rs1),
+            divisor => sext32(((rs1 as u32) % divisor) as u64),
+        },
+        RType::ADDUW => (rs1 as u32 as u64).wrapping_add(rs2),
+        RType::ANDN => rs1 & !rs2,
+        RType::ORN => rs1 | !rs2,
+        RType::XNOR => !(rs1 ^ rs2),
+        RType::MAX => (rs1 as i64).max(rs2 as i64) as u64,
+        RType::MAXU => rs1.max(rs2),
+        RType::MIN => (rs1 as i64).min(rs2 as i64) as u64,
+        RType::MINU => rs1.min(rs2),
+        RType::ROL => rs1.rotate_left(shamt as u32),
+        RType::ROLW => sext32((rs1 as u32).rotate_left(shamt_w) as u64),
+        RType::ROR => rs1.rotate_right(shamt as u32),
+        RType::RORW => sext32((rs1 as u32).rotate_right(shamt_w) as u64),
+        RType::SH1ADD => (rs1 << 1).wrapping_add(rs2),
+        RType::SH1ADDUW => ((rs1 as u32 as u64) << 1).wrapping_add(rs2),
+        RType::SH2ADD => (rs1 << 2).wrapping_add(rs2),
+        RType::SH2ADDUW => ((rs1 as u32 as u64) << 2).wrapping_add(rs2),
+        RType::SH3ADD => (rs1 << 3).wrapping_add(rs2),
+        RType::SH3ADDUW => ((rs1 as u32 as u64) << 3).wrapping_add(rs2),
+        RType::CLMUL => clmul(rs1, rs2) as u64,
+        RType::CLMULH => (clmul(rs1, rs2) >> 64) as u64,
+        RType::CLMULR => (clmul(rs1, rs2) >> 63) as u64,
+        RType::BCLR => rs1 & !(1 << shamt),
+        RType::BEXT => (rs1 >> shamt) & 1,
+        RType::BINV => rs1 ^ (1 << shamt),
+        RType::BSET => rs1 | (1 << shamt),
+    }
+}
+
+fn itype(i: IType, rs1: u64, imm: u64) -> u64 {
+    let shamt = imm & 0x3f;
+    let shamt_w = (imm & 0x1f) as u32;
+    match i {
+        IType::ADDI => rs1.wrapping_add(imm),
+        IType::SLTI => ((rs1 as i64) < (imm as i64)).into(),
+        IType::SLTIU => (rs1 < imm).into(),
+        IType::XORI => rs1 ^ imm,
+        IType::ORI => rs1 | imm,
+        IType::ANDI => rs1 & imm,
+        IType::SLLI => rs1 << shamt,
+        IType::SRLI => rs1 >> shamt,
+        IType::SRAI => ((rs1 as i64) >> shamt) as u64,
+        IType::ADDIW => sext32(rs1.wrapping_add(imm)),
+        IType::SLLIW => sext32(((rs1 as u32) << shamt_w) as u64),
+        IType::SRLIW => sext32(((rs1 as u32) >> shamt_w) as u64),
+        IType::SRAIW => ((rs1 as i32) >> shamt_w) as i64 as u64,
+        IType::RORI => rs1.rotate_right(shamt as u32),
+        IType::RORIW => sext32((rs1 as u32).rotate_right(shamt_w) as u64),
+        IType::SLLIUW => (rs1 as u32 as u64) << shamt,
+        IType::BCLRI => rs1 & !(1 << shamt),
+        IType::BEXTI => (rs1 >> shamt) & 1,
+        IType::BINVI => rs1 ^ (1 << shamt),
+        IType::BSETI => rs1 | (1 << shamt),
+        IType::CLZ => rs1.leading_zeros() as u64,
+        IType::CLZW => (rs1 as u32).leading_zeros() as u64,
+        IType::CTZ => rs1.trailing_zeros() as u64,
+        IType::CTZW => (rs1 as u32).trailing_zeros() as u64,
+        IType::CPOP => rs1.count_ones() as u64,
+        IType::CPOPW => (rs1 as u32).count_ones() as u64,
+        IType::ORCB => {
+            let mut value = 0;
+            for byte in 0..8 {
+                if (rs1 >> (byte * 8)) & 0xff != 0 {
+                    value |= 0xff << (byte * 8);
+                }
+            }
+            value
+        }
+        IType::REV8 => rs1.swap_bytes(),
+        IType::SEXTB => rs1 as i8 as i64 as u64,
+        IType::SEXTH => rs1 as i16 as i64 as u64,
+        IType::ZEXTH => rs1 as u16 as u64,
+        IType::JALR
+        | IType::LB
+        | IType::LH
+        | IType::LW
+        | IType::LD
+        | IType::LBU
+        | IType::LHU
+        | IType::LWU => unreachable!("Not an arithmetic instruction"),
+    }
+}
+
+/// Carry-less product of two registers.
+fn clmul(a: u64, b: u64) -> u128 {
+    let mut result = 0u128;
+    for i in 0..64 {
+        if (b >> i) & 1 == 1 {
+            result ^= (a as u128) << i;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Exits on the first ecall with the code in a0.
+    struct Exit;
+
+    impl Syscalls for Exit {
+        fn ecall(&mut self, machine: &mut Machine) -> Result<Option<i8>, MachineError> {
+            Ok(Some(machine.register(REGISTER_A0) as i8))
+        }
+    }
+
+    fn machine_with_code(code: &[u32]) -> Machine {
+        let mut machine = Machine::new();
+        machine.pc = 0x1000;
+        for (i, bits) in code.iter().enumerate() {
+            machine
+                .store(0x1000 + 4 * i as u64, *bits as u64, 4)
+                .unwrap();
+        }
+        machine
+    }
+
+    #[test]
+    fn run_loop() {
+        // li a0, 0; li a1, 5; loop: addi a0, a0, 3; addi a1, a1, -1;
+        // bnez a1, loop; li a7, 93; ecall
+        let mut machine = machine_with_code(&[
+            0x00000513, 0x00500593, 0x00350513, 0xfff58593, 0xfe059ce3, 0x05d00893, 0x00000073,
+        ]);
+        let trace = machine.run(&mut Exit, 100).unwrap();
+        assert_eq!(trace.return_value, 15);
+        assert_eq!(trace.steps.len(), 2 + 3 * 5 + 2);
+        // Steps hold the registers before the instruction.
+        let last = trace.steps.last().unwrap();
+        assert_eq!((last.registers[10], last.registers[11]), (15, 0));
+
+        // j .
+        let mut machine = machine_with_code(&[0x0000006f]);
+        assert_eq!(
+            machine.run(&mut Exit, 10).unwrap_err(),
+            MachineError::StepLimitExceeded(10)
+        );
+    }
+
+    #[test]
+    fn arithmetic_edge_cases() {
+        assert_eq!(rtype(RType::DIV, 7, 0), u64::MAX);
+        assert_eq!(
+            rtype(RType::DIV, i64::MIN as u64, u64::MAX),
+            i64::MIN as u64
+        );
+        assert_eq!(rtype(RType::REM, i64::MIN as u64, u64::MAX), 0);
+        assert_eq!(
+            rtype(RType::REMUW, 0xffff_ffff_8000_0000, 0),
+            0xffff_ffff_8000_0000
+        );
+        assert_eq!(rtype(RType::SRA, 0x8000_0000_0000_0000, 63), u64::MAX);
+        assert_eq!(rtype(RType::MULHSU, u64::MAX, 2), u64::MAX);
+        assert_eq!(rtype(RType::CLMULH, 1 << 63, 2), 1);
+        assert_eq!(
+            itype(IType::ORCB, 0x0100_0000_0000_2000, 0),
+            0xff00_0000_0000_ff00
+        );
+        assert_eq!(itype(IType::SRAIW, 0x8000_0000, 4), 0xffff_ffff_f800_0000);
+    }
+}
@@ -0,0 +1,78 @@
+use crate::decoder::{decode_bytes, instruction_length};
+use crate::elf::{Elf, ElfError};
+use crate::trace::{Instruction, Step};
+use alloc::collections::BTreeMap;
+
+/// The instructions of a program, indexed by their pc.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Program {
+    pub entry: u64,
+    pub instructions: BTreeMap<u64, Instruction>,
+}
+
+/// A trace step which doesn't execute the instruction the program has at its pc.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProgramMismatch {
+    pub global_clk: u64,
+    pub pc: u64,
+    /// `None` if the program has no (valid) instruction at pc.
+    pub expected: Option<Instruction>,
+    pub actual: Instruction,
+}
+
+impl Program {
+    /// Decodes the code sections of an ELF file, or the executable segments if
+    /// the section headers were stripped.
+    ///
+    /// Invalid encodings (e.g. padding or data in the code) are skipped.
+    pub fn from_elf(bytes: &[u8]) -> Result<Self, ElfError> {
+        let elf = Elf::parse(bytes)?;
+        let mut program = Program {
+            entry: elf.entry,
+            instructions: BTreeMap::new(),
+        };
+        if elf.code_sections.is_empty() {
+            for segment in elf.segments.iter().filter(|s| s.is_executable()) {
+                program.decode_code(segment.vaddr, segment.data);
+            }
+        } else {
+            for section in elf.code_sections.iter() {
+                program.decode_code(section.addr, section.data);
+            }
+        }
+        Ok(program)
+    }
+
+    fn decode_code(&mut self, addr: u64, code: &[u8]) {
+        let mut offset = 0;
+        while offset + 2 <= code.len() {
+            let bytes = &code[offset..];
+            let length = instruction_length(u16::from_le_bytes([bytes[0], bytes[1]]));
+            if let Ok(instruction) = decode_bytes(bytes) {
+                self.instructions.insert(addr + offset as u64, instruction);
+            }
+            offset += length as usize;
+        }
+    }
+
+    pub fn instruction(&self, pc: u64) -> Option<&Instruction> {
+        self.instructions.get(&pc)
+    }
+
+    /// Checks that `step` executes the instruction of the program at its pc, or
+    /// the macro-op it starts.
+    pub fn check_step(&self, step: &Step) -> Result<(), ProgramMismatch> {
+        match self.instruction(step.pc) {
+            Some(instruction) if *instruction == step.instruction => Ok(()),
+            Some(_) if self.fused_instruction(step.pc).as_ref() == Some(&step.instruction) => {
+                Ok(())
+            }
+            expected => Err(ProgramMismatch {
+                global_clk: step.global_clk,
+                pc: step.pc,
+                expected: expected.cloned(),
+                actual: step.instruction.clone(),
+            }),
+        }
+    }
+}
@@ -0,0 +1,326 @@
+//! The CKB syscalls of a script, served from a [`MockTransaction`].
+//!
+//! This is the environment ckb-debugger provides: the embedded interpreter
+//! runs scripts with it, and being `no_std` it can also back native tests of
+//! on-chain code without a node.
+
+use crate::machine::{
+    Machine, MachineError, Syscalls, REGISTER_A0, REGISTER_A1, REGISTER_A2, REGISTER_A3,
+    REGISTER_A4, REGISTER_A7,
+};
+use crate::tx::{CellOutput, MockTransaction, Script};
+use alloc::{string::String, vec::Vec};
+
+pub const SYSCALL_EXIT: u64 = 93;
+pub const SYSCALL_LOAD_SCRIPT: u64 = 2052;
+pub const SYSCALL_LOAD_TX_HASH: u64 = 2061;
+pub const SYSCALL_LOAD_WITNESS: u64 = 2074;
+pub const SYSCALL_LOAD_CELL_DATA: u64 = 2092;
+pub const SYSCALL_DEBUG: u64 = 2177;
+
+// Return codes of the load syscalls.
+pub const SUCCESS: u64 = 0;
+pub const INDEX_OUT_OF_BOUND: u64 = 1;
+pub const ITEM_MISSING: u64 = 2;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Source {
+    Input,
+    Output,
+    CellDep,
+    HeaderDep,
+    /// Inputs of the script group.
+    GroupInput,
+    /// Outputs of the script group.
+    GroupOutput,
+}
+
+impl Source {
+    pub fn from_u64(value: u64) -> Option<Self> {
+        match value {
+            0x1 => Some(Source::Input),
+            0x2 => Some(Source::Output),
+            0x3 => Some(Source::CellDep),
+            0x4 => Some(Source::HeaderDep),
+            0x0100000000000001 => Some(Source::GroupInput),
+            0x0100000000000002 => Some(Source::GroupOutput),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ScriptGroupType {
+    Lock,
+    Type,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CellType {
+    Input,
+    Output,
+}
+
+/// Syscalls of one script of a mocked transaction, like ckb-debugger runs it.
+#[derive(Clone, Debug)]
+pub struct MockSyscalls {
+    pub tx: MockTransaction,
+    /// The running script.
+    pub script: Script,
+    /// Indexes of the inputs and outputs sharing the script.
+    pub group_inputs: Vec<usize>,
+    pub group_outputs: Vec<usize>,
+    /// Messages printed with the debug syscall.
+    pub debug: Vec<String>,
+}
+
+fn group_script(output: &CellOutput, group_type: ScriptGroupType) -> Option<&Script> {
+    match group_type {
+        ScriptGroupType::Lock => Some(&output.lock),
+        ScriptGroupType::Type => output.type_.as_ref(),
+    }
+}
+
+impl MockSyscalls {
+    /// Runs the `group_type` script of the `index`-th `cell_type` cell, `None`
+    /// if there is no such cell or it has no type script.
+    pub fn new(
+        tx: MockTransaction,
+        group_type: ScriptGroupType,
+        cell_type: CellType,
+        index: usize,
+    ) -> Option<Self> {
+        let cell = match cell_type {
+            CellType::Input => &tx.mock_info.inputs.get(index)?.output,
+            CellType::Output => tx.tx.outputs.get(index)?,
+        };
+        let script = group_script(cell, group_type)?.clone();
+        let in_group = |output: &CellOutput| group_script(output, group_type) == Some(&script);
+        let group_inputs = (0..tx.mock_info.inputs.len())
+            .filter(|i| in_group(&tx.mock_info.inputs[*i].output))
+            .collect();
+        // Lock scripts only run for the inputs.
+        let group_outputs = match group_type {
+            ScriptGroupType::Lock => Vec::new(),
+            ScriptGroupType::Type => (0..tx.tx.outputs.len())
+                .filter(|i| in_group(&tx.tx.outputs[*i]))
+                .collect(),
+        };
+        Some(Self {
+            tx,
+            script,
+            group_inputs,
+            group_outputs,
+            debug: Vec::new(),
+        })
+    }
+
+    /// Maps the index of a group source to the index in the transaction.
+    fn resolve(&self, source: Source, index: usize) -> Option<usize> {
+        match source {
+            Source::GroupInput => self.group_inputs.get(index).copied(),
+            Source::GroupOutput => self.group_outputs.get(index).copied(),
+            _ => Some(index),
+        }
+    }
+
+    fn load_witness(&self, machine: &mut Machine) -> Result<u64, MachineError> {
+        let index = machine.register(REGISTER_A3) as usize;
+        let witness = match Source::from_u64(machine.register(REGISTER_A4)) {
+            Some(
+                source
+                @ (Source::Input | Source::Output | Source::GroupInput | Source::GroupOutput),
+            ) => self
+                .resolve(source, index)
+                .and_then(|index| self.tx.tx.witnesses.get(index)),
+            _ => None,
+        };
+        match witness {
+            Some(witness) => store_data(machine, &witness.0),
+            None => Ok(INDEX_OUT_OF_BOUND),
+        }
+    }
+
+    fn load_cell_data(&self, machine: &mut Machine) -> Result<u64, MachineError> {
+        let index = machine.register(REGISTER_A3) as usize;
+        let source = Source::from_u64(machine.register(REGISTER_A4));
+        let data = match source {
+            Some(source @ (Source::Input | Source::GroupInput)) => self
+                .resolve(source, index)
+                .and_then(|index| self.tx.mock_info.inputs.get(index))
+                .map(|input| &input.data),
+            Some(source @ (Source::Output | Source::GroupOutput)) => self
+                .resolve(source, index)
+                .and_then(|index| self.tx.tx.outputs_data.get(index)),
+            Some(Source::CellDep) => self
+                .tx
+                .resolved_cell_deps()
+                .and_then(|cells| cells.get(index).map(|cell| &cell.data)),
+            Some(Source::HeaderDep) | None => None,
+        };
+        match data {
+            Some(data) => store_data(machine, &data.0),
+            None => Ok(INDEX_OUT_OF_BOUND),
+        }
+    }
+
+    fn debug(&mut self, machine: &mut Machine) -> Result<(), MachineError> {
+        let address = machine.register(REGISTER_A0);
+        let mut message = Vec::new();
+        loop {
+            let byte = machine.load(address + message.len() as u64, 1)? as u8;
+            if byte == 0 {
+                break;
+            }
+            message.push(byte);
+        }
+        self.debug
+            .push(String::from_utf8_lossy(&message).into_owned());
+        Ok(())
+    }
+}
+
+/// Partial loading of the load syscalls: copies `data` from the offset in a2
+/// to a0, at most as many bytes as the length a1 points to, which is set to
+/// the length of the data after the offset.
+pub fn store_data(machine: &mut Machine, data: &[u8]) -> Result<u64, MachineError> {
+    let address = machine.register(REGISTER_A0);
+    let size_address = machine.register(REGISTER_A1);
+    let offset = (machine.register(REGISTER_A2) as usize).min(data.len());
+    let size = machine.load(size_address, 8)? as usize;
+    let data = &data[offset..];
+    let copied = size.min(data.len());
+    machine.store(size_address, data.len() as u64, 8)?;
+    machine.write_bytes(address, &data[..copied])?;
+    Ok(SUCCESS)
+}
+
+impl Syscalls for MockSyscalls {
+    fn ecall(&mut self, machine: &mut Machine) -> Result<Option<i8>, MachineError> {
+        let result = match machine.register(REGISTER_A7) {
+            SYSCALL_EXIT => return Ok(Some(machine.register(REGISTER_A0) as i8)),
+            SYSCALL_LOAD_SCRIPT => store_data(machine, &self.script.molecule())?,
+            SYSCALL_LOAD_TX_HASH => store_data(machine, &self.tx.tx.hash())?,
+            SYSCALL_LOAD_WITNESS => self.load_witness(machine)?,
+            SYSCALL_LOAD_CELL_DATA => self.load_cell_data(machine)?,
+            SYSCALL_DEBUG => {
+                self.debug(machine)?;
+                SUCCESS
+            }
+            number => return Err(MachineError::UnsupportedSyscall(number)),
+        };
+        machine.set_register(REGISTER_A0, result);
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    const TX: &str = r#"{
+        "mock_info": {
+            "inputs": [
+                {
+                    "input": {"since": "0x0", "previous_output": {"tx_hash": "0x0000000000000000000000000000000000000000000000000000000000000000", "index": "0x0"}},
+                    "output": {"capacity": "0x10", "lock": {"code_hash": "0x0101010101010101010101010101010101010101010101010101010101010101", "hash_type": "data1", "args": "0x"}, "type": null},
+                    "data": "0x"
+                },
+                {
+                    "input": {"since": "0x0", "previous_output": {"tx_hash": "0x0000000000000000000000000000000000000000000000000000000000000000", "index": "0x1"}},
+                    "output": {"capacity": "0x10", "lock": {"code_hash": "0x0202020202020202020202020202020202020202020202020202020202020202", "hash_type": "type", "args": "0x"}, "type": null},
+                    "data": "0xabcd"
+                }
+            ],
+            "cell_deps": [
+                {
+                    "cell_dep": {"out_point": {"tx_hash": "0x0303030303030303030303030303030303030303030303030303030303030303", "index": "0x0"}, "dep_type": "code"},
+                    "output": {"capacity": "0x10", "lock": {"code_hash": "0x0000000000000000000000000000000000000000000000000000000000000000", "hash_type": "data", "args": "0x"}, "type": null},
+                    "data": "0x7f"
+                }
+            ],
+            "header_deps": []
+        },
+        "tx": {
+            "version": "0x0",
+            "cell_deps": [{"out_point": {"tx_hash": "0x0303030303030303030303030303030303030303030303030303030303030303", "index": "0x0"}, "dep_type": "code"}],
+            "header_deps": [],
+            "inputs": [],
+            "outputs": [],
+            "outputs_data": [],
+            "witnesses": ["0x", "0x0011223344"]
+        }
+    }"#;
+
+    #[test]
+    fn load_witness_of_group() {
+        let tx: MockTransaction = serde_json::from_str(TX).unwrap();
+        let mut syscalls =
+            MockSyscalls::new(tx, ScriptGroupType::Lock, CellType::Input, 1).unwrap();
+        assert_eq!(syscalls.group_inputs, [1]);
+
+        // Load 2 bytes from offset 1 of the first witness of the group.
+        let mut machine = Machine::new();
+        machine.store(0x100, 2, 8).unwrap();
+        for (register, value) in [
+            (REGISTER_A0, 0x200),
+            (REGISTER_A1, 0x100),
+            (REGISTER_A2, 1),
+            (REGISTER_A3, 0),
+            (REGISTER_A4, 0x0100000000000001),
+            (REGISTER_A7, SYSCALL_LOAD_WITNESS),
+        ] {
+            machine.set_register(register, value);
+        }
+        assert_eq!(syscalls.ecall(&mut machine), Ok(None));
+        assert_eq!(machine.register(REGISTER_A0), SUCCESS);
+        assert_eq!(machine.load(0x100, 8), Ok(4));
+        assert_eq!(machine.read_bytes(0x200, 3), Ok(&[0x11, 0x22, 0][..]));
+
+        machine.set_register(REGISTER_A0, 0x200);
+        machine.set_register(REGISTER_A3, 1);
+        assert_eq!(syscalls.ecall(&mut machine), Ok(None));
+        assert_eq!(machine.register(REGISTER_A0), INDEX_OUT_OF_BOUND);
+    }
+
+    #[test]
+    fn load_cell_data_and_hashes() {
+        let tx: MockTransaction = serde_json::from_str(TX).unwrap();
+        let tx_hash = tx.tx.hash();
+        let mut syscalls =
+            MockSyscalls::new(tx, ScriptGroupType::Lock, CellType::Input, 0).unwrap();
+        let mut machine = Machine::new();
+        let mut call = |number, source, index| {
+            machine.store(0x100, 64, 8).unwrap();
+            for (register, value) in [
+                (REGISTER_A0, 0x200),
+                (REGISTER_A1, 0x100),
+                (REGISTER_A2, 0),
+                (REGISTER_A3, index),
+                (REGISTER_A4, source),
+                (REGISTER_A7, number),
+            ] {
+                machine.set_register(register, value);
+            }
+            assert_eq!(syscalls.ecall(&mut machine), Ok(None));
+            let size = machine.load(0x100, 8).unwrap();
+            let data = machine.read_bytes(0x200, size.min(64)).unwrap().to_vec();
+            (machine.register(REGISTER_A0), data)
+        };
+
+        assert_eq!(
+            call(SYSCALL_LOAD_CELL_DATA, 1, 1),
+            (SUCCESS, vec![0xab, 0xcd])
+        );
+        assert_eq!(call(SYSCALL_LOAD_CELL_DATA, 3, 0), (SUCCESS, vec![0x7f]));
+        assert_eq!(call(SYSCALL_LOAD_CELL_DATA, 2, 0).0, INDEX_OUT_OF_BOUND);
+        assert_eq!(
+            call(SYSCALL_LOAD_TX_HASH, 0, 0),
+            (SUCCESS, tx_hash.to_vec())
+        );
+        let (result, script) = call(SYSCALL_LOAD_SCRIPT, 0, 0);
+        assert_eq!((result, script.len()), (SUCCESS, 53));
+        assert_eq!(script[16..48], [1; 32]);
+    }
+}
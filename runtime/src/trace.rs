@@ -1,7 +1,9 @@
 use alloc::{format, string::String, vec::Vec};
-use core::panic;
+use core::fmt;
 use serde::{Deserialize, Serialize};
 
+pub mod binary;
+
 // TODO: relay on ckb opcode
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Opcode {
@@ -85,6 +87,86 @@ pub enum Opcode {
     SLLW = 58,
     SRLW = 59,
     SRAW = 60,
+    MULW = 61,
+    DIVW = 62,
+    DIVUW = 63,
+    REMW = 64,
+    REMUW = 65,
+
+    // Atomic instructions.
+    LRW = 66,
+    SCW = 67,
+    AMOSWAPW = 68,
+    AMOADDW = 69,
+    AMOXORW = 70,
+    AMOANDW = 71,
+    AMOORW = 72,
+    AMOMINW = 73,
+    AMOMAXW = 74,
+    AMOMINUW = 75,
+    AMOMAXUW = 76,
+    LRD = 77,
+    SCD = 78,
+    AMOSWAPD = 79,
+    AMOADDD = 80,
+    AMOXORD = 81,
+    AMOANDD = 82,
+    AMOORD = 83,
+    AMOMIND = 84,
+    AMOMAXD = 85,
+    AMOMINUD = 86,
+    AMOMAXUD = 87,
+
+    // Bit manipulation instructions.
+    ADDUW = 88,
+    ANDN = 89,
+    ORN = 90,
+    XNOR = 91,
+    MAX = 92,
+    MAXU = 93,
+    MIN = 94,
+    MINU = 95,
+    ROL = 96,
+    ROLW = 97,
+    ROR = 98,
+    RORW = 99,
+    SH1ADD = 100,
+    SH1ADDUW = 101,
+    SH2ADD = 102,
+    SH2ADDUW = 103,
+    SH3ADD = 104,
+    SH3ADDUW = 105,
+    CLMUL = 106,
+    CLMULH = 107,
+    CLMULR = 108,
+    BCLR = 109,
+    BEXT = 110,
+    BINV = 111,
+    BSET = 112,
+    RORI = 113,
+    RORIW = 114,
+    SLLIUW = 115,
+    BCLRI = 116,
+    BEXTI = 117,
+    BINVI = 118,
+    BSETI = 119,
+    CLZ = 120,
+    CLZW = 121,
+    CTZ = 122,
+    CTZW = 123,
+    CPOP = 124,
+    CPOPW = 125,
+    ORCB = 126,
+    REV8 = 127,
+    SEXTB = 128,
+    SEXTH = 129,
+    ZEXTH = 130,
+
+    // Macro-op fusion of ckb-vm version 1, see `crate::fusion`.
+    CUSTOMLOADIMM = 131,
+    LDSIGNEXTENDED32CONSTANT = 132,
+    FARJUMPREL = 133,
+    FARJUMPABS = 134,
 
     // Miscellaneaous instructions.
     UNIMP = 255,
@@ -92,7 +174,14 @@ pub enum Opcode {
 
 impl From<u16> for Opcode {
     fn from(value: u16) -> Self {
-        match value {
+        Opcode::from_u16(value).expect("Invalid opcode value")
+    }
+}
+
+impl Opcode {
+    /// Maps the numeric opcode value back to the opcode, `None` for unknown values.
+    pub fn from_u16(value: u16) -> Option<Self> {
+        let opcode = match value {
             0 => Opcode::ADD,
             1 => Opcode::SUB,
             2 => Opcode::XOR,
@@ -153,10 +242,85 @@ impl From<u16> for Opcode {
             58 => Opcode::SLLW,
             59 => Opcode::SRLW,
             60 => Opcode::SRAW,
+            61 => Opcode::MULW,
+            62 => Opcode::DIVW,
+            63 => Opcode::DIVUW,
+            64 => Opcode::REMW,
+            65 => Opcode::REMUW,
+            66 => Opcode::LRW,
+            67 => Opcode::SCW,
+            68 => Opcode::AMOSWAPW,
+            69 => Opcode::AMOADDW,
+            70 => Opcode::AMOXORW,
+            71 => Opcode::AMOANDW,
+            72 => Opcode::AMOORW,
+            73 => Opcode::AMOMINW,
+            74 => Opcode::AMOMAXW,
+            75 => Opcode::AMOMINUW,
+            76 => Opcode::AMOMAXUW,
+            77 => Opcode::LRD,
+            78 => Opcode::SCD,
+            79 => Opcode::AMOSWAPD,
+            80 => Opcode::AMOADDD,
+            81 => Opcode::AMOXORD,
+            82 => Opcode::AMOANDD,
+            83 => Opcode::AMOORD,
+            84 => Opcode::AMOMIND,
+            85 => Opcode::AMOMAXD,
+            86 => Opcode::AMOMINUD,
+            87 => Opcode::AMOMAXUD,
+            88 => Opcode::ADDUW,
+            89 => Opcode::ANDN,
+            90 => Opcode::ORN,
+            91 => Opcode::XNOR,
+            92 => Opcode::MAX,
+            93 => Opcode::MAXU,
+            94 => Opcode::MIN,
+            95 => Opcode::MINU,
+            96 => Opcode::ROL,
+            97 => Opcode::ROLW,
+            98 => Opcode::ROR,
+            99 => Opcode::RORW,
+            100 => Opcode::SH1ADD,
+            101 => Opcode::SH1ADDUW,
+            102 => Opcode::SH2ADD,
+            103 => Opcode::SH2ADDUW,
+            104 => Opcode::SH3ADD,
+            105 => Opcode::SH3ADDUW,
+            106 => Opcode::CLMUL,
+            107 => Opcode::CLMULH,
+            108 => Opcode::CLMULR,
+            109 => Opcode::BCLR,
+            110 => Opcode::BEXT,
+            111 => Opcode::BINV,
+            112 => Opcode::BSET,
+            113 => Opcode::RORI,
+            114 => Opcode::RORIW,
+            115 => Opcode::SLLIUW,
+            116 => Opcode::BCLRI,
+            117 => Opcode::BEXTI,
+            118 => Opcode::BINVI,
+            119 => Opcode::BSETI,
+            120 => Opcode::CLZ,
+            121 => Opcode::CLZW,
+            122 => Opcode::CTZ,
+            123 => Opcode::CTZW,
+            124 => Opcode::CPOP,
+            125 => Opcode::CPOPW,
+            126 => Opcode::ORCB,
+            127 => Opcode::REV8,
+            128 => Opcode::SEXTB,
+            129 => Opcode::SEXTH,
+            130 => Opcode::ZEXTH,
+            131 => Opcode::CUSTOMLOADIMM,
+            132 => Opcode::LDSIGNEXTENDED32CONSTANT,
+            133 => Opcode::FARJUMPREL,
+            134 => Opcode::FARJUMPABS,
 
             255 => Opcode::UNIMP,
-            _ => panic!("Invalid opcode value"),
-        }
+            _ => return None,
+        };
+        Some(opcode)
     }
 }
 
@@ -223,6 +387,80 @@ impl Opcode {
             "SLLW" => Some(Opcode::SLLW),
             "SRLW" => Some(Opcode::SRLW),
             "SRAW" => Some(Opcode::SRAW),
+            "MULW" => Some(Opcode::MULW),
+            "DIVW" => Some(Opcode::DIVW),
+            "DIVUW" => Some(Opcode::DIVUW),
+            "REMW" => Some(Opcode::REMW),
+            "REMUW" => Some(Opcode::REMUW),
+            "LRW" => Some(Opcode::LRW),
+            "SCW" => Some(Opcode::SCW),
+            "AMOSWAPW" => Some(Opcode::AMOSWAPW),
+            "AMOADDW" => Some(Opcode::AMOADDW),
+            "AMOXORW" => Some(Opcode::AMOXORW),
+            "AMOANDW" => Some(Opcode::AMOANDW),
+            "AMOORW" => Some(Opcode::AMOORW),
+            "AMOMINW" => Some(Opcode::AMOMINW),
+            "AMOMAXW" => Some(Opcode::AMOMAXW),
+            "AMOMINUW" => Some(Opcode::AMOMINUW),
+            "AMOMAXUW" => Some(Opcode::AMOMAXUW),
+            "LRD" => Some(Opcode::LRD),
+            "SCD" => Some(Opcode::SCD),
+            "AMOSWAPD" => Some(Opcode::AMOSWAPD),
+            "AMOADDD" => Some(Opcode::AMOADDD),
+            "AMOXORD" => Some(Opcode::AMOXORD),
+            "AMOANDD" => Some(Opcode::AMOANDD),
+            "AMOORD" => Some(Opcode::AMOORD),
+            "AMOMIND" => Some(Opcode::AMOMIND),
+            "AMOMAXD" => Some(Opcode::AMOMAXD),
+            "AMOMINUD" => Some(Opcode::AMOMINUD),
+            "AMOMAXUD" => Some(Opcode::AMOMAXUD),
+            "ADDUW" => Some(Opcode::ADDUW),
+            "ANDN" => Some(Opcode::ANDN),
+            "ORN" => Some(Opcode::ORN),
+            "XNOR" => Some(Opcode::XNOR),
+            "MAX" => Some(Opcode::MAX),
+            "MAXU" => Some(Opcode::MAXU),
+            "MIN" => Some(Opcode::MIN),
+            "MINU" => Some(Opcode::MINU),
+            "ROL" => Some(Opcode::ROL),
+            "ROLW" => Some(Opcode::ROLW),
+            "ROR" => Some(Opcode::ROR),
+            "RORW" => Some(Opcode::RORW),
+            "SH1ADD" => Some(Opcode::SH1ADD),
+            "SH1ADDUW" => Some(Opcode::SH1ADDUW),
+            "SH2ADD" => Some(Opcode::SH2ADD),
+            "SH2ADDUW" => Some(Opcode::SH2ADDUW),
+            "SH3ADD" => Some(Opcode::SH3ADD),
+            "SH3ADDUW" => Some(Opcode::SH3ADDUW),
+            "CLMUL" => Some(Opcode::CLMUL),
+            "CLMULH" => Some(Opcode::CLMULH),
+            "CLMULR" => Some(Opcode::CLMULR),
+            "BCLR" => Some(Opcode::BCLR),
+            "BEXT" => Some(Opcode::BEXT),
+            "BINV" => Some(Opcode::BINV),
+            "BSET" => Some(Opcode::BSET),
+            "RORI" => Some(Opcode::RORI),
+            "RORIW" => Some(Opcode::RORIW),
+            "SLLIUW" => Some(Opcode::SLLIUW),
+            "BCLRI" => Some(Opcode::BCLRI),
+            "BEXTI" => Some(Opcode::BEXTI),
+            "BINVI" => Some(Opcode::BINVI),
+            "BSETI" => Some(Opcode::BSETI),
+            "CLZ" => Some(Opcode::CLZ),
+            "CLZW" => Some(Opcode::CLZW),
+            "CTZ" => Some(Opcode::CTZ),
+            "CTZW" => Some(Opcode::CTZW),
+            "CPOP" => Some(Opcode::CPOP),
+            "CPOPW" => Some(Opcode::CPOPW),
+            "ORCB" => Some(Opcode::ORCB),
+            "REV8" => Some(Opcode::REV8),
+            "SEXTB" => Some(Opcode::SEXTB),
+            "SEXTH" => Some(Opcode::SEXTH),
+            "ZEXTH" => Some(Opcode::ZEXTH),
+            "CUSTOMLOADIMM" => Some(Opcode::CUSTOMLOADIMM),
+            "LDSIGNEXTENDED32CONSTANT" => Some(Opcode::LDSIGNEXTENDED32CONSTANT),
+            "FARJUMPREL" => Some(Opcode::FARJUMPREL),
+            "FARJUMPABS" => Some(Opcode::FARJUMPABS),
 
             "UNIMP" => Some(Opcode::UNIMP),
             _ => None,
@@ -230,12 +468,10 @@ impl Opcode {
     }
 }
 
-impl Serialize for Opcode {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: serde::Serializer,
-    {
-        serializer.serialize_str(match self {
+impl Opcode {
+    /// Name of the opcode, the same as its serialized form.
+    pub fn name(&self) -> &'static str {
+        match self {
             Opcode::ADD => "ADD",
             Opcode::SUB => "SUB",
             Opcode::XOR => "XOR",
@@ -297,7 +533,90 @@ impl Serialize for Opcode {
             Opcode::SLLW => "SLLW",
             Opcode::SRLW => "SRLW",
             Opcode::SRAW => "SRAW",
-        })
+            Opcode::MULW => "MULW",
+            Opcode::DIVW => "DIVW",
+            Opcode::DIVUW => "DIVUW",
+            Opcode::REMW => "REMW",
+            Opcode::REMUW => "REMUW",
+            Opcode::LRW => "LRW",
+            Opcode::SCW => "SCW",
+            Opcode::AMOSWAPW => "AMOSWAPW",
+            Opcode::AMOADDW => "AMOADDW",
+            Opcode::AMOXORW => "AMOXORW",
+            Opcode::AMOANDW => "AMOANDW",
+            Opcode::AMOORW => "AMOORW",
+            Opcode::AMOMINW => "AMOMINW",
+            Opcode::AMOMAXW => "AMOMAXW",
+            Opcode::AMOMINUW => "AMOMINUW",
+            Opcode::AMOMAXUW => "AMOMAXUW",
+            Opcode::LRD => "LRD",
+            Opcode::SCD => "SCD",
+            Opcode::AMOSWAPD => "AMOSWAPD",
+            Opcode::AMOADDD => "AMOADDD",
+            Opcode::AMOXORD => "AMOXORD",
+            Opcode::AMOANDD => "AMOANDD",
+            Opcode::AMOORD => "AMOORD",
+            Opcode::AMOMIND => "AMOMIND",
+            Opcode::AMOMAXD => "AMOMAXD",
+            Opcode::AMOMINUD => "AMOMINUD",
+            Opcode::AMOMAXUD => "AMOMAXUD",
+            Opcode::ADDUW => "ADDUW",
+            Opcode::ANDN => "ANDN",
+            Opcode::ORN => "ORN",
+            Opcode::XNOR => "XNOR",
+            Opcode::MAX => "MAX",
+            Opcode::MAXU => "MAXU",
+            Opcode::MIN => "MIN",
+            Opcode::MINU => "MINU",
+            Opcode::ROL => "ROL",
+            Opcode::ROLW => "ROLW",
+            Opcode::ROR => "ROR",
+            Opcode::RORW => "RORW",
+            Opcode::SH1ADD => "SH1ADD",
+            Opcode::SH1ADDUW => "SH1ADDUW",
+            Opcode::SH2ADD => "SH2ADD",
+            Opcode::SH2ADDUW => "SH2ADDUW",
+            Opcode::SH3ADD => "SH3ADD",
+            Opcode::SH3ADDUW => "SH3ADDUW",
+            Opcode::CLMUL => "CLMUL",
+            Opcode::CLMULH => "CLMULH",
+            Opcode::CLMULR => "CLMULR",
+            Opcode::BCLR => "BCLR",
+            Opcode::BEXT => "BEXT",
+            Opcode::BINV => "BINV",
+            Opcode::BSET => "BSET",
+            Opcode::RORI => "RORI",
+            Opcode::RORIW => "RORIW",
+            Opcode::SLLIUW => "SLLIUW",
+            Opcode::BCLRI => "BCLRI",
+            Opcode::BEXTI => "BEXTI",
+            Opcode::BINVI => "BINVI",
+            Opcode::BSETI => "BSETI",
+            Opcode::CLZ => "CLZ",
+            Opcode::CLZW => "CLZW",
+            Opcode::CTZ => "CTZ",
+            Opcode::CTZW => "CTZW",
+            Opcode::CPOP => "CPOP",
+            Opcode::CPOPW => "CPOPW",
+            Opcode::ORCB => "ORCB",
+            Opcode::REV8 => "REV8",
+            Opcode::SEXTB => "SEXTB",
+            Opcode::SEXTH => "SEXTH",
+            Opcode::ZEXTH => "ZEXTH",
+            Opcode::CUSTOMLOADIMM => "CUSTOMLOADIMM",
+            Opcode::LDSIGNEXTENDED32CONSTANT => "LDSIGNEXTENDED32CONSTANT",
+            Opcode::FARJUMPREL => "FARJUMPREL",
+            Opcode::FARJUMPABS => "FARJUMPABS",
+        }
+    }
+}
+
+impl Serialize for Opcode {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.name())
     }
 }
 
@@ -337,6 +656,12 @@ impl Opcode {
         if let Ok(r) = JType::try_from(*self) {
             return InstructionType::JType(r);
         }
+        if let Ok(r) = AType::try_from(*self) {
+            return InstructionType::AType(r);
+        }
+        if let Ok(r) = FusedType::try_from(*self) {
+            return InstructionType::FusedType(r);
+        }
         if let Ok(r) = NoType::try_from(*self) {
             return InstructionType::NoType(r);
         }
@@ -404,6 +729,36 @@ sub_enum!(RType of Opcode {
     SLLW,
     SRLW,
     SRAW,
+    MULW,
+    DIVW,
+    DIVUW,
+    REMW,
+    REMUW,
+    ADDUW,
+    ANDN,
+    ORN,
+    XNOR,
+    MAX,
+    MAXU,
+    MIN,
+    MINU,
+    ROL,
+    ROLW,
+    ROR,
+    RORW,
+    SH1ADD,
+    SH1ADDUW,
+    SH2ADD,
+    SH2ADDUW,
+    SH3ADD,
+    SH3ADDUW,
+    CLMUL,
+    CLMULH,
+    CLMULR,
+    BCLR,
+    BEXT,
+    BINV,
+    BSET,
 });
 
 sub_enum!(IType of Opcode {
@@ -428,7 +783,27 @@ sub_enum!(IType of Opcode {
     LD,
     LBU,
     LHU,
-    LWU
+    LWU,
+
+    // Unary instructions have an immediate of 0.
+    RORI,
+    RORIW,
+    SLLIUW,
+    BCLRI,
+    BEXTI,
+    BINVI,
+    BSETI,
+    CLZ,
+    CLZW,
+    CTZ,
+    CTZW,
+    CPOP,
+    CPOPW,
+    ORCB,
+    REV8,
+    SEXTB,
+    SEXTH,
+    ZEXTH,
 });
 
 sub_enum!(BType of Opcode {
@@ -456,6 +831,41 @@ sub_enum!(UType of Opcode {
     AUIPC,
 });
 
+// Operands are rd, rs1 (address) and rs2, the aq and rl bits are ignored.
+sub_enum!(AType of Opcode {
+    LRW,
+    SCW,
+    AMOSWAPW,
+    AMOADDW,
+    AMOXORW,
+    AMOANDW,
+    AMOORW,
+    AMOMINW,
+    AMOMAXW,
+    AMOMINUW,
+    AMOMAXUW,
+    LRD,
+    SCD,
+    AMOSWAPD,
+    AMOADDD,
+    AMOXORD,
+    AMOANDD,
+    AMOORD,
+    AMOMIND,
+    AMOMAXD,
+    AMOMINUD,
+    AMOMAXUD,
+});
+
+// Operands are rd and the value (imm_b), the value of the far jumps is the
+// offset from pc for FARJUMPREL and the target for FARJUMPABS.
+sub_enum!(FusedType of Opcode {
+    CUSTOMLOADIMM,
+    LDSIGNEXTENDED32CONSTANT,
+    FARJUMPREL,
+    FARJUMPABS,
+});
+
 sub_enum!(NoType of Opcode {
     FENCE,
     ECALL,
@@ -471,6 +881,8 @@ pub enum InstructionType {
     BType(BType),
     UType(UType),
     JType(JType),
+    AType(AType),
+    FusedType(FusedType),
     NoType(NoType),
 }
 
@@ -492,6 +904,65 @@ impl Instruction {
     }
 }
 
+/// ABI names of the registers.
+pub const REGISTER_NAMES: [&str; 32] = [
+    "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4",
+    "a5", "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4",
+    "t5", "t6",
+];
+
+fn register_name(index: u64) -> &'static str {
+    REGISTER_NAMES.get(index as usize).copied().unwrap_or("?")
+}
+
+/// Disassembles the instruction with the ABI register names, e.g. `ld a0, 8(sp)`.
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.opcode.name().to_ascii_lowercase();
+        let rd = register_name(self.op_a);
+        let rs1 = register_name(self.op_b);
+        let imm_b = self.op_b as i64;
+        let imm_c = self.op_c as i64;
+        match self.opcode.into() {
+            InstructionType::RType(_) | InstructionType::AType(_) => {
+                write!(f, "{} {}, {}, {}", name, rd, rs1, register_name(self.op_c))
+            }
+            InstructionType::IType(
+                IType::JALR
+                | IType::LB
+                | IType::LH
+                | IType::LW
+                | IType::LD
+                | IType::LBU
+                | IType::LHU
+                | IType::LWU,
+            )
+            | InstructionType::SType(_) => write!(f, "{} {}, {}({})", name, rd, imm_c, rs1),
+            InstructionType::IType(
+                IType::CLZ
+                | IType::CLZW
+                | IType::CTZ
+                | IType::CTZW
+                | IType::CPOP
+                | IType::CPOPW
+                | IType::ORCB
+                | IType::REV8
+                | IType::SEXTB
+                | IType::SEXTH
+                | IType::ZEXTH,
+            ) => write!(f, "{} {}, {}", name, rd, rs1),
+            InstructionType::IType(_) | InstructionType::BType(_) => {
+                write!(f, "{} {}, {}, {}", name, rd, rs1, imm_c)
+            }
+            InstructionType::UType(_) | InstructionType::JType(_) => {
+                write!(f, "{} {}, {}", name, rd, imm_b)
+            }
+            InstructionType::FusedType(_) => write!(f, "{} {}, {:#x}", name, rd, self.op_b),
+            InstructionType::NoType(_) => f.write_str(&name),
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, Clone, Debug, Eq, PartialEq)]
 pub struct Step {
     pub global_clk: u64,
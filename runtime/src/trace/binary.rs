@@ -0,0 +1,463 @@
+//! Compact binary encoding of a [`Trace`].
+//!
+//! The JSON trace dumped by ckb-debugger repeats the whole register file and
+//! the decoded instruction on every step. The binary format keeps the same
+//! information but only stores:
+//!
+//! * a header with the format version, the program hash and the cycles,
+//! * varint encoded deltas of `global_clk` and `pc`,
+//! * the instruction only the first time a pc is executed (or if it changes),
+//! * the registers that changed since the previous step.
+//!
+//! Layout (all integers are LEB128 varints unless stated otherwise):
+//!
+//! ```text
+//! header := magic[4] version:u8 program_hash[32] cycles return_value:u8
+//!           register_count:u8 step_count
+//! step   := flags:u8 zigzag(clk_delta) zigzag(pc_delta)
+//!           [opcode length imm_flags:u8 zigzag(op_a) zigzag(op_b) zigzag(op_c)]
+//!           register_mask value*
+//! ```
+//!
+//! The instruction is only present if bit 0 of `flags` is set. `register_mask`
+//! has one bit per register which changed; the new values follow in register
+//! order.
+
+use super::{Instruction, Opcode, Step, Trace};
+use alloc::{collections::BTreeMap, vec, vec::Vec};
+use core::fmt;
+
+/// Magic bytes at the start of every binary trace.
+pub const MAGIC: [u8; 4] = *b"BS2T";
+/// Current version of the binary trace format.
+pub const VERSION: u8 = 1;
+/// Registers are tracked with a `u64` bit mask.
+pub const MAX_REGISTERS: usize = 64;
+
+const FLAG_INSTRUCTION: u8 = 0b01;
+const IMM_B: u8 = 0b01;
+const IMM_C: u8 = 0b10;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceHeader {
+    pub version: u8,
+    /// Hash of the executed program, all zeros if unknown.
+    pub program_hash: [u8; 32],
+    pub cycles: u64,
+    pub return_value: u8,
+    pub register_count: u8,
+    pub step_count: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TraceFormatError {
+    UnexpectedEof,
+    InvalidMagic,
+    UnsupportedVersion(u8),
+    VarintOverflow,
+    InvalidOpcode(u64),
+    /// A step refers to a pc whose instruction was never stored.
+    MissingInstruction(u64),
+    /// The step at `step` doesn't have the register count of the trace.
+    InvalidRegisterCount {
+        step: u64,
+        expected: usize,
+        actual: usize,
+    },
+    /// The register mask of the step at `step` has bits for registers the
+    /// trace doesn't have.
+    InvalidRegisterMask {
+        step: u64,
+        mask: u64,
+    },
+    TrailingBytes(usize),
+}
+
+impl fmt::Display for TraceFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceFormatError::UnexpectedEof => write!(f, "unexpected end of trace"),
+            TraceFormatError::InvalidMagic => write!(f, "not a binary trace"),
+            TraceFormatError::UnsupportedVersion(v) => {
+                write!(f, "unsupported binary trace version {}", v)
+            }
+            TraceFormatError::VarintOverflow => write!(f, "varint overflows u64"),
+            TraceFormatError::InvalidOpcode(v) => write!(f, "invalid opcode value {}", v),
+            TraceFormatError::MissingInstruction(pc) => {
+                write!(f, "no instruction stored for pc {:#x}", pc)
+            }
+            TraceFormatError::InvalidRegisterCount {
+                step,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "step {} has {} registers, expected {}",
+                step, actual, expected
+            ),
+            TraceFormatError::InvalidRegisterMask { step, mask } => write!(
+                f,
+                "step {} changes registers {:#x} out of the registers of the trace",
+                step, mask
+            ),
+            TraceFormatError::TrailingBytes(n) => write!(f, "{} trailing bytes after trace", n),
+        }
+    }
+}
+
+/// Returns true if `bytes` starts with the binary trace magic.
+pub fn is_binary_trace(bytes: &[u8]) -> bool {
+    bytes.starts_with(&MAGIC)
+}
+
+/// Serializes `trace` into the binary format.
+pub fn encode(trace: &Trace, program_hash: [u8; 32]) -> Result<Vec<u8>, TraceFormatError> {
+    let register_count = trace.steps.first().map_or(0, |s| s.registers.len());
+    if register_count > MAX_REGISTERS {
+        return Err(TraceFormatError::InvalidRegisterCount {
+            step: 0,
+            expected: MAX_REGISTERS,
+            actual: register_count,
+        });
+    }
+
+    let mut writer = Writer::default();
+    writer.bytes(&MAGIC);
+    writer.byte(VERSION);
+    writer.bytes(&program_hash);
+    writer.varint(trace.cycles);
+    writer.byte(trace.return_value);
+    writer.byte(register_count as u8);
+    writer.varint(trace.steps.len() as u64);
+
+    let mut seen: BTreeMap<u64, &Instruction> = BTreeMap::new();
+    let mut registers = vec![0u64; register_count];
+    let (mut clk, mut pc) = (0u64, 0u64);
+    for (index, step) in trace.steps.iter().enumerate() {
+        if step.registers.len() != register_count {
+            return Err(TraceFormatError::InvalidRegisterCount {
+                step: index as u64,
+                expected: register_count,
+                actual: step.registers.len(),
+            });
+        }
+
+        let inline = seen.get(&step.pc) != Some(&&step.instruction);
+        writer.byte(if inline { FLAG_INSTRUCTION } else { 0 });
+        writer.signed(step.global_clk.wrapping_sub(clk) as i64);
+        writer.signed(step.pc.wrapping_sub(pc) as i64);
+        if inline {
+            writer.instruction(&step.instruction);
+            seen.insert(step.pc, &step.instruction);
+        }
+
+        let mut mask = 0u64;
+        for (i, (old, new)) in registers.iter().zip(step.registers.iter()).enumerate() {
+            if old != new {
+                mask |= 1 << i;
+            }
+        }
+        writer.varint(mask);
+        for (i, value) in step.registers.iter().enumerate() {
+            if mask & (1 << i) != 0 {
+                writer.varint(*value);
+            }
+        }
+
+        registers.copy_from_slice(&step.registers);
+        clk = step.global_clk;
+        pc = step.pc;
+    }
+
+    Ok(writer.buf)
+}
+
+/// Reads only the header of a binary trace.
+pub fn decode_header(bytes: &[u8]) -> Result<TraceHeader, TraceFormatError> {
+    Reader::new(bytes).header()
+}
+
+/// Deserializes a binary trace.
+pub fn decode(bytes: &[u8]) -> Result<(TraceHeader, Trace), TraceFormatError> {
+    let mut reader = Reader::new(bytes);
+    let header = reader.header()?;
+
+    let mut seen: BTreeMap<u64, Instruction> = BTreeMap::new();
+    let mut registers = vec![0u64; header.register_count as usize];
+    let (mut clk, mut pc) = (0u64, 0u64);
+    // Don't trust the step count for the allocation, it is checked against the input below.
+    let mut steps = Vec::new();
+    for index in 0..header.step_count {
+        let flags = reader.byte()?;
+        clk = clk.wrapping_add(reader.signed()? as u64);
+        pc = pc.wrapping_add(reader.signed()? as u64);
+        if flags & FLAG_INSTRUCTION != 0 {
+            let instruction = reader.instruction()?;
+            seen.insert(pc, instruction);
+        }
+        let instruction = seen
+            .get(&pc)
+            .cloned()
+            .ok_or(TraceFormatError::MissingInstruction(pc))?;
+
+        let mask = reader.varint()?;
+        if mask.checked_shr(header.register_count as u32).unwrap_or(0) != 0 {
+            return Err(TraceFormatError::InvalidRegisterMask { step: index, mask });
+        }
+        for (i, register) in registers.iter_mut().enumerate() {
+            if mask & (1 << i) != 0 {
+                *register = reader.varint()?;
+            }
+        }
+
+        steps.push(Step {
+            global_clk: clk,
+            pc,
+            instruction,
+            registers: registers.clone(),
+        });
+    }
+
+    if reader.remaining() != 0 {
+        return Err(TraceFormatError::TrailingBytes(reader.remaining()));
+    }
+
+    let trace = Trace {
+        cycles: header.cycles,
+        return_value: header.return_value,
+        steps,
+    };
+    Ok((header, trace))
+}
+
+#[derive(Default)]
+struct Writer {
+    buf: Vec<u8>,
+}
+
+impl Writer {
+    fn byte(&mut self, value: u8) {
+        self.buf.push(value);
+    }
+
+    fn bytes(&mut self, value: &[u8]) {
+        self.buf.extend_from_slice(value);
+    }
+
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.buf.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        self.buf.push(value as u8);
+    }
+
+    fn signed(&mut self, value: i64) {
+        // zigzag, so that small negative deltas stay small.
+        self.varint(((value << 1) ^ (value >> 63)) as u64);
+    }
+
+    fn instruction(&mut self, instruction: &Instruction) {
+        self.varint(instruction.opcode as u64);
+        self.varint(instruction.length);
+        let mut imm = 0;
+        if instruction.imm_b {
+            imm |= IMM_B;
+        }
+        if instruction.imm_c {
+            imm |= IMM_C;
+        }
+        self.byte(imm);
+        self.signed(instruction.op_a as i64);
+        self.signed(instruction.op_b as i64);
+        self.signed(instruction.op_c as i64);
+    }
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Self { buf, offset: 0 }
+    }
+
+    fn remaining(&self) -> usize {
+        self.buf.len() - self.offset
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], TraceFormatError> {
+        if self.remaining() < len {
+            return Err(TraceFormatError::UnexpectedEof);
+        }
+        let bytes = &self.buf[self.offset..self.offset + len];
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, TraceFormatError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn varint(&mut self) -> Result<u64, TraceFormatError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            let bits = (byte & 0x7f) as u64;
+            if shift == 63 && bits > 1 {
+                return Err(TraceFormatError::VarintOverflow);
+            }
+            value |= bits << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(TraceFormatError::VarintOverflow)
+    }
+
+    fn signed(&mut self) -> Result<i64, TraceFormatError> {
+        let value = self.varint()?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    fn header(&mut self) -> Result<TraceHeader, TraceFormatError> {
+        if self
+            .bytes(MAGIC.len())
+            .map_err(|_| TraceFormatError::InvalidMagic)?
+            != MAGIC
+        {
+            return Err(TraceFormatError::InvalidMagic);
+        }
+        let version = self.byte()?;
+        if version != VERSION {
+            return Err(TraceFormatError::UnsupportedVersion(version));
+        }
+        let mut program_hash = [0u8; 32];
+        program_hash.copy_from_slice(self.bytes(32)?);
+        let cycles = self.varint()?;
+        let return_value = self.byte()?;
+        let register_count = self.byte()?;
+        if register_count as usize > MAX_REGISTERS {
+            return Err(TraceFormatError::InvalidRegisterCount {
+                step: 0,
+                expected: MAX_REGISTERS,
+                actual: register_count as usize,
+            });
+        }
+        let step_count = self.varint()?;
+        Ok(TraceHeader {
+            version,
+            program_hash,
+            cycles,
+            return_value,
+            register_count,
+            step_count,
+        })
+    }
+
+    fn instruction(&mut self) -> Result<Instruction, TraceFormatError> {
+        let value = self.varint()?;
+        let opcode = u16::try_from(value)
+            .ok()
+            .and_then(Opcode::from_u16)
+            .ok_or(TraceFormatError::InvalidOpcode(value))?;
+        let length = self.varint()?;
+        let imm = self.byte()?;
+        Ok(Instruction {
+            opcode,
+            length,
+            op_a: self.signed()? as u64,
+            op_b: self.signed()? as u64,
+            op_c: self.signed()? as u64,
+            imm_b: imm & IMM_B != 0,
+            imm_c: imm & IMM_C != 0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(global_clk: u64, pc: u64, opcode: Opcode, registers: Vec<u64>) -> Step {
+        Step {
+            global_clk,
+            pc,
+            instruction: Instruction {
+                opcode,
+                length: 4,
+                op_a: 1,
+                op_b: 2,
+                op_c: (-8i64) as u64,
+                imm_b: false,
+                imm_c: true,
+            },
+            registers,
+        }
+    }
+
+    #[test]
+    fn roundtrip() {
+        let mut registers = vec![0u64; 32];
+        registers[2] = 0x3f_ffff;
+        let mut steps = vec![step(0, 0x1000, Opcode::ADDI, registers.clone())];
+        registers[1] = registers[2] - 8;
+        steps.push(step(1, 0x1004, Opcode::LD, registers.clone()));
+        registers[5] = u64::MAX;
+        // Jump back to an already seen pc, the instruction is not stored again.
+        steps.push(step(2, 0x1000, Opcode::ADDI, registers.clone()));
+        let trace = Trace {
+            cycles: 3,
+            return_value: 0,
+            steps,
+        };
+
+        let hash = [7u8; 32];
+        let bytes = encode(&trace, hash).unwrap();
+        assert!(is_binary_trace(&bytes));
+
+        let (header, decoded) = decode(&bytes).unwrap();
+        assert_eq!(header.program_hash, hash);
+        assert_eq!(header.step_count, 3);
+        assert_eq!(decoded, trace);
+    }
+
+    #[test]
+    fn rejects_truncated_input() {
+        let trace = Trace {
+            cycles: 1,
+            return_value: 0,
+            steps: vec![step(0, 0x1000, Opcode::ADD, vec![1; 32])],
+        };
+        let bytes = encode(&trace, [0; 32]).unwrap();
+        assert_eq!(
+            decode(&bytes[..bytes.len() - 1]),
+            Err(TraceFormatError::UnexpectedEof)
+        );
+        assert_eq!(decode(b"JSON"), Err(TraceFormatError::InvalidMagic));
+    }
+
+    #[test]
+    fn rejects_registers_out_of_the_trace() {
+        let trace = Trace {
+            cycles: 1,
+            return_value: 0,
+            steps: vec![step(0, 0x1000, Opcode::ADD, vec![1; 31])],
+        };
+        let mut bytes = encode(&trace, [0; 32]).unwrap();
+        // The mask of the only step, set for the 31 registers, is the 5 bytes
+        // varint before the values, which are one byte each.
+        let mask = bytes.len() - 31 - 5;
+        assert_eq!(bytes[mask..mask + 5], [0xff, 0xff, 0xff, 0xff, 0x07]);
+        bytes[mask + 4] = 0x0f;
+        assert_eq!(
+            decode(&bytes),
+            Err(TraceFormatError::InvalidRegisterMask {
+                step: 0,
+                mask: 0xffff_ffff,
+            })
+        );
+    }
+}
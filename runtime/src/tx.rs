@@ -0,0 +1,378 @@
+//! The mocked transaction of ckb-debugger (`--tx-file`), which holds the cells
+//! of the inputs and cell deps next to the transaction itself.
+//!
+//! Only deserialization of the JSON format is supported, numbers and bytes are
+//! `0x` prefixed hex strings like in the CKB RPC. Scripts and transactions are
+//! serialized with molecule, as the syscalls load them and the tx hash covers.
+
+use alloc::{format, string::String, vec, vec::Vec};
+use serde::{Deserialize, Deserializer};
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct MockTransaction {
+    pub mock_info: MockInfo,
+    pub tx: Transaction,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct MockInfo {
+    #[serde(default)]
+    pub inputs: Vec<MockInput>,
+    #[serde(default)]
+    pub cell_deps: Vec<MockCellDep>,
+}
+
+/// An input together with the cell it consumes.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct MockInput {
+    pub input: CellInput,
+    pub output: CellOutput,
+    pub data: Bytes,
+}
+
+/// A cell dep together with the cell it points to.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct MockCellDep {
+    pub cell_dep: CellDep,
+    pub output: CellOutput,
+    pub data: Bytes,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct Transaction {
+    #[serde(deserialize_with = "hex_u32")]
+    pub version: u32,
+    pub cell_deps: Vec<CellDep>,
+    pub header_deps: Vec<Byte32>,
+    pub inputs: Vec<CellInput>,
+    pub outputs: Vec<CellOutput>,
+    pub outputs_data: Vec<Bytes>,
+    pub witnesses: Vec<Bytes>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct OutPoint {
+    pub tx_hash: Byte32,
+    #[serde(deserialize_with = "hex_u32")]
+    pub index: u32,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DepType {
+    Code,
+    DepGroup,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct CellDep {
+    pub out_point: OutPoint,
+    pub dep_type: DepType,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct CellInput {
+    #[serde(deserialize_with = "hex_u64")]
+    pub since: u64,
+    pub previous_output: OutPoint,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct CellOutput {
+    #[serde(deserialize_with = "hex_u64")]
+    pub capacity: u64,
+    pub lock: Script,
+    #[serde(rename = "type")]
+    pub type_: Option<Script>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScriptHashType {
+    Data,
+    Type,
+    Data1,
+    Data2,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct Script {
+    pub code_hash: Byte32,
+    pub hash_type: ScriptHashType,
+    pub args: Bytes,
+}
+
+impl MockTransaction {
+    fn mock_cell_dep(&self, out_point: &OutPoint) -> Option<&MockCellDep> {
+        self.mock_info
+            .cell_deps
+            .iter()
+            .find(|dep| dep.cell_dep.out_point == *out_point)
+    }
+
+    /// Cell deps in the order scripts see them, dep groups are replaced by the
+    /// cells they list. `None` if a cell is missing in the mock info.
+    pub fn resolved_cell_deps(&self) -> Option<Vec<&MockCellDep>> {
+        let mut cells = Vec::new();
+        for cell_dep in self.tx.cell_deps.iter() {
+            let cell = self.mock_cell_dep(&cell_dep.out_point)?;
+            match cell_dep.dep_type {
+                DepType::Code => cells.push(cell),
+                DepType::DepGroup => {
+                    // The data of a dep group is an OutPointVec.
+                    let data = &cell.data.0;
+                    let count = u32::from_le_bytes(data.get(..4)?.try_into().ok()?) as usize;
+                    for i in 0..count {
+                        let item = data.get(4 + 36 * i..4 + 36 * (i + 1))?;
+                        let out_point = OutPoint {
+                            tx_hash: Byte32(item[..32].try_into().ok()?),
+                            index: u32::from_le_bytes(item[32..].try_into().ok()?),
+                        };
+                        cells.push(self.mock_cell_dep(&out_point)?);
+                    }
+                }
+            }
+        }
+        Some(cells)
+    }
+}
+
+impl Transaction {
+    /// Molecule serialization of the `RawTransaction`, without the witnesses.
+    pub fn raw_molecule(&self) -> Vec<u8> {
+        table(&[
+            self.version.to_le_bytes().to_vec(),
+            fixvec(
+                self.cell_deps.iter().map(CellDep::molecule),
+                self.cell_deps.len(),
+            ),
+            fixvec(
+                self.header_deps.iter().map(|h| h.0.to_vec()),
+                self.header_deps.len(),
+            ),
+            fixvec(
+                self.inputs.iter().map(CellInput::molecule),
+                self.inputs.len(),
+            ),
+            dynvec(self.outputs.iter().map(CellOutput::molecule).collect()),
+            dynvec(
+                self.outputs_data
+                    .iter()
+                    .map(|data| data.molecule())
+                    .collect(),
+            ),
+        ])
+    }
+
+    pub fn hash(&self) -> [u8; 32] {
+        ckb_hash(&self.raw_molecule())
+    }
+}
+
+impl OutPoint {
+    pub fn molecule(&self) -> Vec<u8> {
+        let mut bytes = self.tx_hash.0.to_vec();
+        bytes.extend_from_slice(&self.index.to_le_bytes());
+        bytes
+    }
+}
+
+impl CellDep {
+    pub fn molecule(&self) -> Vec<u8> {
+        let mut bytes = self.out_point.molecule();
+        bytes.push(match self.dep_type {
+            DepType::Code => 0,
+            DepType::DepGroup => 1,
+        });
+        bytes
+    }
+}
+
+impl CellInput {
+    pub fn molecule(&self) -> Vec<u8> {
+        let mut bytes = self.since.to_le_bytes().to_vec();
+        bytes.extend(self.previous_output.molecule());
+        bytes
+    }
+}
+
+impl CellOutput {
+    pub fn molecule(&self) -> Vec<u8> {
+        table(&[
+            self.capacity.to_le_bytes().to_vec(),
+            self.lock.molecule(),
+            // ScriptOpt is empty for None.
+            self.type_
+                .as_ref()
+                .map(Script::molecule)
+                .unwrap_or_default(),
+        ])
+    }
+}
+
+impl ScriptHashType {
+    pub fn byte(&self) -> u8 {
+        match self {
+            ScriptHashType::Data => 0,
+            ScriptHashType::Type => 1,
+            ScriptHashType::Data1 => 2,
+            ScriptHashType::Data2 => 4,
+        }
+    }
+}
+
+impl Script {
+    pub fn molecule(&self) -> Vec<u8> {
+        table(&[
+            self.code_hash.0.to_vec(),
+            vec![self.hash_type.byte()],
+            self.args.molecule(),
+        ])
+    }
+
+    pub fn hash(&self) -> [u8; 32] {
+        ckb_hash(&self.molecule())
+    }
+}
+
+/// Blake2b-256 with the personalization of CKB.
+pub fn ckb_hash(data: &[u8]) -> [u8; 32] {
+    let hash = blake2b_simd::Params::new()
+        .hash_length(32)
+        .personal(b"ckb-default-hash")
+        .hash(data);
+    let mut result = [0u8; 32];
+    result.copy_from_slice(hash.as_bytes());
+    result
+}
+
+/// Molecule table: total size and the offset of each field, then the fields.
+fn table(fields: &[Vec<u8>]) -> Vec<u8> {
+    let header = 4 * (fields.len() + 1);
+    let total = header + fields.iter().map(Vec::len).sum::<usize>();
+    let mut bytes = Vec::with_capacity(total);
+    bytes.extend_from_slice(&(total as u32).to_le_bytes());
+    let mut offset = header;
+    for field in fields.iter() {
+        bytes.extend_from_slice(&(offset as u32).to_le_bytes());
+        offset += field.len();
+    }
+    for field in fields.iter() {
+        bytes.extend_from_slice(field);
+    }
+    bytes
+}
+
+/// Molecule vector of fixed size items: item count, then the items.
+fn fixvec(items: impl Iterator<Item = Vec<u8>>, count: usize) -> Vec<u8> {
+    let mut bytes = (count as u32).to_le_bytes().to_vec();
+    for item in items {
+        bytes.extend(item);
+    }
+    bytes
+}
+
+/// Molecule vector of dynamic size items, laid out like a table.
+fn dynvec(items: Vec<Vec<u8>>) -> Vec<u8> {
+    if items.is_empty() {
+        return 4u32.to_le_bytes().to_vec();
+    }
+    table(&items)
+}
+
+/// Bytes of any length, `0x` prefixed hex in JSON.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Bytes(pub Vec<u8>);
+
+impl Bytes {
+    pub fn molecule(&self) -> Vec<u8> {
+        let mut bytes = (self.0.len() as u32).to_le_bytes().to_vec();
+        bytes.extend_from_slice(&self.0);
+        bytes
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Byte32(pub [u8; 32]);
+
+fn decode_hex(s: &str) -> Result<Vec<u8>, String> {
+    let digits = s
+        .strip_prefix("0x")
+        .ok_or_else(|| format!("{} is missing the 0x prefix", s))?;
+    if digits.len() % 2 != 0 {
+        return Err(format!("{} has an odd number of digits", s));
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).map_err(|_| format!("{} is not hex", s)))
+        .collect()
+}
+
+impl<'de> Deserialize<'de> for Bytes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        decode_hex(&s).map(Bytes).map_err(serde::de::Error::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for Byte32 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let Bytes(bytes) = Bytes::deserialize(deserializer)?;
+        let bytes: [u8; 32] = bytes
+            .try_into()
+            .map_err(|_| serde::de::Error::custom("expected 32 bytes"))?;
+        Ok(Byte32(bytes))
+    }
+}
+
+fn hex_u64<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    let digits = s
+        .strip_prefix("0x")
+        .ok_or_else(|| serde::de::Error::custom(format!("{} is missing the 0x prefix", s)))?;
+    u64::from_str_radix(digits, 16).map_err(serde::de::Error::custom)
+}
+
+fn hex_u32<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    let value = hex_u64(deserializer)?;
+    u32::try_from(value).map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn script_molecule() {
+        let script = Script {
+            code_hash: Byte32([1; 32]),
+            hash_type: ScriptHashType::Type,
+            args: Bytes(vec![0xaa, 0xbb]),
+        };
+        let bytes = script.molecule();
+        assert_eq!(bytes.len(), 16 + 32 + 1 + 4 + 2);
+        assert_eq!(
+            bytes[..16],
+            [55, 0, 0, 0, 16, 0, 0, 0, 48, 0, 0, 0, 49, 0, 0, 0]
+        );
+        assert_eq!(bytes[48..], [1, 2, 0, 0, 0, 0xaa, 0xbb]);
+    }
+
+    #[test]
+    fn empty_raw_transaction_molecule() {
+        let tx = Transaction {
+            version: 0,
+            cell_deps: vec![],
+            header_deps: vec![],
+            inputs: vec![],
+            outputs: vec![],
+            outputs_data: vec![],
+            witnesses: vec![Bytes(vec![1])],
+        };
+        let bytes = tx.raw_molecule();
+        // Header of 7 words, the version and 5 empty vectors.
+        assert_eq!(bytes.len(), 28 + 4 + 5 * 4);
+        assert_eq!(bytes[28..44], [0; 16]);
+        assert_eq!(bytes[44..], [4, 0, 0, 0, 4, 0, 0, 0]);
+    }
+}
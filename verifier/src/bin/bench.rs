@@ -0,0 +1,113 @@
+//! Cycles of the steps of the verifier in ckb-vm, on the proofs of
+//! `circuits/testdata`. Each step is run between two `current_cycles`
+//! syscalls and its cycles are printed with the debug syscall. From
+//! `verifier`:
+//!
+//! ```text
+//! cargo build --release --bin bench
+//! ckb-debugger --max-cycles 9999999999 --bin ../target/riscv64imac-unknown-none-elf/release/bench
+//! ```
+
+#![no_std]
+#![no_main]
+#![feature(lang_items)]
+#![feature(alloc_error_handler)]
+#![feature(panic_info_message)]
+
+#[cfg(test)]
+extern crate alloc;
+
+#[cfg(not(test))]
+use ckb_std::default_alloc;
+#[cfg(not(test))]
+ckb_std::entry!(program_entry);
+#[cfg(not(test))]
+default_alloc!();
+
+use alloc::{format, vec::Vec};
+use circuits::bundle::ProofBundle;
+use circuits::circuit::CircuitKind;
+use circuits::verify::{verify, verify_witness};
+use circuits::witness::{ProofWitness, VerifierKey, VerifierWitness};
+use halo2_proofs::halo2curves::bn256::Bn256;
+use halo2_proofs::poly::kzg::commitment::ParamsVerifierKZG;
+use halo2_proofs::poly::kzg::msm::PreparedG2KZG;
+
+use ckb_std::syscalls::{current_cycles, debug};
+
+const PARAMS: &[u8] = include_bytes!("../../../circuits/testdata/verifier-params.bin");
+const VK: &[u8] = include_bytes!("../../../circuits/testdata/vk.bin");
+const PROOF: &[u8] = include_bytes!("../../../circuits/testdata/proof.bin");
+// The same with `prove --transcript keccak256` and `--transcript poseidon`.
+const PROOF_KECCAK256: &[u8] = include_bytes!("../../../circuits/testdata/proof-keccak256.bin");
+const PROOF_POSEIDON: &[u8] = include_bytes!("../../../circuits/testdata/proof-poseidon.bin");
+
+/// Runs `f`, printing the cycles it takes.
+fn measure<T>(name: &str, f: impl FnOnce() -> T) -> T {
+    let start = current_cycles();
+    let result = f();
+    debug(format!("{}: {} cycles", name, current_cycles() - start));
+    result
+}
+
+fn proof_of(bundle: &[u8]) -> ProofWitness {
+    let bundle = ProofBundle::decode(bundle).expect("testdata proof bundle");
+    ProofWitness {
+        fingerprint: bundle.fingerprint,
+        transcript: bundle.transcript,
+        instances: bundle.instances,
+        proof: bundle.proof,
+    }
+}
+
+pub fn program_entry() -> i8 {
+    // The proofs differ by their transcript only, so do their cycles.
+    let mut results = Vec::new();
+    for bundle in [PROOF, PROOF_KECCAK256, PROOF_POSEIDON] {
+        let proof = proof_of(bundle);
+        results.push(measure(&format!("verify {}", proof.transcript), || {
+            verify(
+                CircuitKind::Main,
+                PARAMS,
+                VK,
+                proof.transcript,
+                &proof.instances,
+                &proof.proof,
+            )
+        }));
+    }
+
+    // The pairing check of the witness verifier, with the G2 line
+    // coefficients computed on every run or read from the key cell.
+    let params = ParamsVerifierKZG::<Bn256>::read_shrunk(&mut &PARAMS[..])
+        .expect("testdata verifier params");
+    let lines = measure("prepare g2 lines", || PreparedG2KZG::new(&params));
+    let mut prepared_bytes = Vec::new();
+    lines
+        .write(&mut prepared_bytes)
+        .expect("writing to a vector doesn't fail");
+    let witness = VerifierWitness {
+        params: Vec::new(),
+        vk: Vec::new(),
+        proofs: alloc::vec![proof_of(PROOF)],
+        circuit: CircuitKind::Main,
+    };
+    let mut key = VerifierKey {
+        params: PARAMS.to_vec(),
+        vk: VK.to_vec(),
+        prepared: Vec::new(),
+        circuit: CircuitKind::Main,
+    };
+    results.push(measure("verify witness", || verify_witness(&key, &witness)));
+    key.prepared = prepared_bytes;
+    results.push(measure("verify witness with prepared g2 lines", || {
+        verify_witness(&key, &witness)
+    }));
+
+    if results.iter().all(Result::is_ok) {
+        0
+    } else {
+        debug("a testdata proof doesn't verify".into());
+        1
+    }
+}
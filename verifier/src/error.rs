@@ -0,0 +1,64 @@
+use circuits::verify::VerifyError;
+use ckb_std::error::SysError;
+
+/// Exit codes of the verifier. They are stable: new errors get new codes and
+/// the codes of removed errors aren't reused.
+#[repr(i8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    IndexOutOfBound = 1,
+    ItemMissing = 2,
+    LengthNotEnough = 3,
+    Encoding = 4,
+    /// A syscall failed with an error of no other code.
+    UnknownSysError = 5,
+    /// No `WitnessArgs` field holds a valid `VerifierWitness`.
+    MalformedWitness = 6,
+    /// The script args are neither empty nor a 32 bytes hash.
+    InvalidArgs = 7,
+    /// No cell dep holds the key pinned by the script args.
+    KeyNotFound = 8,
+    /// The cell dep with the pinned hash isn't a `VerifierKey`.
+    MalformedKey = 9,
+    MalformedParams = 10,
+    MalformedVk = 11,
+    /// The proof ends early or holds invalid points or scalars.
+    MalformedProof = 12,
+    /// The public inputs don't match the instance columns of the circuit.
+    InstanceMismatch = 13,
+    /// The proof doesn't verify: the final pairing check fails.
+    PairingFailure = 14,
+    /// The proof is for another circuit version than the vk.
+    FingerprintMismatch = 15,
+    /// The public inputs of an aggregation proof don't start with a valid
+    /// accumulator.
+    MalformedAccumulator = 18,
+}
+
+impl From<SysError> for Error {
+    fn from(err: SysError) -> Self {
+        match err {
+            SysError::IndexOutOfBound => Self::IndexOutOfBound,
+            SysError::ItemMissing => Self::ItemMissing,
+            SysError::LengthNotEnough(_) => Self::LengthNotEnough,
+            SysError::Encoding => Self::Encoding,
+            _ => Self::UnknownSysError,
+        }
+    }
+}
+
+impl From<VerifyError> for Error {
+    fn from(err: VerifyError) -> Self {
+        match err {
+            VerifyError::MalformedParams => Self::MalformedParams,
+            VerifyError::MalformedVk => Self::MalformedVk,
+            VerifyError::MalformedPrepared => Self::MalformedKey,
+            VerifyError::FingerprintMismatch(_) => Self::FingerprintMismatch,
+            VerifyError::InstanceMismatch => Self::InstanceMismatch,
+            VerifyError::MalformedProof => Self::MalformedProof,
+            VerifyError::PairingFailure => Self::PairingFailure,
+            VerifyError::NoProof => Self::MalformedWitness,
+            VerifyError::MalformedAccumulator => Self::MalformedAccumulator,
+        }
+    }
+}